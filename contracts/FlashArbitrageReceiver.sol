@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+interface IERC20 {
+    function balanceOf(address account) external view returns (uint256);
+    function approve(address spender, uint256 amount) external returns (bool);
+    function transfer(address to, uint256 amount) external returns (bool);
+}
+
+interface IPool {
+    function flashLoanSimple(
+        address receiverAddress,
+        address asset,
+        uint256 amount,
+        bytes calldata params,
+        uint16 referralCode
+    ) external;
+}
+
+/// @title Aave V3 flashLoanSimple receiver for polygon-arb-detector
+/// @notice Borrows `asset`, makes the buy and sell router calls it is handed,
+/// repays the loan plus premium and sends what is left to the owner, reverting
+/// unless that is at least `minProfit`.
+/// @dev The loan is started either by the owner calling `executeArbitrage`, or
+/// by the owner calling `flashLoanSimple` on the pool with this contract as the
+/// receiver and `params` encoded as
+/// `(address buyRouter, bytes buyData, address sellRouter, bytes sellData, uint256 minProfit)`.
+/// Routers must be approved once with `approveToken` for the tokens they spend.
+contract FlashArbitrageReceiver {
+    IPool public immutable POOL;
+    address public immutable owner;
+
+    /// Profit sent to the owner by the last loan.
+    uint256 public lastProfit;
+
+    constructor(IPool pool) {
+        POOL = pool;
+        owner = msg.sender;
+    }
+
+    modifier onlyOwner() {
+        require(msg.sender == owner, "not owner");
+        _;
+    }
+
+    /// Lets `spender` (a router) pull `amount` of `token` from this contract.
+    function approveToken(address token, address spender, uint256 amount) external onlyOwner {
+        require(IERC20(token).approve(spender, amount), "approve failed");
+    }
+
+    /// Borrows `amount` of `asset` and runs the round trip; returns the profit.
+    function executeArbitrage(
+        address asset,
+        uint256 amount,
+        address buyRouter,
+        bytes calldata buyData,
+        address sellRouter,
+        bytes calldata sellData,
+        uint256 minProfit
+    ) external onlyOwner returns (uint256 profit) {
+        bytes memory params = abi.encode(buyRouter, buyData, sellRouter, sellData, minProfit);
+        POOL.flashLoanSimple(address(this), asset, amount, params, 0);
+        return lastProfit;
+    }
+
+    /// Aave V3 callback, made by the pool once `amount` has been sent here.
+    function executeOperation(
+        address asset,
+        uint256 amount,
+        uint256 premium,
+        address initiator,
+        bytes calldata params
+    ) external returns (bool) {
+        require(msg.sender == address(POOL), "not pool");
+        require(initiator == address(this) || initiator == owner, "bad initiator");
+        (
+            address buyRouter,
+            bytes memory buyData,
+            address sellRouter,
+            bytes memory sellData,
+            uint256 minProfit
+        ) = abi.decode(params, (address, bytes, address, bytes, uint256));
+
+        // Anything held before the loan is not counted as profit.
+        uint256 held = IERC20(asset).balanceOf(address(this)) - amount;
+        (bool ok, bytes memory reason) = buyRouter.call(buyData);
+        require(ok, string(abi.encodePacked("buy leg failed: ", reason)));
+        (ok, reason) = sellRouter.call(sellData);
+        require(ok, string(abi.encodePacked("sell leg failed: ", reason)));
+
+        uint256 owed = amount + premium;
+        uint256 balance = IERC20(asset).balanceOf(address(this));
+        require(balance >= held + owed + minProfit, "below minProfit");
+        uint256 profit = balance - held - owed;
+        lastProfit = profit;
+        require(IERC20(asset).approve(address(POOL), owed), "repay approve failed");
+        require(IERC20(asset).transfer(owner, profit), "profit transfer failed");
+        return true;
+    }
+}
@@ -0,0 +1,198 @@
+//! Quotes from the 0x and 1inch REST APIs, as a [`PriceSource`] compared
+//! alongside the on-chain venues.
+
+use async_trait::async_trait;
+use ethers::core::types::{Address, U256};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+use crate::price::{PriceError, PriceSource};
+
+/// Quote API an `aggregator` venue calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum AggregatorApi {
+    /// 0x Swap API v2, `GET /swap/allowance-holder/price`.
+    #[serde(rename = "0x")]
+    ZeroX,
+    /// 1inch Swap API v6, `GET /swap/v6.0/{chain}/quote`.
+    #[serde(rename = "1inch")]
+    OneInch,
+}
+
+impl AggregatorApi {
+    fn default_base_url(self) -> &'static str {
+        match self {
+            Self::ZeroX => "https://api.0x.org",
+            Self::OneInch => "https://api.1inch.dev",
+        }
+    }
+}
+
+/// API settings of an `aggregator` venue (`[dex.aggregator]`).
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct AggregatorConfig {
+    /// Which API to call.
+    pub api: AggregatorApi,
+    /// Sent as `0x-api-key` to 0x and as a bearer token to 1inch.
+    pub api_key: Option<String>,
+    /// Replaces the API's public host, e.g. for a proxy.
+    pub base_url: Option<String>,
+    /// Per-request timeout in milliseconds.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+// Hand-written so the API key never reaches logs through `{:?}`.
+impl fmt::Debug for AggregatorConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AggregatorConfig")
+            .field("api", &self.api)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("base_url", &self.base_url)
+            .field("timeout_ms", &self.timeout_ms)
+            .finish()
+    }
+}
+
+/// A venue quoted through an aggregator's REST API.
+///
+/// The aggregator picks its own route between the path's first and last
+/// token, and always quotes the latest state, so `pin_quote_block` does not
+/// apply to it.
+pub struct AggregatorSource {
+    name: String,
+    cfg: AggregatorConfig,
+    chain_id: u64,
+    client: Client,
+}
+
+impl AggregatorSource {
+    /// Calls the API of `cfg` for quotes on `chain_id`.
+    pub fn new(name: String, cfg: AggregatorConfig, chain_id: u64) -> Self {
+        Self {
+            name,
+            cfg,
+            chain_id,
+            client: Client::new(),
+        }
+    }
+
+    fn request(&self, amount_in: U256, sell: Address, buy: Address) -> reqwest::RequestBuilder {
+        let base = self
+            .cfg
+            .base_url
+            .as_deref()
+            .unwrap_or(self.cfg.api.default_base_url())
+            .trim_end_matches('/');
+        let (sell, buy, amount) = (
+            format!("{:?}", sell),
+            format!("{:?}", buy),
+            amount_in.to_string(),
+        );
+        let request = match self.cfg.api {
+            AggregatorApi::ZeroX => {
+                let request = self
+                    .client
+                    .get(format!("{base}/swap/allowance-holder/price"))
+                    .query(&[
+                        ("chainId", self.chain_id.to_string()),
+                        ("sellToken", sell),
+                        ("buyToken", buy),
+                        ("sellAmount", amount),
+                    ])
+                    .header("0x-version", "v2");
+                match &self.cfg.api_key {
+                    Some(key) => request.header("0x-api-key", key),
+                    None => request,
+                }
+            }
+            AggregatorApi::OneInch => {
+                let request = self
+                    .client
+                    .get(format!("{base}/swap/v6.0/{}/quote", self.chain_id))
+                    .query(&[("src", sell), ("dst", buy), ("amount", amount)]);
+                match &self.cfg.api_key {
+                    Some(key) => request.bearer_auth(key),
+                    None => request,
+                }
+            }
+        };
+        request.timeout(Duration::from_millis(self.cfg.timeout_ms))
+    }
+}
+
+/// The fields read from either API's quote response.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuoteResponse {
+    /// 0x output amount.
+    buy_amount: Option<String>,
+    /// 1inch output amount.
+    dst_amount: Option<String>,
+    /// 0x: `false` when no route fills the amount.
+    liquidity_available: Option<bool>,
+}
+
+#[async_trait]
+impl PriceSource for AggregatorSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn quote_at(
+        &self,
+        amount_in: U256,
+        path: &[Address],
+        _block: Option<u64>,
+    ) -> Result<U256, PriceError> {
+        let (Some(&sell), Some(&buy)) = (path.first(), path.last()) else {
+            return Err(PriceError::NoLiquidityPath("empty path".into()));
+        };
+        let resp = self
+            .request(amount_in, sell, buy)
+            .send()
+            .await
+            .map_err(|err| PriceError::Rpc(format!("{:?} API: {}", self.cfg.api, err)))?;
+        let status = resp.status();
+        let body = resp
+            .text()
+            .await
+            .map_err(|err| PriceError::Rpc(format!("{:?} API: {}", self.cfg.api, err)))?;
+        if !status.is_success() {
+            // Both APIs answer an unroutable amount with a 4xx naming liquidity.
+            if status.is_client_error() && body.to_ascii_lowercase().contains("liquidity") {
+                return Err(PriceError::NoLiquidityPath(body));
+            }
+            if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                return Err(PriceError::Revert(format!("{}: {}", status, body)));
+            }
+            return Err(PriceError::Rpc(format!(
+                "{:?} API returned {}",
+                self.cfg.api, status
+            )));
+        }
+        let quote: QuoteResponse = serde_json::from_str(&body)
+            .map_err(|err| PriceError::Rpc(format!("{:?} API: {}", self.cfg.api, err)))?;
+        if quote.liquidity_available == Some(false) {
+            return Err(PriceError::NoLiquidityPath(
+                "liquidityAvailable is false".into(),
+            ));
+        }
+        let amount = quote
+            .buy_amount
+            .or(quote.dst_amount)
+            .ok_or(PriceError::EmptyResponse)?;
+        U256::from_dec_str(&amount).map_err(|err| {
+            PriceError::Rpc(format!(
+                "{:?} API amount {:?}: {}",
+                self.cfg.api, amount, err
+            ))
+        })
+    }
+}
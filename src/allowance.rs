@@ -0,0 +1,314 @@
+//! Router allowances of the execution wallet: startup approvals, a cache, and
+//! re-approval when an allowance runs short mid-session.
+
+use ethers::abi::{Abi, parse_abi};
+use ethers::contract::Contract;
+use ethers::core::types::{Address, TxHash, U256};
+use ethers::providers::{Http, JsonRpcClient};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+use crate::error::{BotError, BotResult};
+use crate::nonce::NonceManager;
+
+/// ERC-20 fragment used for allowances.
+pub const ERC20_ALLOWANCE_ABI: &[&str] = &[
+    "function allowance(address,address) view returns (uint256)",
+    "function approve(address,uint256) returns (bool)",
+];
+
+/// Allowances at or above this are treated as unlimited and never run down.
+const UNLIMITED: U256 = U256([u64::MAX, u64::MAX, u64::MAX, u64::MAX >> 1]);
+
+/// How much an approval grants (`execution.approval`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalMode {
+    /// Exactly the amount needed: `max_trade_size_usdc` of USDC at startup, and
+    /// a failed swap's amount when [`AllowanceManager::diagnose`] re-approves.
+    #[default]
+    Exact,
+    /// `type(uint256).max`, approved once per token and router at startup.
+    Max,
+}
+
+/// Amount to approve when `current` allowance must cover `needed`; `None` when
+/// it already does.
+pub fn approval_amount(current: U256, needed: U256, mode: ApprovalMode) -> Option<U256> {
+    if current >= needed {
+        return None;
+    }
+    Some(match mode {
+        ApprovalMode::Exact => needed,
+        ApprovalMode::Max => U256::MAX,
+    })
+}
+
+/// Allowance still cached after a swap spent `amount` of it.
+pub fn after_spend(cached: U256, amount: U256) -> U256 {
+    if cached >= UNLIMITED {
+        cached
+    } else {
+        cached.saturating_sub(amount)
+    }
+}
+
+/// Tracks and tops up the wallet's allowance per (token, router).
+pub struct AllowanceManager<P = Http> {
+    wallet: Arc<NonceManager<P>>,
+    erc20: Abi,
+    mode: ApprovalMode,
+    /// Last known allowance per (token, router), run down by [`Self::spent`].
+    cache: Mutex<HashMap<(Address, Address), U256>>,
+}
+
+impl<P: JsonRpcClient + 'static> AllowanceManager<P> {
+    /// Manages the allowances of `wallet`, sending approvals through it.
+    pub fn new(wallet: Arc<NonceManager<P>>, mode: ApprovalMode) -> Self {
+        Self {
+            wallet,
+            erc20: parse_abi(ERC20_ALLOWANCE_ABI).expect("static ERC-20 fragment parses"),
+            mode,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Startup check of every `(token, minimum)` against every router: reads the
+    /// allowance, approves a shortfall and waits for it to confirm.
+    ///
+    /// In `max` mode the minimum is ignored and anything short of unlimited is
+    /// approved.
+    pub async fn prepare(&self, tokens: &[(Address, U256)], routers: &[Address]) -> BotResult<()> {
+        for &(token, minimum) in tokens {
+            let minimum = match self.mode {
+                ApprovalMode::Exact => minimum,
+                ApprovalMode::Max => UNLIMITED,
+            };
+            for &router in routers {
+                let current = self.read(token, router).await.map_err(|err| {
+                    BotError::Rpc(format!(
+                        "reading allowance of token {:?} for router {:?}: {}",
+                        token, router, err
+                    ))
+                })?;
+                match approval_amount(current, minimum, self.mode) {
+                    Some(amount) => {
+                        self.approve(token, router, amount).await?;
+                    }
+                    None => {
+                        self.cache.lock().unwrap().insert((token, router), current);
+                    }
+                }
+                info!(?token, ?router, "Router allowance ready");
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes sure `spender` may spend `amount` of `token`, approving
+    /// `type(uint256).max` on a shortfall whatever `execution.approval` says, so
+    /// a token needing per-trade approval (e.g. WETH just bought) is approved
+    /// only once.
+    ///
+    /// The cache is trusted when it covers `amount`, so repeated trades through
+    /// the same router skip the RPC; otherwise the chain is read. The approval
+    /// is signed by the manager's wallet rather than a signer argument, so it
+    /// takes its nonce in turn with the swaps. Returns the approval's hash when
+    /// one was sent.
+    pub async fn check_and_approve_if_needed(
+        &self,
+        token: Address,
+        spender: Address,
+        amount: U256,
+    ) -> BotResult<Option<TxHash>> {
+        let cached = self.cached(token, spender);
+        if cached >= amount {
+            return Ok(None);
+        }
+        let current = self.read(token, spender).await?;
+        let Some(approve) = approval_amount(current, amount, ApprovalMode::Max) else {
+            self.cache.lock().unwrap().insert((token, spender), current);
+            return Ok(None);
+        };
+        self.approve(token, spender, approve).await.map(Some)
+    }
+
+    /// Records that a confirmed swap spent `amount` of the allowance.
+    pub fn spent(&self, token: Address, router: Address, amount: U256) {
+        if let Some(cached) = self.cache.lock().unwrap().get_mut(&(token, router)) {
+            *cached = after_spend(*cached, amount);
+        }
+    }
+
+    /// After a failed swap: re-reads the allowance and, when it no longer covers
+    /// `amount`, re-approves and describes the shortfall.
+    ///
+    /// `None` when the allowance was not the problem.
+    pub async fn diagnose(&self, token: Address, router: Address, amount: U256) -> Option<String> {
+        self.cache.lock().unwrap().remove(&(token, router));
+        let current = match self.read(token, router).await {
+            Ok(current) => current,
+            Err(err) => return Some(format!("allowance of {:?} unreadable: {}", token, err)),
+        };
+        let approve = approval_amount(current, amount, self.mode)?;
+        let outcome = match self.approve(token, router, approve).await {
+            Ok(hash) => format!("re-approved in {:?}", hash),
+            Err(err) => format!("re-approval failed: {}", err),
+        };
+        Some(format!(
+            "allowance shortfall: router {:?} may spend {} of token {:?}, needed {}; {}",
+            router, current, token, amount, outcome
+        ))
+    }
+
+    fn cached(&self, token: Address, router: Address) -> U256 {
+        self.cache
+            .lock()
+            .unwrap()
+            .get(&(token, router))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    async fn read(&self, token: Address, router: Address) -> BotResult<U256> {
+        let contract = Contract::new(token, self.erc20.clone(), self.wallet.client().clone());
+        Ok(contract
+            .method::<_, U256>("allowance", (self.wallet.address(), router))?
+            .call()
+            .await?)
+    }
+
+    async fn approve(&self, token: Address, router: Address, amount: U256) -> BotResult<TxHash> {
+        let contract = Contract::new(token, self.erc20.clone(), self.wallet.client().clone());
+        let call = contract
+            .method::<_, bool>("approve", (router, amount))?
+            .legacy();
+        let receipt =
+            self.wallet.send(call.tx, "approval").await.map_err(|err| {
+                BotError::Rpc(format!("approval of token {:?}: {:#}", token, err))
+            })?;
+        let hash = receipt.transaction_hash;
+        if receipt.status != Some(1.into()) {
+            return Err(BotError::Rpc(format!(
+                "approval {:?} of token {:?} reverted",
+                hash, token
+            )));
+        }
+        info!(?token, ?router, %amount, tx = ?hash, "Router approved");
+        self.cache.lock().unwrap().insert((token, router), amount);
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ExecutionSettings;
+    use crate::db;
+    use crate::gas::GasConfig;
+    use ethers::abi::{Token, encode};
+    use ethers::core::types::Bytes;
+    use ethers::providers::{MockProvider, Provider};
+
+    /// A well-known development key; nothing is ever signed for a real chain.
+    const KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    async fn manager(
+        name: &str,
+        mode: ApprovalMode,
+    ) -> (AllowanceManager<MockProvider>, MockProvider) {
+        let pool = db::open_pool(&format!("file:{}?mode=memory&cache=shared", name)).unwrap();
+        db::init_db(&pool).unwrap();
+        let mock = MockProvider::new();
+        // The pending nonce `connect` seeds from.
+        mock.push::<U256, _>(U256::zero()).unwrap();
+        let settings = ExecutionSettings {
+            private_key: Some(KEY.into()),
+            ..ExecutionSettings::default()
+        };
+        let wallet = NonceManager::connect(
+            &settings,
+            &GasConfig::default(),
+            137,
+            Provider::new(mock.clone()),
+            pool,
+        )
+        .await
+        .unwrap();
+        (AllowanceManager::new(Arc::new(wallet), mode), mock)
+    }
+
+    #[test]
+    fn covered_allowances_need_no_approval() {
+        let needed = U256::from(1_000);
+        for mode in [ApprovalMode::Exact, ApprovalMode::Max] {
+            assert_eq!(approval_amount(needed, needed, mode), None);
+            assert_eq!(approval_amount(U256::MAX, needed, mode), None);
+        }
+    }
+
+    #[test]
+    fn shortfalls_approve_the_amount_or_max() {
+        let needed = U256::from(1_000);
+        assert_eq!(
+            approval_amount(U256::from(999), needed, ApprovalMode::Exact),
+            Some(needed)
+        );
+        assert_eq!(
+            approval_amount(U256::zero(), needed, ApprovalMode::Max),
+            Some(U256::MAX)
+        );
+    }
+
+    #[test]
+    fn spends_run_down_finite_allowances_only() {
+        assert_eq!(
+            after_spend(U256::from(1_000), U256::from(400)),
+            U256::from(600)
+        );
+        assert_eq!(after_spend(U256::from(300), U256::from(400)), U256::zero());
+        assert_eq!(after_spend(U256::MAX, U256::from(400)), U256::MAX);
+        assert_eq!(after_spend(UNLIMITED, U256::from(400)), UNLIMITED);
+    }
+
+    #[tokio::test]
+    async fn a_covering_cache_skips_the_chain() {
+        let (allowances, mock) = manager("allowance_cache", ApprovalMode::Exact).await;
+        let (token, router) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        allowances
+            .cache
+            .lock()
+            .unwrap()
+            .insert((token, router), U256::from(1_000));
+
+        // No response is scripted, so any read of the chain would fail.
+        assert_eq!(
+            allowances
+                .check_and_approve_if_needed(token, router, U256::from(600))
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(
+            allowances
+                .check_and_approve_if_needed(token, router, U256::from(1_500))
+                .await
+                .is_err()
+        );
+
+        // Past the cache, the chain's allowance is read and cached.
+        mock.push::<Bytes, _>(Bytes::from(encode(&[Token::Uint(U256::from(2_000))])))
+            .unwrap();
+        assert_eq!(
+            allowances
+                .check_and_approve_if_needed(token, router, U256::from(1_500))
+                .await
+                .unwrap(),
+            None
+        );
+        allowances.spent(token, router, U256::from(1_500));
+        assert_eq!(allowances.cached(token, router), U256::from(500));
+    }
+}
@@ -0,0 +1,240 @@
+//! Sanity filter for ticks whose spread or quotes break sharply from their
+//! recent history, as a drained or migrated pool does.
+
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Thresholds of the filter (`[settings.anomaly_filter]`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AnomalyFilterConfig {
+    /// Number of recent ticks the medians are taken over; ticks are judged once
+    /// the window is full.
+    #[serde(default = "default_window")]
+    pub window: usize,
+    /// A spread above this multiple of the median spread is anomalous.
+    #[serde(default = "default_max_spread_multiple")]
+    pub max_spread_multiple: f64,
+    /// A quote further than this from its venue's median, in percent, is anomalous.
+    #[serde(default = "default_max_quote_deviation_pct")]
+    pub max_quote_deviation_pct: f64,
+    /// Stores an anomalous opportunity with `anomaly = 1` instead of dropping it.
+    #[serde(default)]
+    pub record: bool,
+}
+
+fn default_window() -> usize {
+    20
+}
+
+fn default_max_spread_multiple() -> f64 {
+    10.0
+}
+
+fn default_max_quote_deviation_pct() -> f64 {
+    5.0
+}
+
+/// Why a tick was judged anomalous.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    /// The gross spread is over `max_spread_multiple` times its median.
+    Spread {
+        /// This tick's spread.
+        spread: f64,
+        /// Median spread over the window.
+        median: f64,
+    },
+    /// One venue's quote moved over `max_quote_deviation_pct` from its median.
+    Quote {
+        /// Venue that quoted.
+        dex: String,
+        /// This tick's quote.
+        price: f64,
+        /// Median quote of the venue over the window.
+        median: f64,
+    },
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spread { spread, median } => {
+                write!(f, "spread {spread:.6} vs median {median:.6}")
+            }
+            Self::Quote { dex, price, median } => {
+                write!(f, "{dex} quote {price:.6} vs median {median:.6}")
+            }
+        }
+    }
+}
+
+/// Rolling spreads and per-venue quotes of one pair.
+#[derive(Debug)]
+pub struct AnomalyDetector {
+    cfg: AnomalyFilterConfig,
+    spreads: VecDeque<f64>,
+    quotes: HashMap<String, VecDeque<f64>>,
+}
+
+impl AnomalyDetector {
+    /// Creates a detector with empty history.
+    pub fn new(cfg: AnomalyFilterConfig) -> Self {
+        Self {
+            cfg,
+            spreads: VecDeque::new(),
+            quotes: HashMap::new(),
+        }
+    }
+
+    /// Judges this tick's gross `spread` and `quotes` (venue, price) against the
+    /// window, then adds them to it.
+    ///
+    /// Anomalous ticks enter the window too, so a venue that settles at a new
+    /// level stops being flagged after about half a window.
+    pub fn observe(&mut self, spread: f64, quotes: &[(&str, f64)]) -> Option<Anomaly> {
+        let window = self.cfg.window.max(2);
+        let anomaly = self.judge(spread, quotes, window);
+        push(&mut self.spreads, spread, window);
+        for &(dex, price) in quotes {
+            push(
+                self.quotes.entry(dex.to_string()).or_default(),
+                price,
+                window,
+            );
+        }
+        anomaly
+    }
+
+    fn judge(&self, spread: f64, quotes: &[(&str, f64)], window: usize) -> Option<Anomaly> {
+        if self.spreads.len() >= window
+            && let Some(median) = median(&self.spreads)
+            && median > 0.0
+            && spread > median * self.cfg.max_spread_multiple
+        {
+            return Some(Anomaly::Spread { spread, median });
+        }
+        quotes.iter().find_map(|&(dex, price)| {
+            let history = self.quotes.get(dex).filter(|h| h.len() >= window)?;
+            let median = median(history).filter(|m| *m > 0.0)?;
+            let deviation_pct = (price - median).abs() / median * 100.0;
+            (deviation_pct > self.cfg.max_quote_deviation_pct).then(|| Anomaly::Quote {
+                dex: dex.to_string(),
+                price,
+                median,
+            })
+        })
+    }
+}
+
+fn push(values: &mut VecDeque<f64>, value: f64, window: usize) {
+    values.push_back(value);
+    while values.len() > window {
+        values.pop_front();
+    }
+}
+
+/// Median of `values`; the mean of the middle two for an even count.
+pub fn median(values: &VecDeque<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<f64> = values.iter().copied().collect();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    Some(if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector(window: usize) -> AnomalyDetector {
+        AnomalyDetector::new(AnomalyFilterConfig {
+            window,
+            max_spread_multiple: 10.0,
+            max_quote_deviation_pct: 5.0,
+            record: false,
+        })
+    }
+
+    /// Feeds `ticks` of a steady 1.0 spread with QuickSwap at 2500.
+    fn steady(detector: &mut AnomalyDetector, ticks: usize) {
+        for _ in 0..ticks {
+            assert_eq!(detector.observe(1.0, &[("QuickSwap", 2_500.0)]), None);
+        }
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(&VecDeque::new()), None);
+        assert_eq!(median(&VecDeque::from([3.0, 1.0, 2.0])), Some(2.0));
+        assert_eq!(median(&VecDeque::from([4.0, 1.0, 3.0, 2.0])), Some(2.5));
+    }
+
+    #[test]
+    fn spread_spike_is_flagged_once_the_window_is_full() {
+        let mut early = detector(4);
+        steady(&mut early, 3);
+        // Three ticks are not yet a window to judge against.
+        assert_eq!(early.observe(20.0, &[("QuickSwap", 2_500.0)]), None);
+
+        let mut detector = detector(4);
+        steady(&mut detector, 4);
+        assert_eq!(
+            detector.observe(20.0, &[("QuickSwap", 2_500.0)]),
+            Some(Anomaly::Spread {
+                spread: 20.0,
+                median: 1.0
+            })
+        );
+        // 10 times the median is still within the multiple.
+        assert_eq!(detector.observe(9.0, &[("QuickSwap", 2_500.0)]), None);
+    }
+
+    #[test]
+    fn quote_deviating_from_its_venue_is_flagged() {
+        let mut detector = detector(4);
+        steady(&mut detector, 4);
+        // 4% off its median passes; 8% does not.
+        assert_eq!(detector.observe(1.0, &[("QuickSwap", 2_600.0)]), None);
+        assert_eq!(
+            detector.observe(1.0, &[("QuickSwap", 2_700.0)]),
+            Some(Anomaly::Quote {
+                dex: "QuickSwap".into(),
+                price: 2_700.0,
+                median: 2_500.0
+            })
+        );
+        // A venue without a full window of its own is not judged.
+        assert_eq!(
+            detector.observe(1.0, &[("QuickSwap", 2_500.0), ("SushiSwap", 9_999.0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn ticks_older_than_the_window_are_evicted() {
+        let mut detector = detector(4);
+        for _ in 0..4 {
+            detector.observe(50.0, &[("QuickSwap", 2_500.0)]);
+        }
+        steady(&mut detector, 4);
+        // Against the evicted 50s the median would be 25.5, and 15 would pass.
+        assert_eq!(
+            detector.observe(15.0, &[("QuickSwap", 2_500.0)]),
+            Some(Anomaly::Spread {
+                spread: 15.0,
+                median: 1.0
+            })
+        );
+        // Flagged ticks enter the window: a repeated spike stops being flagged
+        // once it holds half of it.
+        assert!(detector.observe(15.0, &[("QuickSwap", 2_500.0)]).is_some());
+        assert_eq!(detector.observe(15.0, &[("QuickSwap", 2_500.0)]), None);
+    }
+}
@@ -0,0 +1,268 @@
+//! HTTP API for dashboards and scripts.
+
+use anyhow::Result;
+use axum::Json;
+use axum::Router;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::{get, post};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::redact_url;
+use crate::db::RecentOpportunity;
+use crate::health::Health;
+use crate::reload::SharedConfig;
+use crate::state::{LastPrice, PriceBoard};
+use crate::store::OpportunityStore;
+
+/// Capacity of the opportunity broadcast channel; slower subscribers skip ahead.
+pub const EVENT_CAPACITY: usize = 256;
+
+/// Published by the scanners to `/stream` clients.
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// A recorded opportunity, as `/opportunities` lists it.
+    Opportunity(RecentOpportunity),
+    /// Every quote of one scanner's tick, as `/prices` lists them.
+    Prices(Vec<LastPrice>),
+}
+
+/// Rows `/opportunities` returns without a `limit`.
+const DEFAULT_OPPORTUNITIES_LIMIT: u64 = 50;
+
+/// Largest `limit` `/opportunities` honours.
+const MAX_OPPORTUNITIES_LIMIT: u64 = 1000;
+
+/// State shared by the API handlers.
+#[derive(Clone)]
+pub struct ApiState {
+    /// Opportunities and tick prices published by the scanners.
+    pub events: broadcast::Sender<StreamEvent>,
+    /// Opportunity and price history.
+    pub store: Arc<dyn OpportunityStore>,
+    /// Tick liveness recorded by the scanner.
+    pub health: Arc<Health>,
+    /// Age of the last successful tick above which `/health` reports 503.
+    pub health_max_age: Duration,
+    /// Emergency stop of live execution, set by `/admin/pause`; detection goes on.
+    pub execution_paused: Arc<AtomicBool>,
+    /// Latest quotes of every scanner.
+    pub prices: PriceBoard,
+    /// Running configuration, as last reloaded.
+    pub config: SharedConfig,
+}
+
+/// Query of `GET /stream`.
+#[derive(Debug, Deserialize)]
+struct StreamQuery {
+    /// Also sends a `prices` event per tick.
+    #[serde(default)]
+    prices: bool,
+}
+
+/// Query of `GET /opportunities`.
+#[derive(Debug, Deserialize)]
+struct OpportunitiesQuery {
+    limit: Option<u64>,
+}
+
+/// Body of `GET /status`.
+#[derive(Debug, Serialize)]
+struct StatusReport {
+    uptime_secs: u64,
+    /// Ticks finished by all scanners since startup.
+    ticks: u64,
+    opportunities_session: u64,
+    execution_paused: bool,
+    config: ConfigSummary,
+}
+
+/// The running configuration without keys, tokens or RPC credentials.
+#[derive(Debug, Serialize)]
+struct ConfigSummary {
+    chains: Vec<ChainSummary>,
+    jobs: Vec<String>,
+    min_profit_usdc: f64,
+    trade_size: String,
+    refresh_rate: u64,
+    /// `off`, `paper` or `live`.
+    execution: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct ChainSummary {
+    chain_id: u64,
+    rpc_url: String,
+    pairs: Vec<String>,
+    dex: Vec<String>,
+}
+
+/// `GET /health`: 200 with a [`HealthReport`](crate::health::HealthReport), or 503
+/// when the last successful tick is older than `health_max_age`.
+async fn health_handler(State(state): State<ApiState>) -> impl IntoResponse {
+    let (report, healthy) = state.health.report(state.health_max_age);
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+/// `GET /stream`: Server-Sent Events, one `opportunity` event per recorded
+/// opportunity and, with `?prices=true`, one `prices` event per tick, with the
+/// bodies of `/opportunities` rows and `/prices`.
+///
+/// Each client gets its own receiver, so it only sees events published after it
+/// connected; the receiver is dropped with the response when the client leaves.
+/// A client that falls more than [`EVENT_CAPACITY`] events behind skips ahead,
+/// so the scanners never wait on it.
+async fn stream_handler(
+    State(state): State<ApiState>,
+    Query(query): Query<StreamQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe()).filter_map(move |msg| match msg {
+        Ok(StreamEvent::Opportunity(opportunity)) => Event::default()
+            .event("opportunity")
+            .json_data(&opportunity)
+            .ok()
+            .map(Ok),
+        Ok(StreamEvent::Prices(prices)) if query.prices => Event::default()
+            .event("prices")
+            .json_data(&prices)
+            .ok()
+            .map(Ok),
+        Ok(StreamEvent::Prices(_)) => None,
+        Err(err) => {
+            warn!(%err, "Stream client lagging; skipped events");
+            None
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// `GET /opportunities?limit=N`: the `N` (default 50, at most 1000) most recent
+/// stored opportunities, newest first.
+///
+/// Read through the store, sharing the database with the scanners' writer.
+async fn opportunities_handler(
+    State(state): State<ApiState>,
+    Query(query): Query<OpportunitiesQuery>,
+) -> Result<Json<Vec<RecentOpportunity>>, (StatusCode, Json<serde_json::Value>)> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_OPPORTUNITIES_LIMIT)
+        .min(MAX_OPPORTUNITIES_LIMIT);
+    let rows = state.store.recent_opportunities(limit).await;
+    rows.map(Json).map_err(|err| {
+        warn!(%err, "Failed to read opportunities for the API");
+        // A busy or unreachable database is worth retrying; anything else is not.
+        let status = if err.is_transient() {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+        (
+            status,
+            Json(serde_json::json!({ "error": err.to_string(), "kind": err.kind() })),
+        )
+    })
+}
+
+/// `GET /prices`: the latest quote of every DEX and pair, ordered by DEX.
+async fn prices_handler(State(state): State<ApiState>) -> Json<Vec<LastPrice>> {
+    Json(state.prices.lock().unwrap().values().cloned().collect())
+}
+
+/// `GET /status`: uptime, tick and opportunity counts and a redacted summary
+/// of the running configuration.
+async fn status_handler(State(state): State<ApiState>) -> Json<StatusReport> {
+    let config = {
+        let cfg = state.config.read().unwrap();
+        ConfigSummary {
+            chains: cfg
+                .chains
+                .iter()
+                .map(|chain| ChainSummary {
+                    chain_id: chain.chain_id,
+                    rpc_url: redact_url(&chain.rpc_url),
+                    pairs: chain
+                        .tokens
+                        .iter()
+                        .map(|pair| pair.label().to_string())
+                        .collect(),
+                    dex: chain.dex.iter().map(|entry| entry.name.clone()).collect(),
+                })
+                .collect(),
+            jobs: cfg.jobs.iter().map(|job| job.name.clone()).collect(),
+            min_profit_usdc: cfg.settings.min_profit_usdc,
+            trade_size: cfg.settings.trade_size.to_string(),
+            refresh_rate: cfg.settings.refresh_rate,
+            execution: match (cfg.execution.enabled, cfg.execution.paper) {
+                (false, _) => "off",
+                (true, true) => "paper",
+                (true, false) => "live",
+            },
+        }
+    };
+    Json(StatusReport {
+        uptime_secs: state.health.uptime().as_secs(),
+        ticks: state.health.ticks(),
+        opportunities_session: state.health.opportunities(),
+        execution_paused: state.execution_paused.load(Ordering::Relaxed),
+        config,
+    })
+}
+
+/// `POST /admin/pause`: stops executing opportunities until `/admin/resume`;
+/// they are still detected and recorded.
+async fn pause_handler(State(state): State<ApiState>) -> impl IntoResponse {
+    set_paused(&state, true)
+}
+
+/// `POST /admin/resume`: lifts `/admin/pause`.
+async fn resume_handler(State(state): State<ApiState>) -> impl IntoResponse {
+    set_paused(&state, false)
+}
+
+fn set_paused(state: &ApiState, paused: bool) -> Json<serde_json::Value> {
+    let was = state.execution_paused.swap(paused, Ordering::Relaxed);
+    if was != paused {
+        warn!(paused, "Execution pause toggled over the API");
+    }
+    Json(serde_json::json!({ "execution_paused": paused }))
+}
+
+/// Builds the API router.
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/stream", get(stream_handler))
+        .route("/health", get(health_handler))
+        .route("/opportunities", get(opportunities_handler))
+        .route("/prices", get(prices_handler))
+        .route("/status", get(status_handler))
+        .route("/admin/pause", post(pause_handler))
+        .route("/admin/resume", post(resume_handler))
+        .with_state(state)
+}
+
+/// Serves the API on `listen` (e.g. `127.0.0.1:8080`) until `shutdown` is cancelled.
+pub async fn serve(listen: &str, state: ApiState, shutdown: CancellationToken) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    info!(addr = %listener.local_addr()?, "API server listening");
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,750 @@
+//! Pure spread and net-profit math, independent of any RPC or database.
+
+use anyhow::{Result, anyhow, bail};
+use ethers::core::types::U256;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Decimals of the WETH base token; `trade_size` is given in these units.
+pub const WETH_DECIMALS: u32 = 18;
+
+/// Decimals of the USDC quote token.
+pub const USDC_DECIMALS: u32 = 6;
+
+/// Re-expresses `amount` of a `from`-decimals token in `to` decimals, truncating.
+pub fn rescale_units(amount: U256, from: u32, to: u32) -> U256 {
+    if from >= to {
+        amount / U256::exp10((from - to) as usize)
+    } else {
+        amount.saturating_mul(U256::exp10((to - from) as usize))
+    }
+}
+
+/// Parses a decimal amount such as `"1.5"` into raw units of a `decimals`-decimals
+/// token, exactly.
+///
+/// Rejects signs, exponents and more fractional digits than the token has.
+pub fn parse_units(amount: &str, decimals: u32) -> Result<U256> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        bail!("{:?} is not a decimal amount", amount);
+    }
+    if fraction.len() > decimals as usize {
+        bail!(
+            "{:?} has {} fractional digits; the token has {} decimals",
+            amount,
+            fraction.len(),
+            decimals
+        );
+    }
+    let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    U256::from_dec_str(&digits).map_err(|err| anyhow!("{:?}: {}", amount, err))
+}
+
+/// Formats raw units of a `decimals`-decimals token as an exact decimal string,
+/// without trailing fractional zeros.
+pub fn format_units(units: U256, decimals: u32) -> String {
+    let scale = U256::exp10(decimals as usize);
+    let (whole, fraction) = units.div_mod(scale);
+    if fraction.is_zero() {
+        return whole.to_string();
+    }
+    let fraction = format!(
+        "{:0>width$}",
+        fraction.to_string(),
+        width = decimals as usize
+    );
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+/// Converts a configured amount to raw units through its shortest decimal form,
+/// so `15.3` becomes exactly 15.3 tokens; sub-unit digits are truncated and
+/// negative or non-finite amounts become zero.
+pub fn amount_to_units(amount: f64, decimals: u32) -> U256 {
+    if !amount.is_finite() || amount <= 0.0 {
+        return U256::zero();
+    }
+    let repr = amount.to_string();
+    let (whole, fraction) = repr.split_once('.').unwrap_or((&repr, ""));
+    let fraction = &fraction[..fraction.len().min(decimals as usize)];
+    parse_units(&format!("{}.{}", whole, fraction), decimals).unwrap_or_default()
+}
+
+/// Nearest `f64` to raw units of a `decimals`-decimals token, for display and
+/// storage only.
+pub fn units_to_amount(units: U256, decimals: u32) -> f64 {
+    format_units(units, decimals).parse().unwrap_or(f64::MAX)
+}
+
+/// Converts a USDC amount to raw token units, truncating sub-unit digits.
+pub fn usdc_to_units(usdc: f64) -> U256 {
+    amount_to_units(usdc, USDC_DECIMALS)
+}
+
+/// Converts raw USDC token units to a display amount.
+pub fn units_to_usdc(units: U256) -> f64 {
+    units_to_amount(units, USDC_DECIMALS)
+}
+
+/// Converts a WETH amount to wei, truncating sub-wei digits.
+pub fn weth_to_units(weth: f64) -> U256 {
+    amount_to_units(weth, WETH_DECIMALS)
+}
+
+/// Converts wei to a display amount of WETH.
+pub fn units_to_weth(units: U256) -> f64 {
+    units_to_amount(units, WETH_DECIMALS)
+}
+
+/// One venue's output amount for the shared trade size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote<'a> {
+    /// Venue name, carried through into [`Spread`].
+    pub dex: &'a str,
+    /// Quote-token units received for the trade size.
+    pub amount_out: U256,
+    /// Venue fee charged on each leg, in basis points of the leg's amount.
+    pub fee_bps: u32,
+}
+
+/// Fee of `fee_bps` basis points on a leg of `amount`.
+pub fn leg_fee(amount: U256, fee_bps: u32) -> U256 {
+    amount * U256::from(fee_bps) / U256::from(10_000)
+}
+
+/// Swap fee, in basis points per leg, implied by a round trip of `probe` out
+/// of and back into the same venue at a size too small to move its price:
+/// such a round trip keeps `(1 - fee)²` of the probe.
+///
+/// 0 when nothing was lost, e.g. for a venue quoting without a fee.
+pub fn implied_fee_bps(probe: U256, back: U256) -> u32 {
+    if probe.is_zero() || back >= probe {
+        return 0;
+    }
+    let kept = units_to_amount(back, 0) / units_to_amount(probe, 0);
+    ((1.0 - kept.sqrt()) * 10_000.0).round() as u32
+}
+
+/// Base and quote reserves of a constant-product pool, in whole tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpReserves {
+    /// Base token (WETH) held by the pool.
+    pub base: f64,
+    /// Quote token (USDC) held by the pool.
+    pub quote: f64,
+}
+
+impl CpReserves {
+    /// The reserves of the constant-product pool charging `fee_bps` that pays
+    /// `spot` quote per base for a marginal sale and `size_out` for `size` base,
+    /// a sale of `x` paying `γ·x·R_quote / (R_base + γ·x)` with `γ = 1 - fee`.
+    ///
+    /// Any venue's quotes can be read this way, so two of its quotes stand in
+    /// for reserves it may not expose. `None` when the quotes show no price
+    /// impact, which no finite pool would produce.
+    pub fn implied(spot: f64, size: f64, size_out: f64, fee_bps: u32) -> Option<Self> {
+        let gamma = 1.0 - fee_bps as f64 / 10_000.0;
+        let impact = size * spot - size_out;
+        if !(spot > 0.0 && size_out > 0.0 && impact > 0.0) {
+            return None;
+        }
+        // From spot = γ·R_quote/R_base and size_out = γ·size·R_quote/(R_base + γ·size).
+        let base = size_out * gamma * size / impact;
+        Some(Self {
+            base,
+            quote: spot * base / gamma,
+        })
+    }
+
+    /// Both reserves valued in the quote token at the pool's mid price, which
+    /// makes the base side worth as much as the quote side.
+    pub fn liquidity(&self) -> f64 {
+        2.0 * self.quote
+    }
+}
+
+/// Quote spent on `buy` that maximizes the profit of buying base there and
+/// selling it on `sell`, both constant-product pools charging their fee.
+///
+/// The cycle returns `K·x / (M + N·x)` for `x` spent, with
+/// `K = γb·γs·B_buy·Q_sell`, `M = Q_buy·B_sell` and `N = γb·(B_sell + γs·B_buy)`,
+/// whose profit peaks at `x = (√(K·M) − M) / N`. `None` when no positive
+/// amount is profitable before gas.
+pub fn optimal_cycle_input(
+    buy: CpReserves,
+    buy_fee_bps: u32,
+    sell: CpReserves,
+    sell_fee_bps: u32,
+) -> Option<f64> {
+    let gamma_buy = 1.0 - buy_fee_bps as f64 / 10_000.0;
+    let gamma_sell = 1.0 - sell_fee_bps as f64 / 10_000.0;
+    let k = gamma_buy * gamma_sell * buy.base * sell.quote;
+    let m = buy.quote * sell.base;
+    let n = gamma_buy * (sell.base + gamma_sell * buy.base);
+    let input = ((k * m).sqrt() - m) / n;
+    (input.is_finite() && input > 0.0).then_some(input)
+}
+
+/// Spread between two venues' mid prices, in basis points, that a round trip
+/// of `size_usdc` needs to break even: the product of both legs' fees and
+/// `gas_usdc` spread over the size.
+pub fn break_even_spread_bps(
+    buy_fee_bps: u32,
+    sell_fee_bps: u32,
+    gas_usdc: f64,
+    size_usdc: f64,
+) -> f64 {
+    let kept = (1.0 - buy_fee_bps as f64 / 10_000.0) * (1.0 - sell_fee_bps as f64 / 10_000.0);
+    let gas = if size_usdc > 0.0 {
+        gas_usdc / size_usdc
+    } else {
+        0.0
+    };
+    ((1.0 + gas) / kept - 1.0) * 10_000.0
+}
+
+/// Direction and size of a spread between two quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spread<'a> {
+    /// Venue paying less for the base token, where it is bought.
+    pub buy_dex: &'a str,
+    /// Venue paying more for the base token, where it is sold.
+    pub sell_dex: &'a str,
+    /// Difference between the two output amounts.
+    pub gross: U256,
+    /// `gross` minus both legs' venue fees and gas cost, floored at zero.
+    pub net_profit: U256,
+}
+
+/// Outcome of comparing two quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision<'a> {
+    /// Both venues quote the same amount.
+    NoSpread,
+    /// A spread exists but its net profit does not exceed the threshold.
+    BelowThreshold(Spread<'a>),
+    /// Net profit strictly exceeds the threshold.
+    Opportunity(Spread<'a>),
+}
+
+/// Picks the buy/sell direction between `a` and `b` and nets out both legs'
+/// venue fees and `gas_cost`.
+///
+/// Returns `None` when both quotes are equal.
+pub fn compute_spread<'a>(a: Quote<'a>, b: Quote<'a>, gas_cost: U256) -> Option<Spread<'a>> {
+    let (cheap, rich) = if a.amount_out > b.amount_out {
+        (b, a)
+    } else if b.amount_out > a.amount_out {
+        (a, b)
+    } else {
+        return None;
+    };
+    let gross = rich.amount_out - cheap.amount_out;
+    Some(Spread {
+        buy_dex: cheap.dex,
+        sell_dex: rich.dex,
+        gross,
+        net_profit: gross
+            .saturating_sub(leg_fee(cheap.amount_out, cheap.fee_bps))
+            .saturating_sub(leg_fee(rich.amount_out, rich.fee_bps))
+            .saturating_sub(gas_cost),
+    })
+}
+
+/// Impermanent loss, in percent, of a 50/50 constant-product LP position
+/// entered at `entry_price` and valued at `current_price`, against holding:
+/// `2 * sqrt(r) / (1 + r) - 1` with `r = current_price / entry_price`.
+///
+/// Never positive; `-5.72` means the position is worth 5.72% less than the
+/// tokens it was opened with. 0 when either price is not positive.
+pub fn compute_impermanent_loss(entry_price: f64, current_price: f64) -> f64 {
+    if entry_price <= 0.0 || current_price <= 0.0 {
+        return 0.0;
+    }
+    let ratio = current_price / entry_price;
+    (2.0 * ratio.sqrt() / (1.0 + ratio) - 1.0) * 100.0
+}
+
+/// Direction a pair's spread has been moving over its recent ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrendDirection {
+    /// The spread is widening.
+    Rising,
+    /// The spread is closing.
+    Falling,
+    /// No clear movement, or too few ticks to tell.
+    Flat,
+}
+
+impl fmt::Display for TrendDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rising => "rising",
+            Self::Falling => "falling",
+            Self::Flat => "flat",
+        })
+    }
+}
+
+/// Least-squares slope, in USDC per tick, below which a spread counts as flat.
+pub const FLAT_TREND_SLOPE_USDC: f64 = 0.01;
+
+/// Trend of `history`, oldest spread first, from the slope of its least-squares
+/// line over the tick index; [`TrendDirection::Flat`] with fewer than two points.
+pub fn spread_trend(history: &VecDeque<f64>) -> TrendDirection {
+    let n = history.len() as f64;
+    if history.len() < 2 {
+        return TrendDirection::Flat;
+    }
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = history.iter().sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (i, y) in history.iter().enumerate() {
+        let dx = i as f64 - mean_x;
+        cov += dx * (y - mean_y);
+        var += dx * dx;
+    }
+    let slope = cov / var;
+    if slope > FLAT_TREND_SLOPE_USDC {
+        TrendDirection::Rising
+    } else if slope < -FLAT_TREND_SLOPE_USDC {
+        TrendDirection::Falling
+    } else {
+        TrendDirection::Flat
+    }
+}
+
+/// Ranks an opportunity by profit per unit of gas spend:
+/// `profit_usdc / (gas_units * gas_price_gwei * matic_price_usdc)`.
+///
+/// Only meaningful relative to other scores. Returns 0 when the gas spend is not
+/// positive, so misconfigured costs never outrank real ones.
+pub fn score_opportunity(
+    profit_usdc: f64,
+    gas_units: u64,
+    gas_price_gwei: f64,
+    matic_price_usdc: f64,
+) -> f64 {
+    let gas_spend = gas_units as f64 * gas_price_gwei * matic_price_usdc;
+    if gas_spend > 0.0 {
+        profit_usdc / gas_spend
+    } else {
+        0.0
+    }
+}
+
+/// Decides whether the spread between `a` and `b` is worth recording.
+///
+/// A spread qualifies only when its net profit is strictly greater than `min_profit`.
+pub fn decide<'a>(a: Quote<'a>, b: Quote<'a>, gas_cost: U256, min_profit: U256) -> Decision<'a> {
+    match compute_spread(a, b, gas_cost) {
+        None => Decision::NoSpread,
+        Some(spread) if spread.net_profit > min_profit => Decision::Opportunity(spread),
+        Some(spread) => Decision::BelowThreshold(spread),
+    }
+}
+
+/// Index in `ranked` of the spread to act on: the first, unless others
+/// netting within `tie` of it buy on a venue of lower `priority`; then the
+/// first of those with the lowest one.
+///
+/// 0 when `ranked` is empty.
+pub fn prefer_by_priority<'a>(
+    ranked: impl IntoIterator<Item = Spread<'a>>,
+    priority: impl Fn(&str) -> u32,
+    tie: U256,
+) -> usize {
+    let ranked: Vec<Spread<'a>> = ranked.into_iter().collect();
+    let Some(first) = ranked.first() else {
+        return 0;
+    };
+    let tied = |spread: &Spread| {
+        let (high, low) = if spread.net_profit > first.net_profit {
+            (spread.net_profit, first.net_profit)
+        } else {
+            (first.net_profit, spread.net_profit)
+        };
+        high - low <= tie
+    };
+    ranked
+        .iter()
+        .enumerate()
+        .filter(|(_, spread)| tied(spread))
+        .min_by_key(|(i, spread)| (priority(spread.buy_dex), *i))
+        .map_or(0, |(i, _)| i)
+}
+
+/// Widest gross spread among a tick's venue prices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbCandidate {
+    /// Venue quoting less, where the base token is bought.
+    pub buy_dex: String,
+    /// Venue quoting more, where it is sold.
+    pub sell_dex: String,
+    /// Difference between the two venues' prices, before fees and gas.
+    pub gross_profit: U256,
+}
+
+/// Compares every `i < j` pair of `prices`, each a venue name and its output
+/// for the shared trade size, and returns the pair with the widest spread;
+/// on equal spreads the first compared wins.
+///
+/// Gross only: [`decide_best`] ranks the same matrix by net profit, with
+/// each venue's fees and each route's gas, and is what the scanner decides
+/// on. `None` when every price is equal.
+pub fn find_best_arbitrage(prices: &[(String, U256)]) -> Option<ArbCandidate> {
+    let mut best: Option<ArbCandidate> = None;
+    for (i, (a_dex, a)) in prices.iter().enumerate() {
+        for (b_dex, b) in &prices[i + 1..] {
+            let (buy_dex, sell_dex, gross_profit) = if a <= b {
+                (a_dex, b_dex, *b - *a)
+            } else {
+                (b_dex, a_dex, *a - *b)
+            };
+            if gross_profit > best.as_ref().map_or(U256::zero(), |best| best.gross_profit) {
+                best = Some(ArbCandidate {
+                    buy_dex: buy_dex.clone(),
+                    sell_dex: sell_dex.clone(),
+                    gross_profit,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// Compares every pair of `quotes` and decides on the one netting the most,
+/// with `gas_cost` pricing the route buying on its first and selling on its
+/// second venue; ties go to the wider gross spread.
+///
+/// Spreads netting within `tie` of the best count as tied with it, and the
+/// one buying on the venue of lowest `priority` is decided on instead, see
+/// [`prefer_by_priority`]. While the best clears `min_profit`, only spreads
+/// that also clear it are considered.
+///
+/// `NoSpread` when every quote is equal.
+pub fn decide_best<'a>(
+    quotes: &[Quote<'a>],
+    gas_cost: impl Fn(&str, &str) -> U256,
+    min_profit: U256,
+    priority: impl Fn(&str) -> u32,
+    tie: U256,
+) -> Decision<'a> {
+    let mut spreads: Vec<Spread<'a>> = Vec::new();
+    for (i, a) in quotes.iter().enumerate() {
+        for b in &quotes[i + 1..] {
+            let (buy, sell) = if a.amount_out <= b.amount_out {
+                (a.dex, b.dex)
+            } else {
+                (b.dex, a.dex)
+            };
+            if let Some(spread) = compute_spread(*a, *b, gas_cost(buy, sell)) {
+                spreads.push(spread);
+            }
+        }
+    }
+    // Stable, so equal spreads keep the order they were compared in.
+    spreads.sort_by_key(|spread| std::cmp::Reverse((spread.net_profit, spread.gross)));
+    // Priority never trades an opportunity for a spread below the threshold.
+    let qualifying = spreads
+        .iter()
+        .take_while(|spread| spread.net_profit > min_profit)
+        .count();
+    let candidates = match qualifying {
+        0 => &spreads[..],
+        n => &spreads[..n],
+    };
+    let best = candidates
+        .get(prefer_by_priority(
+            candidates.iter().copied(),
+            priority,
+            tie,
+        ))
+        .copied();
+    match best {
+        None => Decision::NoSpread,
+        Some(spread) if spread.net_profit > min_profit => Decision::Opportunity(spread),
+        Some(spread) => Decision::BelowThreshold(spread),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(dex: &str, amount_out: u64) -> Quote<'_> {
+        Quote {
+            dex,
+            amount_out: U256::from(amount_out),
+            fee_bps: 0,
+        }
+    }
+
+    #[test]
+    fn equal_prices_are_no_spread() {
+        let a = quote("QuickSwap", 4_000_000_000);
+        let b = quote("SushiSwap", 4_000_000_000);
+        assert_eq!(compute_spread(a, b, U256::zero()), None);
+        assert_eq!(decide(a, b, U256::zero(), U256::zero()), Decision::NoSpread);
+    }
+
+    #[test]
+    fn spread_below_gas_nets_zero() {
+        let a = quote("QuickSwap", 4_000_000_000);
+        let b = quote("SushiSwap", 4_000_500_000);
+        let gas = U256::from(1_000_000);
+        let spread = compute_spread(a, b, gas).unwrap();
+        assert_eq!(spread.buy_dex, "QuickSwap");
+        assert_eq!(spread.sell_dex, "SushiSwap");
+        assert_eq!(spread.gross, U256::from(500_000));
+        assert_eq!(spread.net_profit, U256::zero());
+        assert_eq!(
+            decide(a, b, gas, U256::zero()),
+            Decision::BelowThreshold(spread)
+        );
+    }
+
+    #[test]
+    fn profit_exactly_at_threshold_is_below_it() {
+        let a = quote("QuickSwap", 4_010_000_000);
+        let b = quote("SushiSwap", 4_000_000_000);
+        let gas = U256::from(2_000_000);
+        let min_profit = U256::from(8_000_000);
+        let Decision::BelowThreshold(spread) = decide(a, b, gas, min_profit) else {
+            panic!("a profit equal to the threshold must not qualify");
+        };
+        assert_eq!(spread.buy_dex, "SushiSwap");
+        assert_eq!(spread.net_profit, min_profit);
+        assert!(matches!(
+            decide(a, b, gas, min_profit - 1),
+            Decision::Opportunity(_)
+        ));
+    }
+
+    #[test]
+    fn threshold_near_values_compare_exactly() {
+        let min_profit = usdc_to_units(5.0);
+        let gas = usdc_to_units(0.3);
+        let buy = quote("QuickSwap", 4_000_000_000);
+        for (sell, qualifies) in [
+            (4_005_300_000, false),
+            (4_005_300_001, true),
+            (4_005_299_999, false),
+        ] {
+            let decision = decide(buy, quote("SushiSwap", sell), gas, min_profit);
+            assert_eq!(
+                matches!(decision, Decision::Opportunity(_)),
+                qualifies,
+                "{}",
+                sell
+            );
+        }
+        // 0.1 + 0.2 is 0.30000000000000004 as f64; its shortest form keeps it at 0.3 USDC.
+        assert_eq!(usdc_to_units(0.1 + 0.2), U256::from(300_000));
+        assert_eq!(usdc_to_units(15.3), U256::from(15_300_000));
+        assert_eq!(usdc_to_units(0.0000019), U256::from(1));
+        assert_eq!(usdc_to_units(-1.0), U256::zero());
+    }
+
+    #[test]
+    fn large_18_decimal_amounts_keep_every_digit() {
+        let amount = "123456789.123456789123456789";
+        let units = parse_units(amount, WETH_DECIMALS).unwrap();
+        assert_eq!(
+            units,
+            U256::from_dec_str("123456789123456789123456789").unwrap()
+        );
+        assert_eq!(format_units(units, WETH_DECIMALS), amount);
+        // As f64 the amount loses its last digits.
+        assert_ne!(
+            format!("{}", units_to_weth(units)),
+            format_units(units, WETH_DECIMALS)
+        );
+
+        // One wei apart at this size is a spread f64 cannot see.
+        let a = Quote {
+            dex: "QuickSwap",
+            amount_out: units,
+            fee_bps: 0,
+        };
+        let b = Quote {
+            amount_out: units + 1,
+            dex: "SushiSwap",
+            ..a
+        };
+        assert_eq!(units_to_weth(a.amount_out), units_to_weth(b.amount_out));
+        let Decision::Opportunity(spread) = decide(a, b, U256::zero(), U256::zero()) else {
+            panic!("a one-wei spread must clear a zero threshold");
+        };
+        assert_eq!(spread.buy_dex, "QuickSwap");
+        assert_eq!(spread.net_profit, U256::one());
+    }
+
+    #[test]
+    fn both_legs_pay_their_fee() {
+        let fee = |dex, amount_out| Quote {
+            fee_bps: 30,
+            ..quote(dex, amount_out)
+        };
+        let quotes = [
+            fee("QuickSwap", 4_000_000_000),
+            fee("SushiSwap", 4_040_000_000),
+        ];
+        let gas = U256::from(1_000_000);
+        // 30 bps of 4000 and of 4040 USDC, truncated to whole units.
+        assert_eq!(leg_fee(quotes[0].amount_out, 30), U256::from(12_000_000));
+        assert_eq!(leg_fee(quotes[1].amount_out, 30), U256::from(12_120_000));
+        assert_eq!(
+            leg_fee(U256::from(4_000_003_333u64), 30),
+            U256::from(12_000_009)
+        );
+
+        let Decision::Opportunity(spread) = decide(quotes[0], quotes[1], gas, U256::zero()) else {
+            panic!("the spread outweighs both fees and gas");
+        };
+        assert_eq!(spread.gross, U256::from(40_000_000));
+        assert_eq!(
+            spread.net_profit,
+            U256::from(40_000_000 - 12_000_000 - 12_120_000 - 1_000_000)
+        );
+        assert_eq!(
+            decide_best(&quotes, |_, _| gas, U256::zero(), |_| 0, U256::zero()),
+            Decision::Opportunity(spread)
+        );
+    }
+
+    /// Equal to within float rounding.
+    fn close(actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() <= expected.abs() * 1e-9
+    }
+
+    /// A Uniswap V2 pair's `getAmountOut` charging `fee_bps` on the input.
+    fn v2_out(amount: U256, reserve_in: U256, reserve_out: U256, fee_bps: u32) -> U256 {
+        let with_fee = amount * U256::from(10_000 - fee_bps);
+        with_fee * reserve_out / (reserve_in * U256::from(10_000) + with_fee)
+    }
+
+    #[test]
+    fn probe_round_trip_recovers_the_pool_fee() {
+        let usdc = U256::from(2_500_000) * U256::exp10(6);
+        let weth = U256::from(1_000) * U256::exp10(18);
+        let probe = usdc_to_units(1.0);
+        for fee_bps in [30, 5] {
+            let bought = v2_out(probe, usdc, weth, fee_bps);
+            let back = v2_out(bought, weth, usdc, fee_bps);
+            assert_eq!(implied_fee_bps(probe, back), fee_bps);
+        }
+        assert_eq!(implied_fee_bps(probe, probe), 0);
+        assert_eq!(implied_fee_bps(U256::zero(), probe), 0);
+    }
+
+    #[test]
+    fn reserves_are_recovered_from_two_quotes() {
+        // 1000 WETH and 2.5M USDC at 30 bps: a marginal sale pays
+        // 0.997 · 2500 and 1 WETH pays 0.997 · 2.5M / (1000 + 0.997).
+        let (spot, one_out) = (2_492.5, 2_492_500.0 / 1_000.997);
+        let reserves = CpReserves::implied(spot, 1.0, one_out, 30).unwrap();
+        assert!(close(reserves.base, 1_000.0), "{:?}", reserves);
+        assert!(close(reserves.quote, 2_500_000.0), "{:?}", reserves);
+        assert!(close(reserves.liquidity(), 5_000_000.0));
+        // No price impact is no finite pool.
+        assert_eq!(CpReserves::implied(spot, 1.0, spot, 30), None);
+    }
+
+    #[test]
+    fn optimal_input_maximizes_the_cycle_profit() {
+        let buy = CpReserves {
+            base: 1_000.0,
+            quote: 2_500_000.0,
+        };
+        let sell = CpReserves {
+            base: 1_000.0,
+            quote: 2_600_000.0,
+        };
+        // x · y = k through both pools, 30 bps in and 5 bps out.
+        let profit = |x: f64| {
+            let bought = 0.997 * x * buy.base / (buy.quote + 0.997 * x);
+            0.9995 * bought * sell.quote / (sell.base + 0.9995 * bought) - x
+        };
+        let input = optimal_cycle_input(buy, 30, sell, 5).unwrap();
+        // (√(K·M) − M) / N, worked out by hand.
+        assert!((input - 22_596.481_329).abs() < 1e-3, "{}", input);
+        assert!((profit(input) - 407.153_519).abs() < 1e-3);
+        assert!(profit(input) > profit(input * 0.99));
+        assert!(profit(input) > profit(input * 1.01));
+        // The same pools the other way round lose on any amount.
+        assert_eq!(optimal_cycle_input(sell, 5, buy, 30), None);
+    }
+
+    #[test]
+    fn break_even_spread_covers_both_fees_and_gas() {
+        // 1 / (0.997 · 0.9995) − 1.
+        assert!((break_even_spread_bps(30, 5, 0.0, 10_000.0) - 35.107_825).abs() < 1e-5);
+        // 10 USDC of gas is another 10 bps of a 10,000 USDC trip, before fees.
+        assert!((break_even_spread_bps(30, 5, 10.0, 10_000.0) - 45.142_933).abs() < 1e-5);
+        assert_eq!(break_even_spread_bps(0, 0, 10.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn widest_of_four_dexes_need_not_be_the_first_two() {
+        let prices: Vec<(String, U256)> = [
+            ("QuickSwap", 4_000_000_000u64),
+            ("SushiSwap", 4_001_000_000),
+            ("ApeSwap", 3_990_000_000),
+            ("Dfyn", 4_020_000_000),
+        ]
+        .into_iter()
+        .map(|(dex, out)| (dex.to_string(), U256::from(out)))
+        .collect();
+        assert_eq!(
+            find_best_arbitrage(&prices),
+            Some(ArbCandidate {
+                buy_dex: "ApeSwap".into(),
+                sell_dex: "Dfyn".into(),
+                gross_profit: U256::from(30_000_000),
+            })
+        );
+
+        let flat = vec![("QuickSwap".to_string(), U256::from(4_000_000_000u64)); 4];
+        assert_eq!(find_best_arbitrage(&flat), None);
+        assert_eq!(find_best_arbitrage(&[]), None);
+    }
+
+    #[test]
+    fn best_of_four_dexes_need_not_be_the_first_two() {
+        let quotes = [
+            quote("QuickSwap", 4_000_000_000),
+            quote("SushiSwap", 4_001_000_000),
+            quote("ApeSwap", 3_990_000_000),
+            quote("Dfyn", 4_020_000_000),
+        ];
+        let gas = |_: &str, _: &str| U256::from(1_000_000);
+        let Decision::Opportunity(best) =
+            decide_best(&quotes, gas, U256::from(5_000_000), |_| 0, U256::zero())
+        else {
+            panic!("ApeSwap → Dfyn clears the threshold");
+        };
+        assert_eq!((best.buy_dex, best.sell_dex), ("ApeSwap", "Dfyn"));
+        assert_eq!(best.gross, U256::from(30_000_000));
+        assert_eq!(best.net_profit, U256::from(29_000_000));
+
+        // Below the threshold, the widest spread is still the one reported.
+        let Decision::BelowThreshold(best) =
+            decide_best(&quotes, gas, U256::from(50_000_000), |_| 0, U256::zero())
+        else {
+            panic!("no spread nets 50 USDC");
+        };
+        assert_eq!((best.buy_dex, best.sell_dex), ("ApeSwap", "Dfyn"));
+
+        let flat = [quote("QuickSwap", 4_000_000_000); 4];
+        assert_eq!(
+            decide_best(&flat, gas, U256::zero(), |_| 0, U256::zero()),
+            Decision::NoSpread
+        );
+    }
+}
@@ -0,0 +1,418 @@
+//! Replays recorded opportunities under different gas assumptions, and past
+//! blocks through an archive node.
+
+use anyhow::{Result, anyhow, bail};
+use ethers::core::types::{Address, U256};
+use ethers::providers::{Http, Provider};
+use futures::stream::{self, StreamExt};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use crate::arb::{self, Decision, Quote};
+use crate::config::{Config, DexKind, ReportCurrency};
+use crate::db::{self, BacktestHit, BacktestRun, BacktestTotals, DbPool};
+use crate::gas::GasCost;
+use crate::price::{self, PriceError, PriceSource};
+use crate::ratelimit::RateLimiter;
+use crate::scanner;
+
+/// Error text of a node that no longer holds the state of an old block.
+const PRUNED_STATE: &str = "missing trie node";
+
+/// Blocks replayed between two progress lines.
+const PROGRESS_EVERY: u64 = 100;
+
+/// Aggregate outcome of [`run_backtest`].
+#[derive(Debug, Clone)]
+pub struct BacktestResult {
+    /// Stored opportunities replayed.
+    pub opportunities_found: u64,
+    /// Opportunities still clearing `min_profit_usdc` at the supplied gas cost.
+    pub profitable_count: u64,
+    /// Sum of the re-netted profit over the profitable opportunities, in USDC.
+    pub total_profit_usdc: f64,
+    /// Share of stored opportunities that would not have been profitable.
+    pub false_positive_rate: f64,
+}
+
+/// Re-applies the profit filter to every row of `arbitrage_bot` with `gas_cost_usdc`.
+///
+/// The gross spread of a row is its stored net profit plus the gas cost it was
+/// netted with. Rows recorded before that cost was stored are treated as gross.
+pub fn run_backtest(
+    conn: &Connection,
+    gas_cost_usdc: f64,
+    min_profit_usdc: f64,
+) -> Result<BacktestResult> {
+    let mut stmt = conn.prepare(
+        "SELECT profit_usdc + COALESCE(gas_cost_usdc, 0) FROM arbitrage_bot
+         WHERE profit_usdc IS NOT NULL",
+    )?;
+    let gross = stmt
+        .query_map((), |row| row.get::<_, f64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut result = BacktestResult {
+        opportunities_found: gross.len() as u64,
+        profitable_count: 0,
+        total_profit_usdc: 0.0,
+        false_positive_rate: 0.0,
+    };
+    // Compared in USDC units, as the live threshold is.
+    let gas_cost = arb::usdc_to_units(gas_cost_usdc);
+    let min_profit = arb::usdc_to_units(min_profit_usdc);
+    for spread in gross {
+        let profit = arb::usdc_to_units(spread).checked_sub(gas_cost);
+        if let Some(profit) = profit.filter(|profit| *profit > min_profit) {
+            result.profitable_count += 1;
+            result.total_profit_usdc += arb::units_to_usdc(profit);
+        }
+    }
+    if result.opportunities_found > 0 {
+        let misses = result.opportunities_found - result.profitable_count;
+        result.false_positive_rate = misses as f64 / result.opportunities_found as f64;
+    }
+    Ok(result)
+}
+
+/// Which pair [`replay_blocks`] quotes, and over which blocks.
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    /// Chain to replay; the first of `[[chains]]` when unset.
+    pub chain_id: Option<u64>,
+    /// Label of the pair to replay, e.g. `WETH/USDC`; the chain's first when unset.
+    pub pair: Option<String>,
+    /// First block quoted.
+    pub from_block: u64,
+    /// Last block quoted, inclusive.
+    pub to_block: u64,
+    /// Blocks between two quoted ones; at least 1.
+    pub step: u64,
+    /// Tags the stored rows; derived from the chain, pair and blocks when
+    /// unset, so re-running the same command resumes it.
+    pub run_id: Option<String>,
+    /// Blocks quoted at once.
+    pub concurrency: usize,
+}
+
+/// Outcome of [`replay_blocks`], over every block its run has covered.
+#[derive(Debug, Clone)]
+pub struct ReplaySummary {
+    /// Run id the rows of `backtest_results` carry.
+    pub run_id: String,
+    /// Label of the replayed pair.
+    pub pair: String,
+    /// Symbol profits are stated in.
+    pub quote_symbol: String,
+    /// Blocks quoted, including those of earlier, interrupted invocations.
+    pub blocks_quoted: u64,
+    /// Opportunities stored for the run.
+    pub totals: BacktestTotals,
+}
+
+/// Venues and sizes of the replayed pair.
+struct Replay {
+    sources: Vec<Box<dyn PriceSource>>,
+    paths: Vec<Vec<Vec<Address>>>,
+    labels: Vec<Vec<String>>,
+    fee_bps: Vec<u32>,
+    priorities: Vec<u32>,
+    kinds: Vec<DexKind>,
+    trade_size: U256,
+    trade_size_base: f64,
+    quote_decimals: u32,
+    limiter: Option<RateLimiter>,
+}
+
+/// One venue's winning quote at a block.
+struct BlockQuote {
+    venue: usize,
+    amount_out: U256,
+    path: usize,
+}
+
+impl Replay {
+    /// Quotes every venue at `block`, leaving out those that cannot fill the
+    /// trade there.
+    ///
+    /// Fails on a pruned block, with a hint, and on a node error that
+    /// persists through one retry.
+    async fn quote_block(&self, block: u64) -> Result<Vec<BlockQuote>> {
+        let mut quotes = Vec::with_capacity(self.sources.len());
+        for (venue, source) in self.sources.iter().enumerate() {
+            let paths = &self.paths[venue];
+            let mut result = Err(PriceError::EmptyResponse);
+            for _ in 0..2 {
+                if let Some(limiter) = &self.limiter {
+                    limiter.acquire(paths.len() as u32).await;
+                }
+                result =
+                    price::fetch_best_price(source.as_ref(), self.trade_size, paths, Some(block))
+                        .await;
+                match &result {
+                    Err(err) if err.is_transient() && !pruned(err) => {}
+                    _ => break,
+                }
+            }
+            match result {
+                Ok((amount_out, _)) if amount_out.is_zero() => {}
+                Ok((amount_out, path)) => quotes.push(BlockQuote {
+                    venue,
+                    amount_out,
+                    path,
+                }),
+                Err(err) if pruned(&err) => bail!(
+                    "block {}: the node no longer holds its state ({}); point rpc_url at an \
+                     archive node or replay more recent blocks",
+                    block,
+                    PRUNED_STATE
+                ),
+                Err(err) if err.is_unfillable() => {
+                    debug!(block, dex = source.name(), %err, "Venue cannot fill the trade at this block");
+                }
+                Err(err) => bail!(
+                    "block {}: {} quote failed: {}; run the same command again to resume",
+                    block,
+                    source.name(),
+                    err
+                ),
+            }
+        }
+        Ok(quotes)
+    }
+
+    /// The best spread between `quotes`, decided as the scanner decides it:
+    /// venue fees on both legs, the configured gas cost and `min_profit_usdc`.
+    fn decide(&self, cfg: &Config, block: u64, quotes: &[BlockQuote]) -> Option<BacktestHit<'_>> {
+        let settings = &cfg.settings;
+        let by_dex: HashMap<&str, &BlockQuote> = quotes
+            .iter()
+            .map(|q| (self.sources[q.venue].name(), q))
+            .collect();
+        let gas = match &settings.gas_model {
+            // Historic gas prices are not replayed; `gas_price_gwei` stands in.
+            Some(model) => {
+                let legs = quotes
+                    .iter()
+                    .map(|q| {
+                        let hops = self.paths[q.venue][q.path].len().saturating_sub(1);
+                        (
+                            self.sources[q.venue].name(),
+                            model.leg_units(self.kinds[q.venue], hops),
+                        )
+                    })
+                    .collect();
+                GasCost::routed(settings, model, legs, settings.gas_price_gwei)
+            }
+            None => GasCost::flat(settings),
+        };
+        let candidates: Vec<Quote> = quotes
+            .iter()
+            .map(|q| Quote {
+                dex: self.sources[q.venue].name(),
+                amount_out: q.amount_out,
+                fee_bps: self.fee_bps[q.venue],
+            })
+            .collect();
+        let min_profit = match settings.report_currency {
+            ReportCurrency::Base if !quotes.is_empty() => {
+                let quoted: f64 = quotes
+                    .iter()
+                    .map(|q| arb::units_to_amount(q.amount_out, self.quote_decimals))
+                    .sum();
+                settings.min_profit_usdc * quoted / (quotes.len() as f64 * self.trade_size_base)
+            }
+            _ => settings.min_profit_usdc,
+        };
+        let decision = arb::decide_best(
+            &candidates,
+            |buy, sell| gas.quote_units(buy, sell, self.quote_decimals),
+            arb::amount_to_units(min_profit, self.quote_decimals),
+            |dex| {
+                self.sources
+                    .iter()
+                    .position(|source| source.name() == dex)
+                    .map_or(0, |i| self.priorities[i])
+            },
+            arb::amount_to_units(settings.priority_tie_threshold_usdc, self.quote_decimals),
+        );
+        let Decision::Opportunity(spread) = decision else {
+            return None;
+        };
+        let label = |dex: &str| {
+            let quote = by_dex.get(dex)?;
+            Some(self.labels[quote.venue][quote.path].as_str())
+        };
+        Some(BacktestHit {
+            block_number: block,
+            buy_dex: spread.buy_dex,
+            sell_dex: spread.sell_dex,
+            buy_path: label(spread.buy_dex)?,
+            sell_path: label(spread.sell_dex)?,
+            spread_usdc: arb::units_to_amount(spread.gross, self.quote_decimals),
+            gas_cost_usdc: gas.usdc(spread.buy_dex, spread.sell_dex),
+            profit_usdc: arb::units_to_amount(spread.net_profit, self.quote_decimals),
+        })
+    }
+}
+
+/// Whether `err` is the node refusing state it has pruned.
+fn pruned(err: &PriceError) -> bool {
+    err.to_string().to_ascii_lowercase().contains(PRUNED_STATE)
+}
+
+/// Quotes the venues of one pair at every `step`th block of a range with the
+/// block set on each `eth_call`, and stores the blocks clearing the profit
+/// threshold in `backtest_results`.
+///
+/// Progress is checkpointed after every block, so an interrupted run resumes
+/// where it stopped. Aggregators only quote the latest state and are left out.
+pub async fn replay_blocks(
+    cfg: &Config,
+    pool: &DbPool,
+    opts: &ReplayOptions,
+) -> Result<ReplaySummary> {
+    if opts.to_block < opts.from_block {
+        bail!("--to-block must not be below --from-block");
+    }
+    let chain = match opts.chain_id {
+        Some(id) => cfg
+            .chains
+            .iter()
+            .find(|chain| chain.chain_id == id)
+            .ok_or_else(|| anyhow!("chain {} is not configured", id))?,
+        None => cfg
+            .chains
+            .first()
+            .ok_or_else(|| anyhow!("no chain configured"))?,
+    };
+    let tokens = match &opts.pair {
+        Some(label) => chain
+            .tokens
+            .iter()
+            .find(|tokens| tokens.label() == label)
+            .ok_or_else(|| {
+                anyhow!(
+                    "pair {} is not configured on chain {}",
+                    label,
+                    chain.chain_id
+                )
+            })?,
+        None => chain
+            .tokens
+            .first()
+            .ok_or_else(|| anyhow!("chain {} has no pair", chain.chain_id))?,
+    };
+    let mut dex = cfg.scan_matrix.venues(&chain.dex, tokens);
+    dex.retain(|entry| {
+        let latest_only = entry.kind == DexKind::Aggregator;
+        if latest_only {
+            warn!(dex = %entry.name, "Aggregators only quote the latest block; leaving it out of the backtest");
+        }
+        !latest_only
+    });
+    if dex.len() < 2 {
+        bail!(
+            "pair {} has fewer than two venues to compare",
+            tokens.label()
+        );
+    }
+
+    let provider = Arc::new(Provider::<Http>::try_from(chain.rpc_url.clone())?);
+    let abi = match &cfg.abi_path {
+        Some(path) => price::load_router_abi(path)?,
+        None => price::default_router_abi(),
+    };
+    let base_decimals = match tokens.weth_decimals {
+        Some(decimals) => decimals,
+        None => price::token_decimals(tokens.weth.parse()?, provider.clone()).await?,
+    };
+    let quote_decimals = match tokens.usdc_decimals {
+        Some(decimals) => decimals,
+        None => price::token_decimals(tokens.usdc.parse()?, provider.clone()).await?,
+    };
+    let trade_size = cfg.settings.trade_size.to_units(base_decimals)?;
+    let paths = scanner::quote_paths(tokens, &dex)?;
+    let labels: Vec<Vec<String>> = paths
+        .iter()
+        .map(|candidates| candidates.iter().map(|p| price::path_label(p)).collect())
+        .collect();
+    let replay = Replay {
+        sources: price::build_sources(&dex, provider, &abi, chain.chain_id)?,
+        paths,
+        labels,
+        fee_bps: dex.iter().map(|entry| entry.fee_bps).collect(),
+        priorities: dex.iter().map(|entry| entry.priority).collect(),
+        kinds: dex.iter().map(|entry| entry.kind).collect(),
+        trade_size,
+        trade_size_base: arb::units_to_amount(trade_size, base_decimals),
+        quote_decimals,
+        limiter: chain
+            .rpc_rate_limit
+            .or(cfg.max_requests_per_sec)
+            .map(RateLimiter::new),
+    };
+
+    let mut conn = pool.get()?;
+    let run = db::start_backtest_run(
+        &conn,
+        &BacktestRun {
+            run_id: opts.run_id.clone().unwrap_or_else(|| {
+                format!(
+                    "{}:{}:{}-{}:{}",
+                    chain.chain_id,
+                    tokens.label(),
+                    opts.from_block,
+                    opts.to_block,
+                    opts.step
+                )
+            }),
+            chain_id: chain.chain_id,
+            pair: tokens.label().to_string(),
+            from_block: opts.from_block,
+            to_block: opts.to_block,
+            step: opts.step,
+            next_block: opts.from_block,
+        },
+    )?;
+    if run.next_block > run.from_block && run.next_block <= run.to_block {
+        info!(run_id = %run.run_id, next_block = run.next_block, "Resuming backtest from its checkpoint");
+    }
+
+    let blocks = (run.next_block..=run.to_block).step_by(run.step as usize);
+    let mut quoted = stream::iter(blocks)
+        .map(|block| {
+            let replay = &replay;
+            async move { (block, replay.quote_block(block).await) }
+        })
+        .buffered(opts.concurrency.max(1));
+    let mut done = (run.next_block - run.from_block) / run.step;
+    while let Some((block, quotes)) = quoted.next().await {
+        let quotes = quotes?;
+        let hit = replay.decide(cfg, block, &quotes);
+        if let Some(hit) = &hit {
+            info!(
+                block,
+                buy_dex = hit.buy_dex,
+                sell_dex = hit.sell_dex,
+                profit_usdc = hit.profit_usdc,
+                "Backtest opportunity"
+            );
+        }
+        db::record_backtest_block(&mut conn, &run, block + run.step, hit.as_ref())?;
+        done += 1;
+        if done.is_multiple_of(PROGRESS_EVERY) {
+            info!(block, blocks_quoted = done, "Backtest progress");
+        }
+    }
+
+    Ok(ReplaySummary {
+        totals: db::backtest_totals(&conn, &run.run_id)?,
+        run_id: run.run_id,
+        pair: run.pair,
+        quote_symbol: tokens.quote_symbol().to_string(),
+        blocks_quoted: done,
+    })
+}
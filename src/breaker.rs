@@ -0,0 +1,148 @@
+//! Circuit breaker that pauses scanning while the RPC node is failing, and the
+//! debounced RPC up/down state behind health alerts.
+
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// State of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Ticks run normally.
+    Closed,
+    /// Too many consecutive failures; ticks are paused.
+    Open,
+    /// The pause elapsed; the next tick is a probe deciding whether to close again.
+    HalfOpen,
+}
+
+/// Counts consecutive failed ticks and opens after a threshold.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    /// Consecutive failures since the last success.
+    pub failure_count: u32,
+    /// Current state.
+    pub state: BreakerState,
+    /// When the most recent failure was recorded.
+    pub last_failure: Instant,
+    threshold: u32,
+    open_duration: Duration,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed breaker that opens after `threshold` consecutive failures
+    /// and stays open for `open_duration`.
+    pub fn new(threshold: u32, open_duration: Duration) -> Self {
+        Self {
+            failure_count: 0,
+            state: BreakerState::Closed,
+            last_failure: Instant::now(),
+            threshold,
+            open_duration,
+        }
+    }
+
+    /// How much longer the breaker stays open, or `None` if ticks may run.
+    pub fn open_remaining(&self) -> Option<Duration> {
+        match self.state {
+            BreakerState::Open => Some(
+                self.open_duration
+                    .saturating_sub(self.last_failure.elapsed()),
+            ),
+            BreakerState::Closed | BreakerState::HalfOpen => None,
+        }
+    }
+
+    /// Moves an open breaker whose pause has elapsed to half-open.
+    pub fn try_half_open(&mut self) {
+        if self.state == BreakerState::Open && self.last_failure.elapsed() >= self.open_duration {
+            self.transition(BreakerState::HalfOpen);
+        }
+    }
+
+    /// Records a healthy tick, closing the breaker if it was probing.
+    pub fn record_success(&mut self) {
+        self.failure_count = 0;
+        if self.state != BreakerState::Closed {
+            self.transition(BreakerState::Closed);
+        }
+    }
+
+    /// Records a failed tick, opening the breaker at the threshold or on a failed probe.
+    pub fn record_failure(&mut self) {
+        self.failure_count += 1;
+        self.last_failure = Instant::now();
+        let open = match self.state {
+            BreakerState::Closed => self.failure_count >= self.threshold,
+            BreakerState::HalfOpen => true,
+            BreakerState::Open => false,
+        };
+        if open {
+            self.transition(BreakerState::Open);
+        }
+    }
+
+    fn transition(&mut self, to: BreakerState) {
+        warn!(
+            from = ?self.state,
+            to = ?to,
+            failures = self.failure_count,
+            "Circuit breaker {:?} → {:?}",
+            self.state,
+            to
+        );
+        self.state = to;
+    }
+}
+
+/// A debounced change of the RPC's health, reported by [`RpcWatch::record`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcTransition {
+    /// The RPC failed this many ticks in a row.
+    Down(u32),
+    /// The RPC answered this many ticks in a row after being down.
+    Up(u32),
+}
+
+/// Tracks consecutive failed and successful ticks and reports each switch
+/// between up and down once, after `down_after` failures or `up_after` successes.
+#[derive(Debug, Clone)]
+pub struct RpcWatch {
+    down: bool,
+    failures: u32,
+    successes: u32,
+    down_after: u32,
+    up_after: u32,
+}
+
+impl RpcWatch {
+    /// Starts up. `down_after` of 0 never reports the RPC down.
+    pub fn new(down_after: u32, up_after: u32) -> Self {
+        Self {
+            down: false,
+            failures: 0,
+            successes: 0,
+            down_after,
+            up_after: up_after.max(1),
+        }
+    }
+
+    /// Records one tick, returning the transition it completes, if any.
+    pub fn record(&mut self, rpc_ok: bool) -> Option<RpcTransition> {
+        if rpc_ok {
+            self.failures = 0;
+            self.successes += 1;
+            if self.down && self.successes >= self.up_after {
+                self.down = false;
+                return Some(RpcTransition::Up(self.successes));
+            }
+        } else {
+            self.successes = 0;
+            self.failures += 1;
+            if !self.down && self.down_after > 0 && self.failures >= self.down_after {
+                self.down = true;
+                return Some(RpcTransition::Down(self.failures));
+            }
+        }
+        None
+    }
+}
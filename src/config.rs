@@ -0,0 +1,1695 @@
+//! `config.toml` schema and loading.
+
+use anyhow::{Result, anyhow, bail};
+use ethers::core::types::{Address, U256};
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::{env, fs};
+
+use crate::aggregator::AggregatorConfig;
+use crate::allowance::ApprovalMode;
+use crate::anomaly::AnomalyFilterConfig;
+use crate::arb;
+use crate::error::{BotError, BotResult};
+use crate::gas::{GasConfig, GasModelConfig};
+use crate::logging::LogFormat;
+use crate::notify::{TelegramConfig, WebhookConfig};
+use crate::output::OutputMode;
+use crate::pacing::{AdaptiveIntervalConfig, AdaptiveRefreshConfig};
+use crate::price::TradeSizeSearchConfig;
+use crate::retention::RetentionConfig;
+use crate::simulate::SimulationConfig;
+use crate::store::Backend;
+use crate::throttle::NotifyThrottleConfig;
+
+/// Which on-chain interface a DEX entry speaks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DexKind {
+    /// Uniswap V2 router: `getAmountsOut(amountIn, path)`.
+    #[default]
+    UniswapV2,
+    /// Uniswap V3 QuoterV2: `quoteExactInputSingle` at each of `fee_tiers`.
+    UniswapV3,
+    /// Balancer V2 vault: `queryBatchSwap` through the pool `pool_id`.
+    BalancerV2,
+    /// Curve-style pool: `get_dy(i, j, dx)` between the coins at `coin_indices`.
+    Curve,
+    /// Solidly-fork router: `getAmountsOut(amountIn, routes)` with stable/volatile hops.
+    Solidly,
+    /// 0x or 1inch quote API, configured under `aggregator`.
+    Aggregator,
+}
+
+/// One venue to quote (`[[dex]]`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DexEntry {
+    /// Display name, used in logs and stored records.
+    pub name: String,
+    /// Interface of the contract at `address`; defaults to `uniswap_v2`.
+    #[serde(default)]
+    pub kind: DexKind,
+    /// Contract queried for quotes (the router for `uniswap_v2` and `solidly`, the QuoterV2
+    /// for `uniswap_v3`, the vault for `balancer_v2`, the pool for `curve`, the
+    /// aggregator's router for `aggregator`).
+    pub address: String,
+    /// Pool fee tiers to quote for `uniswap_v3` (500, 3000, 10000); the best is used.
+    #[serde(default)]
+    pub fee_tiers: Vec<u32>,
+    /// Pool to swap through for `balancer_v2` (32-byte hex id).
+    pub pool_id: Option<String>,
+    /// Pool coin indices of WETH and USDC, in that order, for `curve`.
+    pub coin_indices: Option<[u32; 2]>,
+    /// Pool type for `solidly`; both stable and volatile are quoted when absent.
+    pub stable: Option<bool>,
+    /// Quote API for `aggregator` (`[dex.aggregator]`).
+    pub aggregator: Option<AggregatorConfig>,
+    /// Full WETH → … → USDC token path quoted on this venue instead of the
+    /// shared direct/`path_via` candidates.
+    pub path: Option<Vec<String>>,
+    /// Trading fee per leg, in basis points of the leg's USDC amount, that the
+    /// venue's quote does not already include; on-chain quotes usually net the
+    /// pool fee out, so this defaults to 0.
+    #[serde(default)]
+    pub fee_bps: u32,
+    /// Swap fee the venue's pools take inside its quotes, in basis points per
+    /// leg (30 for QuickSwap and SushiSwap); probed at startup when absent.
+    ///
+    /// Only informs the break-even spread, the recorded per-leg fees and the
+    /// analytic optimal size; profit already nets it out through the quotes.
+    pub pool_fee_bps: Option<u32>,
+    /// ABI file quoting this venue instead of its kind's default, e.g. for a
+    /// router fork with a custom interface.
+    pub abi_path: Option<String>,
+    /// Rank of the venue as a buy leg when spreads tie within
+    /// `priority_tie_threshold_usdc`; lower is preferred, e.g. for cheaper gas
+    /// or faster inclusion. Defaults to 0.
+    #[serde(default)]
+    pub priority: u32,
+}
+
+/// Accepts either the `[[dex]]` array or the original `[dex]` table of
+/// `name = "router address"` pairs, which are treated as `uniswap_v2` entries.
+fn deserialize_dex<'de, D>(deserializer: D) -> Result<Vec<DexEntry>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DexSection {
+        Entries(Vec<DexEntry>),
+        Routers(BTreeMap<String, String>),
+    }
+
+    Ok(match DexSection::deserialize(deserializer)? {
+        DexSection::Entries(entries) => entries,
+        DexSection::Routers(routers) => routers
+            .into_iter()
+            .map(|(name, address)| DexEntry {
+                name,
+                kind: DexKind::UniswapV2,
+                address,
+                fee_tiers: Vec::new(),
+                pool_id: None,
+                coin_indices: None,
+                stable: None,
+                aggregator: None,
+                path: None,
+                fee_bps: 0,
+                pool_fee_bps: None,
+                abi_path: None,
+                priority: 0,
+            })
+            .collect(),
+    })
+}
+
+/// A traded pair: the base token (WETH) is sold for the quote token (USDC).
+///
+/// Prices, profits and the `*_usdc` settings are all in the quote token, at its
+/// own decimals, so a USDT or DAI pair needs no code changes.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TokenPair {
+    /// Label used in logs and stored records; `"WETH/USDC"` when absent, or
+    /// `"WETH/<symbol>"` with a `quote_token`.
+    pub name: Option<String>,
+    /// WETH token address.
+    pub weth: String,
+    /// USDC token address; filled in from `quote_token` when that is set instead.
+    #[serde(default)]
+    pub usdc: String,
+    /// The quote token in full, replacing `usdc`, `usdc_decimals` and the
+    /// label's quote symbol.
+    pub quote_token: Option<QuoteToken>,
+    /// Intermediate tokens for an alternative routed path, e.g. `[WMATIC]`.
+    ///
+    /// When set, both the direct and the routed path are quoted on every DEX
+    /// and the better output is used.
+    #[serde(default)]
+    pub path_via: Vec<String>,
+    /// Decimals of the base token; read from its `decimals()` when absent.
+    pub weth_decimals: Option<u32>,
+    /// Decimals of the quote token; read from its `decimals()` when absent.
+    pub usdc_decimals: Option<u32>,
+    /// Chainlink aggregator quoting WETH in USD; quotes are cross-checked when set.
+    /// Falls back to the chain's `chainlink_feeds` entry for the base symbol.
+    pub oracle_feed: Option<String>,
+}
+
+impl TokenPair {
+    /// The configured `name`, or `"WETH/USDC"`.
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or("WETH/USDC")
+    }
+
+    /// Symbol of the base token: the part of [`Self::label`] before the `/`.
+    pub fn base_symbol(&self) -> &str {
+        self.label().split('/').next().unwrap_or_default()
+    }
+
+    /// Symbol of the quote token: that of `quote_token`, else the part of
+    /// [`Self::label`] after the `/`, or `"USDC"` when it has none.
+    pub fn quote_symbol(&self) -> &str {
+        if let Some(quote) = &self.quote_token {
+            return &quote.symbol;
+        }
+        self.label()
+            .split_once('/')
+            .map_or("USDC", |(_, quote)| quote)
+    }
+}
+
+/// Quote token of a pair (`quote_token = { address, symbol, decimals }`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct QuoteToken {
+    /// Token address.
+    pub address: String,
+    /// Symbol shown with prices and profits, e.g. `"USDT"`.
+    pub symbol: String,
+    /// Token decimals, e.g. 6 for USDT or 18 for DAI.
+    pub decimals: u8,
+}
+
+/// EIP-155 id of Polygon PoS, assumed for the single-chain config form.
+pub const POLYGON_CHAIN_ID: u64 = 137;
+
+/// One chain to scan (`[[chains]]`); each of its pairs runs in its own task.
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct ChainConfig {
+    /// EIP-155 chain id, stored with every record.
+    pub chain_id: u64,
+    /// JSON-RPC endpoint of this chain.
+    pub rpc_url: String,
+    /// Venues on this chain (`[[chains.dex]]`).
+    #[serde(deserialize_with = "deserialize_dex")]
+    pub dex: Vec<DexEntry>,
+    /// Pairs quoted on every venue (`[[chains.tokens]]`).
+    pub tokens: Vec<TokenPair>,
+    /// Chainlink USD feed per token symbol (`[chains.chainlink_feeds]`), used for
+    /// pairs without their own `oracle_feed`.
+    #[serde(default)]
+    pub chainlink_feeds: BTreeMap<String, String>,
+    /// Requests a second allowed to `rpc_url`, replacing the top-level
+    /// `max_requests_per_sec` for this chain.
+    pub rpc_rate_limit: Option<u32>,
+}
+
+// Hand-written so an API key in `rpc_url` never reaches logs through `{:?}`.
+impl fmt::Debug for ChainConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainConfig")
+            .field("chain_id", &self.chain_id)
+            .field("rpc_url", &redact_url(&self.rpc_url))
+            .field("dex", &self.dex)
+            .field("tokens", &self.tokens)
+            .field("chainlink_feeds", &self.chainlink_feeds)
+            .field("rpc_rate_limit", &self.rpc_rate_limit)
+            .finish()
+    }
+}
+
+/// `value` of the config field `field` as an address.
+pub fn parse_address(field: &str, value: &str) -> BotResult<Address> {
+    value
+        .parse()
+        .map_err(|err| BotError::Config(format!("{}: invalid address {}: {}", field, value, err)))
+}
+
+/// `url` cut after its host, since providers put API keys in the path, query
+/// or credentials: `https://host/<redacted>`.
+pub fn redact_url(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return "<redacted>".into();
+    };
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let authority = &rest[..end];
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    // Nothing but the host, or the bare `/` a parsed URL renders with.
+    if host == authority && matches!(&rest[end..], "" | "/") {
+        return url.to_string();
+    }
+    format!("{}://{}/<redacted>", scheme, host)
+}
+
+impl ChainConfig {
+    /// Feed cross-checking `pair`: its `oracle_feed`, else the feed of its base symbol.
+    pub fn oracle_feed<'a>(&'a self, pair: &'a TokenPair) -> Option<&'a str> {
+        pair.oracle_feed.as_deref().or_else(|| {
+            self.chainlink_feeds
+                .get(pair.base_symbol())
+                .map(String::as_str)
+        })
+    }
+}
+
+/// Name of the single job run when no `[[jobs]]` are configured.
+pub const DEFAULT_JOB: &str = "default";
+
+/// One independently tuned group of scanners (`[[jobs]]`): a pair and venue
+/// subset with its own thresholds, tagged onto its logs and metrics.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobConfig {
+    /// Name in the `job` log field and the `job_name` metric label.
+    pub name: String,
+    /// Chain the job scans; every configured chain when absent.
+    pub chain_id: Option<u64>,
+    /// Labels of the pairs scanned, as in [`TokenPair::label`]; every pair when empty.
+    #[serde(default)]
+    pub pairs: Vec<String>,
+    /// Names of the venues compared; every venue of the chain when empty.
+    #[serde(default)]
+    pub dex: Vec<String>,
+    /// Replaces `settings.min_profit_usdc` for this job.
+    pub min_profit_usdc: Option<f64>,
+    /// Replaces `settings.trade_size` for this job.
+    pub trade_size: Option<TradeSize>,
+    /// Replaces `settings.refresh_rate` for this job.
+    pub refresh_rate: Option<u64>,
+}
+
+impl JobConfig {
+    /// Whether the job scans `chain`.
+    pub fn covers_chain(&self, chain: &ChainConfig) -> bool {
+        self.chain_id.is_none_or(|id| id == chain.chain_id)
+    }
+
+    /// Whether the job scans `pair`.
+    pub fn covers_pair(&self, pair: &TokenPair) -> bool {
+        self.pairs.is_empty() || self.pairs.iter().any(|label| label == pair.label())
+    }
+
+    /// The venues of `chain` the job compares.
+    pub fn venues(&self, chain: &ChainConfig) -> Vec<DexEntry> {
+        chain
+            .dex
+            .iter()
+            .filter(|entry| self.dex.is_empty() || self.dex.contains(&entry.name))
+            .cloned()
+            .collect()
+    }
+
+    /// Overwrites `settings` with the job's overrides.
+    pub fn apply(&self, settings: &mut BotSettings) {
+        if let Some(min_profit_usdc) = self.min_profit_usdc {
+            settings.min_profit_usdc = min_profit_usdc;
+        }
+        if let Some(trade_size) = &self.trade_size {
+            settings.trade_size = trade_size.clone();
+        }
+        if let Some(refresh_rate) = self.refresh_rate {
+            settings.refresh_rate = refresh_rate;
+        }
+    }
+}
+
+/// One venue quoting one pair, as named in `[scan_matrix]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScanCombo {
+    /// Name of the venue, as in `[[dex]]`.
+    pub dex: String,
+    /// Label of the pair, as in [`TokenPair::label`].
+    pub pair: String,
+}
+
+/// The `(dex, pair)` combinations scanned (`[scan_matrix]`), to prune routes
+/// without liquidity; every venue quotes every pair when both lists are empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ScanMatrix {
+    /// Only these combinations are scanned when not empty.
+    #[serde(default)]
+    pub allow: Vec<ScanCombo>,
+    /// Combinations never scanned, even when allowed.
+    #[serde(default)]
+    pub deny: Vec<ScanCombo>,
+}
+
+impl ScanMatrix {
+    /// Whether venue `dex` quotes `pair`.
+    pub fn includes(&self, dex: &str, pair: &TokenPair) -> bool {
+        let matches = |combo: &ScanCombo| combo.dex == dex && combo.pair == pair.label();
+        (self.allow.is_empty() || self.allow.iter().any(matches)) && !self.deny.iter().any(matches)
+    }
+
+    /// The venues of `dex` that quote `pair`.
+    pub fn venues(&self, dex: &[DexEntry], pair: &TokenPair) -> Vec<DexEntry> {
+        dex.iter()
+            .filter(|entry| self.includes(&entry.name, pair))
+            .cloned()
+            .collect()
+    }
+}
+
+/// `trade_size`: an amount of the base token such as `"1.5"`, or, as older
+/// configs have it, a bare integer of raw units (wei for WETH).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum TradeSize {
+    /// Raw token units.
+    Units(u64),
+    /// Whole tokens as a decimal string.
+    Tokens(String),
+}
+
+impl TradeSize {
+    /// Raw units for a base token of `decimals` decimals; a decimal string with
+    /// more fractional digits than that is rejected.
+    pub fn to_units(&self, decimals: u32) -> BotResult<U256> {
+        match self {
+            Self::Units(units) => Ok(U256::from(*units)),
+            Self::Tokens(amount) => arb::parse_units(amount.trim(), decimals)
+                .map_err(|err| BotError::Config(format!("settings.trade_size: {}", err))),
+        }
+    }
+}
+
+/// `report_currency`: the token profits are thresholded and recorded in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportCurrency {
+    /// The pair's quote token, e.g. USDC.
+    #[default]
+    Quote,
+    /// The pair's base token, e.g. WETH, converted at the tick's quoted price.
+    Base,
+}
+
+impl fmt::Display for TradeSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Units(units) => write!(f, "{} units", units),
+            Self::Tokens(amount) => write!(f, "{:?}", amount),
+        }
+    }
+}
+
+/// Thresholds and timing of the polling loop (`[settings]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct BotSettings {
+    /// Minimum net profit, in USDC, for a spread to be recorded; in the base
+    /// token when `report_currency = "base"`.
+    pub min_profit_usdc: f64,
+    /// Token `min_profit_usdc` is read in and recorded profits are reported in,
+    /// next to the USDC figures.
+    #[serde(default)]
+    pub report_currency: ReportCurrency,
+    /// Amount of the base token quoted each tick, see [`TradeSize`].
+    pub trade_size: TradeSize,
+    /// Searches for the most profitable size of every opportunity and logs it
+    /// next to `trade_size`; off when absent.
+    pub trade_size_search: Option<TradeSizeSearchConfig>,
+    /// Candidate sizes in USDC, e.g. `[100, 500, 1000, 5000]`, quoted as round
+    /// trips across every tick's widest spread; the most profitable one is logged
+    /// and stored with recorded opportunities. Off when empty.
+    #[serde(default)]
+    pub trade_size_sweep: Vec<f64>,
+    /// Price of the base token, in the quote token, at which an LP position was
+    /// entered; every quote then logs and stores the position's impermanent loss.
+    pub lp_entry_price: Option<f64>,
+    /// Flat gas cost subtracted from every spread, in USDC.
+    pub est_gas_cost_usdc: f64,
+    /// Gas units of one arbitrage round trip, used for scoring.
+    #[serde(default = "default_est_gas_units")]
+    pub est_gas_units: u64,
+    /// Gas price in gwei, used for scoring.
+    #[serde(default = "default_gas_price_gwei")]
+    pub gas_price_gwei: f64,
+    /// MATIC price in USDC, used for scoring; replaced by the live price with
+    /// `matic_price_dex`.
+    #[serde(default = "default_matic_price_usdc")]
+    pub matic_price_usdc: f64,
+    /// `uniswap_v2` venue quoting 1 WMATIC into the pair's quote token for a live
+    /// `matic_price_usdc`; the flat gas cost is then `est_gas_units` at
+    /// `gas_price_gwei` in place of `est_gas_cost_usdc`.
+    pub matic_price_dex: Option<String>,
+    /// Wrapped MATIC quoted by `matic_price_dex`.
+    #[serde(default = "default_wmatic")]
+    pub wmatic: String,
+    /// Ticks a fetched MATIC price is reused for.
+    #[serde(default = "default_matic_price_cache_ticks")]
+    pub matic_price_cache_ticks: u32,
+    /// Prices each route by its DEX kinds and hop counts at the live gas price,
+    /// replacing `est_gas_cost_usdc` and `est_gas_units`; flat costs when absent.
+    pub gas_model: Option<GasModelConfig>,
+    /// Seconds between ticks.
+    pub refresh_rate: u64,
+    /// Volatility-driven interval replacing `refresh_rate`; fixed pacing when absent.
+    pub adaptive_interval: Option<AdaptiveIntervalConfig>,
+    /// Opportunity-driven delay replacing `refresh_rate`; fixed pacing when absent.
+    pub adaptive_refresh: Option<AdaptiveRefreshConfig>,
+    /// Largest deviation, in percent, of either leg's quote from `oracle_feed`.
+    #[serde(default = "default_max_oracle_deviation_pct")]
+    pub max_oracle_deviation_pct: f64,
+    /// When set, the TWAP spread over this many seconds must also clear `min_profit_usdc`.
+    pub twap_window_secs: Option<u64>,
+    /// Stores every quote of every tick in `price_history`, in one transaction
+    /// per tick; `twap_window_secs` reads them.
+    #[serde(default = "default_record_prices")]
+    pub record_prices: bool,
+    /// Venues whose pool, as implied by their quotes, holds less than this many
+    /// USDC are left out of the tick's comparison; no check when absent.
+    pub min_pool_liquidity_usdc: Option<f64>,
+    /// Account the swaps of the top opportunity are gas-estimated from with
+    /// `eth_estimateGas`, replacing the modelled cost; it must hold the tokens
+    /// and approvals. No estimate is made when absent.
+    pub estimate_gas_from: Option<String>,
+    /// Quotes every venue at the block read at the start of the tick instead of
+    /// the latest block at each call.
+    #[serde(default)]
+    pub pin_quote_block: bool,
+    /// Flags ticks that break from their recent spreads and quotes; off when absent.
+    pub anomaly_filter: Option<AnomalyFilterConfig>,
+    /// Executes only opportunities whose spread has been widening over the
+    /// pair's last five ticks; closing and flat ones are still recorded.
+    #[serde(default)]
+    pub require_rising_trend: bool,
+    /// Ticks after startup that quote and log as usual but record, notify and
+    /// execute nothing, while rolling state such as `notify_throttle` fills.
+    #[serde(default)]
+    pub warmup_ticks: u64,
+    /// Quotes, compares, logs and notifies as usual but never writes to
+    /// `arbitrage_bot`, and executes nothing.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Under `dry_run`, stores the opportunities in `dry_run_opportunities`
+    /// instead of dropping them.
+    #[serde(default)]
+    pub dry_run_table: bool,
+    /// Spreads netting within this many USDC of the best count as tied, and
+    /// the one buying on the venue of lowest `priority` wins; 0 ties only
+    /// equal profits.
+    #[serde(default)]
+    pub priority_tie_threshold_usdc: f64,
+    /// Dry-runs both swaps of an opportunity with `eth_call` before recording it;
+    /// needs a `[simulation]` section.
+    #[serde(default)]
+    pub simulate_before_record: bool,
+    /// Re-quotes each opportunity as a full USDC → WETH → USDC cycle in both
+    /// directions and records it only when the cycle itself clears the threshold.
+    #[serde(default)]
+    pub round_trip_quotes: bool,
+    /// Consecutive ticks a venue may return the exact same quote before it is
+    /// warned about as possibly cached; `0` turns the check off.
+    #[serde(default = "default_stale_price_tick_threshold")]
+    pub stale_price_tick_threshold: u32,
+    /// Seconds a recorded opportunity stays `detected` before it is marked `expired`.
+    #[serde(default = "default_opportunity_ttl_secs")]
+    pub opportunity_ttl_secs: u64,
+    /// Consecutive RPC-failed ticks before the circuit breaker opens.
+    #[serde(default = "default_circuit_open_threshold")]
+    pub circuit_open_threshold: u32,
+    /// Seconds the breaker stays open before a probe tick is allowed.
+    #[serde(default = "default_circuit_open_duration_secs")]
+    pub circuit_open_duration_secs: u64,
+    /// Consecutive RPC-failed ticks before an "RPC down" alert; `0` sends none.
+    #[serde(default = "default_rpc_down_after_ticks")]
+    pub rpc_down_after_ticks: u32,
+    /// Consecutive healthy ticks after an "RPC down" alert before "RPC recovered".
+    #[serde(default = "default_rpc_up_after_ticks")]
+    pub rpc_up_after_ticks: u32,
+    /// Minimum seconds between Telegram messages; extra alerts are skipped.
+    #[serde(default = "default_telegram_min_interval_secs")]
+    pub telegram_min_interval_secs: u64,
+    /// Per-tick console output; `--output` takes precedence.
+    #[serde(default)]
+    pub output: OutputMode,
+    /// Log output format; `--log-format` takes precedence.
+    #[serde(default)]
+    pub log_format: LogFormat,
+}
+
+/// Live trade execution (`[execution]`); off unless `enabled` is set.
+#[derive(Clone, Deserialize)]
+pub struct ExecutionSettings {
+    /// Sends both swaps of every recorded opportunity through the signer wallet.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Logs the transactions that would be sent without broadcasting them;
+    /// `--paper` sets this and `enabled`.
+    #[serde(default)]
+    pub paper: bool,
+    /// Hex private key of the executing wallet. Prefer `POLYGON_ARB_PRIVATE_KEY`
+    /// over keeping it in `config.toml`.
+    pub private_key: Option<String>,
+    /// Largest USDC amount spent on one buy leg; bigger opportunities are skipped.
+    #[serde(default = "default_max_trade_size_usdc")]
+    pub max_trade_size_usdc: f64,
+    /// No trades are sent while the network gas price is above this, in gwei.
+    #[serde(default = "default_max_gas_price_gwei")]
+    pub max_gas_price_gwei: f64,
+    /// Execution stops for the rest of the UTC day once realized losses reach this, in USDC.
+    #[serde(default = "default_daily_loss_cap_usdc")]
+    pub daily_loss_cap_usdc: f64,
+    /// Tolerated shortfall against each leg's quote, in basis points, for `amountOutMin`.
+    #[serde(default = "default_slippage_bps")]
+    pub slippage_bps: u32,
+    /// Seconds from sending until a swap's `deadline`.
+    #[serde(default = "default_deadline_secs")]
+    pub deadline_secs: u64,
+    /// Address of a deployed Aave V3 flashloan executor; when set, opportunities
+    /// are traded atomically through it instead of as two swaps.
+    pub flashloan_executor: Option<String>,
+    /// Aave V3 Pool whose `flashLoanSimple` is called directly, with
+    /// `flashloan_executor` as the receiver, instead of the executor's
+    /// `executeArbitrage`.
+    pub aave_pool: Option<String>,
+    /// Aave V3 flashloan premium, in basis points.
+    #[serde(default = "default_flashloan_premium_bps")]
+    pub flashloan_premium_bps: u32,
+    /// How much startup approvals and re-approvals after a failed swap grant:
+    /// `"exact"` (default) or `"max"`.
+    #[serde(default)]
+    pub approval: ApprovalMode,
+    /// Seconds after acting on a pair in one direction before the same direction
+    /// is executed again; detection and recording continue meanwhile.
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// Most USDC committed at once across all scanners; an executed trade counts
+    /// against it until its direction's cooldown ends. Unlimited when unset.
+    pub max_exposure_usdc: Option<f64>,
+    /// Blocks a sent transaction may stay unmined before it is re-sent with the
+    /// same nonce at a higher gas price.
+    #[serde(default = "default_replace_after_blocks")]
+    pub replace_after_blocks: u64,
+    /// Gas price increase of each replacement, in percent; nodes usually reject
+    /// less than 10.
+    #[serde(default = "default_gas_bump_percent")]
+    pub gas_bump_percent: u64,
+    /// Replacements sent before giving up on a transaction.
+    #[serde(default = "default_max_replacements")]
+    pub max_replacements: u32,
+}
+
+impl Default for ExecutionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            paper: false,
+            private_key: None,
+            max_trade_size_usdc: default_max_trade_size_usdc(),
+            max_gas_price_gwei: default_max_gas_price_gwei(),
+            daily_loss_cap_usdc: default_daily_loss_cap_usdc(),
+            slippage_bps: default_slippage_bps(),
+            deadline_secs: default_deadline_secs(),
+            flashloan_executor: None,
+            aave_pool: None,
+            flashloan_premium_bps: default_flashloan_premium_bps(),
+            approval: ApprovalMode::default(),
+            cooldown_secs: default_cooldown_secs(),
+            max_exposure_usdc: None,
+            replace_after_blocks: default_replace_after_blocks(),
+            gas_bump_percent: default_gas_bump_percent(),
+            max_replacements: default_max_replacements(),
+        }
+    }
+}
+
+// Hand-written so the key never reaches logs through `{:?}`.
+impl fmt::Debug for ExecutionSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecutionSettings")
+            .field("enabled", &self.enabled)
+            .field("paper", &self.paper)
+            .field(
+                "private_key",
+                &self.private_key.as_ref().map(|_| "<redacted>"),
+            )
+            .field("max_trade_size_usdc", &self.max_trade_size_usdc)
+            .field("max_gas_price_gwei", &self.max_gas_price_gwei)
+            .field("daily_loss_cap_usdc", &self.daily_loss_cap_usdc)
+            .field("slippage_bps", &self.slippage_bps)
+            .field("deadline_secs", &self.deadline_secs)
+            .field("flashloan_executor", &self.flashloan_executor)
+            .field("aave_pool", &self.aave_pool)
+            .field("flashloan_premium_bps", &self.flashloan_premium_bps)
+            .field("approval", &self.approval)
+            .field("cooldown_secs", &self.cooldown_secs)
+            .field("max_exposure_usdc", &self.max_exposure_usdc)
+            .field("replace_after_blocks", &self.replace_after_blocks)
+            .field("gas_bump_percent", &self.gas_bump_percent)
+            .field("max_replacements", &self.max_replacements)
+            .finish()
+    }
+}
+
+/// Top-level contents of `config.toml`.
+///
+/// The single-chain form (`rpc_url`, `[[dex]]`, `[tokens]`) is folded into
+/// `chains` by [`load_config`]; code reading a loaded config only uses `chains`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Polygon JSON-RPC endpoint of the single-chain form.
+    pub rpc_url: Option<String>,
+    /// Chain id `rpc_url` must report in the single-chain form; 137 (Polygon)
+    /// when absent. `[[chains]]` entries give theirs as `chain_id`.
+    pub expected_chain_id: Option<u64>,
+    /// Venues of the single-chain form (`[[dex]]`).
+    #[serde(default, deserialize_with = "deserialize_dex")]
+    pub dex: Vec<DexEntry>,
+    /// The quoted pair of the single-chain form (`[tokens]`).
+    pub tokens: Option<TokenPair>,
+    /// Chainlink feeds of the single-chain form (`[chainlink_feeds]`).
+    #[serde(default)]
+    pub chainlink_feeds: BTreeMap<String, String>,
+    /// Chains to scan (`[[chains]]`).
+    #[serde(default)]
+    pub chains: Vec<ChainConfig>,
+    /// Scanner groups with their own pairs, venues and thresholds (`[[jobs]]`);
+    /// every pair of every chain runs as one [`DEFAULT_JOB`] when empty.
+    #[serde(default)]
+    pub jobs: Vec<JobConfig>,
+    /// `(dex, pair)` combinations scanned (`[scan_matrix]`); fixed at startup.
+    #[serde(default)]
+    pub scan_matrix: ScanMatrix,
+    /// Loop behaviour (`[settings]`).
+    pub settings: BotSettings,
+    /// Port of the Prometheus `/metrics` server; no server is started when absent.
+    pub metrics_port: Option<u16>,
+    /// Seconds between logged RPC latency summaries per endpoint; none when absent.
+    pub rpc_latency_log_secs: Option<u64>,
+    /// Requests a second allowed to each RPC endpoint, across all its scanners;
+    /// calls over the budget wait. Unlimited when absent; a chain's
+    /// `rpc_rate_limit` replaces it.
+    pub max_requests_per_sec: Option<u32>,
+    /// Listen address of the HTTP API (e.g. `"127.0.0.1:8080"`); disabled when absent.
+    pub api_listen: Option<String>,
+    /// Seconds without a successful tick before `/health` reports 503.
+    #[serde(default = "default_health_max_age_secs")]
+    pub health_max_age_secs: u64,
+    /// Opportunity webhook (`[webhook]`); disabled when absent.
+    pub webhook: Option<WebhookConfig>,
+    /// Telegram alerts (`[telegram]`); disabled when absent.
+    pub telegram: Option<TelegramConfig>,
+    /// Repeat-alert throttling of open spreads (`[notify_throttle]`); every
+    /// opportunity alerts when absent.
+    pub notify_throttle: Option<NotifyThrottleConfig>,
+    /// Round-trip simulation (`[simulation]`), used with `simulate_before_record`.
+    pub simulation: Option<SimulationConfig>,
+    /// Pruning of `arbitrage_bot` and `price_history` (`[retention]`); rows are
+    /// kept forever when absent.
+    pub retention: Option<RetentionConfig>,
+    /// JSON file the loop's rolling state is saved to and restored from; nothing
+    /// is persisted when absent.
+    pub state_file: Option<String>,
+    /// Seconds between checks of `config.toml`'s modification time; a change
+    /// reloads it like `SIGHUP`. Only `SIGHUP` reloads when absent.
+    pub config_watch_secs: Option<u64>,
+    /// Seconds between periodic writes of `state_file`; it is also written at shutdown.
+    #[serde(default = "default_state_save_interval_secs")]
+    pub state_save_interval_secs: u64,
+    /// Execution credentials (`[execution]`).
+    #[serde(default)]
+    pub execution: ExecutionSettings,
+    /// Fees of sent transactions (`[gas]`).
+    #[serde(default)]
+    pub gas: GasConfig,
+    /// Router ABI file replacing the UniswapV2 Router02 ABI built into the
+    /// binary, for quoting and sending swaps.
+    pub abi_path: Option<String>,
+    /// Where opportunities and prices are stored, see [`crate::store`].
+    #[serde(default = "default_database_url")]
+    pub database_url: DatabaseUrl,
+    /// Tags every row this bot writes to a shared Postgres database;
+    /// required with a `postgres://` `database_url`.
+    pub instance_id: Option<String>,
+}
+
+/// `database_url`: `sqlite://<path>` or `postgres://…`.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct DatabaseUrl(pub String);
+
+// Hand-written so a password in the URL never reaches logs through `{:?}`.
+impl fmt::Debug for DatabaseUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", redact_url(&self.0))
+    }
+}
+
+fn default_database_url() -> DatabaseUrl {
+    DatabaseUrl("sqlite://arbitrage.db".into())
+}
+
+fn default_est_gas_units() -> u64 {
+    300_000
+}
+
+fn default_gas_price_gwei() -> f64 {
+    30.0
+}
+
+fn default_matic_price_usdc() -> f64 {
+    0.5
+}
+
+fn default_wmatic() -> String {
+    "0x0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270".to_string()
+}
+
+fn default_matic_price_cache_ticks() -> u32 {
+    10
+}
+
+fn default_max_oracle_deviation_pct() -> f64 {
+    2.0
+}
+
+fn default_record_prices() -> bool {
+    true
+}
+
+fn default_health_max_age_secs() -> u64 {
+    300
+}
+
+fn default_max_trade_size_usdc() -> f64 {
+    1000.0
+}
+
+fn default_max_gas_price_gwei() -> f64 {
+    200.0
+}
+
+fn default_daily_loss_cap_usdc() -> f64 {
+    50.0
+}
+
+fn default_slippage_bps() -> u32 {
+    50
+}
+
+fn default_deadline_secs() -> u64 {
+    120
+}
+
+fn default_flashloan_premium_bps() -> u32 {
+    5
+}
+
+fn default_cooldown_secs() -> u64 {
+    60
+}
+
+fn default_replace_after_blocks() -> u64 {
+    5
+}
+
+fn default_gas_bump_percent() -> u64 {
+    15
+}
+
+fn default_max_replacements() -> u32 {
+    3
+}
+
+fn default_state_save_interval_secs() -> u64 {
+    60
+}
+
+fn default_telegram_min_interval_secs() -> u64 {
+    10
+}
+
+fn default_stale_price_tick_threshold() -> u32 {
+    5
+}
+
+fn default_opportunity_ttl_secs() -> u64 {
+    60
+}
+
+fn default_circuit_open_threshold() -> u32 {
+    5
+}
+
+fn default_rpc_down_after_ticks() -> u32 {
+    3
+}
+
+fn default_rpc_up_after_ticks() -> u32 {
+    1
+}
+
+fn default_circuit_open_duration_secs() -> u64 {
+    60
+}
+
+/// Replaces the `rpc_url` of the Polygon chain (id 137) when set.
+pub const ENV_RPC_URL: &str = "POLYGON_ARB_RPC_URL";
+/// Sets `execution.private_key` when set.
+pub const ENV_PRIVATE_KEY: &str = "POLYGON_ARB_PRIVATE_KEY";
+/// Replaces `settings.min_profit_usdc` when set.
+pub const ENV_MIN_PROFIT: &str = "POLYGON_ARB_MIN_PROFIT";
+/// Replaces `telegram.bot_token` when set and `[telegram]` is configured.
+pub const ENV_TELEGRAM_BOT_TOKEN: &str = "POLYGON_ARB_TELEGRAM_BOT_TOKEN";
+
+/// Reads and parses the TOML config at `path`, expands `${NAME}` references in
+/// its strings, folds the single-chain form into `chains`, applies the
+/// `POLYGON_ARB_*` environment overrides and checks the result with
+/// [`Config::validate`].
+pub fn load_config(path: &str) -> BotResult<Config> {
+    read_config(path).map_err(|err| BotError::Config(format!("{:#}", err)))
+}
+
+fn read_config(path: &str) -> Result<Config> {
+    let file = match fs::read_to_string(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let expected = env::current_dir()
+                .map(|dir| dir.join(path).display().to_string())
+                .unwrap_or_else(|_| path.to_string());
+            bail!(
+                "{} not found (expected at {}); run with --init-config to write a commented sample there",
+                path,
+                expected
+            );
+        }
+        Err(err) => bail!("reading {}: {}", path, err),
+    };
+    parse_config(&file)
+}
+
+/// [`read_config`] of a file holding `file`.
+pub(crate) fn parse_config(file: &str) -> Result<Config> {
+    let mut table = toml::Value::Table(toml::from_str(file)?);
+    let mut cfg: Config = if interpolate_env(&mut table, "")? {
+        table.try_into()?
+    } else {
+        // Parsed again from the text so that schema errors keep their line.
+        toml::from_str(file)?
+    };
+    normalize_chains(&mut cfg)?;
+    resolve_quote_tokens(&mut cfg)?;
+    apply_env_overrides(&mut cfg)?;
+    cfg.validate()?;
+    Ok(cfg)
+}
+
+/// Commented sample configuration written by `--init-config`.
+pub const SAMPLE_CONFIG: &str = include_str!("../config.toml");
+
+/// Writes [`SAMPLE_CONFIG`] to `path`, refusing to replace an existing file.
+pub fn write_sample_config(path: &str) -> Result<()> {
+    use std::io::Write;
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                anyhow!(
+                    "{} already exists; move it away to write a fresh sample",
+                    path
+                )
+            }
+            _ => anyhow!("writing {}: {}", path, err),
+        })?;
+    file.write_all(SAMPLE_CONFIG.as_bytes())
+        .map_err(|err| anyhow!("writing {}: {}", path, err))
+}
+
+/// Moves a single-chain config (`rpc_url`, `[[dex]]`, `[tokens]`) into a
+/// one-element `chains` list on Polygon.
+pub fn normalize_chains(cfg: &mut Config) -> Result<()> {
+    let legacy = cfg.rpc_url.is_some()
+        || !cfg.dex.is_empty()
+        || cfg.tokens.is_some()
+        || !cfg.chainlink_feeds.is_empty();
+    if !cfg.chains.is_empty() {
+        if legacy {
+            bail!("use either rpc_url/[[dex]]/[tokens]/[chainlink_feeds] or [[chains]], not both");
+        }
+        if cfg.expected_chain_id.is_some() {
+            bail!(
+                "expected_chain_id applies to the single-chain form; set chain_id on each [[chains]] entry"
+            );
+        }
+        return Ok(());
+    }
+    let (Some(rpc_url), Some(tokens)) = (cfg.rpc_url.take(), cfg.tokens.take()) else {
+        bail!("config needs rpc_url, [[dex]] and [tokens], or a [[chains]] list");
+    };
+    cfg.chains.push(ChainConfig {
+        chain_id: cfg.expected_chain_id.unwrap_or(POLYGON_CHAIN_ID),
+        rpc_url,
+        dex: std::mem::take(&mut cfg.dex),
+        tokens: vec![tokens],
+        chainlink_feeds: std::mem::take(&mut cfg.chainlink_feeds),
+        rpc_rate_limit: None,
+    });
+    Ok(())
+}
+
+/// Fills `usdc`, `usdc_decimals` and a missing `name` of every pair with a
+/// `quote_token` from it.
+pub fn resolve_quote_tokens(cfg: &mut Config) -> Result<()> {
+    for chain in &mut cfg.chains {
+        for pair in &mut chain.tokens {
+            let Some(quote) = &pair.quote_token else {
+                continue;
+            };
+            if !pair.usdc.is_empty() || pair.usdc_decimals.is_some() {
+                bail!(
+                    "chain {}: tokens.{}: set either usdc/usdc_decimals or quote_token, not both",
+                    chain.chain_id,
+                    pair.label()
+                );
+            }
+            pair.usdc = quote.address.clone();
+            pair.usdc_decimals = Some(u32::from(quote.decimals));
+            if pair.name.is_none() {
+                pair.name = Some(format!("WETH/{}", quote.symbol));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replaces `${NAME}` in every string under `value` with the environment
+/// variable `NAME`, failing on one that is unset; `path` is the key of `value`,
+/// used in errors. Returns whether anything was replaced.
+fn interpolate_env(value: &mut toml::Value, path: &str) -> Result<bool> {
+    let mut expanded = false;
+    match value {
+        toml::Value::String(text) if text.contains("${") => {
+            *text = expand_env(text, path)?;
+            expanded = true;
+        }
+        toml::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                expanded |= interpolate_env(item, &format!("{path}[{i}]"))?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (key, item) in table.iter_mut() {
+                let path = match path {
+                    "" => key.clone(),
+                    _ => format!("{path}.{key}"),
+                };
+                expanded |= interpolate_env(item, &path)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(expanded)
+}
+
+/// `text` with each `${NAME}` replaced by the value of `NAME`.
+fn expand_env(text: &str, path: &str) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let reference = &rest[start + 2..];
+        let Some(end) = reference.find('}') else {
+            bail!("{path}: unterminated ${{ reference");
+        };
+        let name = &reference[..end];
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            bail!("{path}: ${{{name}}} is not a valid environment variable name");
+        }
+        let Some(value) = env_var(name)? else {
+            bail!("environment variable {name} is not set (referenced by {path})");
+        };
+        out.push_str(&value);
+        rest = &reference[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Overwrites file values with the environment variables that are set, failing
+/// on one that is present but unusable. Expects [`normalize_chains`] to have run.
+pub fn apply_env_overrides(cfg: &mut Config) -> Result<()> {
+    if let Some(rpc_url) = env_var(ENV_RPC_URL)? {
+        let Some(polygon) = cfg
+            .chains
+            .iter_mut()
+            .find(|chain| chain.chain_id == POLYGON_CHAIN_ID)
+        else {
+            bail!("{ENV_RPC_URL} is set but no chain {POLYGON_CHAIN_ID} is configured");
+        };
+        polygon.rpc_url = rpc_url;
+    }
+    if let Some(private_key) = env_var(ENV_PRIVATE_KEY)? {
+        cfg.execution.private_key = Some(private_key);
+    }
+    if let Some(bot_token) = env_var(ENV_TELEGRAM_BOT_TOKEN)? {
+        let Some(telegram) = cfg.telegram.as_mut() else {
+            bail!("{ENV_TELEGRAM_BOT_TOKEN} is set but no [telegram] section is configured");
+        };
+        telegram.bot_token = bot_token;
+    }
+    if let Some(min_profit) = env_var(ENV_MIN_PROFIT)? {
+        cfg.settings.min_profit_usdc = min_profit
+            .trim()
+            .parse()
+            .map_err(|err| anyhow!("{ENV_MIN_PROFIT}={min_profit:?} is not a number: {err}"))?;
+    }
+    Ok(())
+}
+
+/// `Ok(None)` when `name` is unset, an error when it is not valid UTF-8.
+fn env_var(name: &str) -> Result<Option<String>> {
+    match env::var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(env::VarError::NotUnicode(_)) => bail!("{name} is not valid UTF-8"),
+    }
+}
+
+impl Config {
+    /// Checks what the schema alone cannot rule out: address syntax, distinct
+    /// token and router addresses, positive intervals and sizes, RPC URL schemes
+    /// and option combinations.
+    ///
+    /// Every problem is reported at once, one line each, prefixed with its field.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+        self.check_settings(&mut errors);
+        self.check_chains(&mut errors);
+        self.check_jobs(&mut errors);
+        self.check_scan_matrix(&mut errors);
+        if let Some(executor) = &self.execution.flashloan_executor {
+            check_address("execution.flashloan_executor", executor, &mut errors);
+        }
+        if let Some(pool) = &self.execution.aave_pool {
+            check_address("execution.aave_pool", pool, &mut errors);
+            if self.execution.flashloan_executor.is_none() {
+                errors.push("execution.aave_pool needs execution.flashloan_executor".into());
+            }
+        }
+        if let Some(simulation) = &self.simulation {
+            check_address("simulation.from", &simulation.from, &mut errors);
+        }
+        if let Some(from) = &self.settings.estimate_gas_from {
+            check_address("settings.estimate_gas_from", from, &mut errors);
+        }
+        if let Some(name) = &self.settings.matic_price_dex {
+            check_address("settings.wmatic", &self.settings.wmatic, &mut errors);
+            let v2 = self
+                .chains
+                .iter()
+                .flat_map(|chain| &chain.dex)
+                .any(|entry| &entry.name == name && entry.kind == DexKind::UniswapV2);
+            if !v2 {
+                errors.push(format!(
+                    "settings.matic_price_dex: {:?} is not a uniswap_v2 [[dex]] entry",
+                    name
+                ));
+            }
+            if self.settings.matic_price_cache_ticks == 0 {
+                errors.push("settings.matic_price_cache_ticks: must be at least 1".into());
+            }
+        }
+        self.check_database(&mut errors);
+        if errors.is_empty() {
+            return Ok(());
+        }
+        bail!(
+            "invalid config ({} problems):\n  {}",
+            errors.len(),
+            errors.join("\n  ")
+        )
+    }
+
+    fn check_database(&self, errors: &mut Vec<String>) {
+        match Backend::parse(&self.database_url.0) {
+            Err(reason) => errors.push(format!("database_url: {}", reason)),
+            Ok(Backend::Sqlite(_)) => {}
+            Ok(Backend::Postgres(_)) => {
+                if !cfg!(feature = "postgres") {
+                    errors.push(
+                        "database_url: postgres:// needs a build with --features postgres".into(),
+                    );
+                }
+                if self.instance_id.as_deref().is_none_or(str::is_empty) {
+                    errors.push("instance_id: required with a postgres:// database_url".into());
+                }
+                // Both work on tables that stay in the SQLite file.
+                if self.execution.enabled && !self.execution.paper {
+                    errors.push(
+                        "execution.paper: live execution is not supported with a postgres:// database_url"
+                            .into(),
+                    );
+                }
+                if self.retention.is_some() {
+                    errors.push("retention: not supported with a postgres:// database_url".into());
+                }
+            }
+        }
+    }
+
+    fn check_settings(&self, errors: &mut Vec<String>) {
+        let settings = &self.settings;
+        if self.max_requests_per_sec == Some(0) {
+            errors.push("max_requests_per_sec: must be at least 1".into());
+        }
+        if let Some(throttle) = &self.notify_throttle
+            && let Some(pct) = throttle.min_improvement_pct
+            && !(pct.is_finite() && pct > 0.0)
+        {
+            errors.push("notify_throttle.min_improvement_pct: must be a positive number".into());
+        }
+        if self.config_watch_secs == Some(0) {
+            errors.push("config_watch_secs: must be at least 1 second".into());
+        }
+        if let Some(retention) = &self.retention {
+            if retention.retention_days.is_none() && retention.max_rows.is_none() {
+                errors.push("retention: set retention_days, max_rows or both".into());
+            }
+            if retention.retention_days == Some(0) {
+                errors.push("retention.retention_days: must be at least 1 day".into());
+            }
+            if retention.interval_secs == 0 {
+                errors.push("retention.interval_secs: must be at least 1 second".into());
+            }
+        }
+        if self.rpc_latency_log_secs == Some(0) {
+            errors.push("rpc_latency_log_secs: must be at least 1 second".into());
+        }
+        if settings.refresh_rate == 0 {
+            errors.push("settings.refresh_rate: must be at least 1 second".into());
+        }
+        check_min_profit("settings.min_profit_usdc", settings.min_profit_usdc, errors);
+        check_min_profit(
+            "settings.priority_tie_threshold_usdc",
+            settings.priority_tie_threshold_usdc,
+            errors,
+        );
+        check_trade_size(
+            "settings.trade_size",
+            &settings.trade_size,
+            self.base_decimals(),
+            errors,
+        );
+        if settings
+            .lp_entry_price
+            .is_some_and(|price| !(price.is_finite() && price > 0.0))
+        {
+            errors.push("settings.lp_entry_price: must be a positive price".into());
+        }
+        for (i, size) in settings.trade_size_sweep.iter().enumerate() {
+            if !(size.is_finite() && *size > 0.0) {
+                errors.push(format!(
+                    "settings.trade_size_sweep[{}]: must be a positive USDC amount, got {}",
+                    i, size
+                ));
+            }
+        }
+        if settings.adaptive_interval.is_some() && settings.adaptive_refresh.is_some() {
+            errors.push(
+                "settings.adaptive_interval and settings.adaptive_refresh are mutually exclusive"
+                    .into(),
+            );
+        }
+        if settings
+            .min_pool_liquidity_usdc
+            .is_some_and(|min| !(min.is_finite() && min > 0.0))
+        {
+            errors.push("settings.min_pool_liquidity_usdc: must be a positive USDC amount".into());
+        }
+        if let Some(filter) = &settings.anomaly_filter {
+            if filter.window < 2 {
+                errors.push("settings.anomaly_filter.window: must be at least 2 ticks".into());
+            }
+            if !(filter.max_spread_multiple.is_finite() && filter.max_spread_multiple > 1.0) {
+                errors.push("settings.anomaly_filter.max_spread_multiple: must be above 1".into());
+            }
+            if !(filter.max_quote_deviation_pct.is_finite() && filter.max_quote_deviation_pct > 0.0)
+            {
+                errors.push(
+                    "settings.anomaly_filter.max_quote_deviation_pct: must be positive".into(),
+                );
+            }
+        }
+        if settings.twap_window_secs.is_some() && !settings.record_prices {
+            errors.push("settings.twap_window_secs needs settings.record_prices".into());
+        }
+        if settings.simulate_before_record && self.simulation.is_none() {
+            errors.push("settings.simulate_before_record needs a [simulation] section".into());
+        }
+        if self.execution.slippage_bps >= 10_000 {
+            errors.push("execution.slippage_bps must be below 10000".into());
+        }
+        if self
+            .execution
+            .max_exposure_usdc
+            .is_some_and(|cap| cap <= 0.0)
+        {
+            errors.push("execution.max_exposure_usdc must be positive".into());
+        }
+        if !(0.0..=100.0).contains(&self.gas.gas_percentile) {
+            errors.push(format!(
+                "gas.gas_percentile: must be between 0 and 100, got {}",
+                self.gas.gas_percentile
+            ));
+        }
+    }
+
+    fn check_chains(&self, errors: &mut Vec<String>) {
+        let mut chain_ids = HashSet::new();
+        // Metrics, price history and the TWAP filter key on the DEX name alone.
+        let mut dex_names = HashSet::new();
+        for chain in &self.chains {
+            let id = chain.chain_id;
+            if !chain_ids.insert(id) {
+                errors.push(format!("chain {} is configured twice", id));
+            }
+            check_rpc_url(&format!("chain {}: rpc_url", id), &chain.rpc_url, errors);
+            if chain.rpc_rate_limit == Some(0) {
+                errors.push(format!("chain {}: rpc_rate_limit: must be at least 1", id));
+            }
+            if chain.tokens.is_empty() {
+                errors.push(format!("chain {}: needs at least one token pair", id));
+            }
+            for (symbol, feed) in &chain.chainlink_feeds {
+                check_address(
+                    &format!("chain {}: chainlink_feeds.{}", id, symbol),
+                    feed,
+                    errors,
+                );
+            }
+            // Token addresses, so a router pointed at a token is caught.
+            let mut tokens = HashSet::new();
+            for pair in &chain.tokens {
+                let field = |name: &str| format!("chain {}: tokens.{}.{}", id, pair.label(), name);
+                let weth = check_address(&field("weth"), &pair.weth, errors);
+                let usdc = if pair.usdc.is_empty() {
+                    errors.push(format!(
+                        "{}: missing; set usdc or quote_token",
+                        field("usdc")
+                    ));
+                    None
+                } else {
+                    check_address(&field("usdc"), &pair.usdc, errors)
+                };
+                if weth.is_some() && weth == usdc {
+                    errors.push(format!("{}: same address as weth", field("usdc")));
+                }
+                tokens.extend(weth.into_iter().chain(usdc));
+                for (i, hop) in pair.path_via.iter().enumerate() {
+                    check_address(&field(&format!("path_via[{}]", i)), hop, errors);
+                }
+                if let Some(feed) = &pair.oracle_feed {
+                    check_address(&field("oracle_feed"), feed, errors);
+                }
+            }
+            for entry in &chain.dex {
+                let field = |name: &str| format!("chain {}: dex.{}.{}", id, entry.name, name);
+                if !dex_names.insert(entry.name.as_str()) {
+                    errors.push(format!(
+                        "dex {}: names must be unique across chains",
+                        entry.name
+                    ));
+                }
+                if let Some(address) = check_address(&field("address"), &entry.address, errors)
+                    && tokens.contains(&address)
+                {
+                    errors.push(format!(
+                        "{}: {:?} is a token address of this chain, not a {:?} contract",
+                        field("address"),
+                        entry.address,
+                        entry.kind
+                    ));
+                }
+                if entry.kind == DexKind::Aggregator {
+                    if entry.aggregator.is_none() {
+                        errors.push(format!(
+                            "{}: needed by kind aggregator",
+                            field("aggregator")
+                        ));
+                    }
+                    if entry.path.is_some() {
+                        errors.push(format!(
+                            "{}: not supported by kind aggregator, which routes itself",
+                            field("path")
+                        ));
+                    }
+                }
+                if entry.pool_fee_bps.is_some_and(|fee| fee >= 10_000) {
+                    errors.push(format!("{}: must be below 10000", field("pool_fee_bps")));
+                }
+                if let Some(path) = &entry.path {
+                    if path.len() < 2 {
+                        errors.push(format!(
+                            "{}: needs at least 2 tokens, got {}",
+                            field("path"),
+                            path.len()
+                        ));
+                    }
+                    for (i, token) in path.iter().enumerate() {
+                        check_address(&field(&format!("path[{}]", i)), token, errors);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Every job needs a unique name and must match at least one pair and, on
+    /// each of its chains, refer only to configured pairs and venues.
+    fn check_jobs(&self, errors: &mut Vec<String>) {
+        let mut names = HashSet::new();
+        for job in &self.jobs {
+            if job.name.is_empty() {
+                errors.push("jobs: every job needs a name".into());
+                continue;
+            }
+            let field = |name: &str| format!("jobs.{}.{}", job.name, name);
+            if !names.insert(job.name.as_str()) {
+                errors.push(format!("job {}: names must be unique", job.name));
+            }
+            if job.refresh_rate == Some(0) {
+                errors.push(format!(
+                    "{}: must be at least 1 second",
+                    field("refresh_rate")
+                ));
+            }
+            if let Some(min_profit) = job.min_profit_usdc {
+                check_min_profit(&field("min_profit_usdc"), min_profit, errors);
+            }
+            if let Some(trade_size) = &job.trade_size {
+                check_trade_size(
+                    &field("trade_size"),
+                    trade_size,
+                    self.base_decimals(),
+                    errors,
+                );
+            }
+            let chains: Vec<&ChainConfig> = self
+                .chains
+                .iter()
+                .filter(|chain| job.covers_chain(chain))
+                .collect();
+            if chains.is_empty() {
+                errors.push(format!(
+                    "{}: chain {} is not configured",
+                    field("chain_id"),
+                    job.chain_id.unwrap_or_default()
+                ));
+                continue;
+            }
+            for label in &job.pairs {
+                if !chains
+                    .iter()
+                    .any(|chain| chain.tokens.iter().any(|pair| pair.label() == label))
+                {
+                    errors.push(format!("{}: no pair is labelled {}", field("pairs"), label));
+                }
+            }
+            for name in &job.dex {
+                if !chains
+                    .iter()
+                    .any(|chain| chain.dex.iter().any(|entry| &entry.name == name))
+                {
+                    errors.push(format!("{}: no dex is named {}", field("dex"), name));
+                }
+            }
+        }
+    }
+
+    /// Every `[scan_matrix]` entry must name a venue that quotes the pair on
+    /// some chain, and the matrix must leave at least one combination.
+    fn check_scan_matrix(&self, errors: &mut Vec<String>) {
+        let lists = [
+            ("allow", &self.scan_matrix.allow),
+            ("deny", &self.scan_matrix.deny),
+        ];
+        for (list, combos) in lists {
+            for (i, combo) in combos.iter().enumerate() {
+                let configured = self.chains.iter().any(|chain| {
+                    chain.dex.iter().any(|entry| entry.name == combo.dex)
+                        && chain.tokens.iter().any(|pair| pair.label() == combo.pair)
+                });
+                if !configured {
+                    errors.push(format!(
+                        "scan_matrix.{}[{}]: no chain has both dex {} and pair {}",
+                        list, i, combo.dex, combo.pair
+                    ));
+                }
+            }
+        }
+        let scanned = self.chains.iter().any(|chain| {
+            chain.tokens.iter().any(|pair| {
+                chain
+                    .dex
+                    .iter()
+                    .any(|entry| self.scan_matrix.includes(&entry.name, pair))
+            })
+        });
+        if !self.chains.is_empty() && !scanned {
+            errors.push("scan_matrix: excludes every (dex, pair) combination".into());
+        }
+    }
+
+    /// Decimals `trade_size` is checked at: the largest configured
+    /// `weth_decimals`, or 18.
+    fn base_decimals(&self) -> u32 {
+        self.chains
+            .iter()
+            .flat_map(|chain| &chain.tokens)
+            .filter_map(|pair| pair.weth_decimals)
+            .max()
+            .unwrap_or(18)
+    }
+}
+
+/// Parses `value` as a 20-byte hex address, recording a problem with `field` if it is not.
+fn check_address(field: &str, value: &str, errors: &mut Vec<String>) -> Option<Address> {
+    match value.parse::<Address>() {
+        Ok(address) => Some(address),
+        Err(err) => {
+            errors.push(format!(
+                "{}: {:?} is not a 20-byte hex address ({})",
+                field, value, err
+            ));
+            None
+        }
+    }
+}
+
+/// Requires an `http://` or `https://` URL; the JSON-RPC client is HTTP only.
+fn check_rpc_url(field: &str, url: &str, errors: &mut Vec<String>) {
+    let scheme = url
+        .split_once("://")
+        .map(|(scheme, _)| scheme.to_ascii_lowercase());
+    match scheme.as_deref() {
+        Some("http" | "https") => {}
+        Some("ws" | "wss") => errors.push(format!(
+            "{}: {:?} is a websocket endpoint; use the provider's http(s) URL",
+            field, url
+        )),
+        _ => errors.push(format!(
+            "{}: {:?} needs an http:// or https:// scheme",
+            field, url
+        )),
+    }
+}
+
+fn check_min_profit(field: &str, min_profit: f64, errors: &mut Vec<String>) {
+    if min_profit.is_nan() || min_profit < 0.0 {
+        errors.push(format!("{}: must be 0 or more, got {}", field, min_profit));
+    }
+}
+
+fn check_trade_size(field: &str, trade_size: &TradeSize, decimals: u32, errors: &mut Vec<String>) {
+    let units = match trade_size {
+        TradeSize::Units(units) => Ok(U256::from(*units)),
+        TradeSize::Tokens(amount) => arb::parse_units(amount.trim(), decimals),
+    };
+    match units {
+        Ok(units) if units.is_zero() => errors.push(format!("{}: must be positive", field)),
+        Ok(_) => {}
+        Err(err) => errors.push(format!("{}: {}", field, err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(amount: &str) -> TradeSize {
+        TradeSize::Tokens(amount.into())
+    }
+
+    const VALID: &str = r#"
+rpc_url = "https://polygon-rpc.com"
+
+[[dex]]
+name = "QuickSwap"
+kind = "uniswap_v2"
+address = "0xa5E0829CaCEd8fFDD4De3c43696c57f7d7a678ff"
+
+[tokens]
+weth = "0x7ceb23fd6bc0add59e62ac25578270cff1b9f619"
+usdc = "0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174"
+
+[settings]
+min_profit_usdc = 15.0
+trade_size = "1"
+est_gas_cost_usdc = 10.0
+refresh_rate = 30
+"#;
+
+    /// The problems `validate` reports for [`VALID`] with each `(from, to)`
+    /// replaced, one per line as logged.
+    fn problems(edits: &[(&str, &str)]) -> Vec<String> {
+        let mut text = VALID.to_string();
+        for (from, to) in edits {
+            assert!(text.contains(from), "{:?} is not in the snippet", from);
+            text = text.replace(from, to);
+        }
+        let err = parse_config(&text).expect_err("the snippet is invalid");
+        let message = format!("{:#}", err);
+        let mut lines = message.lines();
+        let header = lines.next().unwrap().to_string();
+        let problems: Vec<String> = lines.map(|line| line.trim().to_string()).collect();
+        assert_eq!(
+            header,
+            format!("invalid config ({} problems):", problems.len())
+        );
+        problems
+    }
+
+    #[test]
+    fn valid_snippet_passes() {
+        parse_config(VALID).unwrap();
+    }
+
+    #[test]
+    fn bad_address_names_its_field() {
+        assert_eq!(
+            problems(&[("0x7ceb23fd6bc0add59e62ac25578270cff1b9f619", "0x7ceb23")]),
+            [
+                "chain 137: tokens.WETH/USDC.weth: \"0x7ceb23\" is not a 20-byte hex address (Invalid input length)"
+            ]
+        );
+    }
+
+    #[test]
+    fn router_at_a_token_address_is_rejected() {
+        assert_eq!(
+            problems(&[(
+                "0xa5E0829CaCEd8fFDD4De3c43696c57f7d7a678ff",
+                "0x7ceb23fd6bc0add59e62ac25578270cff1b9f619"
+            )]),
+            [
+                "chain 137: dex.QuickSwap.address: \"0x7ceb23fd6bc0add59e62ac25578270cff1b9f619\" is a token address of this chain, not a UniswapV2 contract"
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_refresh_rate_is_rejected() {
+        assert_eq!(
+            problems(&[("refresh_rate = 30", "refresh_rate = 0")]),
+            ["settings.refresh_rate: must be at least 1 second"]
+        );
+    }
+
+    #[test]
+    fn negative_min_profit_is_rejected() {
+        assert_eq!(
+            problems(&[("min_profit_usdc = 15.0", "min_profit_usdc = -1.5")]),
+            ["settings.min_profit_usdc: must be 0 or more, got -1.5"]
+        );
+    }
+
+    #[test]
+    fn non_http_rpc_url_is_rejected() {
+        assert_eq!(
+            problems(&[("https://polygon-rpc.com", "ftp://polygon-rpc.com")]),
+            ["chain 137: rpc_url: \"ftp://polygon-rpc.com\" needs an http:// or https:// scheme"]
+        );
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        assert_eq!(
+            problems(&[
+                ("https://polygon-rpc.com", "ftp://polygon-rpc.com"),
+                ("refresh_rate = 30", "refresh_rate = 0"),
+                ("min_profit_usdc = 15.0", "min_profit_usdc = -1.5"),
+                ("trade_size = \"1\"", "trade_size = \"0\""),
+            ]),
+            [
+                "settings.refresh_rate: must be at least 1 second",
+                "settings.min_profit_usdc: must be 0 or more, got -1.5",
+                "settings.trade_size: must be positive",
+                "chain 137: rpc_url: \"ftp://polygon-rpc.com\" needs an http:// or https:// scheme",
+            ]
+        );
+    }
+
+    #[test]
+    fn trade_size_zero_is_rejected() {
+        assert_eq!(
+            problems(&[("trade_size = \"1\"", "trade_size = \"0\"")]),
+            ["settings.trade_size: must be positive"]
+        );
+    }
+
+    #[test]
+    fn trade_size_scales_by_decimals() {
+        assert_eq!(tokens("1.5").to_units(6).unwrap(), U256::from(1_500_000));
+        assert_eq!(tokens("0.25").to_units(8).unwrap(), U256::from(25_000_000));
+        assert_eq!(
+            tokens("1.5").to_units(18).unwrap(),
+            U256::from(1_500_000_000_000_000_000u64)
+        );
+        assert_eq!(tokens("1000").to_units(18).unwrap(), U256::exp10(21));
+        assert_eq!(tokens(" 2 ").to_units(8).unwrap(), U256::from(200_000_000));
+    }
+
+    #[test]
+    fn trade_size_rejects_extra_fractional_digits() {
+        assert!(tokens("1.1234567").to_units(6).is_err());
+        assert!(tokens("0.000000001").to_units(8).is_err());
+        assert!(tokens("1.0000000000000000001").to_units(18).is_err());
+        assert_eq!(tokens("0.00000001").to_units(8).unwrap(), U256::one());
+        for bad in ["", ".", "-1", "1e18", "1.2.3"] {
+            assert!(tokens(bad).to_units(18).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn trade_size_integer_is_raw_units() {
+        #[derive(Deserialize)]
+        struct Settings {
+            trade_size: TradeSize,
+        }
+        let parse = |text: &str| toml::from_str::<Settings>(text).unwrap().trade_size;
+        let raw = parse("trade_size = 1000000000000000000");
+        assert_eq!(raw, TradeSize::Units(1_000_000_000_000_000_000));
+        // Raw units ignore the token's decimals.
+        for decimals in [6, 8, 18] {
+            assert_eq!(raw.to_units(decimals).unwrap(), U256::exp10(18));
+        }
+        assert_eq!(parse("trade_size = \"1.5\""), tokens("1.5"));
+    }
+}
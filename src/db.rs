@@ -1,18 +1,1312 @@
-use anyhow::Result;
-use rusqlite::Connection;
+//! SQLite schema, inserts and read-side aggregates.
 
-pub fn init_db() -> Result<()> {
-    let con = Connection::open("arbitrage.db")?;
+use chrono::{DateTime, Duration, Utc};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::error::{BotError, BotResult};
+use crate::store::OpportunityStore;
+
+/// Shared handle to `arbitrage.db`; the polling loop and HTTP handlers each
+/// check out their own connection.
+pub type DbPool = Pool<SqliteConnectionManager>;
+
+/// Table a `dry_run_table` dry run stores its opportunities in, with the
+/// columns of `arbitrage_bot`.
+pub const DRY_RUN_TABLE: &str = "dry_run_opportunities";
+
+/// How long a writer waits for a lock held by another pooled connection.
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Opens a connection pool on the SQLite file at `path`.
+pub fn open_pool(path: &str) -> BotResult<DbPool> {
+    let manager =
+        SqliteConnectionManager::file(path).with_init(|con| con.busy_timeout(BUSY_TIMEOUT));
+    Ok(Pool::new(manager)?)
+}
+
+/// Creates (or migrates) the schema through a connection from `pool`.
+pub fn init_db(pool: &DbPool) -> BotResult<()> {
+    let con = pool.get()?;
+    // A dry run's table mirrors the real one, so its rows compare column for column.
+    for table in ["arbitrage_bot", DRY_RUN_TABLE] {
+        con.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    buy_dex TEXT,
+                    sell_dex TEXT,
+                    profit_usdc REAL,
+                    timestamp TEXT
+                )",
+                table
+            ),
+            (),
+        )?;
+        add_column_if_missing(&con, table, "buy_path", "TEXT")?;
+        add_column_if_missing(&con, table, "sell_path", "TEXT")?;
+        add_column_if_missing(&con, table, "score", "REAL")?;
+        add_column_if_missing(&con, table, "gas_cost_usdc", "REAL")?;
+        add_column_if_missing(&con, table, "simulated_ok", "INTEGER")?;
+        add_column_if_missing(&con, table, "chain_id", "INTEGER")?;
+        add_column_if_missing(&con, table, "potentially_stale", "INTEGER")?;
+        add_column_if_missing(&con, table, "anomaly", "INTEGER")?;
+        add_column_if_missing(&con, table, "optimal_size_usdc", "REAL")?;
+        add_column_if_missing(&con, table, "buy_fee_usdc", "REAL")?;
+        add_column_if_missing(&con, table, "sell_fee_usdc", "REAL")?;
+        add_column_if_missing(&con, table, "break_even_bps", "REAL")?;
+        add_column_if_missing(&con, table, "analytic_size_usdc", "REAL")?;
+        add_column_if_missing(&con, table, "pair", "TEXT")?;
+        add_column_if_missing(&con, table, "profit_pct", "REAL")?;
+        add_column_if_missing(&con, table, "expires_at", "TEXT")?;
+        add_column_if_missing(&con, table, "status", "TEXT")?;
+        add_column_if_missing(&con, table, "buy_liquidity_usdc", "REAL")?;
+        add_column_if_missing(&con, table, "sell_liquidity_usdc", "REAL")?;
+        add_column_if_missing(&con, table, "report_profit", "REAL")?;
+        add_column_if_missing(&con, table, "report_currency", "TEXT")?;
+        add_column_if_missing(&con, table, "quote_shortfall_bps", "REAL")?;
+        add_column_if_missing(&con, table, "trend", "TEXT")?;
+        if add_column_if_missing(&con, table, "expires_at_ms", "INTEGER")? {
+            con.execute(
+                &format!(
+                    "UPDATE {} SET expires_at_ms =
+                         CAST(ROUND((julianday(expires_at) - 2440587.5) * 86400000) AS INTEGER)
+                     WHERE expires_at IS NOT NULL",
+                    table
+                ),
+                (),
+            )?;
+        }
+    }
     con.execute(
-        "CREATE TABLE IF NOT EXISTS arbitrage_bot (
+        "CREATE TABLE IF NOT EXISTS price_history (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
+            dex TEXT,
+            pair TEXT,
+            price_usdc REAL,
+            block_number INTEGER,
+            timestamp TEXT
+        )",
+        (),
+    )?;
+    add_column_if_missing(&con, "price_history", "volume_usdc", "REAL")?;
+    add_column_if_missing(&con, "price_history", "chain_id", "INTEGER")?;
+    add_column_if_missing(&con, "price_history", "impermanent_loss_pct", "REAL")?;
+    add_column_if_missing(&con, "price_history", "amount_in", "REAL")?;
+    con.execute(
+        "CREATE TABLE IF NOT EXISTS executions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            opportunity_id INTEGER REFERENCES arbitrage_bot(id),
+            chain_id INTEGER,
+            mode TEXT,
+            status TEXT,
+            buy_tx TEXT,
+            sell_tx TEXT,
+            usdc_in REAL,
+            weth_received REAL,
+            usdc_out REAL,
+            realized_profit_usdc REAL,
+            error TEXT,
+            timestamp TEXT
+        )",
+        (),
+    )?;
+    add_column_if_missing(&con, "executions", "kind", "TEXT")?;
+    add_column_if_missing(&con, "executions", "wallet_check_passed", "INTEGER")?;
+    con.execute(
+        "CREATE TABLE IF NOT EXISTS transactions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            chain_id INTEGER,
+            wallet TEXT,
+            hash TEXT,
+            nonce INTEGER,
+            gas_price_gwei REAL,
+            label TEXT,
+            status TEXT,
+            confirmed_block INTEGER,
+            timestamp TEXT
+        )",
+        (),
+    )?;
+    con.execute(
+        "CREATE TABLE IF NOT EXISTS backtest_runs (
+            run_id TEXT PRIMARY KEY,
+            chain_id INTEGER,
+            pair TEXT,
+            from_block INTEGER,
+            to_block INTEGER,
+            step INTEGER,
+            next_block INTEGER,
+            started_at TEXT
+        )",
+        (),
+    )?;
+    con.execute(
+        "CREATE TABLE IF NOT EXISTS backtest_results (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id TEXT NOT NULL,
+            chain_id INTEGER,
+            pair TEXT,
+            block_number INTEGER,
             buy_dex TEXT,
             sell_dex TEXT,
+            buy_path TEXT,
+            sell_path TEXT,
+            spread_usdc REAL,
+            gas_cost_usdc REAL,
             profit_usdc REAL,
             timestamp TEXT
         )",
-        (), 
+        (),
+    )?;
+    for table in [
+        "arbitrage_bot",
+        "price_history",
+        "executions",
+        "transactions",
+        "backtest_results",
+        DRY_RUN_TABLE,
+    ] {
+        add_timestamp_ms(&con, table)?;
+    }
+    con.execute(
+        "CREATE INDEX IF NOT EXISTS arbitrage_bot_expiry
+         ON arbitrage_bot (status, expires_at_ms)",
+        (),
+    )?;
+    con.execute(
+        "CREATE INDEX IF NOT EXISTS price_history_window
+         ON price_history (dex, pair, timestamp_ms)",
+        (),
+    )?;
+    tracing::info!("Database and table created!");
+    Ok(())
+}
+
+/// Adds `column` to `table` unless an earlier version of the schema already has
+/// it; returns whether it was added.
+fn add_column_if_missing(
+    con: &Connection,
+    table: &str,
+    column: &str,
+    decl: &str,
+) -> BotResult<bool> {
+    let mut stmt = con.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map((), |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .iter()
+        .any(|name| name == column);
+    if !exists {
+        con.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl),
+            (),
+        )?;
+    }
+    Ok(!exists)
+}
+
+/// Adds the integer `timestamp_ms` (UTC epoch milliseconds) next to the RFC 3339
+/// `timestamp` of `table`, filling it in for rows written before it existed.
+fn add_timestamp_ms(con: &Connection, table: &str) -> BotResult<()> {
+    if add_column_if_missing(con, table, "timestamp_ms", "INTEGER")? {
+        con.execute(
+            &format!(
+                "UPDATE {} SET timestamp_ms =
+                     CAST(ROUND((julianday(timestamp) - 2440587.5) * 86400000) AS INTEGER)
+                 WHERE timestamp IS NOT NULL",
+                table
+            ),
+            (),
+        )?;
+    }
+    Ok(())
+}
+
+/// One successful quote, as stored in `price_history`.
+#[derive(Debug, Clone)]
+pub struct PriceRecord {
+    /// Venue that quoted.
+    pub dex: String,
+    /// Label of the quoted pair.
+    pub pair: String,
+    /// Base token quoted, i.e. `trade_size` in whole tokens.
+    pub amount_in: f64,
+    /// Output for `trade_size`, in the quote token.
+    pub price_usdc: f64,
+    /// Quoted volume in the quote token: the output for the whole trade size,
+    /// so equal to `price_usdc`.
+    pub volume_usdc: f64,
+    /// Block the quote was taken at, when known.
+    pub block_number: Option<u64>,
+    /// Impermanent loss against `lp_entry_price`, in percent, when one is set.
+    pub impermanent_loss_pct: Option<f64>,
+}
+
+/// Records one tick's quotes on `chain_id`, taken `at`, in `price_history`,
+/// in a single transaction and under one timestamp, so [`spread_series`] can
+/// group them.
+pub fn insert_prices(
+    conn: &mut Connection,
+    chain_id: u64,
+    at: DateTime<Utc>,
+    records: &[PriceRecord],
+) -> BotResult<()> {
+    let tx = conn.transaction()?;
+    stage_prices(&tx, chain_id, at, records)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// The inserts of [`insert_prices`], inside the caller's transaction.
+fn stage_prices(
+    conn: &Connection,
+    chain_id: u64,
+    at: DateTime<Utc>,
+    records: &[PriceRecord],
+) -> BotResult<()> {
+    let (timestamp, timestamp_ms) = (at.to_rfc3339(), at.timestamp_millis());
+    let mut stmt = conn.prepare(
+        "INSERT INTO price_history (dex, pair, price_usdc, volume_usdc, block_number, timestamp, chain_id, timestamp_ms,
+                                    impermanent_loss_pct, amount_in)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    for record in records {
+        stmt.execute((
+            &record.dex,
+            &record.pair,
+            record.price_usdc,
+            record.volume_usdc,
+            record.block_number,
+            &timestamp,
+            chain_id,
+            timestamp_ms,
+            record.impermanent_loss_pct,
+            record.amount_in,
+        ))?;
+    }
+    Ok(())
+}
+
+/// Block range and progress of one archive replay (`backtest_runs`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BacktestRun {
+    /// Tags the run's rows in `backtest_results`.
+    pub run_id: String,
+    /// Chain replayed.
+    pub chain_id: u64,
+    /// Label of the replayed pair.
+    pub pair: String,
+    /// First block quoted.
+    pub from_block: u64,
+    /// Last block quoted, inclusive.
+    pub to_block: u64,
+    /// Blocks between two quoted ones.
+    pub step: u64,
+    /// Next block to quote; past `to_block` once the run is complete.
+    pub next_block: u64,
+}
+
+/// Starts `run`, or resumes the stored run of the same id from its checkpoint.
+///
+/// A stored run with another chain, pair or block range is refused rather
+/// than mixed into, so each run id names one replay.
+pub fn start_backtest_run(conn: &Connection, run: &BacktestRun) -> BotResult<BacktestRun> {
+    let stored = conn
+        .query_row(
+            "SELECT chain_id, pair, from_block, to_block, step, next_block
+             FROM backtest_runs WHERE run_id = ?1",
+            [&run.run_id],
+            |row| {
+                Ok(BacktestRun {
+                    run_id: run.run_id.clone(),
+                    chain_id: row.get(0)?,
+                    pair: row.get(1)?,
+                    from_block: row.get(2)?,
+                    to_block: row.get(3)?,
+                    step: row.get(4)?,
+                    next_block: row.get(5)?,
+                })
+            },
+        )
+        .optional()?;
+    if let Some(stored) = stored {
+        let same = (
+            &stored.pair,
+            stored.chain_id,
+            stored.from_block,
+            stored.to_block,
+            stored.step,
+        ) == (
+            &run.pair,
+            run.chain_id,
+            run.from_block,
+            run.to_block,
+            run.step,
+        );
+        if !same {
+            return Err(BotError::Config(format!(
+                "backtest run {} covers {} on chain {}, blocks {}..={} every {}; pick another --run-id",
+                run.run_id,
+                stored.pair,
+                stored.chain_id,
+                stored.from_block,
+                stored.to_block,
+                stored.step
+            )));
+        }
+        return Ok(stored);
+    }
+    conn.execute(
+        "INSERT INTO backtest_runs (run_id, chain_id, pair, from_block, to_block, step, next_block, started_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        (
+            &run.run_id,
+            run.chain_id,
+            &run.pair,
+            run.from_block,
+            run.to_block,
+            run.step,
+            run.next_block,
+            Utc::now().to_rfc3339(),
+        ),
+    )?;
+    Ok(run.clone())
+}
+
+/// An opportunity found at one block of an archive replay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestHit<'a> {
+    /// Block the venues were quoted at.
+    pub block_number: u64,
+    /// Venue the base token is bought on.
+    pub buy_dex: &'a str,
+    /// Venue it is sold on.
+    pub sell_dex: &'a str,
+    /// Token path quoted on `buy_dex`.
+    pub buy_path: &'a str,
+    /// Token path quoted on `sell_dex`.
+    pub sell_path: &'a str,
+    /// Difference between the two quotes, in the quote token.
+    pub spread_usdc: f64,
+    /// Gas cost the spread was netted with.
+    pub gas_cost_usdc: f64,
+    /// Net profit after venue fees and gas.
+    pub profit_usdc: f64,
+}
+
+/// Records the outcome of `block` and moves the checkpoint of `run` to
+/// `next_block`, in one transaction, so a resumed run neither skips nor
+/// repeats a block.
+pub fn record_backtest_block(
+    conn: &mut Connection,
+    run: &BacktestRun,
+    next_block: u64,
+    hit: Option<&BacktestHit<'_>>,
+) -> BotResult<()> {
+    let tx = conn.transaction()?;
+    if let Some(hit) = hit {
+        let now = Utc::now();
+        tx.execute(
+            "INSERT INTO backtest_results (run_id, chain_id, pair, block_number, buy_dex, sell_dex, buy_path, sell_path,
+                                           spread_usdc, gas_cost_usdc, profit_usdc, timestamp, timestamp_ms)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            (
+                &run.run_id,
+                run.chain_id,
+                &run.pair,
+                hit.block_number,
+                hit.buy_dex,
+                hit.sell_dex,
+                hit.buy_path,
+                hit.sell_path,
+                hit.spread_usdc,
+                hit.gas_cost_usdc,
+                hit.profit_usdc,
+                now.to_rfc3339(),
+                now.timestamp_millis(),
+            ),
+        )?;
+    }
+    tx.execute(
+        "UPDATE backtest_runs SET next_block = ?2 WHERE run_id = ?1",
+        (&run.run_id, next_block),
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Totals of the rows one archive replay stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestTotals {
+    /// Blocks with an opportunity.
+    pub opportunities_found: u64,
+    /// Sum of their net profit, in the quote token.
+    pub total_profit_usdc: f64,
+    /// Block and profit of the most profitable one.
+    pub best_block: Option<(u64, f64)>,
+}
+
+/// [`BacktestTotals`] of `run_id`.
+pub fn backtest_totals(conn: &Connection, run_id: &str) -> BotResult<BacktestTotals> {
+    let (opportunities_found, total_profit_usdc) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(profit_usdc), 0) FROM backtest_results WHERE run_id = ?1",
+        [run_id],
+        |row| Ok((row.get::<_, u64>(0)?, row.get::<_, f64>(1)?)),
+    )?;
+    let best_block = conn
+        .query_row(
+            "SELECT block_number, profit_usdc FROM backtest_results WHERE run_id = ?1
+             ORDER BY profit_usdc DESC, block_number LIMIT 1",
+            [run_id],
+            |row| Ok((row.get::<_, u64>(0)?, row.get::<_, f64>(1)?)),
+        )
+        .optional()?;
+    Ok(BacktestTotals {
+        opportunities_found,
+        total_profit_usdc,
+        best_block,
+    })
+}
+
+/// The quotes of one tick of a pair, reduced to their spread.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpreadPoint {
+    /// When the tick's quotes were stored, in UTC epoch milliseconds.
+    pub timestamp_ms: i64,
+    /// Block the quotes were taken at, when known.
+    pub block_number: Option<u64>,
+    /// Lowest quote of the tick, in the quote token.
+    pub low_usdc: f64,
+    /// Highest quote of the tick.
+    pub high_usdc: f64,
+    /// `high_usdc - low_usdc`.
+    pub spread_usdc: f64,
+}
+
+/// Spread of `pair` on `chain_id` at each tick stored between `from_ms` and
+/// `to_ms` (UTC epoch milliseconds, inclusive), oldest first; ticks that
+/// quoted fewer than two venues are left out.
+pub fn spread_series(
+    conn: &Connection,
+    chain_id: u64,
+    pair: &str,
+    from_ms: i64,
+    to_ms: i64,
+) -> BotResult<Vec<SpreadPoint>> {
+    let mut stmt = conn.prepare(
+        "SELECT timestamp_ms, MAX(block_number), MIN(price_usdc), MAX(price_usdc) FROM price_history
+         WHERE chain_id = ?1 AND pair = ?2 AND timestamp_ms BETWEEN ?3 AND ?4
+         GROUP BY timestamp_ms
+         HAVING COUNT(*) >= 2
+         ORDER BY timestamp_ms",
+    )?;
+    let rows = stmt
+        .query_map((chain_id, pair, from_ms, to_ms), |row| {
+            let (low_usdc, high_usdc): (f64, f64) = (row.get(2)?, row.get(3)?);
+            Ok(SpreadPoint {
+                timestamp_ms: row.get(0)?,
+                block_number: row.get(1)?,
+                low_usdc,
+                high_usdc,
+                spread_usdc: high_usdc - low_usdc,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// One recorded opportunity, as stored in `arbitrage_bot` and serialized to JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Opportunity {
+    /// EIP-155 id of the chain it was found on.
+    pub chain_id: u64,
+    /// Label of the quoted pair.
+    pub pair: String,
+    /// DEX bought on.
+    pub buy_dex: String,
+    /// DEX sold on.
+    pub sell_dex: String,
+    /// Net profit after fees and gas, in the pair's quote token (USDC unless configured otherwise).
+    pub profit_usdc: f64,
+    /// `profit_usdc` as a percentage of the USDC spent on the buy leg.
+    pub profit_pct: f64,
+    /// Gas cost of the route, in USDC.
+    pub gas_cost_usdc: f64,
+    /// Ranking from [`crate::arb::score_opportunity`].
+    pub score: f64,
+    /// Quote path of the buy leg, as in [`crate::price::path_label`].
+    pub buy_path: String,
+    /// Quote path of the sell leg.
+    pub sell_path: String,
+    /// Outcome of the round-trip simulation, when one ran.
+    pub simulated_ok: Option<bool>,
+    /// Whether either leg's quote had been repeating exactly.
+    pub potentially_stale: bool,
+    /// The anomaly filter judged the tick off its recent history; not notified or executed.
+    pub anomaly: bool,
+    /// Most profitable `trade_size_sweep` size, when configured.
+    pub optimal_size_usdc: Option<f64>,
+    /// Fee of the buy leg, when its pool fee is known.
+    pub buy_fee_usdc: Option<f64>,
+    /// Fee of the sell leg, when its pool fee is known.
+    pub sell_fee_usdc: Option<f64>,
+    /// Mid-price spread covering both legs' fees and gas, in basis points.
+    pub break_even_bps: Option<f64>,
+    /// Closed-form most profitable size, when it could be derived.
+    pub analytic_size_usdc: Option<f64>,
+    /// RFC 3339 detection time.
+    pub timestamp: String,
+    /// Detection time in UTC epoch milliseconds.
+    pub timestamp_ms: i64,
+    /// RFC 3339 time after which [`mark_expired_opportunities`] marks it expired.
+    pub expires_at: String,
+    /// `expires_at` in UTC epoch milliseconds.
+    pub expires_at_ms: i64,
+    /// Implied pool liquidity of the buy venue, when `min_pool_liquidity_usdc` measured it.
+    pub buy_liquidity_usdc: Option<f64>,
+    /// Implied pool liquidity of the sell venue, likewise.
+    pub sell_liquidity_usdc: Option<f64>,
+    /// `profit_usdc` in the configured `report_currency`.
+    pub report_profit: f64,
+    /// Symbol of the token `report_profit` is in.
+    pub report_currency: String,
+    /// Larger shortfall of a simulated leg below its `getAmountsOut`, in basis
+    /// points, when the round trip was simulated.
+    pub quote_shortfall_bps: Option<f64>,
+    /// Spread trend over the pair's last ticks: `rising`, `falling` or `flat`.
+    pub trend: String,
+}
+
+/// Stores `opportunity` in `arbitrage_bot` with status `detected` and returns
+/// its row id.
+pub fn insert_opportunity(conn: &Connection, opportunity: &Opportunity) -> BotResult<i64> {
+    insert_into(conn, "arbitrage_bot", opportunity)
+}
+
+/// [`insert_opportunity`] into [`DRY_RUN_TABLE`].
+pub fn insert_dry_run_opportunity(conn: &Connection, opportunity: &Opportunity) -> BotResult<i64> {
+    insert_into(conn, DRY_RUN_TABLE, opportunity)
+}
+
+fn insert_into(conn: &Connection, table: &str, opportunity: &Opportunity) -> BotResult<i64> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (chain_id, pair, buy_dex, sell_dex, profit_usdc, profit_pct, gas_cost_usdc,
+                                    score, buy_path, sell_path, simulated_ok, potentially_stale,
+                                    optimal_size_usdc, buy_fee_usdc, sell_fee_usdc, break_even_bps,
+                                    analytic_size_usdc, timestamp, timestamp_ms, anomaly, expires_at,
+                                    buy_liquidity_usdc, sell_liquidity_usdc, report_profit,
+                                    report_currency, quote_shortfall_bps, trend, expires_at_ms, status)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21,
+                 ?22, ?23, ?24, ?25, ?26, ?27, ?28, 'detected')",
+            table
+        ),
+        rusqlite::params![
+            opportunity.chain_id,
+            opportunity.pair,
+            opportunity.buy_dex,
+            opportunity.sell_dex,
+            opportunity.profit_usdc,
+            opportunity.profit_pct,
+            opportunity.gas_cost_usdc,
+            opportunity.score,
+            opportunity.buy_path,
+            opportunity.sell_path,
+            opportunity.simulated_ok,
+            opportunity.potentially_stale,
+            opportunity.optimal_size_usdc,
+            opportunity.buy_fee_usdc,
+            opportunity.sell_fee_usdc,
+            opportunity.break_even_bps,
+            opportunity.analytic_size_usdc,
+            opportunity.timestamp,
+            opportunity.timestamp_ms,
+            opportunity.anomaly,
+            opportunity.expires_at,
+            opportunity.buy_liquidity_usdc,
+            opportunity.sell_liquidity_usdc,
+            opportunity.report_profit,
+            opportunity.report_currency,
+            opportunity.quote_shortfall_bps,
+            opportunity.trend,
+            opportunity.expires_at_ms,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Sets status `expired` on every `detected` opportunity whose `expires_at_ms`
+/// is before `as_of_ms` (UTC epoch milliseconds) and returns how many were marked.
+pub fn mark_expired_opportunities(conn: &Connection, as_of_ms: u64) -> BotResult<u64> {
+    let marked = conn.execute(
+        "UPDATE arbitrage_bot SET status = 'expired' WHERE status = 'detected' AND expires_at_ms < ?1",
+        [as_of_ms as i64],
+    )?;
+    Ok(marked as u64)
+}
+
+/// A write queued for the task of [`spawn_writer`].
+#[derive(Debug)]
+pub enum DbWrite {
+    /// Stores an opportunity; its row id is sent back once committed when
+    /// the sender keeps a receiver.
+    InsertOpportunity(Box<Opportunity>, Option<oneshot::Sender<i64>>),
+    /// Stores an opportunity of a dry run in [`DRY_RUN_TABLE`].
+    InsertDryRun(Box<Opportunity>),
+    /// Expires `detected` opportunities as of this UTC epoch millisecond, see
+    /// [`mark_expired_opportunities`].
+    MarkExpired(u64),
+    /// Stores one tick's quotes, see [`insert_prices`]; the number of rows is
+    /// sent back once committed when the sender keeps a receiver.
+    InsertPrices(PriceTick, Option<oneshot::Sender<i64>>),
+    /// Stores an execution attempt of the opportunity row id on the chain id,
+    /// see [`insert_execution`].
+    InsertExecution(i64, u64, Box<ExecutionRecord>),
+}
+
+/// One tick's quotes, queued as [`DbWrite::InsertPrices`].
+#[derive(Debug, Clone)]
+pub struct PriceTick {
+    /// Chain quoted.
+    pub chain_id: u64,
+    /// When the quotes were taken.
+    pub at: DateTime<Utc>,
+    /// One row per venue.
+    pub records: Vec<PriceRecord>,
+}
+
+/// Writes queued before senders wait for the writer.
+pub const WRITE_QUEUE_CAPACITY: usize = 1024;
+
+/// How long the writer keeps collecting writes after the first of a batch
+/// before committing them together.
+const WRITE_BATCH_WINDOW: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Applies the writes sent on the returned channel to `store` from a
+/// background task, committing each 100 ms batch in one transaction, so the
+/// polling loop never waits on the database.
+///
+/// A failed write is logged and dropped. The task ends once every sender is
+/// dropped and the queued writes are applied; await the handle at shutdown.
+pub fn spawn_writer(store: Arc<dyn OpportunityStore>) -> (mpsc::Sender<DbWrite>, JoinHandle<()>) {
+    let (sender, mut receiver) = mpsc::channel(WRITE_QUEUE_CAPACITY);
+    let handle = tokio::spawn(async move {
+        while let Some(first) = receiver.recv().await {
+            let mut batch = vec![first];
+            let window = tokio::time::sleep(WRITE_BATCH_WINDOW);
+            tokio::pin!(window);
+            loop {
+                tokio::select! {
+                    _ = &mut window => break,
+                    write = receiver.recv() => match write {
+                        Some(write) => batch.push(write),
+                        None => break,
+                    },
+                }
+            }
+            apply_writes(store.as_ref(), batch).await;
+        }
+        tracing::info!("Database writer drained");
+    });
+    (sender, handle)
+}
+
+/// Applies `batch` through [`OpportunityStore::apply_batch`], logging each
+/// failed write; replies are sent only once the batch commits.
+async fn apply_writes(store: &dyn OpportunityStore, batch: Vec<DbWrite>) {
+    let writes = batch.len();
+    let outcomes = match store.apply_batch(&batch).await {
+        Ok(outcomes) => outcomes,
+        Err(err) => {
+            tracing::error!(%err, writes, "Database batch failed; dropping its writes");
+            return;
+        }
+    };
+    for (write, outcome) in batch.into_iter().zip(outcomes) {
+        match (write, outcome) {
+            (
+                DbWrite::InsertOpportunity(_, Some(reply)) | DbWrite::InsertPrices(_, Some(reply)),
+                Ok(outcome),
+            ) => {
+                // The scanner may have stopped waiting.
+                let _ = reply.send(outcome);
+            }
+            (DbWrite::InsertOpportunity(opportunity, _), Err(err)) => {
+                tracing::error!(%err, pair = %opportunity.pair, "Opportunity not stored")
+            }
+            (DbWrite::InsertDryRun(opportunity), Err(err)) => {
+                tracing::error!(%err, pair = %opportunity.pair, "Dry-run opportunity not stored")
+            }
+            (DbWrite::MarkExpired(_), Ok(expired)) if expired > 0 => {
+                tracing::debug!(expired, "Marked opportunities past their TTL as expired")
+            }
+            (DbWrite::MarkExpired(_), Err(err)) => {
+                tracing::error!(%err, "Expiring opportunities failed")
+            }
+            (DbWrite::InsertPrices(tick, _), Err(err)) => {
+                tracing::error!(%err, chain_id = tick.chain_id, "Prices not stored")
+            }
+            (DbWrite::InsertExecution(opportunity_id, _, record), Err(err)) => {
+                tracing::error!(%err, opportunity_id, ?record, "Execution not stored")
+            }
+            _ => {}
+        }
+    }
+}
+
+/// [`OpportunityStore::apply_batch`] on SQLite: applies `batch` in one
+/// transaction. A failed statement leaves the rest of the transaction intact,
+/// so its error is returned in its slot.
+pub(crate) fn stage_writes(
+    conn: &mut Connection,
+    batch: &[DbWrite],
+) -> BotResult<Vec<BotResult<i64>>> {
+    let tx = conn.transaction()?;
+    let outcomes = batch
+        .iter()
+        .map(|write| match write {
+            DbWrite::InsertOpportunity(opportunity, _) => insert_opportunity(&tx, opportunity),
+            DbWrite::InsertDryRun(opportunity) => insert_dry_run_opportunity(&tx, opportunity),
+            DbWrite::MarkExpired(as_of_ms) => {
+                mark_expired_opportunities(&tx, *as_of_ms).map(|marked| marked as i64)
+            }
+            DbWrite::InsertPrices(tick, _) => {
+                stage_prices(&tx, tick.chain_id, tick.at, &tick.records)
+                    .map(|()| tick.records.len() as i64)
+            }
+            DbWrite::InsertExecution(opportunity_id, chain_id, record) => {
+                insert_execution(&tx, *opportunity_id, *chain_id, record).map(|()| 1)
+            }
+        })
+        .collect();
+    tx.commit()?;
+    Ok(outcomes)
+}
+
+/// One execution attempt of a recorded opportunity, as stored in `executions`.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionRecord {
+    /// `"live"` or `"paper"`.
+    pub mode: &'static str,
+    /// `"swaps"` for two sequential swaps, `"flashloan"` for the atomic executor.
+    pub kind: &'static str,
+    /// `"paper"`, `"skipped"`, `"confirmed"` or `"failed"`.
+    pub status: &'static str,
+    /// Hash of the buy-leg swap, or of the flashloan transaction, once sent.
+    pub buy_tx: Option<String>,
+    /// Hash of the sell-leg swap, once sent; unused by flashloans.
+    pub sell_tx: Option<String>,
+    /// USDC spent on the buy leg.
+    pub usdc_in: f64,
+    /// WETH received on the buy leg; quoted for paper runs.
+    pub weth_received: Option<f64>,
+    /// USDC received on the sell leg; quoted for paper runs.
+    pub usdc_out: Option<f64>,
+    /// Wallet USDC change over both legs, excluding gas; live runs only.
+    pub realized_profit_usdc: Option<f64>,
+    /// Why the attempt was skipped or failed.
+    pub error: Option<String>,
+    /// Whether the wallet held the trade's input and gas; live runs that got
+    /// that far only.
+    pub wallet_check_passed: Option<bool>,
+}
+
+/// Stores `record` against the `arbitrage_bot` row `opportunity_id`.
+pub fn insert_execution(
+    conn: &Connection,
+    opportunity_id: i64,
+    chain_id: u64,
+    record: &ExecutionRecord,
+) -> BotResult<()> {
+    let now = Utc::now();
+    conn.execute(
+        "INSERT INTO executions (opportunity_id, chain_id, mode, status, buy_tx, sell_tx, usdc_in,
+                                 weth_received, usdc_out, realized_profit_usdc, error, timestamp, kind,
+                                 timestamp_ms, wallet_check_passed)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        (
+            opportunity_id,
+            chain_id,
+            record.mode,
+            record.status,
+            &record.buy_tx,
+            &record.sell_tx,
+            record.usdc_in,
+            record.weth_received,
+            record.usdc_out,
+            record.realized_profit_usdc,
+            &record.error,
+            now.to_rfc3339(),
+            record.kind,
+            now.timestamp_millis(),
+            record.wallet_check_passed,
+        ),
     )?;
-    println!("Database and table created!");
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Realized USDC lost by live executions on `chain_id` since UTC midnight, as a
+/// positive number; profitable executions do not offset it.
+pub fn realized_loss_today(conn: &Connection, chain_id: u64) -> BotResult<f64> {
+    let midnight = utc_midnight_ms();
+    let loss: Option<f64> = conn.query_row(
+        "SELECT SUM(-realized_profit_usdc) FROM executions
+         WHERE mode = 'live' AND chain_id = ?1 AND realized_profit_usdc < 0
+           AND timestamp_ms >= ?2",
+        (chain_id, midnight),
+        |row| row.get(0),
+    )?;
+    Ok(loss.unwrap_or(0.0))
+}
+
+/// Last UTC midnight in epoch milliseconds.
+pub(crate) fn utc_midnight_ms() -> i64 {
+    Utc::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight exists")
+        .and_utc()
+        .timestamp_millis()
+}
+
+/// One broadcast transaction of the execution wallet, as stored in `transactions`.
+#[derive(Debug, Clone)]
+pub struct SentTransaction<'a> {
+    /// Sending wallet, as `{:?}` of its address.
+    pub wallet: &'a str,
+    /// Transaction hash, as `{:?}` of the hash.
+    pub hash: &'a str,
+    /// Nonce it was sent with; replacements share it.
+    pub nonce: u64,
+    /// Gas price it was sent at.
+    pub gas_price_gwei: f64,
+    /// What it does: `"approval"`, `"swap"` or `"flashloan"`.
+    pub label: &'a str,
+}
+
+/// Stores a just-broadcast `tx` on `chain_id` with status `pending`.
+pub fn insert_transaction(conn: &Connection, chain_id: u64, tx: &SentTransaction) -> BotResult<()> {
+    let now = Utc::now();
+    conn.execute(
+        "INSERT INTO transactions (chain_id, wallet, hash, nonce, gas_price_gwei, label, status, timestamp,
+                                   timestamp_ms)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'pending', ?7, ?8)",
+        (
+            chain_id,
+            tx.wallet,
+            tx.hash,
+            tx.nonce,
+            tx.gas_price_gwei,
+            tx.label,
+            now.to_rfc3339(),
+            now.timestamp_millis(),
+        ),
+    )?;
+    Ok(())
+}
+
+/// Resolves transaction `hash` to `status` (`"confirmed"`, `"reverted"`,
+/// `"replaced"` or `"dropped"`), with the block it was mined in if any.
+pub fn resolve_transaction(
+    conn: &Connection,
+    hash: &str,
+    status: &str,
+    confirmed_block: Option<u64>,
+) -> BotResult<()> {
+    conn.execute(
+        "UPDATE transactions SET status = ?2, confirmed_block = ?3 WHERE hash = ?1",
+        (hash, status, confirmed_block),
+    )?;
+    Ok(())
+}
+
+/// `(hash, nonce)` of every transaction of `wallet` on `chain_id` still marked
+/// `pending`, oldest first.
+pub fn pending_transactions(
+    conn: &Connection,
+    chain_id: u64,
+    wallet: &str,
+) -> BotResult<Vec<(String, u64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT hash, nonce FROM transactions
+         WHERE chain_id = ?1 AND wallet = ?2 AND status = 'pending'
+         ORDER BY id",
+    )?;
+    let rows = stmt
+        .query_map((chain_id, wallet), |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Windowed price statistics for one DEX/pair, derived from `price_history`.
+#[derive(Debug, Clone)]
+pub struct OpportunityStats {
+    /// DEX the statistics were computed for.
+    pub dex: String,
+    /// Pair the statistics were computed for.
+    pub pair: String,
+    /// Time-weighted average price, see [`compute_twap`].
+    pub twap: f64,
+    /// Volume-weighted average price, see [`compute_vwap`].
+    pub vwap: f64,
+}
+
+/// TWAP and VWAP of `dex`/`pair` over the last `window_secs` seconds.
+pub fn opportunity_stats(
+    conn: &Connection,
+    dex: &str,
+    pair: &str,
+    window_secs: u64,
+) -> BotResult<OpportunityStats> {
+    Ok(OpportunityStats {
+        dex: dex.to_string(),
+        pair: pair.to_string(),
+        twap: compute_twap(conn, dex, pair, window_secs)?,
+        vwap: compute_vwap(conn, dex, pair, window_secs)?,
+    })
+}
+
+/// Time-weighted average price of `dex`/`pair` over the last `window_secs` seconds.
+///
+/// Each observation is weighted by how long it stayed the latest price, i.e. the
+/// gap until the next row. A window holding a single observation returns it as-is.
+pub fn compute_twap(conn: &Connection, dex: &str, pair: &str, window_secs: u64) -> BotResult<f64> {
+    let since = window_start_ms(window_secs);
+    let mut stmt = conn.prepare(
+        "SELECT price_usdc, timestamp_ms FROM price_history
+         WHERE dex = ?1 AND pair = ?2 AND timestamp_ms >= ?3
+         ORDER BY timestamp_ms ASC",
+    )?;
+    let points = stmt
+        .query_map((dex, pair, since), |row| {
+            Ok((row.get::<_, f64>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    time_weighted(&points).ok_or_else(|| {
+        BotError::Db(format!(
+            "no price history for {} on {} in the last {}s",
+            pair, dex, window_secs
+        ))
+    })
+}
+
+/// Average of `(price, timestamp_ms)` points in time order, each weighted by
+/// the gap until the next; the last price when they share one instant, `None`
+/// when there are none.
+pub(crate) fn time_weighted(points: &[(f64, i64)]) -> Option<f64> {
+    let &(last_price, _) = points.last()?;
+    let mut weighted = 0.0;
+    let mut total_secs = 0.0;
+    for w in points.windows(2) {
+        let (price, start) = w[0];
+        let (_, end) = w[1];
+        let secs = (end - start) as f64 / 1000.0;
+        weighted += price * secs;
+        total_secs += secs;
+    }
+
+    if total_secs > 0.0 {
+        Some(weighted / total_secs)
+    } else {
+        Some(last_price)
+    }
+}
+
+/// `timestamp_ms` of the start of a window ending now.
+pub(crate) fn window_start_ms(window_secs: u64) -> i64 {
+    (Utc::now() - Duration::seconds(window_secs as i64)).timestamp_millis()
+}
+
+/// Volume-weighted average price of `dex`/`pair` over the last `window_secs` seconds.
+pub fn compute_vwap(conn: &Connection, dex: &str, pair: &str, window_secs: u64) -> BotResult<f64> {
+    let (weighted, volume): (Option<f64>, Option<f64>) = conn.query_row(
+        "SELECT SUM(price_usdc * volume_usdc), SUM(volume_usdc) FROM price_history
+         WHERE dex = ?1 AND pair = ?2 AND timestamp_ms >= ?3 AND volume_usdc IS NOT NULL",
+        (dex, pair, window_start_ms(window_secs)),
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    match (weighted, volume) {
+        (Some(weighted), Some(volume)) if volume > 0.0 => Ok(weighted / volume),
+        _ => Err(BotError::Db(format!(
+            "no volume history for {} on {} in the last {}s",
+            pair, dex, window_secs
+        ))),
+    }
+}
+
+/// One stored opportunity with its score.
+#[derive(Debug, Clone)]
+pub struct ScoredOpportunity {
+    /// DEX bought on.
+    pub buy_dex: String,
+    /// DEX sold on.
+    pub sell_dex: String,
+    /// Net profit in USDC.
+    pub profit_usdc: f64,
+    /// Ranking from [`crate::arb::score_opportunity`].
+    pub score: f64,
+    /// RFC 3339 detection time.
+    pub timestamp: String,
+}
+
+/// Up to `limit` stored opportunities, highest score first. Rows recorded before
+/// scoring existed have no score and are left out.
+pub fn sort_by_score(conn: &Connection, limit: u64) -> BotResult<Vec<ScoredOpportunity>> {
+    let mut stmt = conn.prepare(
+        "SELECT buy_dex, sell_dex, profit_usdc, score, timestamp FROM arbitrage_bot
+         WHERE score IS NOT NULL
+         ORDER BY score DESC
+         LIMIT ?1",
+    )?;
+    let rows = stmt
+        .query_map([limit], |row| {
+            Ok(ScoredOpportunity {
+                buy_dex: row.get(0)?,
+                sell_dex: row.get(1)?,
+                profit_usdc: row.get(2)?,
+                score: row.get(3)?,
+                timestamp: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// One stored opportunity as listed by `GET /opportunities`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentOpportunity {
+    /// Row id.
+    pub id: i64,
+    /// Chain it was detected on; `null` on rows from before multi-chain support.
+    pub chain_id: Option<u64>,
+    /// Label of the pair; `null` on rows from before multi-pair support.
+    pub pair: Option<String>,
+    /// DEX bought on.
+    pub buy_dex: String,
+    /// DEX sold on.
+    pub sell_dex: String,
+    /// Net profit in the pair's quote token.
+    pub profit_usdc: f64,
+    /// Gas cost of the route, when stored.
+    pub gas_cost_usdc: Option<f64>,
+    /// Ranking from [`crate::arb::score_opportunity`], when stored.
+    pub score: Option<f64>,
+    /// `detected` or `expired`; `null` on rows from before the TTL.
+    pub status: Option<String>,
+    /// RFC 3339 detection time.
+    pub timestamp: String,
+    /// `instance_id` of the bot that stored it; only set under Postgres.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+}
+
+impl RecentOpportunity {
+    /// Row `id`, just stored from `opportunity`, as it will read back.
+    pub fn recorded(id: i64, opportunity: &Opportunity) -> Self {
+        Self {
+            id,
+            chain_id: Some(opportunity.chain_id),
+            pair: Some(opportunity.pair.clone()),
+            buy_dex: opportunity.buy_dex.clone(),
+            sell_dex: opportunity.sell_dex.clone(),
+            profit_usdc: opportunity.profit_usdc,
+            gas_cost_usdc: Some(opportunity.gas_cost_usdc),
+            score: Some(opportunity.score),
+            status: Some("detected".into()),
+            timestamp: opportunity.timestamp.clone(),
+            instance_id: None,
+        }
+    }
+}
+
+/// The `limit` most recently stored opportunities, newest first.
+pub fn recent_opportunities(conn: &Connection, limit: u64) -> BotResult<Vec<RecentOpportunity>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, chain_id, pair, buy_dex, sell_dex, profit_usdc, gas_cost_usdc, score, status,
+                timestamp
+         FROM arbitrage_bot
+         ORDER BY id DESC
+         LIMIT ?1",
+    )?;
+    let rows = stmt
+        .query_map([limit], |row| {
+            Ok(RecentOpportunity {
+                id: row.get(0)?,
+                chain_id: row.get(1)?,
+                pair: row.get(2)?,
+                buy_dex: row.get(3)?,
+                sell_dex: row.get(4)?,
+                profit_usdc: row.get(5)?,
+                gas_cost_usdc: row.get(6)?,
+                score: row.get(7)?,
+                status: row.get(8)?,
+                timestamp: row.get(9)?,
+                instance_id: None,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows)
+}
+
+/// Opportunity count and profit aggregates for one buy → sell direction.
+#[derive(Debug, Clone)]
+pub struct RouteStats {
+    /// DEX bought on.
+    pub buy_dex: String,
+    /// DEX sold on.
+    pub sell_dex: String,
+    /// Opportunities recorded in this direction.
+    pub count: u64,
+    /// Mean profit in USDC.
+    pub avg_profit_usdc: f64,
+    /// Largest profit in USDC.
+    pub max_profit_usdc: f64,
+}
+
+/// Number of opportunities recorded on one UTC day (`YYYY-MM-DD`).
+#[derive(Debug, Clone)]
+pub struct DailyCount {
+    /// UTC day as `YYYY-MM-DD`.
+    pub day: String,
+    /// Opportunities recorded that day.
+    pub count: u64,
+}
+
+/// Aggregates over the whole `arbitrage_bot` table.
+#[derive(Debug, Clone)]
+pub struct HistoryStats {
+    /// Total opportunities recorded.
+    pub total: u64,
+    /// Mean profit in USDC.
+    pub avg_profit_usdc: f64,
+    /// Median profit in USDC.
+    pub median_profit_usdc: f64,
+    /// Largest profit in USDC.
+    pub max_profit_usdc: f64,
+    /// Breakdown per buy → sell direction, most frequent first.
+    pub routes: Vec<RouteStats>,
+    /// Breakdown per UTC day, oldest first.
+    pub per_day: Vec<DailyCount>,
+}
+
+/// Summarizes the whole `arbitrage_bot` table, zeros when empty.
+pub fn history_stats(conn: &Connection) -> BotResult<HistoryStats> {
+    let (total, avg_profit_usdc, max_profit_usdc): (u64, Option<f64>, Option<f64>) = conn
+        .query_row(
+            "SELECT COUNT(*), AVG(profit_usdc), MAX(profit_usdc) FROM arbitrage_bot",
+            (),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+    // SQLite has no MEDIAN(); average the one or two middle rows instead.
+    let median_profit_usdc: Option<f64> = conn.query_row(
+        "SELECT AVG(profit_usdc) FROM (
+            SELECT profit_usdc FROM arbitrage_bot ORDER BY profit_usdc
+            LIMIT 2 - (SELECT COUNT(*) FROM arbitrage_bot) % 2
+            OFFSET ((SELECT COUNT(*) FROM arbitrage_bot) - 1) / 2
+        )",
+        (),
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT buy_dex, sell_dex, COUNT(*), AVG(profit_usdc), MAX(profit_usdc)
+         FROM arbitrage_bot
+         GROUP BY buy_dex, sell_dex
+         ORDER BY COUNT(*) DESC",
+    )?;
+    let routes = stmt
+        .query_map((), |row| {
+            Ok(RouteStats {
+                buy_dex: row.get(0)?,
+                sell_dex: row.get(1)?,
+                count: row.get(2)?,
+                avg_profit_usdc: row.get(3)?,
+                max_profit_usdc: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT substr(timestamp, 1, 10) AS day, COUNT(*)
+         FROM arbitrage_bot
+         GROUP BY day
+         ORDER BY day",
+    )?;
+    let per_day = stmt
+        .query_map((), |row| {
+            Ok(DailyCount {
+                day: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(HistoryStats {
+        total,
+        avg_profit_usdc: avg_profit_usdc.unwrap_or(0.0),
+        median_profit_usdc: median_profit_usdc.unwrap_or(0.0),
+        max_profit_usdc: max_profit_usdc.unwrap_or(0.0),
+        routes,
+        per_day,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(dex: &str, price_usdc: f64, volume_usdc: f64) -> PriceRecord {
+        PriceRecord {
+            dex: dex.into(),
+            pair: "WETH/USDC".into(),
+            amount_in: 1.0,
+            price_usdc,
+            volume_usdc,
+            block_number: None,
+            impermanent_loss_pct: None,
+        }
+    }
+
+    #[test]
+    fn stats_weigh_prices_by_time_and_by_volume() {
+        let pool = open_pool("file:stats_weigh_prices?mode=memory&cache=shared").unwrap();
+        init_db(&pool).unwrap();
+        let mut conn = pool.get().unwrap();
+        let now = Utc::now();
+        for (secs_ago, price_usdc, volume_usdc) in [
+            // Outside the 600 s window.
+            (1_000, 2_000.0, 9_999.0),
+            (300, 2_500.0, 1_000.0),
+            (200, 2_600.0, 3_000.0),
+            (100, 2_700.0, 2_000.0),
+        ] {
+            let at = now - Duration::seconds(secs_ago);
+            insert_prices(
+                &mut conn,
+                137,
+                at,
+                &[price("QuickSwap", price_usdc, volume_usdc)],
+            )
+            .unwrap();
+        }
+        // Another venue's rows do not count.
+        insert_prices(&mut conn, 137, now, &[price("SushiSwap", 9_000.0, 1.0)]).unwrap();
+
+        let stats = opportunity_stats(&conn, "QuickSwap", "WETH/USDC", 600).unwrap();
+        // 2500 and 2600 each stood for 100 s; the latest price has no duration yet.
+        assert_eq!(stats.twap, 2_550.0);
+        // SUM(price × volume) / SUM(volume) over the three rows in the window.
+        let vwap = (2_500.0 * 1_000.0 + 2_600.0 * 3_000.0 + 2_700.0 * 2_000.0) / 6_000.0;
+        assert!((stats.vwap - vwap).abs() < 1e-9, "{}", stats.vwap);
+
+        assert!(opportunity_stats(&conn, "QuickSwap", "WETH/USDT", 600).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Error type of the core functions, so callers can tell a bad config from a
+//! failing node or database instead of matching on message text.
+
+use crate::price::PriceError;
+
+/// What a core function failed on.
+#[derive(Debug, thiserror::Error)]
+pub enum BotError {
+    /// `config.toml` is missing, unparseable or invalid; no retry fixes it.
+    #[error("config: {0}")]
+    Config(String),
+    /// The RPC node failed to answer or disagreed with the config.
+    #[error("rpc: {0}")]
+    Rpc(String),
+    /// Reading or writing the database failed.
+    #[error("db: {0}")]
+    Db(String),
+    /// An ABI could not be read, parsed or encoded against.
+    #[error("abi: {0}")]
+    Abi(String),
+    /// A venue cannot fill the path: no pool, or the quote reverted.
+    #[error("no liquidity: {0}")]
+    NoLiquidity(String),
+}
+
+/// Result of the core functions.
+pub type BotResult<T> = std::result::Result<T, BotError>;
+
+impl BotError {
+    /// Short name of the variant, e.g. for API error bodies.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Config(_) => "config",
+            Self::Rpc(_) => "rpc",
+            Self::Db(_) => "db",
+            Self::Abi(_) => "abi",
+            Self::NoLiquidity(_) => "no_liquidity",
+        }
+    }
+
+    /// Whether the failure may clear on a retry: node and database errors,
+    /// unlike config, ABI and liquidity ones.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Rpc(_) | Self::Db(_))
+    }
+}
+
+impl From<rusqlite::Error> for BotError {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::Db(err.to_string())
+    }
+}
+
+impl From<r2d2::Error> for BotError {
+    fn from(err: r2d2::Error) -> Self {
+        Self::Db(err.to_string())
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<tokio_postgres::Error> for BotError {
+    fn from(err: tokio_postgres::Error) -> Self {
+        // Its `Display` leaves out the server's message, which is the source.
+        match std::error::Error::source(&err) {
+            Some(cause) => Self::Db(format!("{}: {}", err, cause)),
+            None => Self::Db(err.to_string()),
+        }
+    }
+}
+
+impl From<ethers::providers::ProviderError> for BotError {
+    fn from(err: ethers::providers::ProviderError) -> Self {
+        Self::Rpc(err.to_string())
+    }
+}
+
+impl From<ethers::abi::AbiError> for BotError {
+    fn from(err: ethers::abi::AbiError) -> Self {
+        Self::Abi(err.to_string())
+    }
+}
+
+impl<M: ethers::providers::Middleware> From<ethers::contract::ContractError<M>> for BotError {
+    fn from(err: ethers::contract::ContractError<M>) -> Self {
+        match err {
+            ethers::contract::ContractError::AbiError(err) => Self::Abi(err.to_string()),
+            err => Self::Rpc(err.to_string()),
+        }
+    }
+}
+
+impl From<PriceError> for BotError {
+    fn from(err: PriceError) -> Self {
+        match err {
+            PriceError::NoLiquidityPath(reason) | PriceError::Revert(reason) => {
+                Self::NoLiquidity(reason)
+            }
+            PriceError::AbiEncoding(reason) => Self::Abi(reason),
+            err @ (PriceError::Rpc(_) | PriceError::EmptyResponse) => Self::Rpc(err.to_string()),
+        }
+    }
+}
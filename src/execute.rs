@@ -0,0 +1,695 @@
+//! Sends the two swaps of a recorded opportunity, or logs them in paper mode.
+
+use anyhow::Result;
+use chrono::Utc;
+use ethers::abi::{Abi, parse_abi};
+use ethers::contract::Contract;
+use ethers::core::types::{Address, TransactionReceipt, U256};
+use ethers::providers::{Http, Middleware, Provider};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::allowance::AllowanceManager;
+use crate::arb;
+use crate::config::{self, DexEntry, ExecutionSettings};
+use crate::db::ExecutionRecord;
+use crate::error::{BotError, BotResult};
+use crate::flashloan::{self, FlashParams};
+use crate::nonce::NonceManager;
+use crate::price;
+
+/// The round trip chosen for an opportunity: buy WETH with `usdc_in` on
+/// `buy_dex`, then sell it on `sell_dex`.
+#[derive(Debug, Clone, Copy)]
+pub struct Trade<'a> {
+    /// Venue WETH is bought on.
+    pub buy_dex: &'a str,
+    /// Quoted WETH → … → USDC path of the buy venue; the buy leg trades it in reverse.
+    pub buy_path: &'a [Address],
+    /// Venue WETH is sold on.
+    pub sell_dex: &'a str,
+    /// Quoted WETH → … → USDC path of the sell venue.
+    pub sell_path: &'a [Address],
+    /// USDC spent on the buy leg, in token units.
+    pub usdc_in: U256,
+    /// Native coin the wallet must hold for gas, in wei.
+    pub gas_reserve: U256,
+}
+
+/// A balance the wallet holds less of than a trade needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceShortfall {
+    /// Token contract; `None` for the chain's native coin.
+    pub token: Option<Address>,
+    /// Amount needed, in token units.
+    pub required: U256,
+    /// Amount held, in token units.
+    pub available: U256,
+}
+
+impl fmt::Display for BalanceShortfall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.token {
+            Some(token) => write!(f, "token {:?}", token)?,
+            None => write!(f, "native coin")?,
+        }
+        write!(f, ": holds {}, needs {}", self.available, self.required)
+    }
+}
+
+/// `balanceOf(wallet)` of every `(token, required)`; returns those falling short.
+pub async fn check_wallet_balances(
+    provider: &Provider<Http>,
+    wallet: Address,
+    required: &[(Address, U256)],
+) -> Result<Vec<BalanceShortfall>> {
+    let erc20 = parse_abi(&["function balanceOf(address) view returns (uint256)"])
+        .expect("static ERC-20 fragment parses");
+    let provider = Arc::new(provider.clone());
+    let mut shortfalls = Vec::new();
+    for &(token, required) in required {
+        let available = Contract::new(token, erc20.clone(), provider.clone())
+            .method::<_, U256>("balanceOf", wallet)?
+            .call()
+            .await?;
+        if available < required {
+            shortfalls.push(BalanceShortfall {
+                token: Some(token),
+                required,
+                available,
+            });
+        }
+    }
+    Ok(shortfalls)
+}
+
+/// Both legs as re-quoted just before sending.
+struct Legs {
+    buy_router: Address,
+    sell_router: Address,
+    /// USDC → … → WETH.
+    buy_path: Vec<Address>,
+    weth_quote: U256,
+    weth_min: U256,
+    usdc_min: U256,
+    deadline: U256,
+}
+
+/// Why an attempt stopped early.
+enum Abort {
+    /// A safety limit or an unsupported venue; nothing was sent.
+    Skip(String),
+    /// An RPC call or transaction failed.
+    Fail(String),
+}
+
+/// USDC committed by recent executions, shared by every scanner and held against
+/// `execution.max_exposure_usdc`.
+#[derive(Debug, Default)]
+pub struct ExposureLedger {
+    /// Open commitments: ticket, release time and USDC amount.
+    open: Mutex<Vec<(u64, Instant, f64)>>,
+    next_ticket: Mutex<u64>,
+}
+
+impl ExposureLedger {
+    /// Commits `usdc` for `hold` when that keeps the total within `cap`.
+    ///
+    /// Returns a ticket for [`Self::release`], or the current exposure when the
+    /// trade would exceed the cap.
+    pub fn reserve(&self, usdc: f64, cap: Option<f64>, hold: Duration) -> Result<u64, f64> {
+        let mut open = self.open.lock().unwrap();
+        let now = Instant::now();
+        open.retain(|&(_, until, _)| until > now);
+        let current: f64 = open.iter().map(|&(_, _, amount)| amount).sum();
+        if cap.is_some_and(|cap| current + usdc > cap) {
+            return Err(current);
+        }
+        let mut next = self.next_ticket.lock().unwrap();
+        let ticket = *next;
+        *next += 1;
+        open.push((ticket, now + hold, usdc));
+        Ok(ticket)
+    }
+
+    /// Frees a commitment early, e.g. when the attempt was skipped.
+    pub fn release(&self, ticket: u64) {
+        self.open.lock().unwrap().retain(|&(id, _, _)| id != ticket);
+    }
+}
+
+/// Executes opportunities through the `uniswap_v2` routers of one chain.
+pub struct Executor {
+    settings: ExecutionSettings,
+    provider: Arc<Provider<Http>>,
+    /// Sending wallet; absent in paper mode.
+    wallet: Option<Arc<NonceManager>>,
+    /// Router allowances of the signing wallet; absent in paper mode.
+    allowances: Option<AllowanceManager>,
+    router_abi: Abi,
+    erc20: Abi,
+    /// Router per `uniswap_v2` entry name.
+    routers: HashMap<String, Address>,
+    weth: Address,
+    /// Decimals of `weth`, the pair's base token.
+    base_decimals: u32,
+    usdc: Address,
+    /// Decimals of `usdc`, the pair's quote token.
+    quote_decimals: u32,
+    /// Flashloan executor contract, when configured.
+    flash_executor: Option<Address>,
+    executor_abi: Abi,
+    /// Aave V3 Pool lending to `flash_executor` directly, when configured.
+    aave_pool: Option<Address>,
+}
+
+impl Executor {
+    /// Prepares execution through the routers of `dex`, trading the base token
+    /// `weth` for the quote token `usdc`, each of the given decimals; live mode
+    /// sends from `wallet`, which paper mode does without.
+    pub fn new(
+        settings: &ExecutionSettings,
+        dex: &[DexEntry],
+        (weth, base_decimals): (Address, u32),
+        (usdc, quote_decimals): (Address, u32),
+        router_abi: Abi,
+        provider: Arc<Provider<Http>>,
+        wallet: Option<Arc<NonceManager>>,
+    ) -> BotResult<Self> {
+        if !settings.paper && wallet.is_none() {
+            return Err(BotError::Config(
+                "live execution needs the chain's wallet".into(),
+            ));
+        }
+        let wallet = wallet.filter(|_| !settings.paper);
+        let allowances = wallet
+            .as_ref()
+            .map(|wallet| AllowanceManager::new(wallet.clone(), settings.approval));
+        Ok(Self {
+            settings: settings.clone(),
+            provider,
+            wallet,
+            allowances,
+            router_abi,
+            erc20: parse_abi(&["function balanceOf(address) view returns (uint256)"])
+                .expect("static ERC-20 fragment parses"),
+            routers: price::v2_routers(dex)?,
+            weth,
+            base_decimals,
+            usdc,
+            quote_decimals,
+            flash_executor: match &settings.flashloan_executor {
+                Some(address) => Some(config::parse_address(
+                    "execution.flashloan_executor",
+                    address,
+                )?),
+                None => None,
+            },
+            executor_abi: flashloan::executor_abi(),
+            aave_pool: match &settings.aave_pool {
+                Some(address) => Some(config::parse_address("execution.aave_pool", address)?),
+                None => None,
+            },
+        })
+    }
+
+    /// Raw units of `amount` of the quote token.
+    fn quote_units(&self, amount: f64) -> U256 {
+        arb::amount_to_units(amount, self.quote_decimals)
+    }
+
+    /// Amount of the quote token in raw `units`.
+    fn quote_amount(&self, units: U256) -> f64 {
+        arb::units_to_amount(units, self.quote_decimals)
+    }
+
+    /// Amount of the base token in raw `units`.
+    fn base_amount(&self, units: U256) -> f64 {
+        arb::units_to_amount(units, self.base_decimals)
+    }
+
+    /// Startup approvals of USDC and WETH for every router the swaps can use;
+    /// nothing to do in paper mode or with a flashloan executor.
+    pub async fn prepare(&self) -> BotResult<()> {
+        let Some(allowances) = &self.allowances else {
+            return Ok(());
+        };
+        if self.flash_executor.is_some() {
+            return Ok(());
+        }
+        let routers: Vec<Address> = self.routers.values().copied().collect();
+        let usdc_minimum = self.quote_units(self.settings.max_trade_size_usdc);
+        // The WETH amount is only known once a buy lands, so exact mode approves
+        // it per trade.
+        allowances
+            .prepare(
+                &[(self.usdc, usdc_minimum), (self.weth, U256::zero())],
+                &routers,
+            )
+            .await
+    }
+
+    /// Runs `trade` once; `loss_today_usdc` is the realized loss already counted
+    /// against the daily cap.
+    ///
+    /// Never fails: skips and errors are reported in the returned record.
+    pub async fn execute(&self, trade: &Trade<'_>, loss_today_usdc: f64) -> ExecutionRecord {
+        let mut record = ExecutionRecord {
+            mode: if self.settings.paper { "paper" } else { "live" },
+            kind: if self.flash_executor.is_some() {
+                "flashloan"
+            } else {
+                "swaps"
+            },
+            usdc_in: self.quote_amount(trade.usdc_in),
+            ..Default::default()
+        };
+        match self.attempt(trade, loss_today_usdc, &mut record).await {
+            Ok(status) => record.status = status,
+            Err(Abort::Skip(reason)) => {
+                record.status = "skipped";
+                record.error = Some(reason);
+            }
+            Err(Abort::Fail(reason)) => {
+                record.status = "failed";
+                record.error = Some(reason);
+            }
+        }
+        record
+    }
+
+    async fn attempt(
+        &self,
+        trade: &Trade<'_>,
+        loss_today_usdc: f64,
+        record: &mut ExecutionRecord,
+    ) -> Result<&'static str, Abort> {
+        let buy_router = self.router(trade.buy_dex)?;
+        let sell_router = self.router(trade.sell_dex)?;
+        if record.usdc_in > self.settings.max_trade_size_usdc {
+            return Err(Abort::Skip(format!(
+                "trade size {:.2} USDC exceeds max_trade_size_usdc {:.2}",
+                record.usdc_in, self.settings.max_trade_size_usdc
+            )));
+        }
+        if loss_today_usdc >= self.settings.daily_loss_cap_usdc {
+            return Err(Abort::Skip(format!(
+                "daily loss cap reached ({:.2} USDC lost today)",
+                loss_today_usdc
+            )));
+        }
+        let gas_price = self.provider.get_gas_price().await.map_err(fail)?;
+        let gas_gwei = gas_price.as_u128() as f64 / 1e9;
+        if gas_gwei > self.settings.max_gas_price_gwei {
+            return Err(Abort::Skip(format!(
+                "gas price {:.1} gwei exceeds max_gas_price_gwei {:.1}",
+                gas_gwei, self.settings.max_gas_price_gwei
+            )));
+        }
+        if let Some(wallet) = &self.wallet {
+            self.check_wallet(wallet.address(), trade, record).await?;
+        }
+
+        let buy_path: Vec<Address> = trade.buy_path.iter().rev().copied().collect();
+        let weth_quote = self.quote(buy_router, trade.usdc_in, &buy_path).await?;
+        let usdc_quote = self.quote(sell_router, weth_quote, trade.sell_path).await?;
+        record.weth_received = Some(self.base_amount(weth_quote));
+        record.usdc_out = Some(self.quote_amount(usdc_quote));
+        let weth_min = self.min_out(weth_quote);
+        let usdc_min = self.min_out(usdc_quote);
+        let deadline = U256::from(Utc::now().timestamp() as u64 + self.settings.deadline_secs);
+        if let Some(executor) = self.flash_executor {
+            let legs = Legs {
+                buy_router,
+                sell_router,
+                buy_path,
+                weth_quote,
+                weth_min,
+                usdc_min,
+                deadline,
+            };
+            return self.attempt_flash(executor, trade, &legs, record).await;
+        }
+
+        let (Some(sender), Some(allowances)) = (&self.wallet, &self.allowances) else {
+            info!(
+                buy_dex = trade.buy_dex,
+                sell_dex = trade.sell_dex,
+                usdc_in = record.usdc_in,
+                weth_min = self.base_amount(weth_min),
+                usdc_min = self.quote_amount(usdc_min),
+                %deadline,
+                "Paper trade: would swap {} → {}",
+                trade.buy_dex,
+                trade.sell_dex
+            );
+            return Ok("paper");
+        };
+
+        let wallet = sender.address();
+        let usdc_before = self.balance(self.usdc, wallet).await?;
+        let weth_before = self.balance(self.weth, wallet).await?;
+        let args = (trade.usdc_in, weth_min, buy_path, wallet, deadline);
+        let leg = (allowances, buy_router, self.usdc);
+        self.swap(sender, leg, args, &mut record.buy_tx).await?;
+
+        let weth_got = self
+            .balance(self.weth, wallet)
+            .await?
+            .saturating_sub(weth_before);
+        record.weth_received = Some(self.base_amount(weth_got));
+        // The sell leg's floor follows the WETH actually received.
+        let sell_min = usdc_min * weth_got / weth_quote.max(U256::one());
+        let args = (
+            weth_got,
+            sell_min,
+            trade.sell_path.to_vec(),
+            wallet,
+            deadline,
+        );
+        let leg = (allowances, sell_router, self.weth);
+        self.swap(sender, leg, args, &mut record.sell_tx).await?;
+
+        let usdc_after = self.balance(self.usdc, wallet).await?;
+        let realized = self.quote_amount(usdc_after) - self.quote_amount(usdc_before);
+        record.usdc_out = Some(record.usdc_in + realized);
+        record.realized_profit_usdc = Some(realized);
+        info!(
+            buy_dex = trade.buy_dex,
+            sell_dex = trade.sell_dex,
+            realized,
+            "Round trip executed"
+        );
+        Ok("confirmed")
+    }
+
+    /// Trades both legs in one `executeArbitrage` call, after an `eth_call` of it
+    /// succeeds; in paper mode only the `eth_call` is made.
+    async fn attempt_flash(
+        &self,
+        executor: Address,
+        trade: &Trade<'_>,
+        legs: &Legs,
+        record: &mut ExecutionRecord,
+    ) -> Result<&'static str, Abort> {
+        // The sell leg can only count on the buy leg's minimum; any WETH above it
+        // stays in the executor contract.
+        let sell_min = legs.usdc_min * legs.weth_min / legs.weth_quote.max(U256::one());
+        let owed =
+            trade.usdc_in + flashloan::premium(trade.usdc_in, self.settings.flashloan_premium_bps);
+        let min_profit = sell_min.saturating_sub(owed);
+        if min_profit.is_zero() {
+            return Err(Abort::Skip(format!(
+                "after slippage the round trip returns {:.2} USDC, not more than the {:.2} owed to the flashloan",
+                self.quote_amount(sell_min),
+                self.quote_amount(owed)
+            )));
+        }
+        let params = FlashParams {
+            asset: self.usdc,
+            amount: trade.usdc_in,
+            buy_router: legs.buy_router,
+            buy_data: flashloan::swap_calldata(
+                &self.router_abi,
+                trade.usdc_in,
+                legs.weth_min,
+                &legs.buy_path,
+                executor,
+                legs.deadline,
+            )
+            .map_err(fail)?,
+            sell_router: legs.sell_router,
+            sell_data: flashloan::swap_calldata(
+                &self.router_abi,
+                legs.weth_min,
+                sell_min,
+                trade.sell_path,
+                executor,
+                legs.deadline,
+            )
+            .map_err(fail)?,
+            min_profit,
+        };
+        if let Some(pool) = self.aave_pool {
+            return self
+                .attempt_pool_flash(pool, executor, trade, &params, record)
+                .await;
+        }
+
+        let Some(sender) = &self.wallet else {
+            let contract =
+                Contract::new(executor, self.executor_abi.clone(), self.provider.clone());
+            let simulated = simulate_flash(&contract, &params).await?;
+            info!(
+                buy_dex = trade.buy_dex,
+                sell_dex = trade.sell_dex,
+                usdc_in = record.usdc_in,
+                min_profit_usdc = self.quote_amount(min_profit),
+                simulated_profit_usdc = self.quote_amount(simulated),
+                calldata = %params.encode(&self.executor_abi).map_err(fail)?,
+                "Paper flashloan: would call executeArbitrage on {:?}",
+                executor
+            );
+            return Ok("paper");
+        };
+
+        let contract = Contract::new(executor, self.executor_abi.clone(), sender.client().clone());
+        let simulated = simulate_flash(&contract, &params).await?;
+        let wallet = sender.address();
+        let usdc_before = self.balance(self.usdc, wallet).await?;
+        let call = contract
+            .method::<_, U256>("executeArbitrage", params.args())
+            .map_err(fail)?
+            .legacy();
+        let receipt = sender.send(call.tx, "flashloan").await.map_err(fail)?;
+        record.buy_tx = Some(format!("{:?}", receipt.transaction_hash));
+        confirm(&receipt, "flashloan")?;
+
+        let usdc_after = self.balance(self.usdc, wallet).await?;
+        let realized = self.quote_amount(usdc_after) - self.quote_amount(usdc_before);
+        record.realized_profit_usdc = Some(realized);
+        info!(
+            buy_dex = trade.buy_dex,
+            sell_dex = trade.sell_dex,
+            simulated = self.quote_amount(simulated),
+            realized,
+            "Flashloan round trip executed"
+        );
+        Ok("confirmed")
+    }
+
+    /// Sends `params` as `flashLoanSimple` on the Aave V3 `pool`, lending to
+    /// `receiver`, after an `eth_call` of it succeeds; in paper mode only the
+    /// `eth_call` is made.
+    async fn attempt_pool_flash(
+        &self,
+        pool: Address,
+        receiver: Address,
+        trade: &Trade<'_>,
+        params: &FlashParams,
+        record: &mut ExecutionRecord,
+    ) -> Result<&'static str, Abort> {
+        let Some(sender) = &self.wallet else {
+            let contract = Contract::new(pool, flashloan::aave_pool_abi(), self.provider.clone());
+            let call = flashloan::flash_loan_simple(&contract, receiver, params).map_err(fail)?;
+            call.call()
+                .await
+                .map_err(|err| Abort::Fail(format!("flashloan simulation failed: {}", err)))?;
+            info!(
+                buy_dex = trade.buy_dex,
+                sell_dex = trade.sell_dex,
+                usdc_in = record.usdc_in,
+                min_profit_usdc = self.quote_amount(params.min_profit),
+                receiver = ?receiver,
+                "Paper flashloan: would call flashLoanSimple on {:?}",
+                pool
+            );
+            return Ok("paper");
+        };
+
+        let contract = Contract::new(pool, flashloan::aave_pool_abi(), sender.client().clone());
+        let call = flashloan::flash_loan_simple(&contract, receiver, params)
+            .map_err(fail)?
+            .from(sender.address());
+        call.call()
+            .await
+            .map_err(|err| Abort::Fail(format!("flashloan simulation failed: {}", err)))?;
+        let wallet = sender.address();
+        let usdc_before = self.balance(self.usdc, wallet).await?;
+        let receipt = sender
+            .send(call.legacy().tx, "flashloan")
+            .await
+            .map_err(fail)?;
+        record.buy_tx = Some(format!("{:?}", receipt.transaction_hash));
+        confirm(&receipt, "flashloan")?;
+
+        let usdc_after = self.balance(self.usdc, wallet).await?;
+        let realized = self.quote_amount(usdc_after) - self.quote_amount(usdc_before);
+        record.realized_profit_usdc = Some(realized);
+        info!(
+            buy_dex = trade.buy_dex,
+            sell_dex = trade.sell_dex,
+            realized,
+            "Flashloan round trip executed through the pool"
+        );
+        Ok("confirmed")
+    }
+
+    /// Skips the trade unless `wallet` holds its USDC input (the flashloan
+    /// borrows it instead) and its gas reserve.
+    async fn check_wallet(
+        &self,
+        wallet: Address,
+        trade: &Trade<'_>,
+        record: &mut ExecutionRecord,
+    ) -> Result<(), Abort> {
+        let tokens = match self.flash_executor {
+            Some(_) => vec![],
+            None => vec![(self.usdc, trade.usdc_in)],
+        };
+        let mut shortfalls = check_wallet_balances(&self.provider, wallet, &tokens)
+            .await
+            .map_err(fail)?;
+        let native = self
+            .provider
+            .get_balance(wallet, None)
+            .await
+            .map_err(fail)?;
+        if native < trade.gas_reserve {
+            shortfalls.push(BalanceShortfall {
+                token: None,
+                required: trade.gas_reserve,
+                available: native,
+            });
+        }
+        record.wallet_check_passed = Some(shortfalls.is_empty());
+        if shortfalls.is_empty() {
+            return Ok(());
+        }
+        let reason = shortfalls
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        warn!(?wallet, shortfalls = %reason, "Wallet cannot cover the trade");
+        Err(Abort::Skip(format!("insufficient balance: {}", reason)))
+    }
+
+    fn router(&self, dex: &str) -> Result<Address, Abort> {
+        self.routers
+            .get(dex)
+            .copied()
+            .ok_or_else(|| Abort::Skip(format!("{} is not a uniswap_v2 router", dex)))
+    }
+
+    /// `amount` less the configured slippage tolerance.
+    fn min_out(&self, amount: U256) -> U256 {
+        amount * U256::from(10_000 - self.settings.slippage_bps) / U256::from(10_000)
+    }
+
+    async fn quote(
+        &self,
+        router: Address,
+        amount_in: U256,
+        path: &[Address],
+    ) -> Result<U256, Abort> {
+        let contract = Contract::new(router, self.router_abi.clone(), self.provider.clone());
+        let amounts: Vec<U256> = contract
+            .method::<_, Vec<U256>>("getAmountsOut", (amount_in, path.to_vec()))
+            .map_err(fail)?
+            .call()
+            .await
+            .map_err(fail)?;
+        price::amounts_out(&amounts, path.len()).map_err(fail)
+    }
+
+    async fn balance(&self, token: Address, holder: Address) -> Result<U256, Abort> {
+        Contract::new(token, self.erc20.clone(), self.provider.clone())
+            .method::<_, U256>("balanceOf", holder)
+            .map_err(fail)?
+            .call()
+            .await
+            .map_err(fail)
+    }
+
+    /// Approves `router` for the input if needed, then sends
+    /// `swapExactTokensForTokens` and waits for it to succeed; `sent` gets the
+    /// hash of the version that mined.
+    ///
+    /// A failed swap is re-checked against the allowance, so a shortfall is
+    /// reported (and re-approved) instead of the router's revert.
+    async fn swap(
+        &self,
+        wallet: &NonceManager,
+        (allowances, router, token_in): (&AllowanceManager, Address, Address),
+        args: (U256, U256, Vec<Address>, Address, U256),
+        sent: &mut Option<String>,
+    ) -> Result<(), Abort> {
+        let amount_in = args.0;
+        allowances
+            .check_and_approve_if_needed(token_in, router, amount_in)
+            .await
+            .map_err(fail)?;
+        match self.send_swap(wallet, router, args, sent).await {
+            Ok(()) => {
+                allowances.spent(token_in, router, amount_in);
+                Ok(())
+            }
+            Err(Abort::Fail(reason)) => Err(Abort::Fail(
+                allowances
+                    .diagnose(token_in, router, amount_in)
+                    .await
+                    .unwrap_or(reason),
+            )),
+            Err(skip) => Err(skip),
+        }
+    }
+
+    async fn send_swap(
+        &self,
+        wallet: &NonceManager,
+        router: Address,
+        args: (U256, U256, Vec<Address>, Address, U256),
+        sent: &mut Option<String>,
+    ) -> Result<(), Abort> {
+        let contract = Contract::new(router, self.router_abi.clone(), wallet.client().clone());
+        let call = contract
+            .method::<_, Vec<U256>>("swapExactTokensForTokens", args)
+            .map_err(fail)?
+            .legacy();
+        let receipt = wallet.send(call.tx, "swap").await.map_err(fail)?;
+        *sent = Some(format!("{:?}", receipt.transaction_hash));
+        confirm(&receipt, "swap")
+    }
+}
+
+/// `eth_call` of `executeArbitrage`, returning the profit it reports.
+async fn simulate_flash<M: Middleware + 'static>(
+    contract: &Contract<M>,
+    params: &FlashParams,
+) -> Result<U256, Abort> {
+    contract
+        .method::<_, U256>("executeArbitrage", params.args())
+        .map_err(fail)?
+        .call()
+        .await
+        .map_err(|err| Abort::Fail(format!("flashloan simulation failed: {}", err)))
+}
+
+fn fail(err: impl std::fmt::Display) -> Abort {
+    Abort::Fail(err.to_string())
+}
+
+fn confirm(receipt: &TransactionReceipt, what: &str) -> Result<(), Abort> {
+    if receipt.status == Some(1.into()) {
+        Ok(())
+    } else {
+        Err(Abort::Fail(format!(
+            "{} {:?} reverted",
+            what, receipt.transaction_hash
+        )))
+    }
+}
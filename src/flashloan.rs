@@ -0,0 +1,258 @@
+//! Interface of the user-deployed Aave V3 flashloan executor contract.
+//!
+//! The contract borrows `amount` of `asset` with `flashLoanSimple`, makes the
+//! two router calls it is handed, repays the loan plus premium, reverts unless
+//! at least `minProfit` of `asset` is left, and sends that profit to the caller.
+//! `contracts/FlashArbitrageReceiver.sol` is one such contract; the loan is
+//! started either through its `executeArbitrage` or by calling the pool's
+//! `flashLoanSimple` directly with it as the receiver ([`execute_flash_arb`]).
+
+use anyhow::Result;
+use ethers::abi::{Abi, Token, Tokenize, parse_abi};
+use ethers::contract::{Contract, ContractCall};
+use ethers::core::types::{Address, Bytes, TxHash, U256};
+use ethers::providers::Middleware;
+
+use crate::price;
+
+/// ABI of `contracts/FlashArbitrageReceiver.sol`, built into the binary.
+pub const EXECUTOR_ABI: &str = include_str!("../abi/flash_arbitrage_receiver_abi.json");
+
+/// The part of the Aave V3 Pool the bot calls.
+pub const AAVE_POOL_ABI: &[&str] = &[
+    "function flashLoanSimple(address receiverAddress, address asset, uint256 amount, bytes params, uint16 referralCode)",
+];
+
+/// Parsed [`EXECUTOR_ABI`].
+pub fn executor_abi() -> Abi {
+    price::parse_abi_json(EXECUTOR_ABI, "built-in executor ABI")
+        .expect("built-in executor ABI parses")
+}
+
+/// Parsed [`AAVE_POOL_ABI`].
+pub fn aave_pool_abi() -> Abi {
+    parse_abi(AAVE_POOL_ABI).expect("static pool fragment parses")
+}
+
+/// Arguments of one `executeArbitrage` call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlashParams {
+    /// Token borrowed and repaid; the round trip starts and ends in it.
+    pub asset: Address,
+    /// Amount borrowed, in `asset` units.
+    pub amount: U256,
+    /// Router of the buy leg.
+    pub buy_router: Address,
+    /// Calldata of the buy leg, see [`swap_calldata`].
+    pub buy_data: Bytes,
+    /// Router of the sell leg.
+    pub sell_router: Address,
+    /// Calldata of the sell leg.
+    pub sell_data: Bytes,
+    /// `asset` that must remain after repaying the loan and premium.
+    pub min_profit: U256,
+}
+
+impl FlashParams {
+    /// The call arguments in ABI order.
+    pub fn args(&self) -> (Address, U256, Address, Bytes, Address, Bytes, U256) {
+        (
+            self.asset,
+            self.amount,
+            self.buy_router,
+            self.buy_data.clone(),
+            self.sell_router,
+            self.sell_data.clone(),
+            self.min_profit,
+        )
+    }
+
+    /// The `params` the receiver decodes in `executeOperation`:
+    /// `abi.encode(buyRouter, buyData, sellRouter, sellData, minProfit)`.
+    pub fn receiver_params(&self) -> Bytes {
+        ethers::abi::encode(&[
+            Token::Address(self.buy_router),
+            Token::Bytes(self.buy_data.to_vec()),
+            Token::Address(self.sell_router),
+            Token::Bytes(self.sell_data.to_vec()),
+            Token::Uint(self.min_profit),
+        ])
+        .into()
+    }
+
+    /// Full `executeArbitrage` calldata, selector included.
+    pub fn encode(&self, abi: &Abi) -> Result<Bytes> {
+        let data = abi
+            .function("executeArbitrage")?
+            .encode_input(&self.args().into_tokens())?;
+        Ok(data.into())
+    }
+}
+
+/// `swapExactTokensForTokens` calldata for a Uniswap V2 style router, paying out
+/// to `to` (the executor contract).
+pub fn swap_calldata(
+    router_abi: &Abi,
+    amount_in: U256,
+    amount_out_min: U256,
+    path: &[Address],
+    to: Address,
+    deadline: U256,
+) -> Result<Bytes> {
+    let args = (amount_in, amount_out_min, path.to_vec(), to, deadline);
+    let data = router_abi
+        .function("swapExactTokensForTokens")?
+        .encode_input(&args.into_tokens())?;
+    Ok(data.into())
+}
+
+/// Flashloan fee on `amount` at `premium_bps`, rounded up so the estimate is
+/// never below what the pool charges.
+pub fn premium(amount: U256, premium_bps: u32) -> U256 {
+    let (fee, rest) = (amount * U256::from(premium_bps)).div_mod(U256::from(10_000));
+    if rest.is_zero() { fee } else { fee + 1 }
+}
+
+/// `flashLoanSimple` on the Aave V3 `pool`, lending `params.amount` of
+/// `params.asset` to `receiver` for the round trip in `params`; the caller
+/// must be the receiver's owner.
+pub fn flash_loan_simple<M: Middleware>(
+    pool: &Contract<M>,
+    receiver: Address,
+    params: &FlashParams,
+) -> Result<ContractCall<M, ()>> {
+    let args = (
+        receiver,
+        params.asset,
+        params.amount,
+        params.receiver_params(),
+        0u16,
+    );
+    Ok(pool.method::<_, ()>("flashLoanSimple", args)?)
+}
+
+/// Sends [`flash_loan_simple`] from the pool contract's client and returns the
+/// transaction hash without waiting for it to be mined.
+pub async fn execute_flash_arb<M: Middleware + 'static>(
+    pool: &Contract<M>,
+    receiver: Address,
+    params: &FlashParams,
+) -> Result<TxHash> {
+    let call = flash_loan_simple(pool, receiver, params)?;
+    let pending = call
+        .send()
+        .await
+        .map_err(|err| anyhow::anyhow!("flashLoanSimple: {}", err))?;
+    Ok(pending.tx_hash())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::ParamType;
+
+    fn params() -> FlashParams {
+        let router_abi = price::default_router_abi();
+        let (usdc, weth) = (Address::repeat_byte(0x22), Address::repeat_byte(0x11));
+        let executor = Address::repeat_byte(0xee);
+        let deadline = U256::from(1_800_000_000u64);
+        let amount = U256::from(2_500_000_000u64);
+        let weth_min = U256::exp10(18);
+        FlashParams {
+            asset: usdc,
+            amount,
+            buy_router: Address::repeat_byte(0xa1),
+            buy_data: swap_calldata(
+                &router_abi,
+                amount,
+                weth_min,
+                &[usdc, weth],
+                executor,
+                deadline,
+            )
+            .unwrap(),
+            sell_router: Address::repeat_byte(0xb2),
+            sell_data: swap_calldata(
+                &router_abi,
+                weth_min,
+                U256::from(2_505_000_000u64),
+                &[weth, usdc],
+                executor,
+                deadline,
+            )
+            .unwrap(),
+            min_profit: U256::from(1_000_000),
+        }
+    }
+
+    #[test]
+    fn execute_arbitrage_round_trips() {
+        let params = params();
+        let abi = executor_abi();
+        let function = abi.function("executeArbitrage").unwrap();
+        let data = params.encode(&abi).unwrap();
+        assert_eq!(data[..4], function.short_signature());
+        let decoded = function.decode_input(&data[4..]).unwrap();
+        assert_eq!(
+            decoded,
+            [
+                Token::Address(params.asset),
+                Token::Uint(params.amount),
+                Token::Address(params.buy_router),
+                Token::Bytes(params.buy_data.to_vec()),
+                Token::Address(params.sell_router),
+                Token::Bytes(params.sell_data.to_vec()),
+                Token::Uint(params.min_profit),
+            ]
+        );
+    }
+
+    #[test]
+    fn receiver_params_round_trip() {
+        let params = params();
+        let decoded = ethers::abi::decode(
+            &[
+                ParamType::Address,
+                ParamType::Bytes,
+                ParamType::Address,
+                ParamType::Bytes,
+                ParamType::Uint(256),
+            ],
+            &params.receiver_params(),
+        )
+        .unwrap();
+        assert_eq!(
+            decoded,
+            [
+                Token::Address(params.buy_router),
+                Token::Bytes(params.buy_data.to_vec()),
+                Token::Address(params.sell_router),
+                Token::Bytes(params.sell_data.to_vec()),
+                Token::Uint(params.min_profit),
+            ]
+        );
+
+        // Each leg's calldata decodes back to its swap.
+        let swap = price::default_router_abi();
+        let swap = swap.function("swapExactTokensForTokens").unwrap();
+        let buy = swap.decode_input(&params.buy_data[4..]).unwrap();
+        assert_eq!(buy[0], Token::Uint(params.amount));
+        assert_eq!(
+            buy[2],
+            Token::Array(vec![
+                Token::Address(params.asset),
+                Token::Address(Address::repeat_byte(0x11)),
+            ])
+        );
+        assert_eq!(buy[3], Token::Address(Address::repeat_byte(0xee)));
+        let sell = swap.decode_input(&params.sell_data[4..]).unwrap();
+        assert_eq!(sell[1], Token::Uint(U256::from(2_505_000_000u64)));
+    }
+
+    #[test]
+    fn premium_rounds_up() {
+        assert_eq!(premium(U256::from(10_000), 5), U256::from(5));
+        assert_eq!(premium(U256::from(10_001), 5), U256::from(6));
+        assert_eq!(premium(U256::zero(), 5), U256::zero());
+    }
+}
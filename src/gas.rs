@@ -0,0 +1,370 @@
+//! Gas pricing: per-route cost by DEX kind and hop count, the live MATIC
+//! price, and EIP-1559 fee suggestions for sent transactions.
+
+use anyhow::{Result, anyhow, bail};
+use ethers::abi::Abi;
+use ethers::contract::Contract;
+use ethers::core::types::transaction::eip2718::TypedTransaction;
+use ethers::core::types::{Address, BlockNumber, U256};
+use ethers::providers::{Http, JsonRpcClient, Middleware, Provider};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::arb::{self, Spread};
+use crate::config::{BotSettings, DexEntry, DexKind};
+use crate::error::BotResult;
+use crate::price;
+use crate::ratelimit::RateLimiter;
+
+/// Ticks an `eth_estimateGas` result is reused for by [`GasEstimator`].
+pub const GAS_ESTIMATE_TICKS: u64 = 10;
+
+/// Blocks of fee history behind [`suggest_eip1559_fees`].
+const FEE_HISTORY_BLOCKS: u64 = 10;
+
+/// Fees of sent transactions (`[gas]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct GasConfig {
+    /// Sends EIP-1559 transactions priced by [`suggest_eip1559_fees`] instead of
+    /// legacy ones at `eth_gasPrice`.
+    #[serde(default)]
+    pub eip1559: bool,
+    /// Percentile of recent priority fees paid, from 0 to 100; 50 is the
+    /// median, 90 buys faster inclusion.
+    #[serde(default = "default_gas_percentile")]
+    pub gas_percentile: f64,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            eip1559: false,
+            gas_percentile: default_gas_percentile(),
+        }
+    }
+}
+
+fn default_gas_percentile() -> f64 {
+    50.0
+}
+
+/// `(max_priority_fee, max_fee)` from `eth_feeHistory` over the last
+/// [`FEE_HISTORY_BLOCKS`] blocks: the median of their `percentile` priority
+/// fees, and the median base fee plus that priority fee.
+pub async fn suggest_eip1559_fees<P: JsonRpcClient>(
+    provider: &Provider<P>,
+    percentile: f64,
+) -> Result<(U256, U256)> {
+    let history = provider
+        .fee_history(FEE_HISTORY_BLOCKS, BlockNumber::Latest, &[percentile])
+        .await?;
+    let tips: Vec<U256> = history
+        .reward
+        .iter()
+        .filter_map(|rewards| rewards.first().copied())
+        .collect();
+    let (Some(base_fee), Some(priority_fee)) = (median(history.base_fee_per_gas), median(tips))
+    else {
+        bail!("eth_feeHistory returned no blocks");
+    };
+    Ok((priority_fee, base_fee + priority_fee))
+}
+
+fn median(mut values: Vec<U256>) -> Option<U256> {
+    values.sort();
+    values.get(values.len() / 2).copied()
+}
+
+/// Route-aware gas estimate replacing the flat `est_gas_cost_usdc`
+/// (`[settings.gas_model]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct GasModelConfig {
+    /// Units of a round trip on top of its swaps: transaction base costs and
+    /// any executor overhead.
+    #[serde(default = "default_overhead_units")]
+    pub overhead_units: u64,
+    /// Units of a single-hop swap per DEX kind (`[settings.gas_model.gas_units]`);
+    /// kinds left out use [`default_leg_units`].
+    #[serde(default)]
+    pub gas_units: BTreeMap<DexKind, u64>,
+    /// Units added for each hop of a leg beyond its first.
+    #[serde(default = "default_per_hop_units")]
+    pub per_hop_units: u64,
+}
+
+fn default_overhead_units() -> u64 {
+    // The base cost of the buy and the sell transaction.
+    42_000
+}
+
+fn default_per_hop_units() -> u64 {
+    60_000
+}
+
+/// Typical units of a single-hop swap on a `kind` venue.
+pub fn default_leg_units(kind: DexKind) -> u64 {
+    match kind {
+        DexKind::UniswapV2 => 110_000,
+        DexKind::UniswapV3 => 130_000,
+        DexKind::BalancerV2 => 150_000,
+        DexKind::Curve => 140_000,
+        DexKind::Solidly => 120_000,
+        DexKind::Aggregator => 250_000,
+    }
+}
+
+impl GasModelConfig {
+    /// Units of one swap leg on a `kind` venue along a path of `hops` pools.
+    pub fn leg_units(&self, kind: DexKind, hops: usize) -> u64 {
+        let single = self
+            .gas_units
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| default_leg_units(kind));
+        single + self.per_hop_units * hops.saturating_sub(1) as u64
+    }
+}
+
+/// Gas pricing of the routes quoted in one tick.
+#[derive(Debug, Clone)]
+pub struct GasCost<'a> {
+    /// Units of each quoted venue's leg, with the model's overhead; `None`
+    /// prices every route at the flat `est_gas_cost_usdc`.
+    legs: Option<(u64, HashMap<&'a str, u64>)>,
+    flat_usdc: f64,
+    flat_units: u64,
+    gas_price_gwei: f64,
+    matic_price_usdc: f64,
+}
+
+impl<'a> GasCost<'a> {
+    /// The flat `est_gas_cost_usdc` and `est_gas_units` of `settings` for every
+    /// route; with a live MATIC price (`matic_price_dex`) the cost is the units'
+    /// instead.
+    pub fn flat(settings: &BotSettings) -> Self {
+        let flat_usdc = match settings.matic_price_dex {
+            Some(_) => {
+                settings.est_gas_units as f64
+                    * settings.gas_price_gwei
+                    * 1e-9
+                    * settings.matic_price_usdc
+            }
+            None => settings.est_gas_cost_usdc,
+        };
+        Self {
+            legs: None,
+            flat_usdc,
+            flat_units: settings.est_gas_units,
+            gas_price_gwei: settings.gas_price_gwei,
+            matic_price_usdc: settings.matic_price_usdc,
+        }
+    }
+
+    /// Routes priced by `model` at `gas_price_gwei`; `legs` holds the units of
+    /// each quoted venue's leg.
+    pub fn routed(
+        settings: &BotSettings,
+        model: &GasModelConfig,
+        legs: HashMap<&'a str, u64>,
+        gas_price_gwei: f64,
+    ) -> Self {
+        Self {
+            legs: Some((model.overhead_units, legs)),
+            gas_price_gwei,
+            ..Self::flat(settings)
+        }
+    }
+
+    /// Gas units of buying on `buy_dex` and selling on `sell_dex`.
+    pub fn units(&self, buy_dex: &str, sell_dex: &str) -> u64 {
+        match &self.legs {
+            Some((overhead, legs)) => {
+                let leg = |dex: &str| legs.get(dex).copied().unwrap_or_default();
+                overhead + leg(buy_dex) + leg(sell_dex)
+            }
+            None => self.flat_units,
+        }
+    }
+
+    /// Gas cost in USDC of buying on `buy_dex` and selling on `sell_dex`.
+    pub fn usdc(&self, buy_dex: &str, sell_dex: &str) -> f64 {
+        match self.legs {
+            Some(_) => {
+                self.units(buy_dex, sell_dex) as f64
+                    * self.gas_price_gwei
+                    * 1e-9
+                    * self.matic_price_usdc
+            }
+            None => self.flat_usdc,
+        }
+    }
+
+    /// [`Self::usdc`] in raw units of a quote token of `decimals` decimals.
+    pub fn quote_units(&self, buy_dex: &str, sell_dex: &str, decimals: u32) -> U256 {
+        arb::amount_to_units(self.usdc(buy_dex, sell_dex), decimals)
+    }
+
+    /// [`arb::score_opportunity`] of `profit_usdc` over the route's gas.
+    pub fn score(&self, profit_usdc: f64, buy_dex: &str, sell_dex: &str) -> f64 {
+        arb::score_opportunity(
+            profit_usdc,
+            self.units(buy_dex, sell_dex),
+            self.gas_price_gwei,
+            self.matic_price_usdc,
+        )
+    }
+}
+
+/// Gas units `tx` would use, from the node's `eth_estimateGas` at the latest block.
+pub async fn estimate_execution_gas(
+    provider: &Provider<Http>,
+    tx: &TypedTransaction,
+) -> Result<U256> {
+    provider
+        .estimate_gas(tx, None)
+        .await
+        .map_err(|err| anyhow!("eth_estimateGas: {}", err))
+}
+
+/// Estimates an opportunity's buy and sell swaps with `eth_estimateGas`, as
+/// sent from `estimate_gas_from`, caching each direction for
+/// [`GAS_ESTIMATE_TICKS`] ticks.
+pub struct GasEstimator {
+    provider: Arc<Provider<Http>>,
+    router_abi: Abi,
+    /// Router per `uniswap_v2` entry name.
+    routers: HashMap<String, Address>,
+    from: Address,
+    limiter: Option<Arc<RateLimiter>>,
+    /// Units per (buy dex, sell dex) and the tick they were estimated at.
+    cache: HashMap<(String, String), (u64, u64)>,
+}
+
+impl GasEstimator {
+    /// Estimates swaps from `from` through the `uniswap_v2` entries of `dex`,
+    /// within the endpoint's `limiter` budget.
+    pub fn new(
+        from: Address,
+        dex: &[DexEntry],
+        router_abi: Abi,
+        provider: Arc<Provider<Http>>,
+        limiter: Option<Arc<RateLimiter>>,
+    ) -> BotResult<Self> {
+        Ok(Self {
+            provider,
+            router_abi,
+            routers: price::v2_routers(dex)?,
+            from,
+            limiter,
+            cache: HashMap::new(),
+        })
+    }
+
+    /// Units of buying `base_in` with `usdc_in` on the buy venue and selling it
+    /// on the sell venue of `spread`, along the quoted WETH → … → USDC `routes`.
+    ///
+    /// Reuses the direction's estimate when it is under [`GAS_ESTIMATE_TICKS`]
+    /// ticks older than `tick`. Both legs must be `uniswap_v2` routers, and the
+    /// node only estimates swaps that would succeed, so `from` needs the
+    /// balances and approvals.
+    pub async fn units(
+        &mut self,
+        tick: u64,
+        spread: &Spread<'_>,
+        routes: &HashMap<&str, Vec<Address>>,
+        usdc_in: U256,
+        base_in: U256,
+    ) -> Result<u64> {
+        let key = (spread.buy_dex.to_string(), spread.sell_dex.to_string());
+        if let Some(&(units, at)) = self.cache.get(&key)
+            && tick.saturating_sub(at) < GAS_ESTIMATE_TICKS
+        {
+            return Ok(units);
+        }
+        let route = |dex: &str| {
+            routes
+                .get(dex)
+                .cloned()
+                .ok_or_else(|| anyhow!("no quoted route on {}", dex))
+        };
+        let mut buy_path = route(spread.buy_dex)?;
+        buy_path.reverse();
+        let buy = self.leg(spread.buy_dex, buy_path, usdc_in).await?;
+        let sell = self
+            .leg(spread.sell_dex, route(spread.sell_dex)?, base_in)
+            .await?;
+        let units = (buy + sell).as_u64();
+        self.cache.insert(key, (units, tick));
+        Ok(units)
+    }
+
+    /// Estimated units of `swapExactTokensForTokens` of `amount_in` along `path` on `dex`.
+    async fn leg(&self, dex: &str, path: Vec<Address>, amount_in: U256) -> Result<U256> {
+        let router = *self
+            .routers
+            .get(dex)
+            .ok_or_else(|| anyhow!("{} is not a uniswap_v2 router", dex))?;
+        let contract = Contract::new(router, self.router_abi.clone(), self.provider.clone());
+        let call = contract
+            .method::<_, Vec<U256>>(
+                "swapExactTokensForTokens",
+                (amount_in, U256::zero(), path, self.from, U256::MAX),
+            )?
+            .from(self.from);
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire(1).await;
+        }
+        estimate_execution_gas(&self.provider, &call.tx).await
+    }
+}
+
+/// Live MATIC price in the quote token, quoted on a `uniswap_v2` router and
+/// reused for `matic_price_cache_ticks` ticks.
+pub struct MaticPricer {
+    provider: Arc<Provider<Http>>,
+    router: Contract<Provider<Http>>,
+    wmatic: Address,
+    usdc: Address,
+    /// Tick of the last successful fetch.
+    fetched_at: Option<u64>,
+}
+
+impl MaticPricer {
+    /// Quotes `wmatic` into `usdc` on the router at `router`.
+    pub fn new(
+        router: Address,
+        router_abi: Abi,
+        wmatic: Address,
+        usdc: Address,
+        provider: Arc<Provider<Http>>,
+    ) -> Self {
+        Self {
+            router: Contract::new(router, router_abi, provider.clone()),
+            provider,
+            wmatic,
+            usdc,
+            fetched_at: None,
+        }
+    }
+
+    /// Whether the price fetched last is at least `cache_ticks` ticks older than
+    /// `tick`, or none was fetched yet.
+    pub fn due(&self, tick: u64, cache_ticks: u32) -> bool {
+        self.fetched_at
+            .is_none_or(|at| tick.saturating_sub(at) >= u64::from(cache_ticks))
+    }
+
+    /// Fetches the price at `tick`; a failure leaves it due.
+    pub async fn fetch(&mut self, tick: u64) -> Result<f64> {
+        let price = price::fetch_matic_price_usdc(
+            self.provider.clone(),
+            &self.router,
+            self.wmatic,
+            self.usdc,
+        )
+        .await?;
+        self.fetched_at = Some(tick);
+        Ok(price)
+    }
+}
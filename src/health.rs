@@ -0,0 +1,86 @@
+//! Liveness state shared between the polling loop and `GET /health`.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Updated by the scanner after every tick, read by the health endpoint.
+#[derive(Debug)]
+pub struct Health {
+    started: Instant,
+    last_success: Mutex<Option<Instant>>,
+    rpc_ok: AtomicBool,
+    opportunities: AtomicU64,
+    ticks: AtomicU64,
+}
+
+/// Body of `GET /health`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// Whether the last tick reached the RPC node.
+    pub rpc_ok: bool,
+    /// Seconds since the last successful tick; `null` before the first one.
+    pub last_tick_age_secs: Option<u64>,
+    /// Opportunities recorded since startup.
+    pub opportunities_session: u64,
+}
+
+impl Health {
+    /// Starts the clock; until the first successful tick staleness counts from now.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_success: Mutex::new(None),
+            rpc_ok: AtomicBool::new(false),
+            opportunities: AtomicU64::new(0),
+            ticks: AtomicU64::new(0),
+        }
+    }
+
+    /// Records a finished tick. Ticks where the RPC failed do not refresh the
+    /// last-success time.
+    pub fn record_tick(&self, rpc_ok: bool, opportunity: bool) {
+        self.rpc_ok.store(rpc_ok, Ordering::Relaxed);
+        self.ticks.fetch_add(1, Ordering::Relaxed);
+        if opportunity {
+            self.opportunities.fetch_add(1, Ordering::Relaxed);
+        }
+        if rpc_ok {
+            *self.last_success.lock().unwrap() = Some(Instant::now());
+        }
+    }
+
+    /// Time since startup.
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Ticks finished since startup, across all scanners.
+    pub fn ticks(&self) -> u64 {
+        self.ticks.load(Ordering::Relaxed)
+    }
+
+    /// Opportunities recorded since startup.
+    pub fn opportunities(&self) -> u64 {
+        self.opportunities.load(Ordering::Relaxed)
+    }
+
+    /// Current report, and whether the loop ticked successfully within `max_age`.
+    pub fn report(&self, max_age: Duration) -> (HealthReport, bool) {
+        let last_success = *self.last_success.lock().unwrap();
+        let age = last_success.unwrap_or(self.started).elapsed();
+        let report = HealthReport {
+            rpc_ok: self.rpc_ok.load(Ordering::Relaxed),
+            last_tick_age_secs: last_success.map(|at| at.elapsed().as_secs()),
+            opportunities_session: self.opportunities.load(Ordering::Relaxed),
+        };
+        (report, age <= max_age)
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,40 @@
+#![warn(missing_docs)]
+
+//! Detects arbitrage opportunities between Uniswap V2 style DEXs on Polygon.
+//!
+//! The binary in `main.rs` wires these modules together; the profit math in
+//! [`arb`] is pure so it can be reused and tested without a node.
+
+pub mod aggregator;
+pub mod allowance;
+pub mod anomaly;
+pub mod api;
+pub mod arb;
+pub mod backtest;
+pub mod breaker;
+pub mod config;
+pub mod db;
+pub mod error;
+pub mod execute;
+pub mod flashloan;
+pub mod gas;
+pub mod health;
+pub mod logging;
+pub mod metrics;
+pub mod nonce;
+pub mod notify;
+pub mod oracle;
+pub mod output;
+pub mod pacing;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod price;
+pub mod ratelimit;
+pub mod reload;
+pub mod retention;
+pub mod scanner;
+pub mod shutdown;
+pub mod simulate;
+pub mod state;
+pub mod store;
+pub mod throttle;
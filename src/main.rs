@@ -1,144 +1,657 @@
-use anyhow::Result;
-use chrono::Utc;
-use ethers::abi::Abi;
-use ethers::contract::Contract;
-use ethers::core::types::{Address, U256};
+use anyhow::{Result, anyhow, bail};
+use clap::{Parser, Subcommand};
 use ethers::providers::{Http, Provider};
-use rusqlite::Connection;
-use serde::Deserialize;
-use std::fs;
-use std::sync::Arc;
+use polygon_arb_detector::api::{self, ApiState};
+use polygon_arb_detector::backtest::{self, BacktestResult, ReplayOptions, ReplaySummary};
+use polygon_arb_detector::config::{
+    Config, DEFAULT_JOB, JobConfig, TokenPair, load_config, write_sample_config,
+};
+use polygon_arb_detector::error::BotResult;
+use polygon_arb_detector::execute::ExposureLedger;
+use polygon_arb_detector::health::Health;
+use polygon_arb_detector::logging::{self, LogFormat};
+use polygon_arb_detector::metrics::{self, Metrics};
+use polygon_arb_detector::nonce::NonceManager;
+use polygon_arb_detector::output::OutputMode;
+use polygon_arb_detector::price::{self, load_router_abi};
+use polygon_arb_detector::ratelimit::RateLimiter;
+use polygon_arb_detector::reload::SharedConfig;
+use polygon_arb_detector::scanner::{ScanServices, ScanTarget, Scanner, SessionSummary};
+use polygon_arb_detector::store::{self, Backend, OpportunityStore, SqliteStore};
+use polygon_arb_detector::{db, reload, retention, shutdown, state};
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
-use tokio::time::interval;
+use tokio::sync::{Notify, broadcast};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, error, info, info_span, warn};
 
-mod db;
+#[derive(Debug, Parser)]
+#[command(about = "Polygon DEX arbitrage opportunity detector")]
+struct Cli {
+    /// Log output format; overrides `log_format` in config.toml.
+    #[arg(long, value_enum)]
+    log_format: Option<LogFormat>,
 
-#[derive(Debug, Deserialize)]
-struct DexRouters {
-    quickswap: String,
-    sushiswap: String,
+    /// Per-tick console output; overrides `output` in config.toml.
+    #[arg(long, value_enum)]
+    output: Option<OutputMode>,
+
+    /// Print aggregates over recorded opportunities and exit.
+    #[arg(long)]
+    stats: bool,
+
+    /// Replay recorded opportunities with `--gas-cost` and exit.
+    #[arg(long, requires = "gas_cost")]
+    backtest: bool,
+
+    /// Gas cost in USDC assumed by `--backtest`.
+    #[arg(long, value_name = "F")]
+    gas_cost: Option<f64>,
+
+    /// Profit threshold for `--backtest`; defaults to `min_profit_usdc` in config.toml.
+    #[arg(long, value_name = "F")]
+    min_profit: Option<f64>,
+
+    /// Enable `[execution]` but only log the transactions it would send.
+    #[arg(long)]
+    paper: bool,
+
+    /// Quote, log and notify as usual, but write nothing to `arbitrage_bot` and
+    /// execute nothing. Overrides `dry_run` in config.toml.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// `--dry-run`, storing the opportunities in `dry_run_opportunities`.
+    #[arg(long)]
+    dry_run_table: bool,
+
+    /// Entry price of an LP position; logs and stores its impermanent loss on
+    /// every quote. Overrides `lp_entry_price` in config.toml.
+    #[arg(long, value_name = "F")]
+    lp_entry_price: Option<f64>,
+
+    /// Skip the startup checks of each RPC endpoint's chain id and of bytecode
+    /// at every DEX address.
+    #[arg(long)]
+    skip_chain_checks: bool,
+
+    /// Write a commented sample config.toml to the working directory and exit.
+    #[arg(long)]
+    init_config: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-#[derive(Debug, Deserialize)]
-struct Tokens {
-    weth: String,
-    usdc: String,
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Replay a block range through an archive node into `backtest_results` and exit.
+    Backtest {
+        /// First block quoted.
+        #[arg(long, value_name = "N")]
+        from_block: u64,
+
+        /// Last block quoted, inclusive.
+        #[arg(long, value_name = "N")]
+        to_block: u64,
+
+        /// Blocks between two quoted ones.
+        #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        step: u64,
+
+        /// Chain to replay; defaults to the first in config.toml.
+        #[arg(long, value_name = "ID")]
+        chain_id: Option<u64>,
+
+        /// Pair to replay, e.g. WETH/USDC; defaults to the chain's first.
+        #[arg(long, value_name = "LABEL")]
+        pair: Option<String>,
+
+        /// Tags the stored rows; re-running with the same id resumes the run.
+        /// Defaults to one derived from the chain, pair and blocks.
+        #[arg(long, value_name = "ID")]
+        run_id: Option<String>,
+
+        /// Blocks quoted at once.
+        #[arg(long, value_name = "N", default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..))]
+        concurrency: u64,
+    },
 }
 
-#[derive(Debug, Deserialize)]
-struct BotSettings {
-    min_profit_usdc: f64,
-    trade_size: u64,
-    est_gas_cost_usdc: f64,
-    refresh_rate: u64,
+fn print_stats(stats: &db::HistoryStats) {
+    println!("Opportunities: {}", stats.total);
+    println!(
+        "Profit (USDC): avg {:.6} | median {:.6} | max {:.6}",
+        stats.avg_profit_usdc, stats.median_profit_usdc, stats.max_profit_usdc
+    );
+
+    println!();
+    println!(
+        "{:<12} {:<12} {:>8} {:>14} {:>14}",
+        "BUY", "SELL", "COUNT", "AVG PROFIT", "MAX PROFIT"
+    );
+    for route in &stats.routes {
+        println!(
+            "{:<12} {:<12} {:>8} {:>14.6} {:>14.6}",
+            route.buy_dex,
+            route.sell_dex,
+            route.count,
+            route.avg_profit_usdc,
+            route.max_profit_usdc
+        );
+    }
+
+    println!();
+    println!("{:<12} {:>8}", "DAY", "COUNT");
+    for day in &stats.per_day {
+        println!("{:<12} {:>8}", day.day, day.count);
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct Config {
-    rpc_url: String,
-    dex: DexRouters,
-    tokens: Tokens,
-    settings: BotSettings,
+fn print_backtest(result: &BacktestResult, gas_cost: f64, min_profit: f64) {
+    println!(
+        "Backtest at gas {:.6} USDC, min profit {:.6} USDC",
+        gas_cost, min_profit
+    );
+    println!("Opportunities:     {}", result.opportunities_found);
+    println!("Still profitable:  {}", result.profitable_count);
+    println!("Total profit:      {:.6} USDC", result.total_profit_usdc);
+    println!(
+        "False positives:   {:.2}%",
+        result.false_positive_rate * 100.0
+    );
 }
 
-fn load_config(path: &str) -> Result<Config> {
-    let file = fs::read_to_string(path)?;
-    Ok(toml::from_str(&file)?)
+fn print_replay(summary: &ReplaySummary) {
+    println!("Backtest run {} of {}", summary.run_id, summary.pair);
+    println!("Blocks quoted:     {}", summary.blocks_quoted);
+    println!("Opportunities:     {}", summary.totals.opportunities_found);
+    println!(
+        "Total profit:      {:.6} {}",
+        summary.totals.total_profit_usdc, summary.quote_symbol
+    );
+    match summary.totals.best_block {
+        Some((block, profit)) => println!(
+            "Best block:        {} ({:.6} {})",
+            block, profit, summary.quote_symbol
+        ),
+        None => println!("Best block:        -"),
+    }
 }
 
-fn load_router_abi(path: &str) -> Result<Abi> {
-    let abi_data = fs::read_to_string(path)?;
-    Ok(serde_json::from_str(&abi_data)?)
+/// RPC handles of one chain, shared by every scanner on it.
+struct ChainHandles {
+    provider: Provider<Http>,
+    limiter: Option<Arc<RateLimiter>>,
+    wallet: Option<Arc<NonceManager>>,
 }
 
-async fn fetch_price(
-    contract: &Contract<Provider<Http>>,
-    trade_size: U256,
-    path: Vec<Address>,
-) -> U256 {
-    contract
-        .method::<_, Vec<U256>>("getAmountsOut", (trade_size, path))
-        .unwrap()
-        .call()
-        .await
-        .unwrap_or_else(|err| {
-            eprintln!("Error fetching price: {:?}", err);
-            vec![U256::zero(), U256::zero()]
-        })
-        .get(1)
-        .cloned()
-        .unwrap_or(U256::zero())
+/// A started scanner, as compared against a reloaded config.
+struct RunningScanner {
+    stop: CancellationToken,
+    tokens: TokenPair,
+    state_file: Option<String>,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cfg = load_config("config.toml")?;
-    println!(" Config loaded: {:?}", cfg);
+/// Job name, chain id and pair label, naming one scanner across reloads.
+type TargetKey = (String, u64, String);
 
-    let abi = load_router_abi("abi/uniswap_v2_router02_abi.json")?;
-    println!(" ABI loaded");
+fn target_key(target: &ScanTarget) -> TargetKey {
+    (
+        target
+            .job
+            .as_ref()
+            .map_or(DEFAULT_JOB, |job| &job.name)
+            .to_string(),
+        target.chain_id,
+        target.tokens.label().to_string(),
+    )
+}
 
-    db::init_db()?;
-    let conn = Connection::open("arbitrage.db")?;
-    println!(" Database connected");
+/// The `[[jobs]]` entries; without any, every pair of every chain runs as the
+/// default job.
+fn jobs_of(cfg: &Config) -> Vec<Option<JobConfig>> {
+    if cfg.jobs.is_empty() {
+        vec![None]
+    } else {
+        cfg.jobs.iter().cloned().map(Some).collect()
+    }
+}
 
-    let provider = Provider::<Http>::try_from(cfg.rpc_url.clone())?;
-    let weth: Address = cfg.tokens.weth.parse()?;
-    let usdc: Address = cfg.tokens.usdc.parse()?;
-    let trade_size = U256::from(cfg.settings.trade_size);
-    let dex1_address: Address = cfg.dex.quickswap.parse()?;
-    let dex2_address: Address = cfg.dex.sushiswap.parse()?;
+/// One scanner per job, chain and pair of `cfg`, on the chains in `chains`;
+/// state files are left unset.
+fn scan_targets(cfg: &Config, chains: &HashMap<u64, ChainHandles>) -> Vec<ScanTarget> {
+    let jobs = jobs_of(cfg);
+    let mut targets = Vec::new();
+    for chain in &cfg.chains {
+        let Some(handles) = chains.get(&chain.chain_id) else {
+            continue;
+        };
+        let chain_jobs = jobs
+            .iter()
+            .filter(|job| job.as_ref().is_none_or(|job| job.covers_chain(chain)));
+        for job in chain_jobs {
+            let dex = match job {
+                Some(job) => job.venues(chain),
+                None => chain.dex.clone(),
+            };
+            if dex.is_empty() {
+                continue;
+            }
+            for tokens in &chain.tokens {
+                if job.as_ref().is_some_and(|job| !job.covers_pair(tokens)) {
+                    continue;
+                }
+                let dex = cfg.scan_matrix.venues(&dex, tokens);
+                if dex.is_empty() {
+                    continue;
+                }
+                let mut tokens = tokens.clone();
+                tokens.oracle_feed = chain.oracle_feed(&tokens).map(str::to_string);
+                targets.push(ScanTarget {
+                    job: job.clone(),
+                    chain_id: chain.chain_id,
+                    provider: handles.provider.clone(),
+                    dex,
+                    tokens,
+                    state_file: None,
+                    wallet: handles.wallet.clone(),
+                    limiter: handles.limiter.clone(),
+                });
+            }
+        }
+    }
+    targets
+}
 
-    let quickswap = Contract::new(dex1_address, abi.clone(), Arc::new(provider.clone()));
-    let sushiswap = Contract::new(dex2_address, abi.clone(), Arc::new(provider.clone()));
+/// `path` narrowed to the target's chain, job and pair.
+fn scoped_state_file(path: &str, target: &ScanTarget) -> String {
+    match &target.job {
+        None => state::scoped_path(path, target.chain_id, target.tokens.label()),
+        Some(job) => state::scoped_path(
+            path,
+            target.chain_id,
+            &format!("{}.{}", job.name, target.tokens.label()),
+        ),
+    }
+}
 
-    println!(" DEX contracts ready");
+async fn start_scanner(
+    shared: &SharedConfig,
+    target: ScanTarget,
+    services: &ScanServices,
+    stop: CancellationToken,
+    scanners: &mut JoinSet<BotResult<SessionSummary>>,
+) -> Result<()> {
+    let span = info_span!(
+        "scan",
+        job = target.job.as_ref().map_or(DEFAULT_JOB, |job| &job.name),
+        chain_id = target.chain_id,
+        pair = target.tokens.label()
+    );
+    let scanner = Scanner::new(shared.clone(), target, services.clone())
+        .instrument(span.clone())
+        .await?;
+    scanners.spawn(scanner.run(stop).instrument(span));
+    Ok(())
+}
 
-    let mut ticker = interval(Duration::from_secs(cfg.settings.refresh_rate));
-    loop {
-        ticker.tick().await;
+/// Stops the scanners of pairs a reload removed or changed and starts those of
+/// pairs it added or changed. A pair that fails to start is logged and retried
+/// on the next reload.
+async fn restart_changed_pairs(
+    shared: &SharedConfig,
+    chains: &HashMap<u64, ChainHandles>,
+    services: &ScanServices,
+    running: &mut HashMap<TargetKey, RunningScanner>,
+    scanners: &mut JoinSet<BotResult<SessionSummary>>,
+    shutdown: &CancellationToken,
+) {
+    let cfg = shared.read().unwrap().clone();
+    let targets = scan_targets(&cfg, chains);
+    let wanted: HashMap<TargetKey, &ScanTarget> = targets
+        .iter()
+        .map(|target| (target_key(target), target))
+        .collect();
+    // A changed pair keeps its snapshot file.
+    let mut stopped = HashMap::new();
+    running.retain(|key, run| {
+        let keep = wanted
+            .get(key)
+            .is_some_and(|target| target.tokens == run.tokens);
+        if !keep {
+            info!(job = %key.0, chain_id = key.1, pair = %key.2, "Stopping scanner");
+            run.stop.cancel();
+            stopped.insert(key.clone(), run.state_file.clone());
+        }
+        keep
+    });
+    for mut target in targets {
+        let key = target_key(&target);
+        if running.contains_key(&key) {
+            continue;
+        }
+        target.state_file = stopped.remove(&key).unwrap_or_else(|| {
+            cfg.state_file
+                .as_ref()
+                .map(|path| scoped_state_file(path, &target))
+        });
+        let run = RunningScanner {
+            stop: shutdown.child_token(),
+            tokens: target.tokens.clone(),
+            state_file: target.state_file.clone(),
+        };
+        info!(job = %key.0, chain_id = key.1, pair = %key.2, "Starting scanner");
+        match start_scanner(shared, target, services, run.stop.clone(), scanners).await {
+            Ok(()) => {
+                running.insert(key, run);
+            }
+            Err(err) => error!(
+                job = %key.0,
+                chain_id = key.1,
+                pair = %key.2,
+                %err,
+                "Scanner failed to start; fix the pair and reload"
+            ),
+        }
+    }
+}
+
+/// SQLite file of `cfg`'s `database_url`, for `what`, which only runs on SQLite.
+fn sqlite_path<'a>(cfg: &'a Config, what: &str) -> Result<&'a str> {
+    match Backend::parse(&cfg.database_url.0).map_err(|err| anyhow!("database_url: {}", err))? {
+        Backend::Sqlite(path) => Ok(path),
+        Backend::Postgres(_) => bail!("{} needs a sqlite:// database_url", what),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    if cli.init_config {
+        write_sample_config("config.toml")?;
+        println!(
+            "Wrote config.toml; set rpc_url and review [[dex]], [tokens] and [settings] before running."
+        );
+        return Ok(());
+    }
+    if cli.stats {
+        let cfg = load_config("config.toml")?;
+        let store = store::open(&cfg.database_url.0, cfg.instance_id.as_deref()).await?;
+        store.migrate().await?;
+        print_stats(&store.history_stats().await?);
+        return Ok(());
+    }
+    if let (true, Some(gas_cost)) = (cli.backtest, cli.gas_cost) {
+        let cfg = load_config("config.toml")?;
+        let min_profit = cli.min_profit.unwrap_or(cfg.settings.min_profit_usdc);
+        let pool = db::open_pool(sqlite_path(&cfg, "--backtest")?)?;
+        db::init_db(&pool)?;
+        let conn = pool.get()?;
+        let result = backtest::run_backtest(&conn, gas_cost, min_profit)?;
+        print_backtest(&result, gas_cost, min_profit);
+        return Ok(());
+    }
+    if let Some(Command::Backtest {
+        from_block,
+        to_block,
+        step,
+        chain_id,
+        pair,
+        run_id,
+        concurrency,
+    }) = cli.command
+    {
+        let cfg = load_config("config.toml")?;
+        logging::init(cli.log_format.unwrap_or(cfg.settings.log_format));
+        let pool = db::open_pool(sqlite_path(&cfg, "backtest")?)?;
+        db::init_db(&pool)?;
+        let opts = ReplayOptions {
+            chain_id,
+            pair,
+            from_block,
+            to_block,
+            step,
+            run_id,
+            concurrency: concurrency as usize,
+        };
+        print_replay(&backtest::replay_blocks(&cfg, &pool, &opts).await?);
+        return Ok(());
+    }
 
-        println!("\n Checking prices...");
+    let mut cfg = load_config("config.toml")?;
+    if let Some(output) = cli.output {
+        cfg.settings.output = output;
+    }
+    if let Some(price) = cli.lp_entry_price {
+        cfg.settings.lp_entry_price = Some(price);
+        cfg.validate()?;
+    }
+    if cli.paper {
+        cfg.execution.enabled = true;
+        cfg.execution.paper = true;
+        cfg.validate()?;
+    }
+    cfg.settings.dry_run_table |= cli.dry_run_table;
+    cfg.settings.dry_run |= cli.dry_run || cfg.settings.dry_run_table;
+    if cfg.settings.dry_run {
+        cfg.execution.enabled = false;
+    }
+    logging::init(cli.log_format.unwrap_or(cfg.settings.log_format));
+    if cfg.settings.dry_run {
+        warn!(
+            table = cfg.settings.dry_run_table.then_some(db::DRY_RUN_TABLE),
+            "[DRY RUN] Nothing is written to arbitrage_bot, and nothing is executed"
+        );
+    }
+    info!("Config loaded: {:?}", cfg);
 
-        let quick_price = fetch_price(&quickswap, trade_size, vec![weth, usdc]).await;
-        let sushi_price = fetch_price(&sushiswap, trade_size, vec![weth, usdc]).await;
+    let abi = match &cfg.abi_path {
+        Some(path) => load_router_abi(path).map_err(|err| anyhow!("abi_path: {}", err))?,
+        None => price::default_router_abi(),
+    };
+    info!("ABI loaded");
 
-        if quick_price.is_zero() || sushi_price.is_zero() {
-            println!(" Skipping invalid prices");
-            continue;
+    // Sent transactions and retention stay in SQLite, so Postgres has no pool.
+    let (store, pool): (Arc<dyn OpportunityStore>, _) = match Backend::parse(&cfg.database_url.0)
+        .map_err(|err| anyhow!("database_url: {}", err))?
+    {
+        Backend::Sqlite(path) => {
+            let pool = db::open_pool(path)?;
+            (Arc::new(SqliteStore::new(pool.clone())), Some(pool))
         }
+        Backend::Postgres(_) => (
+            store::open(&cfg.database_url.0, cfg.instance_id.as_deref()).await?,
+            None,
+        ),
+    };
+    store.migrate().await?;
+    info!(backend = store.backend(), "Database connected");
 
-        let quick_usdc = quick_price.as_u128() as f64 / 1e6;
-        let sushi_usdc = sushi_price.as_u128() as f64 / 1e6;
+    let shutdown = CancellationToken::new();
+    let signal_handler = shutdown::spawn_signal_handler(shutdown.clone());
 
-        println!(" QuickSwap: {} USDC | SushiSwap: {} USDC", quick_usdc, sushi_usdc);
+    let metrics = Arc::new(Metrics::new()?);
+    if let Some(port) = cfg.metrics_port {
+        let metrics = metrics.clone();
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(err) = metrics::serve(metrics, port, shutdown).await {
+                error!(%err, "Metrics server stopped");
+            }
+        });
+    }
+
+    if let Some(secs) = cfg.rpc_latency_log_secs {
+        tokio::spawn(metrics::log_rpc_latency(
+            metrics.clone(),
+            Duration::from_secs(secs),
+            shutdown.clone(),
+        ));
+    }
+
+    if let (Some(retention), Some(pool)) = (cfg.retention.clone(), &pool) {
+        tokio::spawn(retention::run(pool.clone(), retention, shutdown.clone()));
+    }
 
-        let (buy_on, sell_on, diff) = if quick_price > sushi_price {
-            ("SushiSwap", "QuickSwap", quick_price - sushi_price)
-        } else if sushi_price > quick_price {
-            ("QuickSwap", "SushiSwap", sushi_price - quick_price)
-        } else {
-            println!(" Prices equal → No arbitrage");
+    let health = Arc::new(Health::new());
+    let execution_paused = Arc::new(AtomicBool::new(false));
+    let (events, _) = broadcast::channel(api::EVENT_CAPACITY);
+    let prices = state::PriceBoard::default();
+    let shared = Arc::new(RwLock::new(cfg.clone()));
+    if let Some(listen) = cfg.api_listen.clone() {
+        let state = ApiState {
+            events: events.clone(),
+            store: store.clone(),
+            health: health.clone(),
+            health_max_age: Duration::from_secs(cfg.health_max_age_secs),
+            execution_paused: execution_paused.clone(),
+            prices: prices.clone(),
+            config: shared.clone(),
+        };
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(err) = api::serve(&listen, state, shutdown).await {
+                error!(%err, "API server stopped");
+            }
+        });
+    }
+
+    let mut chains = HashMap::new();
+    for chain in &cfg.chains {
+        if !jobs_of(&cfg)
+            .iter()
+            .any(|job| job.as_ref().is_none_or(|job| job.covers_chain(chain)))
+        {
             continue;
+        }
+        let provider = Provider::<Http>::try_from(chain.rpc_url.clone())?;
+        let limiter = chain
+            .rpc_rate_limit
+            .or(cfg.max_requests_per_sec)
+            .map(|per_sec| Arc::new(RateLimiter::new(per_sec)));
+        if !cli.skip_chain_checks {
+            price::ensure_chain_id(&provider, chain.chain_id).await?;
+            price::ensure_contracts(&chain.dex, &provider).await?;
+        }
+        let wallet = match &pool {
+            Some(pool) if cfg.execution.enabled && !cfg.execution.paper => {
+                let wallet = NonceManager::connect(
+                    &cfg.execution,
+                    &cfg.gas,
+                    chain.chain_id,
+                    provider.clone(),
+                    pool.clone(),
+                )
+                .await?;
+                Some(Arc::new(wallet))
+            }
+            _ => None,
         };
+        chains.insert(
+            chain.chain_id,
+            ChainHandles {
+                provider,
+                limiter,
+                wallet,
+            },
+        );
+    }
+    let mut targets = scan_targets(&cfg, &chains);
+    info!(
+        scanners = targets.len(),
+        combinations = targets.iter().map(|target| target.dex.len()).sum::<usize>(),
+        "Scan matrix built"
+    );
+    // Several scanners would overwrite one snapshot, so each gets its own file.
+    if let Some(path) = &cfg.state_file {
+        let scoped = targets.len() > 1;
+        for target in &mut targets {
+            target.state_file = Some(if scoped {
+                scoped_state_file(path, target)
+            } else {
+                path.clone()
+            });
+        }
+    }
+
+    let watch = cfg.config_watch_secs.map(Duration::from_secs);
+    let pairs_changed = Arc::new(Notify::new());
+    let reload_handler = reload::spawn_reload_handler(
+        "config.toml".to_string(),
+        shared.clone(),
+        watch,
+        pairs_changed.clone(),
+    );
+    let (writes, writer) = db::spawn_writer(store.clone());
+    let services = ScanServices {
+        store,
+        writes,
+        abi,
+        metrics,
+        events,
+        health,
+        prices,
+        exposure: Arc::new(ExposureLedger::default()),
+        execution_paused,
+    };
+    let mut scanners = JoinSet::new();
+    let mut running = HashMap::new();
+    for target in targets {
+        let key = target_key(&target);
+        let run = RunningScanner {
+            stop: shutdown.child_token(),
+            tokens: target.tokens.clone(),
+            state_file: target.state_file.clone(),
+        };
+        start_scanner(&shared, target, &services, run.stop.clone(), &mut scanners).await?;
+        running.insert(key, run);
+    }
+    info!("DEX contracts ready");
 
-        let gas_cost = U256::from((cfg.settings.est_gas_cost_usdc * 1e6) as u128);
-        let net_profit = if diff > gas_cost { diff - gas_cost } else { U256::zero() };
-        let profit_usdc = net_profit.as_u128() as f64 / 1e6;
-
-        println!(" Net Profit (after gas): {:.6} USDC", profit_usdc);
-
-        if profit_usdc > cfg.settings.min_profit_usdc {
-            println!(" Arbitrage Opportunity: Buy on {} → Sell on {}", buy_on, sell_on);
-            let timestamp = Utc::now().to_rfc3339();
-            conn.execute(
-                "INSERT INTO arbitrage_bot (buy_dex, sell_dex, profit_usdc, timestamp) 
-                 VALUES (?1, ?2, ?3, ?4)",
-                (&buy_on, &sell_on, &profit_usdc, &timestamp),
-            )?;
-            println!(" Opportunity saved!");
-        } else {
-            println!(" Profit too small, skipping");
+    // One failing scanner stops the others so the process exits with its error.
+    let mut summary = SessionSummary::default();
+    let mut failure = None;
+    loop {
+        let joined = tokio::select! {
+            joined = scanners.join_next() => joined,
+            _ = pairs_changed.notified(), if !shutdown.is_cancelled() => {
+                restart_changed_pairs(&shared, &chains, &services, &mut running, &mut scanners, &shutdown).await;
+                continue;
+            }
+        };
+        let Some(joined) = joined else {
+            break;
+        };
+        match joined? {
+            Ok(part) => {
+                summary.ticks += part.ticks;
+                summary.opportunities += part.opportunities;
+            }
+            Err(err) => {
+                error!(%err, "Scanner stopped");
+                shutdown.cancel();
+                failure.get_or_insert(err);
+            }
         }
     }
+    signal_handler.abort();
+    reload_handler.abort();
+    // The writer applies what the scanners queued once its last sender is gone.
+    drop(services);
+    writer.await?;
+    if let Some(err) = failure {
+        return Err(err.into());
+    }
+    info!(
+        ticks = summary.ticks,
+        opportunities = summary.opportunities,
+        "Session summary: {} ticks run, {} opportunities found",
+        summary.ticks,
+        summary.opportunities
+    );
+    Ok(())
 }
@@ -0,0 +1,304 @@
+//! Prometheus metrics and the `/metrics` HTTP endpoint.
+
+use anyhow::Result;
+use axum::Router;
+use axum::extract::State;
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use prometheus::{
+    Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+    Opts, Registry, TextEncoder,
+};
+use std::collections::{BTreeMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// Label naming the `[[jobs]]` entry a per-loop metric belongs to.
+const JOB_LABEL: &str = "job_name";
+
+/// Calls per endpoint behind its rolling latency average and p95.
+const LATENCY_WINDOW: usize = 200;
+
+/// Rolling latency of one RPC endpoint over its last [`LATENCY_WINDOW`] calls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencySummary {
+    /// Calls in the window.
+    pub samples: usize,
+    /// Mean latency in seconds.
+    pub avg_secs: f64,
+    /// 95th-percentile latency in seconds.
+    pub p95_secs: f64,
+}
+
+impl LatencySummary {
+    /// Summary of `samples` (seconds); `None` when empty.
+    pub fn of(samples: &VecDeque<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = samples.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (sorted.len() as f64 * 0.95).ceil() as usize;
+        Some(Self {
+            samples: sorted.len(),
+            avg_secs: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p95_secs: sorted[rank.clamp(1, sorted.len()) - 1],
+        })
+    }
+}
+
+/// Health and performance counters of the polling loop.
+pub struct Metrics {
+    registry: Registry,
+    /// `arbitrage_ticks_total{job_name}`
+    pub ticks: IntCounterVec,
+    /// `arbitrage_opportunities_detected_total{job_name}`
+    pub opportunities: IntCounterVec,
+    /// `arbitrage_rpc_errors_total{job_name}`
+    pub rpc_errors: IntCounterVec,
+    /// `arbitrage_notifications_suppressed_total{job_name}`
+    pub notifications_suppressed: IntCounterVec,
+    /// `arbitrage_price_usdc{chain_id,pair,dex}`, covering every configured venue.
+    pub price: GaugeVec,
+    /// `arbitrage_net_profit_usdc_last{job_name}`
+    pub net_profit_last: GaugeVec,
+    /// `arbitrage_tick_duration_seconds{job_name}`
+    pub tick_duration: HistogramVec,
+    /// `arbitrage_rpc_latency_seconds{endpoint,call}`
+    pub rpc_latency: HistogramVec,
+    /// `arbitrage_rpc_latency_avg_seconds{endpoint}`, over the rolling window.
+    pub rpc_latency_avg: GaugeVec,
+    /// `arbitrage_rpc_latency_p95_seconds{endpoint}`, over the rolling window.
+    pub rpc_latency_p95: GaugeVec,
+    /// Last [`LATENCY_WINDOW`] call latencies per endpoint, in seconds.
+    latency_windows: Mutex<BTreeMap<String, VecDeque<f64>>>,
+}
+
+/// The per-loop metrics of one job, resolved by [`Metrics::for_job`].
+#[derive(Clone)]
+pub struct JobMetrics {
+    /// `arbitrage_ticks_total`
+    pub ticks: IntCounter,
+    /// `arbitrage_opportunities_detected_total`
+    pub opportunities: IntCounter,
+    /// `arbitrage_rpc_errors_total`
+    pub rpc_errors: IntCounter,
+    /// `arbitrage_notifications_suppressed_total`
+    pub notifications_suppressed: IntCounter,
+    /// `arbitrage_net_profit_usdc_last`
+    pub net_profit_last: Gauge,
+    /// `arbitrage_tick_duration_seconds`
+    pub tick_duration: Histogram,
+}
+
+impl Metrics {
+    /// Creates and registers every metric in a fresh registry.
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+
+        let ticks = IntCounterVec::new(
+            Opts::new("arbitrage_ticks_total", "Price-check ticks run"),
+            &[JOB_LABEL],
+        )?;
+        let opportunities = IntCounterVec::new(
+            Opts::new(
+                "arbitrage_opportunities_detected_total",
+                "Opportunities above the profit threshold",
+            ),
+            &[JOB_LABEL],
+        )?;
+        let rpc_errors = IntCounterVec::new(
+            Opts::new("arbitrage_rpc_errors_total", "Failed RPC quote calls"),
+            &[JOB_LABEL],
+        )?;
+        let notifications_suppressed = IntCounterVec::new(
+            Opts::new(
+                "arbitrage_notifications_suppressed_total",
+                "Opportunity alerts held back by [notify_throttle]",
+            ),
+            &[JOB_LABEL],
+        )?;
+        let price = GaugeVec::new(
+            Opts::new("arbitrage_price_usdc", "Last quote in USDC per venue"),
+            &["chain_id", "pair", "dex"],
+        )?;
+        let net_profit_last = GaugeVec::new(
+            Opts::new(
+                "arbitrage_net_profit_usdc_last",
+                "Net profit after gas of the last compared tick",
+            ),
+            &[JOB_LABEL],
+        )?;
+        let tick_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "arbitrage_tick_duration_seconds",
+                "Wall-clock duration of one tick",
+            ),
+            &[JOB_LABEL],
+        )?;
+
+        let rpc_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "arbitrage_rpc_latency_seconds",
+                "Wall-clock latency of RPC calls per endpoint",
+            ),
+            &["endpoint", "call"],
+        )?;
+        let rpc_latency_avg = GaugeVec::new(
+            Opts::new(
+                "arbitrage_rpc_latency_avg_seconds",
+                "Mean RPC latency over the endpoint's recent calls",
+            ),
+            &["endpoint"],
+        )?;
+        let rpc_latency_p95 = GaugeVec::new(
+            Opts::new(
+                "arbitrage_rpc_latency_p95_seconds",
+                "95th-percentile RPC latency over the endpoint's recent calls",
+            ),
+            &["endpoint"],
+        )?;
+
+        registry.register(Box::new(ticks.clone()))?;
+        registry.register(Box::new(opportunities.clone()))?;
+        registry.register(Box::new(rpc_errors.clone()))?;
+        registry.register(Box::new(notifications_suppressed.clone()))?;
+        registry.register(Box::new(price.clone()))?;
+        registry.register(Box::new(net_profit_last.clone()))?;
+        registry.register(Box::new(tick_duration.clone()))?;
+        registry.register(Box::new(rpc_latency.clone()))?;
+        registry.register(Box::new(rpc_latency_avg.clone()))?;
+        registry.register(Box::new(rpc_latency_p95.clone()))?;
+
+        Ok(Self {
+            registry,
+            ticks,
+            opportunities,
+            rpc_errors,
+            notifications_suppressed,
+            price,
+            net_profit_last,
+            tick_duration,
+            rpc_latency,
+            rpc_latency_avg,
+            rpc_latency_p95,
+            latency_windows: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// The per-loop metrics labelled with `job`.
+    pub fn for_job(&self, job: &str) -> JobMetrics {
+        JobMetrics {
+            ticks: self.ticks.with_label_values(&[job]),
+            opportunities: self.opportunities.with_label_values(&[job]),
+            rpc_errors: self.rpc_errors.with_label_values(&[job]),
+            notifications_suppressed: self.notifications_suppressed.with_label_values(&[job]),
+            net_profit_last: self.net_profit_last.with_label_values(&[job]),
+            tick_duration: self.tick_duration.with_label_values(&[job]),
+        }
+    }
+
+    /// Sets the price gauge of `pair` on venue `dex` of chain `chain_id`.
+    pub fn record_price(&self, chain_id: u64, pair: &str, dex: &str, price_usdc: f64) {
+        self.price
+            .with_label_values(&[&chain_id.to_string(), pair, dex])
+            .set(price_usdc);
+    }
+
+    /// Records one `call` (e.g. `"quote"`, `"gas_price"`) to `endpoint` that took
+    /// `elapsed`, updating its rolling average and p95.
+    pub fn observe_rpc_latency(&self, endpoint: &str, call: &str, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        self.rpc_latency
+            .with_label_values(&[endpoint, call])
+            .observe(secs);
+        let mut windows = self.latency_windows.lock().unwrap();
+        let window = windows.entry(endpoint.to_string()).or_default();
+        if window.len() == LATENCY_WINDOW {
+            window.pop_front();
+        }
+        window.push_back(secs);
+        if let Some(summary) = LatencySummary::of(window) {
+            self.rpc_latency_avg
+                .with_label_values(&[endpoint])
+                .set(summary.avg_secs);
+            self.rpc_latency_p95
+                .with_label_values(&[endpoint])
+                .set(summary.p95_secs);
+        }
+    }
+
+    /// Rolling latency of every endpoint called so far, by endpoint.
+    pub fn rpc_latency_summaries(&self) -> Vec<(String, LatencySummary)> {
+        self.latency_windows
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(endpoint, window)| Some((endpoint.clone(), LatencySummary::of(window)?)))
+            .collect()
+    }
+
+    /// Renders every registered metric in the Prometheus text format.
+    pub fn render(&self) -> Result<String> {
+        let mut buf = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    }
+}
+
+async fn metrics_handler(State(metrics): State<Arc<Metrics>>) -> impl IntoResponse {
+    match metrics.render() {
+        Ok(body) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+            body,
+        )
+            .into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+/// Logs [`Metrics::rpc_latency_summaries`] every `interval` until `shutdown` is
+/// cancelled.
+pub async fn log_rpc_latency(
+    metrics: Arc<Metrics>,
+    interval: Duration,
+    shutdown: CancellationToken,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    // The first tick completes immediately, before any call was measured.
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = ticker.tick() => {}
+        }
+        for (endpoint, summary) in metrics.rpc_latency_summaries() {
+            info!(
+                endpoint,
+                samples = summary.samples,
+                avg_ms = summary.avg_secs * 1e3,
+                p95_ms = summary.p95_secs * 1e3,
+                "RPC latency"
+            );
+        }
+    }
+}
+
+/// Serves `GET /metrics` on `port` until `shutdown` is cancelled.
+pub async fn serve(metrics: Arc<Metrics>, port: u16, shutdown: CancellationToken) -> Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(metrics);
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!(%addr, "Metrics server listening");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,616 @@
+//! The execution wallet of one chain: local nonce assignment, gas-bumped
+//! replacement of transactions that do not mine, and the `transactions` log.
+
+use anyhow::{Result, anyhow, bail};
+use ethers::core::types::transaction::eip2718::TypedTransaction;
+use ethers::core::types::{
+    Address, BlockNumber, Eip1559TransactionRequest, H256, TransactionReceipt, U256,
+};
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, JsonRpcClient, Middleware, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::config::ExecutionSettings;
+use crate::db::{self, DbPool, SentTransaction};
+use crate::error::BotError;
+use crate::gas::{self, GasConfig};
+
+/// Signing client of the execution wallet, over the node's HTTP transport
+/// unless a test scripts a mock one.
+pub type Client<P = Http> = SignerMiddleware<Provider<P>, LocalWallet>;
+
+/// How often a sent transaction is checked for a receipt.
+const RECEIPT_POLL: Duration = Duration::from_secs(2);
+
+/// Sends every transaction of one wallet on one chain, so scanners sharing the
+/// wallet never race for a nonce.
+pub struct NonceManager<P = Http> {
+    client: Arc<Client<P>>,
+    pool: DbPool,
+    chain_id: u64,
+    /// `{:?}` of the wallet address, as stored in `transactions`.
+    wallet: String,
+    /// Next nonce to assign; `None` until seeded, or after a rejected broadcast
+    /// left it uncertain.
+    next: Mutex<Option<U256>>,
+    replace: ReplacePolicy,
+    gas: GasConfig,
+}
+
+impl<P: JsonRpcClient + 'static> NonceManager<P> {
+    /// Signs with `settings.private_key` on `chain_id`, resolves transactions an
+    /// earlier run left pending and seeds the nonce from the chain; `gas` prices
+    /// what it sends.
+    pub async fn connect(
+        settings: &ExecutionSettings,
+        gas: &GasConfig,
+        chain_id: u64,
+        provider: Provider<P>,
+        pool: DbPool,
+    ) -> Result<Self> {
+        let Some(key) = &settings.private_key else {
+            bail!(
+                "execution is enabled without a private key; set execution.private_key \
+                 or POLYGON_ARB_PRIVATE_KEY, or run with --paper"
+            );
+        };
+        let wallet = key
+            .trim_start_matches("0x")
+            .parse::<LocalWallet>()
+            .map_err(|err| anyhow!("execution.private_key: {}", err))?
+            .with_chain_id(chain_id);
+        info!(wallet = ?wallet.address(), chain_id, "Live execution enabled");
+        let manager = Self {
+            wallet: format!("{:?}", wallet.address()),
+            client: Arc::new(SignerMiddleware::new(provider, wallet)),
+            pool,
+            chain_id,
+            next: Mutex::new(None),
+            replace: ReplacePolicy {
+                after_blocks: settings.replace_after_blocks,
+                gas_bump_percent: settings.gas_bump_percent,
+                max_replacements: settings.max_replacements,
+                max_gas_price: U256::from((settings.max_gas_price_gwei * 1e9) as u128),
+            },
+            gas: gas.clone(),
+        };
+        manager.reconcile().await?;
+        let nonce = manager
+            .pending_nonce()
+            .await
+            .map_err(|err| anyhow!("reading the nonce of wallet {}: {}", manager.wallet, err))?;
+        *manager.next.lock().await = Some(nonce);
+        info!(%nonce, "Nonce seeded");
+        Ok(manager)
+    }
+
+    /// The signing client, for calls made from the wallet.
+    pub fn client(&self) -> &Arc<Client<P>> {
+        &self.client
+    }
+
+    /// Address of the wallet.
+    pub fn address(&self) -> Address {
+        self.client.address()
+    }
+
+    /// Sends `tx` at the next nonce and waits for it to mine, re-sending it at
+    /// `gas_bump_percent` more gas every `replace_after_blocks` blocks it stays
+    /// pending, at most `max_replacements` times and never above
+    /// `max_gas_price_gwei`.
+    ///
+    /// `tx` should be legacy; with `gas.eip1559` it is sent as an EIP-1559
+    /// transaction instead. Returns the receipt of whichever version mined,
+    /// reverted or not; `label` is stored with every version.
+    pub async fn send(&self, tx: TypedTransaction, label: &str) -> Result<TransactionReceipt> {
+        let mut tx = if self.gas.eip1559 { to_eip1559(tx) } else { tx };
+        tx.set_from(self.address());
+        let mut fees = self.fees().await?;
+        fees.apply(&mut tx);
+        let mut sent = Vec::new();
+        {
+            let mut next = self.next.lock().await;
+            let nonce = match *next {
+                Some(nonce) => nonce,
+                None => self.pending_nonce().await?,
+            };
+            tx.set_nonce(nonce);
+            self.client.fill_transaction(&mut tx, None).await?;
+            match self.broadcast(&tx, label).await {
+                Ok(hash) => {
+                    *next = Some(nonce + 1);
+                    sent.push(hash);
+                }
+                Err(err) => {
+                    *next = None;
+                    return Err(err);
+                }
+            }
+        }
+
+        let mut sent_at = self.client.get_block_number().await?.as_u64();
+        let mut replacements = 0;
+        loop {
+            tokio::time::sleep(RECEIPT_POLL).await;
+            for &hash in &sent {
+                if let Some(receipt) = self.client.get_transaction_receipt(hash).await? {
+                    self.settle(&sent, &receipt);
+                    return Ok(receipt);
+                }
+            }
+            let block = self.client.get_block_number().await?.as_u64();
+            match self
+                .replace
+                .decide(fees, block.saturating_sub(sent_at), replacements)
+            {
+                Replacement::Wait => continue,
+                Replacement::GiveUp => bail!(
+                    "{} {:?} not mined after {} replacements; left pending",
+                    label,
+                    sent.last().copied().unwrap_or_default(),
+                    replacements
+                ),
+                Replacement::Resend(bumped) => fees = bumped,
+            }
+            fees.apply(&mut tx);
+            match self.broadcast(&tx, label).await {
+                Ok(hash) => {
+                    replacements += 1;
+                    warn!(
+                        label,
+                        replaced = ?sent.last().copied().unwrap_or_default(),
+                        replacement = ?hash,
+                        gas_price_gwei = gwei(fees.ceiling()),
+                        "Transaction not mined; replaced at a higher gas price"
+                    );
+                    sent.push(hash);
+                }
+                // Usually "nonce too low": an earlier version just mined, and the
+                // next poll finds its receipt.
+                Err(err) => warn!(label, %err, "Replacement rejected"),
+            }
+            sent_at = block;
+        }
+    }
+
+    /// Resolves the rows an earlier run left `pending`: mined ones take their
+    /// receipt's status, ones whose nonce was used by another transaction become
+    /// `replaced`, and ones the node no longer knows become `dropped`.
+    async fn reconcile(&self) -> Result<()> {
+        let pending = {
+            let conn = self.pool.get()?;
+            db::pending_transactions(&conn, self.chain_id, &self.wallet)?
+        };
+        if pending.is_empty() {
+            return Ok(());
+        }
+        let mined = self
+            .client
+            .get_transaction_count(self.address(), Some(BlockNumber::Latest.into()))
+            .await?;
+        for (hash, nonce) in pending {
+            let parsed: H256 = hash
+                .parse()
+                .map_err(|err| anyhow!("stored transaction hash {}: {}", hash, err))?;
+            let resolved = match self.client.get_transaction_receipt(parsed).await? {
+                Some(receipt) => Some((receipt_status(&receipt), block_of(&receipt))),
+                None if U256::from(nonce) < mined => Some(("replaced", None)),
+                None if self.client.get_transaction(parsed).await?.is_none() => {
+                    Some(("dropped", None))
+                }
+                None => None,
+            };
+            match resolved {
+                Some((status, block)) => {
+                    let conn = self.pool.get()?;
+                    db::resolve_transaction(&conn, &hash, status, block)?;
+                    info!(hash, nonce, status, "Resolved transaction left pending");
+                }
+                None => warn!(
+                    hash,
+                    nonce, "Transaction from an earlier run is still pending"
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Fees of a first version: the suggested EIP-1559 fees, or `eth_gasPrice`.
+    async fn fees(&self) -> Result<Fees> {
+        if !self.gas.eip1559 {
+            return Ok(Fees::Legacy(self.client.get_gas_price().await?));
+        }
+        let (max_priority_fee, max_fee) =
+            gas::suggest_eip1559_fees(self.client.provider(), self.gas.gas_percentile).await?;
+        Ok(Fees::Eip1559 {
+            max_priority_fee,
+            max_fee,
+        })
+    }
+
+    async fn pending_nonce(&self) -> Result<U256> {
+        Ok(self
+            .client
+            .get_transaction_count(self.address(), Some(BlockNumber::Pending.into()))
+            .await?)
+    }
+
+    /// Broadcasts `tx` and logs it as `pending`.
+    async fn broadcast(&self, tx: &TypedTransaction, label: &str) -> Result<H256> {
+        let hash = self
+            .client
+            .send_transaction(tx.clone(), None)
+            .await?
+            .tx_hash();
+        let record = SentTransaction {
+            wallet: &self.wallet,
+            hash: &format!("{:?}", hash),
+            nonce: tx.nonce().map_or(0, |nonce| nonce.as_u64()),
+            gas_price_gwei: gwei(tx.gas_price().unwrap_or_default()),
+            label,
+        };
+        // The transaction is out either way; a failed write must not lose its nonce.
+        let stored = self
+            .pool
+            .get()
+            .map_err(BotError::from)
+            .and_then(|conn| db::insert_transaction(&conn, self.chain_id, &record));
+        if let Err(err) = stored {
+            error!(%err, ?record, "Could not store transaction");
+        }
+        Ok(hash)
+    }
+
+    /// Marks the version that mined with its receipt and every other version
+    /// of the same nonce as `replaced`.
+    fn settle(&self, sent: &[H256], receipt: &TransactionReceipt) {
+        let settled = self
+            .pool
+            .get()
+            .map_err(anyhow::Error::from)
+            .and_then(|conn| {
+                for &hash in sent {
+                    let (status, block) = if hash == receipt.transaction_hash {
+                        (receipt_status(receipt), block_of(receipt))
+                    } else {
+                        ("replaced", None)
+                    };
+                    db::resolve_transaction(&conn, &format!("{:?}", hash), status, block)?;
+                }
+                Ok(())
+            });
+        if let Err(err) = settled {
+            error!(%err, tx = ?receipt.transaction_hash, "Could not update transactions");
+        }
+    }
+}
+
+/// When [`NonceManager::send`] replaces a transaction that does not mine.
+#[derive(Debug, Clone, Copy)]
+struct ReplacePolicy {
+    /// Blocks a version may stay pending before it is replaced.
+    after_blocks: u64,
+    /// Fee increase of each replacement, in percent.
+    gas_bump_percent: u64,
+    /// Replacements sent before giving up.
+    max_replacements: u32,
+    /// Gas price no replacement may exceed.
+    max_gas_price: U256,
+}
+
+/// What to do with a transaction whose latest version is still pending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Replacement {
+    /// Keep polling for its receipt.
+    Wait,
+    /// Send a replacement at these fees.
+    Resend(Fees),
+    /// Stop replacing and leave it pending.
+    GiveUp,
+}
+
+impl ReplacePolicy {
+    /// Decides on a version sent at `fees` that has been pending for `waited`
+    /// blocks, after `replacements` earlier replacements.
+    fn decide(&self, fees: Fees, waited: u64, replacements: u32) -> Replacement {
+        if waited < self.after_blocks {
+            return Replacement::Wait;
+        }
+        let bumped = fees.bumped(self.gas_bump_percent);
+        if replacements >= self.max_replacements || bumped.ceiling() > self.max_gas_price {
+            Replacement::GiveUp
+        } else {
+            Replacement::Resend(bumped)
+        }
+    }
+}
+
+/// Gas pricing of one version of a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fees {
+    Legacy(U256),
+    Eip1559 {
+        max_priority_fee: U256,
+        max_fee: U256,
+    },
+}
+
+impl Fees {
+    /// Every fee raised by `percent`, as a replacement must be.
+    fn bumped(self, percent: u64) -> Self {
+        match self {
+            Self::Legacy(gas_price) => Self::Legacy(bump(gas_price, percent)),
+            Self::Eip1559 {
+                max_priority_fee,
+                max_fee,
+            } => Self::Eip1559 {
+                max_priority_fee: bump(max_priority_fee, percent),
+                max_fee: bump(max_fee, percent),
+            },
+        }
+    }
+
+    /// The most one unit of gas may cost.
+    fn ceiling(self) -> U256 {
+        match self {
+            Self::Legacy(gas_price) => gas_price,
+            Self::Eip1559 { max_fee, .. } => max_fee,
+        }
+    }
+
+    fn apply(self, tx: &mut TypedTransaction) {
+        match (self, tx) {
+            (
+                Self::Eip1559 {
+                    max_priority_fee,
+                    max_fee,
+                },
+                TypedTransaction::Eip1559(inner),
+            ) => {
+                inner.max_priority_fee_per_gas = Some(max_priority_fee);
+                inner.max_fee_per_gas = Some(max_fee);
+            }
+            (fees, tx) => {
+                tx.set_gas_price(fees.ceiling());
+            }
+        }
+    }
+}
+
+/// `tx` as an EIP-1559 request with its fees left unset.
+fn to_eip1559(tx: TypedTransaction) -> TypedTransaction {
+    match tx {
+        TypedTransaction::Legacy(legacy) => TypedTransaction::Eip1559(Eip1559TransactionRequest {
+            from: legacy.from,
+            to: legacy.to,
+            gas: legacy.gas,
+            value: legacy.value,
+            data: legacy.data,
+            nonce: legacy.nonce,
+            chain_id: legacy.chain_id,
+            ..Default::default()
+        }),
+        tx => tx,
+    }
+}
+
+/// `gas_price` raised by `percent`, and by at least one wei.
+fn bump(gas_price: U256, percent: u64) -> U256 {
+    (gas_price * U256::from(100 + percent) / U256::from(100)).max(gas_price + 1)
+}
+
+fn receipt_status(receipt: &TransactionReceipt) -> &'static str {
+    if receipt.status == Some(1.into()) {
+        "confirmed"
+    } else {
+        "reverted"
+    }
+}
+
+fn block_of(receipt: &TransactionReceipt) -> Option<u64> {
+    receipt.block_number.map(|block| block.as_u64())
+}
+
+fn gwei(wei: U256) -> f64 {
+    wei.as_u128() as f64 / 1e9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::core::types::TransactionRequest;
+    use ethers::providers::MockProvider;
+    use serde_json::json;
+
+    const GWEI: u64 = 1_000_000_000;
+
+    fn policy() -> ReplacePolicy {
+        ReplacePolicy {
+            after_blocks: 3,
+            gas_bump_percent: 20,
+            max_replacements: 2,
+            max_gas_price: U256::from(200 * GWEI),
+        }
+    }
+
+    fn legacy(gwei: u64) -> Fees {
+        Fees::Legacy(U256::from(gwei * GWEI))
+    }
+
+    #[test]
+    fn waits_until_replace_after_blocks() {
+        let policy = policy();
+        assert_eq!(policy.decide(legacy(100), 0, 0), Replacement::Wait);
+        assert_eq!(policy.decide(legacy(100), 2, 0), Replacement::Wait);
+        assert_eq!(
+            policy.decide(legacy(100), 3, 0),
+            Replacement::Resend(legacy(120))
+        );
+    }
+
+    #[test]
+    fn bumps_every_replacement_and_gives_up_after_the_last() {
+        let policy = policy();
+        let mut fees = legacy(100);
+        let mut sent = vec![fees];
+        for replacements in 0.. {
+            match policy.decide(fees, policy.after_blocks, replacements) {
+                Replacement::Resend(bumped) => {
+                    fees = bumped;
+                    sent.push(fees);
+                }
+                Replacement::GiveUp => break,
+                Replacement::Wait => unreachable!("waited long enough"),
+            }
+        }
+        assert_eq!(sent, [legacy(100), legacy(120), legacy(144)]);
+    }
+
+    #[test]
+    fn never_bumps_above_max_gas_price() {
+        let policy = policy();
+        assert_eq!(
+            policy.decide(legacy(166), 3, 0),
+            Replacement::Resend(Fees::Legacy(U256::from(199_200_000_000u64)))
+        );
+        assert_eq!(policy.decide(legacy(170), 3, 0), Replacement::GiveUp);
+    }
+
+    #[test]
+    fn eip1559_bumps_both_fees() {
+        let fees = Fees::Eip1559 {
+            max_priority_fee: U256::from(30 * GWEI),
+            max_fee: U256::from(150 * GWEI),
+        };
+        assert_eq!(
+            policy().decide(fees, 5, 1),
+            Replacement::Resend(Fees::Eip1559 {
+                max_priority_fee: U256::from(36 * GWEI),
+                max_fee: U256::from(180 * GWEI),
+            })
+        );
+        // The ceiling is the max fee, not the tip.
+        assert_eq!(
+            policy().decide(
+                Fees::Eip1559 {
+                    max_priority_fee: U256::from(30 * GWEI),
+                    max_fee: U256::from(170 * GWEI),
+                },
+                5,
+                1
+            ),
+            Replacement::GiveUp
+        );
+    }
+
+    #[test]
+    fn bump_raises_by_at_least_one_wei() {
+        assert_eq!(bump(U256::from(3), 10), U256::from(4));
+        assert_eq!(bump(U256::from(100), 0), U256::from(101));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unmined_transaction_is_replaced_at_the_same_nonce() {
+        let pool = db::open_pool("file:nonce_replace?mode=memory&cache=shared").unwrap();
+        db::init_db(&pool).unwrap();
+        let (first, replacement) = (H256::repeat_byte(0xa1), H256::repeat_byte(0xb2));
+        let mined = TransactionReceipt {
+            transaction_hash: replacement,
+            block_number: Some(103.into()),
+            status: Some(1.into()),
+            ..Default::default()
+        };
+        // The node's answers in request order; the mock replies last-pushed first.
+        let replies = [
+            json!(U256::from(7)),          // pending nonce, at connect
+            json!(U256::from(100 * GWEI)), // eth_gasPrice
+            json!(first),                  // eth_sendRawTransaction
+            json!(U256::from(100)),        // block it was sent in
+            json!(null),                   // first poll: no receipt,
+            json!(U256::from(101)),        // one block pending
+            json!(null),                   // second poll: no receipt,
+            json!(U256::from(102)),        // two blocks pending: replace
+            json!(replacement),            // eth_sendRawTransaction
+            json!(null),                   // third poll: the first is unmined,
+            json!(mined),                  // and the replacement mined
+        ];
+        let mock = MockProvider::new();
+        for reply in replies.iter().rev() {
+            mock.push::<serde_json::Value, _>(reply).unwrap();
+        }
+        let settings = ExecutionSettings {
+            private_key: Some(
+                "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80".into(),
+            ),
+            replace_after_blocks: 2,
+            gas_bump_percent: 20,
+            max_replacements: 3,
+            max_gas_price_gwei: 500.0,
+            ..ExecutionSettings::default()
+        };
+        let wallet = NonceManager::connect(
+            &settings,
+            &GasConfig::default(),
+            137,
+            Provider::new(mock.clone()),
+            pool.clone(),
+        )
+        .await
+        .unwrap();
+        let tx = TransactionRequest::new()
+            .to(Address::repeat_byte(0x22))
+            .gas(100_000)
+            .data(vec![0x01]);
+        let receipt = wallet.send(tx.into(), "swap").await.unwrap();
+        assert_eq!(receipt.transaction_hash, replacement);
+
+        let conn = pool.get().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT hash, nonce, gas_price_gwei, label, status, confirmed_block
+                 FROM transactions ORDER BY id",
+            )
+            .unwrap();
+        let rows: Vec<(String, u64, f64, String, String, Option<u64>)> = stmt
+            .query_map((), |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            [
+                (
+                    format!("{:?}", first),
+                    7,
+                    100.0,
+                    "swap".into(),
+                    "replaced".into(),
+                    None
+                ),
+                (
+                    format!("{:?}", replacement),
+                    7,
+                    120.0,
+                    "swap".into(),
+                    "confirmed".into(),
+                    Some(103)
+                ),
+            ]
+        );
+        // The nonce moved on past the one replaced.
+        assert_eq!(*wallet.next.lock().await, Some(U256::from(8)));
+    }
+}
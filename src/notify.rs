@@ -0,0 +1,311 @@
+//! Outbound notifications for recorded opportunities and RPC health changes.
+
+use anyhow::{Result, bail};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio_util::task::TaskTracker;
+use tracing::warn;
+
+use crate::config::redact_url;
+
+/// Target of opportunity POSTs (`[webhook]`).
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct WebhookConfig {
+    /// Endpoint receiving the JSON body.
+    pub url: String,
+    /// Sent verbatim as the `Authorization` header, e.g. `"Bearer <token>"`.
+    pub auth_header: Option<String>,
+    /// Per-request timeout in milliseconds.
+    pub timeout_ms: u64,
+}
+
+/// Telegram bot used for opportunity messages (`[telegram]`).
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct TelegramConfig {
+    /// Token issued by @BotFather.
+    pub bot_token: String,
+    /// Chat (user, group or channel) receiving the messages.
+    pub chat_id: i64,
+}
+
+// Both hand-written so credentials never reach logs through `{:?}`.
+impl fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("url", &redact_url(&self.url))
+            .field(
+                "auth_header",
+                &self.auth_header.as_ref().map(|_| "<redacted>"),
+            )
+            .field("timeout_ms", &self.timeout_ms)
+            .finish()
+    }
+}
+
+impl fmt::Debug for TelegramConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TelegramConfig")
+            .field("bot_token", &"<redacted>")
+            .field("chat_id", &self.chat_id)
+            .finish()
+    }
+}
+
+/// Sends `msg` to the configured chat through the Bot API `sendMessage` method.
+pub async fn send_telegram_alert(config: &TelegramConfig, msg: &str) -> Result<()> {
+    let url = format!(
+        "https://api.telegram.org/bot{}/sendMessage",
+        config.bot_token
+    );
+    let resp = Client::new()
+        .post(&url)
+        .timeout(TELEGRAM_TIMEOUT)
+        .json(&serde_json::json!({ "chat_id": config.chat_id, "text": msg }))
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        bail!("Telegram returned {}", resp.status());
+    }
+    Ok(())
+}
+
+const TELEGRAM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JSON body posted to the webhook and pushed on `/stream` for every recorded opportunity.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpportunityAlert {
+    /// DEX to buy on.
+    pub buy_dex: String,
+    /// DEX to sell on.
+    pub sell_dex: String,
+    /// Net profit after gas in USDC.
+    pub profit_usdc: f64,
+    /// RFC 3339 detection time, identical to the stored row.
+    pub timestamp: String,
+    /// Set when the bot runs with `dry_run`; the opportunity is not in `arbitrage_bot`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+}
+
+impl OpportunityAlert {
+    /// Human-readable rendering for chat messages.
+    pub fn to_message(&self) -> String {
+        format!(
+            "{}Arbitrage opportunity\nBuy on {} → Sell on {}\nProfit: {:.6} USDC\nDetected: {}",
+            if self.dry_run { "[DRY RUN] " } else { "" },
+            self.buy_dex,
+            self.sell_dex,
+            self.profit_usdc,
+            self.timestamp
+        )
+    }
+}
+
+/// JSON body posted to the webhook when a scanner's RPC goes down or recovers.
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcHealthAlert {
+    /// `"rpc_down"` or `"rpc_up"`; opportunity bodies carry no `event`.
+    pub event: &'static str,
+    /// Chain of the scanner.
+    pub chain_id: u64,
+    /// Pair of the scanner.
+    pub pair: String,
+    /// Consecutive failed (down) or successful (up) ticks behind the change.
+    pub consecutive_ticks: u32,
+    /// RFC 3339 time of the change.
+    pub timestamp: String,
+}
+
+impl RpcHealthAlert {
+    /// Human-readable rendering for chat messages.
+    pub fn to_message(&self) -> String {
+        let state = match self.event {
+            "rpc_down" => "RPC down",
+            _ => "RPC recovered",
+        };
+        format!(
+            "{} on chain {} ({})\n{} consecutive ticks\nAt: {}",
+            state, self.chain_id, self.pair, self.consecutive_ticks, self.timestamp
+        )
+    }
+}
+
+/// JSON body posted to the webhook when a throttled spread closes.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpreadClosedAlert {
+    /// Always `"spread_closed"`.
+    pub event: &'static str,
+    /// Pair of the scanner.
+    pub pair: String,
+    /// DEX the spread bought on.
+    pub buy_dex: String,
+    /// DEX the spread sold on.
+    pub sell_dex: String,
+    /// Seconds from its first to its last qualifying tick.
+    pub open_secs: u64,
+    /// Highest net profit seen while open, in USDC.
+    pub peak_profit_usdc: f64,
+    /// Opportunity alerts held back while open.
+    pub suppressed: u64,
+    /// RFC 3339 time the close was noticed.
+    pub timestamp: String,
+}
+
+impl SpreadClosedAlert {
+    /// Human-readable rendering for chat messages.
+    pub fn to_message(&self) -> String {
+        format!(
+            "Spread closed on {}\nBuy on {} → Sell on {}\nSpread open {}m{:02}s, peak profit {:.6} USDC\n{} alerts suppressed\nAt: {}",
+            self.pair,
+            self.buy_dex,
+            self.sell_dex,
+            self.open_secs / 60,
+            self.open_secs % 60,
+            self.peak_profit_usdc,
+            self.suppressed,
+            self.timestamp
+        )
+    }
+}
+
+/// Fire-and-forget delivery of opportunity alerts.
+///
+/// Every delivery runs on its own task so a slow endpoint never delays a tick;
+/// the tasks are tracked so shutdown can wait for them to drain.
+pub struct Notifier {
+    client: Client,
+    webhook: Option<WebhookConfig>,
+    telegram: Option<TelegramConfig>,
+    telegram_min_interval: Duration,
+    last_telegram: Mutex<Option<Instant>>,
+    tasks: TaskTracker,
+}
+
+impl Notifier {
+    /// Creates a notifier for the configured sinks; with none, every alert is dropped.
+    ///
+    /// Telegram messages are sent at most once per `telegram_min_interval`;
+    /// alerts arriving sooner are skipped for that sink.
+    pub fn new(
+        webhook: Option<WebhookConfig>,
+        telegram: Option<TelegramConfig>,
+        telegram_min_interval: Duration,
+    ) -> Self {
+        Self {
+            client: Client::new(),
+            webhook,
+            telegram,
+            telegram_min_interval,
+            last_telegram: Mutex::new(None),
+            tasks: TaskTracker::new(),
+        }
+    }
+
+    /// Replaces the sinks and the Telegram interval after a config reload;
+    /// deliveries already queued go to the old sinks.
+    pub fn reconfigure(
+        &mut self,
+        webhook: Option<WebhookConfig>,
+        telegram: Option<TelegramConfig>,
+        telegram_min_interval: Duration,
+    ) {
+        self.webhook = webhook;
+        self.telegram = telegram;
+        self.telegram_min_interval = telegram_min_interval;
+    }
+
+    /// When the last Telegram message was sent, if any.
+    pub fn last_telegram(&self) -> Option<Instant> {
+        *self.last_telegram.lock().unwrap()
+    }
+
+    /// Resumes the Telegram rate limit from a previous run's last message.
+    pub fn restore_last_telegram(&self, at: Instant) {
+        *self.last_telegram.lock().unwrap() = Some(at);
+    }
+
+    /// Queues `alert` for background delivery to every configured sink.
+    pub fn notify(&self, alert: OpportunityAlert) {
+        if self.telegram.is_some() && self.telegram_allowed() {
+            self.notify_telegram(alert.to_message());
+        }
+        self.notify_webhook(alert);
+    }
+
+    /// Queues an RPC health change for every configured sink. Changes are
+    /// already debounced, so they bypass the Telegram rate limit.
+    pub fn notify_rpc(&self, alert: RpcHealthAlert) {
+        self.notify_telegram(alert.to_message());
+        self.notify_webhook(alert);
+    }
+
+    /// Queues the summary of a closed spread for every configured sink. There is
+    /// one per spread, so it bypasses the Telegram rate limit.
+    pub fn notify_closed(&self, alert: SpreadClosedAlert) {
+        self.notify_telegram(alert.to_message());
+        self.notify_webhook(alert);
+    }
+
+    /// Takes the Telegram rate limit's slot, unless a message went out too recently.
+    fn telegram_allowed(&self) -> bool {
+        let mut last = self.last_telegram.lock().unwrap();
+        let now = Instant::now();
+        if last.is_some_and(|t| now.duration_since(t) < self.telegram_min_interval) {
+            return false;
+        }
+        *last = Some(now);
+        true
+    }
+
+    fn notify_telegram(&self, msg: String) {
+        let Some(telegram) = self.telegram.clone() else {
+            return;
+        };
+        self.tasks.spawn(async move {
+            if let Err(err) = send_telegram_alert(&telegram, &msg).await {
+                warn!(%err, "Telegram alert failed");
+            }
+        });
+    }
+
+    fn notify_webhook<T: Serialize + Send + 'static>(&self, alert: T) {
+        let Some(webhook) = self.webhook.clone() else {
+            return;
+        };
+        let client = self.client.clone();
+        self.tasks.spawn(async move {
+            let mut request = client
+                .post(&webhook.url)
+                .timeout(Duration::from_millis(webhook.timeout_ms))
+                .json(&alert);
+            if let Some(auth) = &webhook.auth_header {
+                request = request.header(reqwest::header::AUTHORIZATION, auth);
+            }
+            match request.send().await {
+                Ok(resp) if resp.status().is_success() => {}
+                Ok(resp) => warn!(status = %resp.status(), "Webhook returned non-2xx status"),
+                Err(err) if err.is_timeout() => {
+                    warn!(timeout_ms = webhook.timeout_ms, "Webhook timed out")
+                }
+                Err(err) => warn!(%err, "Webhook request failed"),
+            }
+        });
+    }
+
+    /// Waits up to `grace` for in-flight deliveries to finish.
+    pub async fn flush(&self, grace: Duration) {
+        self.tasks.close();
+        if tokio::time::timeout(grace, self.tasks.wait())
+            .await
+            .is_err()
+        {
+            warn!(
+                pending = self.tasks.len(),
+                "Dropping undelivered notifications"
+            );
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Chainlink reference prices used to reject manipulated or stale quotes.
+
+use anyhow::{Result, bail};
+use ethers::contract::Contract;
+use ethers::core::types::{Address, I256, U256};
+use ethers::providers::{Http, Provider};
+use std::sync::Arc;
+
+use crate::error::{BotError, BotResult};
+use crate::price::load_router_abi;
+
+/// Chainlink `AggregatorV3Interface` ABI fragment.
+pub const AGGREGATOR_ABI_PATH: &str = "abi/chainlink_aggregator_v3_abi.json";
+
+/// A Chainlink price feed, e.g. ETH / USD.
+pub struct ChainlinkFeed {
+    feed: Contract<Provider<Http>>,
+    decimals: u32,
+}
+
+impl ChainlinkFeed {
+    /// Connects to the aggregator at `address` and reads its `decimals()`.
+    pub async fn connect(address: Address, provider: Arc<Provider<Http>>) -> BotResult<Self> {
+        let feed = Contract::new(address, load_router_abi(AGGREGATOR_ABI_PATH)?, provider);
+        let decimals: u8 = feed
+            .method::<_, u8>("decimals", ())?
+            .call()
+            .await
+            .map_err(|err| BotError::Rpc(format!("oracle {:?} decimals(): {}", address, err)))?;
+        Ok(Self {
+            feed,
+            decimals: decimals as u32,
+        })
+    }
+
+    /// Latest answer of the feed as a decimal price.
+    pub async fn latest_price(&self) -> Result<f64> {
+        let (_, answer, _, _, _) = self
+            .feed
+            .method::<_, (U256, I256, U256, U256, U256)>("latestRoundData", ())?
+            .call()
+            .await?;
+        if !answer.is_positive() {
+            bail!("oracle answered {}", answer);
+        }
+        Ok(answer.as_u128() as f64 / 10f64.powi(self.decimals as i32))
+    }
+}
+
+/// How far `price` is from `reference`, in percent of `reference`.
+pub fn deviation_pct(price: f64, reference: f64) -> f64 {
+    ((price - reference) / reference).abs() * 100.0
+}
@@ -0,0 +1,197 @@
+//! Per-tick console output in `plain`, `table` or `quiet` mode.
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+/// How much the scanner reports about each tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    /// One line per quote plus the spread and verdict, as events with fields.
+    #[default]
+    Plain,
+    /// One aligned row per tick under a header.
+    Table,
+    /// Nothing per tick; only qualifying opportunities (and warnings) are logged.
+    Quiet,
+}
+
+/// How a tick ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// Fewer than two venues quoted.
+    InvalidPrices,
+    /// The best quotes were equal.
+    NoSpread,
+    /// The widest spread did not clear `min_profit_usdc` after gas.
+    BelowThreshold,
+    /// A spread qualified but a later check (oracle, TWAP) rejected it.
+    Filtered(String),
+    /// An opportunity was recorded.
+    Opportunity,
+}
+
+/// Everything a formatter needs to describe one tick.
+#[derive(Debug, Clone)]
+pub struct TickReport {
+    /// Block the quotes were taken at, when known.
+    pub block: Option<u64>,
+    /// Every venue that quoted.
+    pub quotes: Vec<QuoteReport>,
+    /// Net profit of the widest spread, when two venues were compared.
+    pub net_profit_usdc: Option<f64>,
+    /// Outcome of the tick.
+    pub verdict: Verdict,
+}
+
+/// One venue's quote within a [`TickReport`].
+#[derive(Debug, Clone)]
+pub struct QuoteReport {
+    /// Venue that quoted.
+    pub dex: String,
+    /// Output for `trade_size`, in the quote token.
+    pub price: f64,
+    /// Label of the winning path.
+    pub path: String,
+    /// Impermanent loss, in percent, of an LP position entered at
+    /// `lp_entry_price`; absent when none is set.
+    pub impermanent_loss_pct: Option<f64>,
+}
+
+impl TickReport {
+    /// An empty report, to be filled in as the tick progresses.
+    pub fn new() -> Self {
+        Self {
+            block: None,
+            quotes: Vec::new(),
+            net_profit_usdc: None,
+            verdict: Verdict::InvalidPrices,
+        }
+    }
+}
+
+impl Default for TickReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Renders [`TickReport`]s according to an [`OutputMode`].
+#[derive(Debug)]
+pub struct TickFormatter {
+    mode: OutputMode,
+    /// Column order of the table: the configured venues.
+    dexes: Vec<String>,
+    /// Symbol of the quote token, printed after prices.
+    quote_symbol: String,
+    header_logged: bool,
+}
+
+impl TickFormatter {
+    /// A formatter with one table column per entry of `dexes`, printing prices
+    /// in `quote_symbol`.
+    pub fn new(mode: OutputMode, dexes: Vec<String>, quote_symbol: &str) -> Self {
+        Self {
+            mode,
+            dexes,
+            quote_symbol: quote_symbol.to_string(),
+            header_logged: false,
+        }
+    }
+
+    /// Announces the start of a tick (plain mode only).
+    pub fn start(&self) {
+        if self.mode == OutputMode::Plain {
+            info!("Checking prices...");
+        }
+    }
+
+    /// Reports a finished tick.
+    pub fn render(&mut self, pair: &str, report: &TickReport) {
+        match self.mode {
+            OutputMode::Plain => render_plain(pair, &self.quote_symbol, report),
+            OutputMode::Table => self.render_table(report),
+            OutputMode::Quiet => {}
+        }
+    }
+
+    fn render_table(&mut self, report: &TickReport) {
+        if !self.header_logged {
+            let mut header = format!("{:>10}", "BLOCK");
+            for dex in &self.dexes {
+                header.push_str(&format!(" {:>14}", dex));
+            }
+            header.push_str(&format!(" {:>12}  {}", "NET", "RESULT"));
+            info!("{}", header);
+            self.header_logged = true;
+        }
+        let mut row = match report.block {
+            Some(block) => format!("{:>10}", block),
+            None => format!("{:>10}", "-"),
+        };
+        for dex in &self.dexes {
+            match report.quotes.iter().find(|quote| &quote.dex == dex) {
+                Some(quote) => row.push_str(&format!(" {:>14.6}", quote.price)),
+                None => row.push_str(&format!(" {:>14}", "-")),
+            }
+        }
+        match report.net_profit_usdc {
+            Some(net) => row.push_str(&format!(" {:>12.6}", net)),
+            None => row.push_str(&format!(" {:>12}", "-")),
+        }
+        row.push_str(&format!("  {}", verdict_label(&report.verdict)));
+        info!("{}", row);
+    }
+}
+
+fn verdict_label(verdict: &Verdict) -> String {
+    match verdict {
+        Verdict::InvalidPrices => "invalid prices".to_string(),
+        Verdict::NoSpread => "no spread".to_string(),
+        Verdict::BelowThreshold => "below threshold".to_string(),
+        Verdict::Filtered(reason) => format!("filtered: {}", reason),
+        Verdict::Opportunity => "OPPORTUNITY".to_string(),
+    }
+}
+
+fn render_plain(pair: &str, quote_symbol: &str, report: &TickReport) {
+    for quote in &report.quotes {
+        info!(
+            dex = %quote.dex,
+            pair,
+            price = quote.price,
+            block = report.block,
+            impermanent_loss_pct = quote.impermanent_loss_pct,
+            "Quote"
+        );
+    }
+    if report.verdict == Verdict::InvalidPrices {
+        warn!(pair, "Skipping invalid prices");
+        return;
+    }
+    let summary: Vec<String> = report
+        .quotes
+        .iter()
+        .map(|quote| {
+            let il = match quote.impermanent_loss_pct {
+                Some(il) => format!(", IL {:.4}%", il),
+                None => String::new(),
+            };
+            format!(
+                "{}: {} {} ({}{})",
+                quote.dex, quote.price, quote_symbol, quote.path, il
+            )
+        })
+        .collect();
+    info!("{}", summary.join(" | "));
+    if let Some(profit) = report.net_profit_usdc {
+        info!(pair, profit, "Net Profit (after gas): {:.6} USDC", profit);
+    }
+    match &report.verdict {
+        Verdict::NoSpread => info!(pair, "Prices equal → No arbitrage"),
+        Verdict::BelowThreshold => info!(pair, "Profit too small, skipping"),
+        Verdict::Filtered(reason) => info!(pair, "{}, skipping", reason),
+        Verdict::InvalidPrices | Verdict::Opportunity => {}
+    }
+}
@@ -0,0 +1,149 @@
+//! Adaptive tick pacing driven by how fast the observed spread moves.
+
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// Average relative spread change per tick at which the interval reaches its floor.
+const VOLATILE_CHANGE_RATIO: f64 = 0.05;
+
+/// Bounds of the adaptive interval (`[settings.adaptive_interval]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdaptiveIntervalConfig {
+    /// Shortest interval in seconds, used while spreads move quickly.
+    pub min_interval: u64,
+    /// Longest interval in seconds, used while spreads are flat.
+    pub max_interval: u64,
+    /// Number of recent spreads per pair considered.
+    #[serde(default = "default_window")]
+    pub window: usize,
+}
+
+fn default_window() -> usize {
+    10
+}
+
+/// Opportunity-driven refresh bounds (`[settings.adaptive_refresh]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdaptiveRefreshConfig {
+    /// Shortest delay between ticks, in seconds.
+    pub min_rate_secs: u64,
+    /// Longest delay between ticks, in seconds.
+    pub max_rate_secs: u64,
+    /// Growth of the delay after a tick without an opportunity (e.g. `1.2`).
+    pub opportunity_speedup_factor: f64,
+}
+
+/// Halves the delay after an opportunity and backs off by a factor otherwise.
+#[derive(Debug)]
+pub struct RefreshController {
+    cfg: AdaptiveRefreshConfig,
+    current: Duration,
+}
+
+impl RefreshController {
+    /// Starts from `initial` (normally `refresh_rate`), clamped to the configured bounds.
+    pub fn new(cfg: AdaptiveRefreshConfig, initial: Duration) -> Self {
+        let mut controller = Self {
+            cfg,
+            current: initial,
+        };
+        controller.current = controller.clamp(initial.as_secs_f64());
+        controller
+    }
+
+    /// Delay before the next tick, updated from whether this tick found an opportunity.
+    pub fn after_tick(&mut self, opportunity: bool) -> Duration {
+        let secs = self.current.as_secs_f64();
+        self.current = if opportunity {
+            self.clamp(secs / 2.0)
+        } else {
+            self.clamp(secs * self.cfg.opportunity_speedup_factor)
+        };
+        self.current
+    }
+
+    /// Delay returned by the last [`Self::after_tick`], or the initial one.
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// Continues from a delay saved by a previous run, clamped to the bounds.
+    pub fn resume_from(&mut self, delay: Duration) {
+        self.current = self.clamp(delay.as_secs_f64());
+    }
+
+    fn clamp(&self, secs: f64) -> Duration {
+        let min = self.cfg.min_rate_secs.min(self.cfg.max_rate_secs) as f64;
+        let max = self.cfg.max_rate_secs.max(self.cfg.min_rate_secs) as f64;
+        Duration::from_secs_f64(secs.clamp(min, max))
+    }
+}
+
+/// Tracks recent spreads per pair and derives the next tick interval.
+#[derive(Debug)]
+pub struct VolatilityPacer {
+    cfg: AdaptiveIntervalConfig,
+    spreads: HashMap<String, VecDeque<f64>>,
+}
+
+impl VolatilityPacer {
+    /// Creates a pacer with empty history; it starts at `max_interval`.
+    pub fn new(cfg: AdaptiveIntervalConfig) -> Self {
+        Self {
+            cfg,
+            spreads: HashMap::new(),
+        }
+    }
+
+    /// Adds the latest gross spread of `pair`, dropping the oldest beyond the window.
+    pub fn observe(&mut self, pair: &str, spread: f64) {
+        let window = self.spreads.entry(pair.to_string()).or_default();
+        window.push_back(spread);
+        while window.len() > self.cfg.window.max(2) {
+            window.pop_front();
+        }
+    }
+
+    /// Recent spreads per pair, oldest first; replaying them through
+    /// [`Self::observe`] restores the pacer.
+    pub fn history(&self) -> impl Iterator<Item = (&str, Vec<f64>)> {
+        self.spreads
+            .iter()
+            .map(|(pair, spreads)| (pair.as_str(), spreads.iter().copied().collect()))
+    }
+
+    /// Interval until the next tick: the most volatile pair sets the pace.
+    ///
+    /// Volatility is the mean absolute tick-to-tick spread change relative to the
+    /// mean spread; it maps linearly from `max_interval` (no change) down to
+    /// `min_interval` (at or above [`VOLATILE_CHANGE_RATIO`]).
+    pub fn next_interval(&self) -> Duration {
+        let min = self.cfg.min_interval.min(self.cfg.max_interval) as f64;
+        let max = self.cfg.max_interval.max(self.cfg.min_interval) as f64;
+        let ratio = self
+            .spreads
+            .values()
+            .filter_map(change_ratio)
+            .fold(0.0, f64::max);
+        let urgency = (ratio / VOLATILE_CHANGE_RATIO).min(1.0);
+        Duration::from_secs_f64(max - (max - min) * urgency)
+    }
+}
+
+fn change_ratio(spreads: &VecDeque<f64>) -> Option<f64> {
+    if spreads.len() < 2 {
+        return None;
+    }
+    let mean = spreads.iter().map(|s| s.abs()).sum::<f64>() / spreads.len() as f64;
+    if mean == 0.0 {
+        return Some(0.0);
+    }
+    let changes: Vec<f64> = spreads
+        .iter()
+        .zip(spreads.iter().skip(1))
+        .map(|(a, b)| (b - a).abs())
+        .collect();
+    let mean_change = changes.iter().sum::<f64>() / changes.len() as f64;
+    Some(mean_change / mean)
+}
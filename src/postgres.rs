@@ -0,0 +1,589 @@
+//! [`OpportunityStore`] on a PostgreSQL database several bot instances share
+//! (`postgres` feature).
+//!
+//! Every row carries the `instance_id` of the bot that wrote it. Expiry, the
+//! TWAP/VWAP windows and the daily loss limit only look at the instance's own
+//! rows; the history and `/opportunities` cover every instance.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::{Mutex, MutexGuard};
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, NoTls, Transaction};
+use tracing::{error, info, warn};
+
+use crate::db::{
+    self, DRY_RUN_TABLE, DailyCount, DbWrite, ExecutionRecord, HistoryStats, Opportunity,
+    OpportunityStats, PriceRecord, PriceTick, RecentOpportunity, RouteStats,
+};
+use crate::error::{BotError, BotResult};
+use crate::store::OpportunityStore;
+
+/// Columns of `arbitrage_bot` and of its dry-run twin.
+const OPPORTUNITY_COLUMNS: &str = "
+    id BIGSERIAL PRIMARY KEY,
+    instance_id TEXT NOT NULL,
+    chain_id BIGINT NOT NULL,
+    pair TEXT NOT NULL,
+    buy_dex TEXT NOT NULL,
+    sell_dex TEXT NOT NULL,
+    profit_usdc DOUBLE PRECISION NOT NULL,
+    profit_pct DOUBLE PRECISION,
+    gas_cost_usdc DOUBLE PRECISION,
+    score DOUBLE PRECISION,
+    buy_path TEXT,
+    sell_path TEXT,
+    simulated_ok BOOLEAN,
+    potentially_stale BOOLEAN,
+    optimal_size_usdc DOUBLE PRECISION,
+    buy_fee_usdc DOUBLE PRECISION,
+    sell_fee_usdc DOUBLE PRECISION,
+    break_even_bps DOUBLE PRECISION,
+    analytic_size_usdc DOUBLE PRECISION,
+    timestamp TIMESTAMPTZ NOT NULL,
+    timestamp_ms BIGINT NOT NULL,
+    anomaly BOOLEAN,
+    expires_at TIMESTAMPTZ,
+    buy_liquidity_usdc DOUBLE PRECISION,
+    sell_liquidity_usdc DOUBLE PRECISION,
+    report_profit DOUBLE PRECISION,
+    report_currency TEXT,
+    quote_shortfall_bps DOUBLE PRECISION,
+    trend TEXT,
+    status TEXT NOT NULL DEFAULT 'detected'";
+
+/// Schema steps in order. Each runs once per database and is recorded in
+/// `schema_migrations` under its 1-based position; append new steps, never
+/// edit applied ones.
+fn migrations() -> Vec<String> {
+    let mut steps = vec![format!(
+        "CREATE TABLE IF NOT EXISTS arbitrage_bot ({columns});
+         CREATE TABLE IF NOT EXISTS {dry_run} ({columns});
+         CREATE INDEX IF NOT EXISTS arbitrage_bot_expiry
+             ON arbitrage_bot (instance_id, status, expires_at);
+         CREATE TABLE IF NOT EXISTS price_history (
+             id BIGSERIAL PRIMARY KEY,
+             instance_id TEXT NOT NULL,
+             chain_id BIGINT NOT NULL,
+             dex TEXT NOT NULL,
+             pair TEXT NOT NULL,
+             amount_in DOUBLE PRECISION,
+             price_usdc DOUBLE PRECISION NOT NULL,
+             volume_usdc DOUBLE PRECISION,
+             block_number BIGINT,
+             impermanent_loss_pct DOUBLE PRECISION,
+             timestamp TIMESTAMPTZ NOT NULL,
+             timestamp_ms BIGINT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS price_history_window
+             ON price_history (instance_id, dex, pair, timestamp_ms);",
+        columns = OPPORTUNITY_COLUMNS,
+        dry_run = DRY_RUN_TABLE,
+    )];
+    steps.push(format!(
+        "ALTER TABLE arbitrage_bot ADD COLUMN IF NOT EXISTS expires_at_ms BIGINT;
+         ALTER TABLE {dry_run} ADD COLUMN IF NOT EXISTS expires_at_ms BIGINT;
+         UPDATE arbitrage_bot
+             SET expires_at_ms = FLOOR(EXTRACT(EPOCH FROM expires_at) * 1000)::BIGINT
+             WHERE expires_at IS NOT NULL;
+         UPDATE {dry_run}
+             SET expires_at_ms = FLOOR(EXTRACT(EPOCH FROM expires_at) * 1000)::BIGINT
+             WHERE expires_at IS NOT NULL;
+         DROP INDEX IF EXISTS arbitrage_bot_expiry;
+         CREATE INDEX arbitrage_bot_expiry
+             ON arbitrage_bot (instance_id, status, expires_at_ms);",
+        dry_run = DRY_RUN_TABLE,
+    ));
+    steps.push(
+        "CREATE TABLE IF NOT EXISTS executions (
+             id BIGSERIAL PRIMARY KEY,
+             instance_id TEXT NOT NULL,
+             opportunity_id BIGINT REFERENCES arbitrage_bot(id),
+             chain_id BIGINT NOT NULL,
+             mode TEXT NOT NULL,
+             kind TEXT,
+             status TEXT NOT NULL,
+             buy_tx TEXT,
+             sell_tx TEXT,
+             usdc_in DOUBLE PRECISION,
+             weth_received DOUBLE PRECISION,
+             usdc_out DOUBLE PRECISION,
+             realized_profit_usdc DOUBLE PRECISION,
+             error TEXT,
+             wallet_check_passed BOOLEAN,
+             timestamp TIMESTAMPTZ NOT NULL,
+             timestamp_ms BIGINT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS executions_daily
+             ON executions (instance_id, chain_id, timestamp_ms);"
+            .to_string(),
+    );
+    steps
+}
+
+/// Advisory lock held while migrating, so instances starting together apply
+/// each step once.
+const MIGRATION_LOCK: i64 = 0x6172_6269_7462_6f74;
+
+/// A PostgreSQL database, over one connection that is reopened when it drops.
+///
+/// The writer and the API readers take turns on the connection.
+pub struct PostgresStore {
+    url: String,
+    instance_id: String,
+    client: Mutex<Client>,
+}
+
+impl PostgresStore {
+    /// Connects to `url` (TLS is not supported) and tags every row written
+    /// with `instance_id`.
+    pub async fn connect(url: &str, instance_id: &str) -> BotResult<Self> {
+        Ok(Self {
+            url: url.to_string(),
+            instance_id: instance_id.to_string(),
+            client: Mutex::new(open_client(url).await?),
+        })
+    }
+
+    async fn client(&self) -> BotResult<MutexGuard<'_, Client>> {
+        let mut client = self.client.lock().await;
+        if client.is_closed() {
+            warn!("Postgres connection lost; reconnecting");
+            *client = open_client(&self.url).await?;
+        }
+        Ok(client)
+    }
+
+    async fn insert_into(
+        &self,
+        tx: &Transaction<'_>,
+        table: &str,
+        opportunity: &Opportunity,
+    ) -> BotResult<i64> {
+        let timestamp = DateTime::<Utc>::from_timestamp_millis(opportunity.timestamp_ms)
+            .ok_or_else(|| {
+                BotError::Db(format!(
+                    "timestamp {} ms out of range",
+                    opportunity.timestamp_ms
+                ))
+            })?;
+        let expires_at = DateTime::parse_from_rfc3339(&opportunity.expires_at)
+            .map_err(|err| {
+                BotError::Db(format!("expires_at {:?}: {}", opportunity.expires_at, err))
+            })?
+            .with_timezone(&Utc);
+        let chain_id = opportunity.chain_id as i64;
+        let params: [&(dyn ToSql + Sync); 29] = [
+            &self.instance_id,
+            &chain_id,
+            &opportunity.pair,
+            &opportunity.buy_dex,
+            &opportunity.sell_dex,
+            &opportunity.profit_usdc,
+            &opportunity.profit_pct,
+            &opportunity.gas_cost_usdc,
+            &opportunity.score,
+            &opportunity.buy_path,
+            &opportunity.sell_path,
+            &opportunity.simulated_ok,
+            &opportunity.potentially_stale,
+            &opportunity.optimal_size_usdc,
+            &opportunity.buy_fee_usdc,
+            &opportunity.sell_fee_usdc,
+            &opportunity.break_even_bps,
+            &opportunity.analytic_size_usdc,
+            &timestamp,
+            &opportunity.timestamp_ms,
+            &opportunity.anomaly,
+            &expires_at,
+            &opportunity.buy_liquidity_usdc,
+            &opportunity.sell_liquidity_usdc,
+            &opportunity.report_profit,
+            &opportunity.report_currency,
+            &opportunity.quote_shortfall_bps,
+            &opportunity.trend,
+            &opportunity.expires_at_ms,
+        ];
+        let row = tx
+            .query_one(
+                &format!(
+                    "INSERT INTO {} (instance_id, chain_id, pair, buy_dex, sell_dex, profit_usdc,
+                                     profit_pct, gas_cost_usdc, score, buy_path, sell_path,
+                                     simulated_ok, potentially_stale, optimal_size_usdc,
+                                     buy_fee_usdc, sell_fee_usdc, break_even_bps,
+                                     analytic_size_usdc, timestamp, timestamp_ms, anomaly,
+                                     expires_at, buy_liquidity_usdc, sell_liquidity_usdc,
+                                     report_profit, report_currency, quote_shortfall_bps, trend,
+                                     expires_at_ms)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15,
+                             $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28,
+                             $29)
+                     RETURNING id",
+                    table
+                ),
+                &params,
+            )
+            .await?;
+        Ok(row.get(0))
+    }
+
+    async fn stage_prices(&self, tx: &Transaction<'_>, tick: &PriceTick) -> BotResult<()> {
+        let stmt = tx
+            .prepare(
+                "INSERT INTO price_history (instance_id, chain_id, dex, pair, amount_in, price_usdc,
+                                            volume_usdc, block_number, impermanent_loss_pct,
+                                            timestamp, timestamp_ms)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            )
+            .await?;
+        let (chain_id, timestamp_ms) = (tick.chain_id as i64, tick.at.timestamp_millis());
+        for PriceRecord {
+            dex,
+            pair,
+            amount_in,
+            price_usdc,
+            volume_usdc,
+            block_number,
+            impermanent_loss_pct,
+        } in &tick.records
+        {
+            let block_number = block_number.map(|block| block as i64);
+            tx.execute(
+                &stmt,
+                &[
+                    &self.instance_id,
+                    &chain_id,
+                    dex,
+                    pair,
+                    amount_in,
+                    price_usdc,
+                    volume_usdc,
+                    &block_number,
+                    impermanent_loss_pct,
+                    &tick.at,
+                    &timestamp_ms,
+                ],
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn mark_expired(&self, tx: &Transaction<'_>, as_of_ms: u64) -> BotResult<u64> {
+        Ok(tx
+            .execute(
+                "UPDATE arbitrage_bot SET status = 'expired'
+                 WHERE instance_id = $1 AND status = 'detected' AND expires_at_ms < $2",
+                &[&self.instance_id, &(as_of_ms as i64)],
+            )
+            .await?)
+    }
+
+    async fn stage_execution(
+        &self,
+        tx: &Transaction<'_>,
+        opportunity_id: i64,
+        chain_id: u64,
+        record: &ExecutionRecord,
+    ) -> BotResult<()> {
+        let now = Utc::now();
+        tx.execute(
+            "INSERT INTO executions (instance_id, opportunity_id, chain_id, mode, kind, status,
+                                     buy_tx, sell_tx, usdc_in, weth_received, usdc_out,
+                                     realized_profit_usdc, error, wallet_check_passed,
+                                     timestamp, timestamp_ms)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)",
+            &[
+                &self.instance_id,
+                &opportunity_id,
+                &(chain_id as i64),
+                &record.mode,
+                &record.kind,
+                &record.status,
+                &record.buy_tx,
+                &record.sell_tx,
+                &record.usdc_in,
+                &record.weth_received,
+                &record.usdc_out,
+                &record.realized_profit_usdc,
+                &record.error,
+                &record.wallet_check_passed,
+                &now,
+                &now.timestamp_millis(),
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn stage_write(&self, tx: &Transaction<'_>, write: &DbWrite) -> BotResult<i64> {
+        match write {
+            DbWrite::InsertOpportunity(opportunity, _) => {
+                self.insert_into(tx, "arbitrage_bot", opportunity).await
+            }
+            DbWrite::InsertDryRun(opportunity) => {
+                self.insert_into(tx, DRY_RUN_TABLE, opportunity).await
+            }
+            DbWrite::MarkExpired(as_of_ms) => Ok(self.mark_expired(tx, *as_of_ms).await? as i64),
+            DbWrite::InsertPrices(tick, _) => {
+                self.stage_prices(tx, tick).await?;
+                Ok(tick.records.len() as i64)
+            }
+            DbWrite::InsertExecution(opportunity_id, chain_id, record) => {
+                self.stage_execution(tx, *opportunity_id, *chain_id, record)
+                    .await?;
+                Ok(1)
+            }
+        }
+    }
+}
+
+async fn open_client(url: &str) -> BotResult<Client> {
+    let (client, connection) = tokio_postgres::connect(url, NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(err) = connection.await {
+            error!(err = %BotError::from(err), "Postgres connection closed");
+        }
+    });
+    Ok(client)
+}
+
+#[async_trait]
+impl OpportunityStore for PostgresStore {
+    fn backend(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn migrate(&self) -> BotResult<()> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK])
+            .await?;
+        tx.batch_execute(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (
+                version INTEGER PRIMARY KEY,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+        )
+        .await?;
+        let applied: i32 = tx
+            .query_one(
+                "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+                &[],
+            )
+            .await?
+            .get(0);
+        for (version, step) in (1..).zip(migrations()).skip(applied as usize) {
+            tx.batch_execute(&step).await?;
+            tx.execute(
+                "INSERT INTO schema_migrations (version) VALUES ($1)",
+                &[&version],
+            )
+            .await?;
+            info!(version, "Postgres schema migrated");
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_opportunity(&self, opportunity: &Opportunity) -> BotResult<i64> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        let id = self.insert_into(&tx, "arbitrage_bot", opportunity).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn insert_prices(&self, tick: &PriceTick) -> BotResult<()> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        self.stage_prices(&tx, tick).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn apply_batch(&self, batch: &[DbWrite]) -> BotResult<Vec<BotResult<i64>>> {
+        let mut client = self.client().await?;
+        let mut tx = client.transaction().await?;
+        let mut outcomes = Vec::with_capacity(batch.len());
+        for write in batch {
+            // A failed statement aborts a Postgres transaction, so each write
+            // gets a savepoint to roll back to.
+            let savepoint = tx.transaction().await?;
+            let outcome = self.stage_write(&savepoint, write).await;
+            if outcome.is_ok() {
+                savepoint.commit().await?;
+            } else {
+                savepoint.rollback().await?;
+            }
+            outcomes.push(outcome);
+        }
+        tx.commit().await?;
+        Ok(outcomes)
+    }
+
+    async fn recent_opportunities(&self, limit: u64) -> BotResult<Vec<RecentOpportunity>> {
+        let client = self.client().await?;
+        let rows = client
+            .query(
+                "SELECT id, chain_id, pair, buy_dex, sell_dex, profit_usdc, gas_cost_usdc, score,
+                        status, timestamp, instance_id
+                 FROM arbitrage_bot
+                 ORDER BY id DESC
+                 LIMIT $1",
+                &[&(limit as i64)],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| RecentOpportunity {
+                id: row.get(0),
+                chain_id: Some(row.get::<_, i64>(1) as u64),
+                pair: Some(row.get(2)),
+                buy_dex: row.get(3),
+                sell_dex: row.get(4),
+                profit_usdc: row.get(5),
+                gas_cost_usdc: row.get(6),
+                score: row.get(7),
+                status: Some(row.get(8)),
+                timestamp: row.get::<_, DateTime<Utc>>(9).to_rfc3339(),
+                instance_id: Some(row.get(10)),
+            })
+            .collect())
+    }
+
+    async fn opportunity_stats(
+        &self,
+        dex: &str,
+        pair: &str,
+        window_secs: u64,
+    ) -> BotResult<OpportunityStats> {
+        let client = self.client().await?;
+        let since = db::window_start_ms(window_secs);
+        let points: Vec<(f64, i64)> = client
+            .query(
+                "SELECT price_usdc, timestamp_ms FROM price_history
+                 WHERE instance_id = $1 AND dex = $2 AND pair = $3 AND timestamp_ms >= $4
+                 ORDER BY timestamp_ms ASC",
+                &[&self.instance_id, &dex, &pair, &since],
+            )
+            .await?
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+        let twap = db::time_weighted(&points).ok_or_else(|| {
+            BotError::Db(format!(
+                "no price history for {} on {} in the last {}s",
+                pair, dex, window_secs
+            ))
+        })?;
+        let row = client
+            .query_one(
+                "SELECT SUM(price_usdc * volume_usdc), SUM(volume_usdc) FROM price_history
+                 WHERE instance_id = $1 AND dex = $2 AND pair = $3 AND timestamp_ms >= $4
+                   AND volume_usdc IS NOT NULL",
+                &[&self.instance_id, &dex, &pair, &since],
+            )
+            .await?;
+        let vwap = match (row.get::<_, Option<f64>>(0), row.get::<_, Option<f64>>(1)) {
+            (Some(weighted), Some(volume)) if volume > 0.0 => weighted / volume,
+            _ => {
+                return Err(BotError::Db(format!(
+                    "no volume history for {} on {} in the last {}s",
+                    pair, dex, window_secs
+                )));
+            }
+        };
+        Ok(OpportunityStats {
+            dex: dex.to_string(),
+            pair: pair.to_string(),
+            twap,
+            vwap,
+        })
+    }
+
+    async fn history_stats(&self) -> BotResult<HistoryStats> {
+        let client = self.client().await?;
+        let totals = client
+            .query_one(
+                "SELECT COUNT(*), AVG(profit_usdc), MAX(profit_usdc),
+                        percentile_cont(0.5) WITHIN GROUP (ORDER BY profit_usdc)
+                 FROM arbitrage_bot",
+                &[],
+            )
+            .await?;
+        let routes = client
+            .query(
+                "SELECT buy_dex, sell_dex, COUNT(*), AVG(profit_usdc), MAX(profit_usdc)
+                 FROM arbitrage_bot
+                 GROUP BY buy_dex, sell_dex
+                 ORDER BY COUNT(*) DESC",
+                &[],
+            )
+            .await?
+            .iter()
+            .map(|row| RouteStats {
+                buy_dex: row.get(0),
+                sell_dex: row.get(1),
+                count: row.get::<_, i64>(2) as u64,
+                avg_profit_usdc: row.get(3),
+                max_profit_usdc: row.get(4),
+            })
+            .collect();
+        let per_day = client
+            .query(
+                "SELECT to_char(timestamp AT TIME ZONE 'UTC', 'YYYY-MM-DD') AS day, COUNT(*)
+                 FROM arbitrage_bot
+                 GROUP BY day
+                 ORDER BY day",
+                &[],
+            )
+            .await?
+            .iter()
+            .map(|row| DailyCount {
+                day: row.get(0),
+                count: row.get::<_, i64>(1) as u64,
+            })
+            .collect();
+        Ok(HistoryStats {
+            total: totals.get::<_, i64>(0) as u64,
+            avg_profit_usdc: totals.get::<_, Option<f64>>(1).unwrap_or(0.0),
+            max_profit_usdc: totals.get::<_, Option<f64>>(2).unwrap_or(0.0),
+            median_profit_usdc: totals.get::<_, Option<f64>>(3).unwrap_or(0.0),
+            routes,
+            per_day,
+        })
+    }
+
+    async fn insert_execution(
+        &self,
+        opportunity_id: i64,
+        chain_id: u64,
+        record: &ExecutionRecord,
+    ) -> BotResult<()> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await?;
+        self.stage_execution(&tx, opportunity_id, chain_id, record)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn realized_loss_today(&self, chain_id: u64) -> BotResult<f64> {
+        let client = self.client().await?;
+        let loss: Option<f64> = client
+            .query_one(
+                "SELECT SUM(-realized_profit_usdc) FROM executions
+                 WHERE instance_id = $1 AND mode = 'live' AND chain_id = $2
+                   AND realized_profit_usdc < 0 AND timestamp_ms >= $3",
+                &[
+                    &self.instance_id,
+                    &(chain_id as i64),
+                    &db::utc_midnight_ms(),
+                ],
+            )
+            .await?
+            .get(0);
+        Ok(loss.unwrap_or(0.0))
+    }
+}
@@ -0,0 +1,1466 @@
+//! On-chain quote fetching behind the [`PriceSource`] trait.
+
+use async_trait::async_trait;
+use ethers::abi::{Abi, Detokenize, parse_abi};
+use ethers::contract::{Contract, ContractCall, ContractError};
+use ethers::core::types::{Address, Bytes, H256, I256, U256};
+use ethers::providers::{Http, JsonRpcClient, Middleware, Provider};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::warn;
+
+use crate::aggregator::AggregatorSource;
+use crate::arb;
+use crate::config::{DexEntry, DexKind};
+use crate::error::{BotError, BotResult};
+
+/// QuoterV2 ABI, loaded only when a `uniswap_v3` entry is configured.
+pub const QUOTER_V2_ABI_PATH: &str = "abi/uniswap_v3_quoter_v2_abi.json";
+
+/// Balancer V2 vault ABI, loaded only when a `balancer_v2` entry is configured.
+pub const BALANCER_VAULT_ABI_PATH: &str = "abi/balancer_v2_vault_abi.json";
+
+/// Solidly router ABI, loaded only when a `solidly` entry is configured.
+pub const SOLIDLY_ROUTER_ABI_PATH: &str = "abi/solidly_router_abi.json";
+
+/// Curve pool ABI, loaded only when a `curve` entry is configured.
+pub const CURVE_POOL_ABI_PATH: &str = "abi/curve_pool_abi.json";
+
+/// UniswapV2 Router02 ABI built into the binary; `abi_path` replaces it.
+pub const UNISWAP_V2_ROUTER_ABI: &str = include_str!("../abi/uniswap_v2_router02_abi.json");
+
+/// Fee tiers (in hundredths of a bip) of the Uniswap V3 deployment.
+pub const UNISWAP_V3_FEE_TIERS: [u32; 3] = [500, 3000, 10000];
+
+/// Compiler artifact as exported by Hardhat or Foundry, of which only the ABI
+/// is read.
+#[derive(Deserialize)]
+struct AbiArtifact {
+    abi: Abi,
+}
+
+/// Reads an ABI from `path`, see [`parse_abi_json`].
+pub fn load_router_abi(path: &str) -> BotResult<Abi> {
+    let abi_data = fs::read_to_string(path)
+        .map_err(|err| BotError::Abi(format!("reading {}: {}", path, err)))?;
+    parse_abi_json(&abi_data, path)
+}
+
+/// The UniswapV2 Router02 ABI built into the binary.
+pub fn default_router_abi() -> Abi {
+    parse_abi_json(UNISWAP_V2_ROUTER_ABI, "built-in router ABI")
+        .expect("built-in router ABI parses")
+}
+
+/// Parses either a raw JSON ABI array or an artifact object holding it under
+/// `abi`; `source` names the input in errors.
+pub fn parse_abi_json(abi_data: &str, source: &str) -> BotResult<Abi> {
+    let array_err = match serde_json::from_str::<Abi>(abi_data) {
+        Ok(abi) => return Ok(abi),
+        Err(err) => err,
+    };
+    match serde_json::from_str::<AbiArtifact>(abi_data) {
+        Ok(artifact) => Ok(artifact.abi),
+        Err(artifact_err) => Err(BotError::Abi(format!(
+            "{} is neither an ABI array ({}) nor an artifact with an `abi` field ({})",
+            source, array_err, artifact_err
+        ))),
+    }
+}
+
+/// Why a quote could not be obtained from a venue.
+#[derive(Debug, thiserror::Error)]
+pub enum PriceError {
+    /// The venue does not quote the path, e.g. a pair its pool does not hold.
+    #[error("no liquidity path: {0}")]
+    NoLiquidityPath(String),
+    /// The quote call reverted, usually for lack of a pool or liquidity along
+    /// the path; carries the decoded reason.
+    #[error("reverted: {0}")]
+    Revert(String),
+    /// The call could not be encoded against the venue's ABI: a missing method
+    /// or mismatched arguments, which no retry fixes.
+    #[error("abi encoding: {0}")]
+    AbiEncoding(String),
+    /// Transport failures, timeouts and undecodable responses.
+    #[error("rpc: {0}")]
+    Rpc(String),
+    /// The call succeeded but returned no output amount.
+    #[error("empty response")]
+    EmptyResponse,
+}
+
+impl PriceError {
+    /// Whether the failure may clear on a retry and reflects on the endpoint:
+    /// transport errors and empty responses, unlike reverts and ABI mistakes.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Rpc(_) | Self::EmptyResponse)
+    }
+
+    /// Whether the venue cannot fill the path, as opposed to failing to answer.
+    pub fn is_unfillable(&self) -> bool {
+        matches!(self, Self::NoLiquidityPath(_) | Self::Revert(_))
+    }
+}
+
+/// A venue that can quote an exact-input swap along a token path.
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// Display name, used in logs and stored records.
+    fn name(&self) -> &str;
+
+    /// Amount of the last token in `path` received for `amount_in` of the first.
+    async fn quote(&self, amount_in: U256, path: &[Address]) -> Result<U256, PriceError> {
+        self.quote_at(amount_in, path, None).await
+    }
+
+    /// [`Self::quote`] against the state of `block`; `None` is the latest block.
+    async fn quote_at(
+        &self,
+        amount_in: U256,
+        path: &[Address],
+        block: Option<u64>,
+    ) -> Result<U256, PriceError>;
+
+    /// Checks at startup that the venue can trade `tokens`, each with the
+    /// decimals its amounts are given in; most venues accept anything.
+    async fn validate(&self, _tokens: &[(Address, u32)]) -> BotResult<()> {
+        Ok(())
+    }
+}
+
+/// Uniswap V2 style router quoted through `getAmountsOut`.
+pub struct UniswapV2Source {
+    name: String,
+    router: Contract<Provider<Http>>,
+}
+
+impl UniswapV2Source {
+    /// Wraps the router at `address` using the Uniswap V2 router `abi`.
+    pub fn new(name: String, address: Address, abi: Abi, provider: Arc<Provider<Http>>) -> Self {
+        Self {
+            name,
+            router: Contract::new(address, abi, provider),
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for UniswapV2Source {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn quote_at(
+        &self,
+        amount_in: U256,
+        path: &[Address],
+        block: Option<u64>,
+    ) -> Result<U256, PriceError> {
+        fetch_price(&self.router, amount_in, path.to_vec(), block).await
+    }
+}
+
+/// Uniswap V3 QuoterV2 quoted through `quoteExactInputSingle` at several fee tiers.
+///
+/// The quoter simulates the swap and reverts instead of returning, so it is only
+/// ever used through `eth_call`. Only two-token paths can be quoted.
+pub struct UniswapV3Source {
+    name: String,
+    quoter: Contract<Provider<Http>>,
+    fee_tiers: Vec<u32>,
+}
+
+impl UniswapV3Source {
+    /// Wraps the QuoterV2 at `address`, quoting each of `fee_tiers`.
+    pub fn new(
+        name: String,
+        address: Address,
+        abi: Abi,
+        fee_tiers: Vec<u32>,
+        provider: Arc<Provider<Http>>,
+    ) -> Self {
+        Self {
+            name,
+            quoter: Contract::new(address, abi, provider),
+            fee_tiers,
+        }
+    }
+
+    async fn quote_tier(
+        &self,
+        amount_in: U256,
+        token_in: Address,
+        token_out: Address,
+        fee: u32,
+        block: Option<u64>,
+    ) -> Result<U256, PriceError> {
+        let params = (token_in, token_out, amount_in, fee, U256::zero());
+        let call = self
+            .quoter
+            .method::<_, (U256, U256, u32, U256)>("quoteExactInputSingle", (params,))
+            .map_err(abi_encoding_error)?;
+        let (amount_out, _, _, _) = at_block(call, block)
+            .call()
+            .await
+            .map_err(classify_call_error)?;
+        Ok(amount_out)
+    }
+}
+
+#[async_trait]
+impl PriceSource for UniswapV3Source {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn quote_at(
+        &self,
+        amount_in: U256,
+        path: &[Address],
+        block: Option<u64>,
+    ) -> Result<U256, PriceError> {
+        let &[token_in, token_out] = path else {
+            return Err(PriceError::NoLiquidityPath(
+                "multi-hop paths are not quoted on uniswap_v3".into(),
+            ));
+        };
+        // A tier without an initialized pool reverts; it only counts as a
+        // failure when no tier quotes at all.
+        let mut results = Vec::with_capacity(self.fee_tiers.len());
+        for &fee in &self.fee_tiers {
+            results.push(
+                self.quote_tier(amount_in, token_in, token_out, fee, block)
+                    .await,
+            );
+        }
+        best_quote(results)
+    }
+}
+
+/// Solidly-fork router (Dystopia, Pearl, …) quoted through `getAmountsOut` on
+/// `(from, to, stable)` routes.
+pub struct SolidlySource {
+    name: String,
+    router: Contract<Provider<Http>>,
+    /// Pool type used on every hop; `None` quotes both and keeps the better.
+    stable: Option<bool>,
+}
+
+impl SolidlySource {
+    /// Wraps the router at `address` using the Solidly router `abi`.
+    pub fn new(
+        name: String,
+        address: Address,
+        abi: Abi,
+        stable: Option<bool>,
+        provider: Arc<Provider<Http>>,
+    ) -> Self {
+        Self {
+            name,
+            router: Contract::new(address, abi, provider),
+            stable,
+        }
+    }
+
+    /// `getAmountsOut(amount_in, routes)` along `path`, every hop through a
+    /// pool of the given `stable` type.
+    fn get_amounts_out(
+        &self,
+        amount_in: U256,
+        path: &[Address],
+        stable: bool,
+    ) -> Result<ContractCall<Provider<Http>, Vec<U256>>, PriceError> {
+        let routes: Vec<(Address, Address, bool)> = path
+            .windows(2)
+            .map(|hop| (hop[0], hop[1], stable))
+            .collect();
+        self.router
+            .method::<_, Vec<U256>>("getAmountsOut", (amount_in, routes))
+            .map_err(abi_encoding_error)
+    }
+
+    async fn quote_routes(
+        &self,
+        amount_in: U256,
+        path: &[Address],
+        stable: bool,
+        block: Option<u64>,
+    ) -> Result<U256, PriceError> {
+        let call = self.get_amounts_out(amount_in, path, stable)?;
+        let amounts = at_block(call, block)
+            .call()
+            .await
+            .map_err(classify_call_error)?;
+        amounts_out(&amounts, path.len())
+    }
+}
+
+#[async_trait]
+impl PriceSource for SolidlySource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn quote_at(
+        &self,
+        amount_in: U256,
+        path: &[Address],
+        block: Option<u64>,
+    ) -> Result<U256, PriceError> {
+        let flags = match self.stable {
+            Some(stable) => vec![stable],
+            None => vec![false, true],
+        };
+        let mut results = Vec::with_capacity(flags.len());
+        for stable in flags {
+            results.push(self.quote_routes(amount_in, path, stable, block).await);
+        }
+        best_quote(results)
+    }
+}
+
+/// Highest successful quote among `results`; the first error if none succeeded.
+fn best_quote(results: Vec<Result<U256, PriceError>>) -> Result<U256, PriceError> {
+    let mut best: Option<U256> = None;
+    let mut first_err = None;
+    for result in results {
+        match result {
+            Ok(amount) if best.is_none_or(|b| amount > b) => best = Some(amount),
+            Ok(_) => {}
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    match (best, first_err) {
+        (Some(best), _) => Ok(best),
+        (None, Some(err)) => Err(err),
+        (None, None) => Ok(U256::zero()),
+    }
+}
+
+/// `SwapKind.GIVEN_IN`: the step amount is the exact input.
+const BALANCER_GIVEN_IN: u8 = 0;
+
+/// Balancer V2 vault quoted through `queryBatchSwap` on a single pool.
+///
+/// Like the V3 quoter, `queryBatchSwap` is state-changing and only meant for
+/// `eth_call`. Paths are swapped hop by hop inside the configured pool, so every
+/// token on the path must be one of its tokens.
+pub struct BalancerV2Source {
+    name: String,
+    vault: Contract<Provider<Http>>,
+    pool_id: H256,
+    /// Filled by [`PriceSource::validate`] from `getPoolTokens`.
+    pool_tokens: OnceLock<Vec<Address>>,
+}
+
+impl BalancerV2Source {
+    /// Wraps the vault at `address`, swapping through `pool_id`.
+    pub fn new(
+        name: String,
+        address: Address,
+        abi: Abi,
+        pool_id: H256,
+        provider: Arc<Provider<Http>>,
+    ) -> Self {
+        Self {
+            name,
+            vault: Contract::new(address, abi, provider),
+            pool_id,
+            pool_tokens: OnceLock::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for BalancerV2Source {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn quote_at(
+        &self,
+        amount_in: U256,
+        path: &[Address],
+        block: Option<u64>,
+    ) -> Result<U256, PriceError> {
+        if let Some(pool_tokens) = self.pool_tokens.get()
+            && let Some(missing) = path.iter().find(|t| !pool_tokens.contains(t))
+        {
+            return Err(PriceError::NoLiquidityPath(format!(
+                "{:?} is not in pool {:?}",
+                missing, self.pool_id
+            )));
+        }
+        match *path {
+            [asset_in, asset_out] => {
+                let pool_id = self.pool_id;
+                fetch_balancer_price(&self.vault, pool_id, asset_in, asset_out, amount_in, block)
+                    .await
+            }
+            _ => query_batch_swap(&self.vault, self.pool_id, amount_in, path, block).await,
+        }
+    }
+
+    async fn validate(&self, tokens: &[(Address, u32)]) -> BotResult<()> {
+        let (pool_tokens, _, _) = self
+            .vault
+            .method::<_, (Vec<Address>, Vec<U256>, U256)>("getPoolTokens", self.pool_id)?
+            .call()
+            .await
+            .map_err(|err| {
+                BotError::Rpc(format!(
+                    "dex {}: getPoolTokens({:?}): {}",
+                    self.name, self.pool_id, err
+                ))
+            })?;
+        if let Some((missing, _)) = tokens.iter().find(|(t, _)| !pool_tokens.contains(t)) {
+            return Err(BotError::Config(format!(
+                "dex {}: pool {:?} does not contain token {:?}",
+                self.name, self.pool_id, missing
+            )));
+        }
+        let _ = self.pool_tokens.set(pool_tokens);
+        Ok(())
+    }
+}
+
+/// Output of swapping `amount_in` of `asset_in` for `asset_out` in the Balancer
+/// V2 pool `pool_id`, from the vault's `queryBatchSwap` at `block` (the latest
+/// when `None`): the negated delta of `asset_out`.
+///
+/// The pool id is 32 bytes: the pool's address, its specialization (2 bytes)
+/// and a registration nonce (10 bytes).
+pub async fn fetch_balancer_price(
+    vault: &Contract<Provider<Http>>,
+    pool_id: H256,
+    asset_in: Address,
+    asset_out: Address,
+    amount_in: U256,
+    block: Option<u64>,
+) -> Result<U256, PriceError> {
+    query_batch_swap(vault, pool_id, amount_in, &[asset_in, asset_out], block).await
+}
+
+/// `queryBatchSwap` along `path`, one `GIVEN_IN` step per hop through `pool_id`.
+async fn query_batch_swap(
+    vault: &Contract<Provider<Http>>,
+    pool_id: H256,
+    amount_in: U256,
+    path: &[Address],
+    block: Option<u64>,
+) -> Result<U256, PriceError> {
+    // An amount of zero chains the previous step's output.
+    let steps: Vec<(H256, U256, U256, U256, Bytes)> = (0..path.len().saturating_sub(1))
+        .map(|i| {
+            let amount = if i == 0 { amount_in } else { U256::zero() };
+            (
+                pool_id,
+                U256::from(i),
+                U256::from(i + 1),
+                amount,
+                Bytes::new(),
+            )
+        })
+        .collect();
+    let funds = (Address::zero(), false, Address::zero(), false);
+    let call = vault
+        .method::<_, Vec<I256>>(
+            "queryBatchSwap",
+            (BALANCER_GIVEN_IN, steps, path.to_vec(), funds),
+        )
+        .map_err(abi_encoding_error)?;
+    let deltas = at_block(call, block)
+        .call()
+        .await
+        .map_err(classify_call_error)?;
+    balancer_amount_out(&deltas, path.len().saturating_sub(1))
+}
+
+/// Output amount from `queryBatchSwap` asset deltas.
+///
+/// Deltas are from the vault's point of view: positive amounts are paid in,
+/// negative ones paid out. The output asset must therefore be negative; anything
+/// else means the pool returned nothing.
+pub fn balancer_amount_out(deltas: &[I256], out_index: usize) -> Result<U256, PriceError> {
+    let delta = deltas.get(out_index).ok_or_else(|| {
+        PriceError::Rpc(format!(
+            "queryBatchSwap returned {} deltas, expected index {}",
+            deltas.len(),
+            out_index
+        ))
+    })?;
+    if delta.is_negative() {
+        Ok(delta.unsigned_abs())
+    } else {
+        Err(PriceError::NoLiquidityPath(format!(
+            "vault pays out nothing (delta {})",
+            delta
+        )))
+    }
+}
+
+/// One coin of a Curve pool as checked by [`PriceSource::validate`].
+#[derive(Debug, Clone, Copy)]
+struct CurveCoin {
+    token: Address,
+    /// Decimals of the pool's amounts, from the coin's `decimals()`.
+    pool_decimals: u32,
+    /// Decimals the caller's amounts are in.
+    decimals: u32,
+}
+
+/// Curve-style pool quoted through `get_dy(i, j, dx)`.
+///
+/// The pool's coins `i` and `j` must be the pair's tokens. Amounts are
+/// rescaled between the decimals configured for the pair and the coins' own,
+/// so a pair set up with other decimals than its tokens report still quotes
+/// in its own units. Only two-token paths are quoted.
+pub struct CurveSource<P = Http> {
+    name: String,
+    pool: Contract<Provider<P>>,
+    i: u32,
+    j: u32,
+    /// Coins `i` and `j`, filled by [`PriceSource::validate`].
+    coins: OnceLock<[CurveCoin; 2]>,
+}
+
+impl<P: JsonRpcClient + 'static> CurveSource<P> {
+    /// Wraps the pool at `address`, swapping coin `i` (WETH) for coin `j` (USDC).
+    pub fn new(
+        name: String,
+        address: Address,
+        abi: Abi,
+        [i, j]: [u32; 2],
+        provider: Arc<Provider<P>>,
+    ) -> Self {
+        Self {
+            name,
+            pool: Contract::new(address, abi, provider),
+            i,
+            j,
+            coins: OnceLock::new(),
+        }
+    }
+
+    async fn coin(&self, index: u32) -> BotResult<Address> {
+        self.pool
+            .method::<_, Address>("coins", U256::from(index))?
+            .call()
+            .await
+            .map_err(|err| BotError::Rpc(format!("dex {}: coins({}): {}", self.name, index, err)))
+    }
+}
+
+#[async_trait]
+impl<P: JsonRpcClient + 'static> PriceSource for CurveSource<P> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn quote_at(
+        &self,
+        amount_in: U256,
+        path: &[Address],
+        block: Option<u64>,
+    ) -> Result<U256, PriceError> {
+        let [coin_i, coin_j] = self
+            .coins
+            .get()
+            .ok_or_else(|| PriceError::Rpc("curve pool coins not validated".into()))?;
+        // Either direction of the configured pair.
+        let (i, j, coin_in, coin_out) = if path == [coin_i.token, coin_j.token] {
+            (self.i, self.j, coin_i, coin_j)
+        } else if path == [coin_j.token, coin_i.token] {
+            (self.j, self.i, coin_j, coin_i)
+        } else {
+            return Err(PriceError::NoLiquidityPath(
+                "only the configured coin pair is quoted on curve".into(),
+            ));
+        };
+        let dx = arb::rescale_units(amount_in, coin_in.decimals, coin_in.pool_decimals);
+        let dy = fetch_curve_price(&self.pool, i.into(), j.into(), dx, block).await?;
+        Ok(arb::rescale_units(
+            dy,
+            coin_out.pool_decimals,
+            coin_out.decimals,
+        ))
+    }
+
+    async fn validate(&self, tokens: &[(Address, u32)]) -> BotResult<()> {
+        let &[(token_in, decimals_in), (token_out, decimals_out)] = tokens else {
+            return Err(BotError::Config(format!(
+                "dex {}: curve quotes exactly one token pair",
+                self.name
+            )));
+        };
+        let coin_i = self.coin(self.i).await?;
+        let coin_j = self.coin(self.j).await?;
+        if coin_i != token_in || coin_j != token_out {
+            return Err(BotError::Config(format!(
+                "dex {}: coins({})/coins({}) are {:?}/{:?}, expected {:?}/{:?}",
+                self.name, self.i, self.j, coin_i, coin_j, token_in, token_out
+            )));
+        }
+        let client = self.pool.client();
+        let coins = [
+            CurveCoin {
+                token: token_in,
+                pool_decimals: token_decimals(token_in, client.clone()).await?,
+                decimals: decimals_in,
+            },
+            CurveCoin {
+                token: token_out,
+                pool_decimals: token_decimals(token_out, client).await?,
+                decimals: decimals_out,
+            },
+        ];
+        let _ = self.coins.set(coins);
+        Ok(())
+    }
+}
+
+/// Off-chain [`PriceSource`] replaying scripted outputs, one per quote call and
+/// regardless of amount or path; the last output repeats once the script runs
+/// out. Lets the comparison and recording logic run without a node, through
+/// [`Scanner::with_sources`](crate::scanner::Scanner::with_sources).
+pub struct ScriptedSource {
+    name: String,
+    script: Mutex<Vec<U256>>,
+}
+
+impl ScriptedSource {
+    /// A source named `name` answering with `outputs` in order; an empty script
+    /// quotes zero.
+    pub fn new(name: String, outputs: Vec<U256>) -> Self {
+        let mut script = outputs;
+        script.reverse();
+        Self {
+            name,
+            script: Mutex::new(script),
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for ScriptedSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn quote_at(
+        &self,
+        _amount_in: U256,
+        _path: &[Address],
+        _block: Option<u64>,
+    ) -> Result<U256, PriceError> {
+        let mut script = self.script.lock().unwrap();
+        let next = script.last().copied().unwrap_or_default();
+        if script.len() > 1 {
+            script.pop();
+        }
+        Ok(next)
+    }
+}
+
+/// Builds one [`PriceSource`] per configured DEX entry, in config order.
+pub fn build_sources(
+    entries: &[DexEntry],
+    provider: Arc<Provider<Http>>,
+    router_abi: &Abi,
+    chain_id: u64,
+) -> BotResult<Vec<Box<dyn PriceSource>>> {
+    let mut sources: Vec<Box<dyn PriceSource>> = Vec::with_capacity(entries.len());
+    let mut quoter_abi = None;
+    let mut vault_abi = None;
+    let mut curve_abi = None;
+    let mut solidly_abi = None;
+    for entry in entries {
+        let address: Address = entry.address.parse().map_err(|err| {
+            BotError::Config(format!(
+                "dex {}: invalid address {}: {}",
+                entry.name, entry.address, err
+            ))
+        })?;
+        let own_abi = match &entry.abi_path {
+            Some(path) => Some(load_router_abi(path).map_err(|err| match err {
+                BotError::Abi(reason) => {
+                    BotError::Abi(format!("dex {}: abi_path: {}", entry.name, reason))
+                }
+                err => err,
+            })?),
+            None => None,
+        };
+        // Each default ABI is loaded once, on the first entry of its kind that needs it.
+        let shared = |cache: &mut Option<Abi>, path: &str| -> BotResult<Abi> {
+            if let Some(abi) = &own_abi {
+                return Ok(abi.clone());
+            }
+            match cache {
+                Some(abi) => Ok(abi.clone()),
+                None => Ok(cache.insert(load_router_abi(path)?).clone()),
+            }
+        };
+        match entry.kind {
+            DexKind::UniswapV2 => sources.push(Box::new(UniswapV2Source::new(
+                entry.name.clone(),
+                address,
+                own_abi.clone().unwrap_or_else(|| router_abi.clone()),
+                provider.clone(),
+            ))),
+            DexKind::UniswapV3 => {
+                if entry.fee_tiers.is_empty() {
+                    return Err(BotError::Config(format!(
+                        "dex {}: uniswap_v3 needs at least one fee tier",
+                        entry.name
+                    )));
+                }
+                if let Some(fee) = entry
+                    .fee_tiers
+                    .iter()
+                    .find(|fee| !UNISWAP_V3_FEE_TIERS.contains(fee))
+                {
+                    return Err(BotError::Config(format!(
+                        "dex {}: unsupported fee tier {} (expected one of {:?})",
+                        entry.name, fee, UNISWAP_V3_FEE_TIERS
+                    )));
+                }
+                let abi = shared(&mut quoter_abi, QUOTER_V2_ABI_PATH)?;
+                sources.push(Box::new(UniswapV3Source::new(
+                    entry.name.clone(),
+                    address,
+                    abi,
+                    entry.fee_tiers.clone(),
+                    provider.clone(),
+                )));
+            }
+            DexKind::BalancerV2 => {
+                let Some(pool_id) = &entry.pool_id else {
+                    return Err(BotError::Config(format!(
+                        "dex {}: balancer_v2 needs a pool_id",
+                        entry.name
+                    )));
+                };
+                let pool_id: H256 = pool_id.parse().map_err(|err| {
+                    BotError::Config(format!("dex {}: invalid pool_id: {}", entry.name, err))
+                })?;
+                let abi = shared(&mut vault_abi, BALANCER_VAULT_ABI_PATH)?;
+                sources.push(Box::new(BalancerV2Source::new(
+                    entry.name.clone(),
+                    address,
+                    abi,
+                    pool_id,
+                    provider.clone(),
+                )));
+            }
+            DexKind::Curve => {
+                let Some(indices) = entry.coin_indices else {
+                    return Err(BotError::Config(format!(
+                        "dex {}: curve needs coin_indices = [i, j]",
+                        entry.name
+                    )));
+                };
+                if indices[0] == indices[1] {
+                    return Err(BotError::Config(format!(
+                        "dex {}: coin_indices must differ",
+                        entry.name
+                    )));
+                }
+                let abi = shared(&mut curve_abi, CURVE_POOL_ABI_PATH)?;
+                sources.push(Box::new(CurveSource::new(
+                    entry.name.clone(),
+                    address,
+                    abi,
+                    indices,
+                    provider.clone(),
+                )));
+            }
+            DexKind::Solidly => {
+                let abi = shared(&mut solidly_abi, SOLIDLY_ROUTER_ABI_PATH)?;
+                sources.push(Box::new(SolidlySource::new(
+                    entry.name.clone(),
+                    address,
+                    abi,
+                    entry.stable,
+                    provider.clone(),
+                )));
+            }
+            DexKind::Aggregator => {
+                let Some(aggregator) = &entry.aggregator else {
+                    return Err(BotError::Config(format!(
+                        "dex {}: aggregator needs an [dex.aggregator] table",
+                        entry.name
+                    )));
+                };
+                sources.push(Box::new(AggregatorSource::new(
+                    entry.name.clone(),
+                    aggregator.clone(),
+                    chain_id,
+                )));
+            }
+        }
+    }
+    Ok(sources)
+}
+
+/// Router address per `uniswap_v2` entry name, for the swap-sending paths.
+pub fn v2_routers(entries: &[DexEntry]) -> BotResult<HashMap<String, Address>> {
+    let mut routers = HashMap::new();
+    for entry in entries.iter().filter(|e| e.kind == DexKind::UniswapV2) {
+        let address = entry.address.parse().map_err(|err| {
+            BotError::Config(format!("dex {}: invalid address: {}", entry.name, err))
+        })?;
+        routers.insert(entry.name.clone(), address);
+    }
+    Ok(routers)
+}
+
+/// Fails unless the node behind `provider` reports chain id `expected`, so an
+/// endpoint of another network is caught before quoting addresses absent there.
+pub async fn ensure_chain_id(provider: &Provider<Http>, expected: u64) -> BotResult<()> {
+    let actual = provider.get_chainid().await.map_err(|err| {
+        BotError::Rpc(format!(
+            "chain {}: could not fetch the chain id: {}",
+            expected, err
+        ))
+    })?;
+    if actual != expected.into() {
+        return Err(BotError::Config(format!(
+            "chain {}: the RPC endpoint reports chain id {}; point rpc_url at chain {} or fix the configured id",
+            expected, actual, expected
+        )));
+    }
+    Ok(())
+}
+
+/// Fails on the first entry whose `address` holds no bytecode, e.g. a typo that
+/// points at an EOA or the zero address.
+pub async fn ensure_contracts(entries: &[DexEntry], provider: &Provider<Http>) -> BotResult<()> {
+    for entry in entries {
+        let address: Address = entry.address.parse().map_err(|err| {
+            BotError::Config(format!(
+                "dex {}: invalid address {}: {}",
+                entry.name, entry.address, err
+            ))
+        })?;
+        let code = provider.get_code(address, None).await.map_err(|err| {
+            BotError::Rpc(format!(
+                "dex {}: could not fetch code at {:?}: {}",
+                entry.name, address, err
+            ))
+        })?;
+        if code.is_empty() {
+            return Err(BotError::Config(format!(
+                "dex {}: no contract deployed at {:?} (kind {:?}); check the address",
+                entry.name, address, entry.kind
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Decimals of the ERC-20 at `token`, from its `decimals()`.
+pub async fn token_decimals<M: Middleware + 'static>(
+    token: Address,
+    provider: Arc<M>,
+) -> BotResult<u32> {
+    let abi = parse_abi(&["function decimals() view returns (uint8)"])
+        .expect("static ERC-20 fragment parses");
+    let decimals = Contract::new(token, abi, provider)
+        .method::<_, u8>("decimals", ())
+        .map_err(|err| BotError::Abi(err.to_string()))?
+        .call()
+        .await
+        .map_err(|err| BotError::Rpc(format!("reading decimals() of {:?}: {}", token, err)))?;
+    Ok(decimals.into())
+}
+
+/// Price of 1 WMATIC in `usdc`, quoted directly on the V2 router `quickswap`
+/// with [`fetch_price`] and scaled by the token's `decimals()`.
+pub async fn fetch_matic_price_usdc(
+    provider: Arc<Provider<Http>>,
+    quickswap: &Contract<Provider<Http>>,
+    wmatic: Address,
+    usdc: Address,
+) -> BotResult<f64> {
+    let one_matic = U256::exp10(18);
+    let out = fetch_price(quickswap, one_matic, vec![wmatic, usdc], None)
+        .await
+        .map_err(|err| BotError::Rpc(format!("quoting 1 WMATIC: {}", err)))?;
+    let decimals = token_decimals(usdc, provider).await?;
+    Ok(arb::units_to_amount(out, decimals))
+}
+
+/// Quotes `trade_size` of `path[0]` into the last token of `path` via
+/// `getAmountsOut`, at `block` or the latest one.
+pub async fn fetch_price(
+    contract: &Contract<Provider<Http>>,
+    trade_size: U256,
+    path: Vec<Address>,
+    block: Option<u64>,
+) -> Result<U256, PriceError> {
+    let path_len = path.len();
+    let call = contract
+        .method::<_, Vec<U256>>("getAmountsOut", (trade_size, path))
+        .map_err(abi_encoding_error)?;
+    let amounts = at_block(call, block)
+        .call()
+        .await
+        .map_err(classify_call_error)?;
+    amounts_out(&amounts, path_len)
+}
+
+/// The final amount of a `getAmountsOut` result, which holds one amount per
+/// token of a `path_len`-token path.
+///
+/// A result of any other length is malformed, and is logged and failed rather
+/// than read at the wrong index.
+pub fn amounts_out(amounts: &[U256], path_len: usize) -> Result<U256, PriceError> {
+    if amounts.is_empty() {
+        return Err(PriceError::EmptyResponse);
+    }
+    if amounts.len() != path_len {
+        warn!(
+            returned = amounts.len(),
+            expected = path_len,
+            "getAmountsOut returned a malformed amounts array"
+        );
+        return Err(PriceError::Rpc(format!(
+            "getAmountsOut returned {} amounts for a {}-token path",
+            amounts.len(),
+            path_len
+        )));
+    }
+    Ok(amounts[path_len - 1])
+}
+
+/// Sends `call` against the state of `block` instead of the latest one.
+fn at_block<M: Middleware, D: Detokenize>(
+    call: ContractCall<M, D>,
+    block: Option<u64>,
+) -> ContractCall<M, D> {
+    match block {
+        Some(block) => call.block(block),
+        None => call,
+    }
+}
+
+/// A call that could not be built from the venue's ABI.
+fn abi_encoding_error(err: ethers::abi::AbiError) -> PriceError {
+    PriceError::AbiEncoding(err.to_string())
+}
+
+/// Output of swapping `dx` of coin `i` for coin `j` on a Curve StableSwap pool,
+/// from its `get_dy(int128,int128,uint256)` at `block` (the latest when `None`);
+/// both amounts are in the coins' own decimals.
+pub async fn fetch_curve_price<M: Middleware + 'static>(
+    pool: &Contract<M>,
+    i: i128,
+    j: i128,
+    dx: U256,
+    block: Option<u64>,
+) -> Result<U256, PriceError> {
+    let call = pool
+        .method::<_, U256>("get_dy", (I256::from(i), I256::from(j), dx))
+        .map_err(abi_encoding_error)?;
+    at_block(call, block)
+        .call()
+        .await
+        .map_err(classify_call_error)
+}
+
+/// Reverts mean the venue cannot fill the path; anything else is an RPC failure.
+pub(crate) fn classify_call_error<M: Middleware>(err: ContractError<M>) -> PriceError {
+    if err.is_revert() {
+        let reason = err
+            .decode_revert::<String>()
+            .unwrap_or_else(|| "no reason".into());
+        PriceError::Revert(reason)
+    } else if let ContractError::AbiError(err) = err {
+        abi_encoding_error(err)
+    } else {
+        PriceError::Rpc(format!("{:?}", err))
+    }
+}
+
+/// Quotes every candidate path and keeps the one paying the most.
+///
+/// Every path is quoted at `block`, or the latest block when `None`. Returns
+/// the amount out together with the index of the winning path. If no path
+/// quotes successfully, the first path's error is returned.
+pub async fn fetch_best_price(
+    source: &dyn PriceSource,
+    trade_size: U256,
+    paths: &[Vec<Address>],
+    block: Option<u64>,
+) -> Result<(U256, usize), PriceError> {
+    let mut best: Option<(U256, usize)> = None;
+    let mut first_err = None;
+    for (i, path) in paths.iter().enumerate() {
+        match source.quote_at(trade_size, path, block).await {
+            Ok(amount) if best.is_none_or(|(b, _)| amount > b) => best = Some((amount, i)),
+            Ok(_) => {}
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    match (best, first_err) {
+        (Some(best), _) => Ok(best),
+        (None, Some(err)) => Err(err),
+        (None, None) => Ok((U256::zero(), 0)),
+    }
+}
+
+/// Bounds of the per-opportunity trade size search (`[settings.trade_size_search]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct TradeSizeSearchConfig {
+    /// Smallest size tried, in wei of the first path token.
+    pub min_size: u64,
+    /// Largest size tried, in wei of the first path token.
+    pub max_size: u64,
+    /// Search steps; each one costs two quotes.
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: u32,
+}
+
+fn default_max_iterations() -> u32 {
+    20
+}
+
+/// Searches `[min_size, max_size]` for the size maximizing `amount_out(size) - size - gas_cost`.
+///
+/// Net profit is assumed to rise and then fall with size, as slippage grows, so
+/// each step compares the midpoint with a point just above it and keeps the half
+/// that is still climbing. Sizes a venue cannot fill count as unprofitable.
+/// Returns the best size seen and its net profit, zero when none was profitable.
+pub async fn search_trade_size<F, Fut>(
+    min_size: U256,
+    max_size: U256,
+    gas_cost: U256,
+    max_iterations: u32,
+    amount_out: F,
+) -> Result<(U256, U256), PriceError>
+where
+    F: Fn(U256) -> Fut,
+    Fut: Future<Output = Result<U256, PriceError>>,
+{
+    let net = |size: U256, out: Result<U256, PriceError>| match out {
+        Ok(out) => Ok(I256::from_raw(out) - I256::from_raw(size) - I256::from_raw(gas_cost)),
+        Err(err) if err.is_unfillable() => Ok(I256::MIN),
+        Err(err) => Err(err),
+    };
+    let (mut lo, mut hi) = (min_size.min(max_size), max_size.max(min_size));
+    let mut best = (lo, net(lo, amount_out(lo).await)?);
+    for _ in 0..max_iterations {
+        if hi <= lo {
+            break;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let next = (mid + ((hi - lo) / 100).max(U256::one())).min(hi);
+        let at_mid = net(mid, amount_out(mid).await)?;
+        let at_next = net(next, amount_out(next).await)?;
+        for candidate in [(mid, at_mid), (next, at_next)] {
+            if candidate.1 > best.1 {
+                best = candidate;
+            }
+        }
+        if at_next > at_mid {
+            lo = next;
+        } else {
+            hi = mid;
+        }
+    }
+    let profit = if best.1 > I256::zero() {
+        best.1.into_raw()
+    } else {
+        U256::zero()
+    };
+    Ok((best.0, profit))
+}
+
+/// Optimal size for a cyclic `path` (first token = last token) on one V2 router.
+pub async fn optimize_trade_size(
+    contract: &Contract<Provider<Http>>,
+    path: Vec<Address>,
+    min_size: U256,
+    max_size: U256,
+    gas_cost: U256,
+    max_iterations: u32,
+) -> Result<(U256, U256), PriceError> {
+    search_trade_size(min_size, max_size, gas_cost, max_iterations, |size| {
+        fetch_price(contract, size, path.clone(), None)
+    })
+    .await
+}
+
+/// Optimal size for selling on `sell` along `sell_path` and buying back on `buy`
+/// along `buy_path`, which must end in the token `sell_path` starts with.
+pub async fn optimize_round_trip(
+    sell: &dyn PriceSource,
+    sell_path: &[Address],
+    buy: &dyn PriceSource,
+    buy_path: &[Address],
+    search: &TradeSizeSearchConfig,
+    gas_cost: U256,
+) -> Result<(U256, U256), PriceError> {
+    search_trade_size(
+        U256::from(search.min_size),
+        U256::from(search.max_size),
+        gas_cost,
+        search.max_iterations,
+        |size| async move {
+            let proceeds = sell.quote(size, sell_path).await?;
+            buy.quote(proceeds, buy_path).await
+        },
+    )
+    .await
+}
+
+/// Quotes an executable cycle: buys the base token with `quote_in` on `buy`, along
+/// `buy_path` reversed, then sells what was bought on `sell` along `sell_path`.
+///
+/// Both paths run base → quote, as quoted by [`fetch_best_price`]. Returns the
+/// base amount bought and the quote amount received back.
+pub async fn quote_round_trip(
+    buy: &dyn PriceSource,
+    buy_path: &[Address],
+    sell: &dyn PriceSource,
+    sell_path: &[Address],
+    quote_in: U256,
+) -> Result<(U256, U256), PriceError> {
+    let reversed: Vec<Address> = buy_path.iter().rev().copied().collect();
+    let bought = buy.quote(quote_in, &reversed).await?;
+    let quote_out = sell.quote(bought, sell_path).await?;
+    Ok((bought, quote_out))
+}
+
+/// Swap fee of `source` along `path`, in basis points per leg, from a round
+/// trip of `probe` quote units through it; see [`arb::implied_fee_bps`].
+///
+/// `probe` should be small next to the venue's liquidity, e.g. one quote token.
+pub async fn probe_fee_bps(
+    source: &dyn PriceSource,
+    path: &[Address],
+    probe: U256,
+) -> Result<u32, PriceError> {
+    let (_, back) = quote_round_trip(source, path, source, path, probe).await?;
+    Ok(arb::implied_fee_bps(probe, back))
+}
+
+/// Short description of a quote path for logs and stored records.
+///
+/// Two-token paths are `"direct"`; longer ones list the intermediate hops,
+/// e.g. `"via 0x0d50…"`.
+pub fn path_label(path: &[Address]) -> String {
+    if path.len() <= 2 {
+        return "direct".to_string();
+    }
+    let hops: Vec<String> = path[1..path.len() - 1]
+        .iter()
+        .map(|a| format!("{:?}", a))
+        .collect();
+    format!("via {}", hops.join(" > "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::Token;
+    use ethers::core::types::transaction::eip2718::TypedTransaction;
+    use ethers::core::types::{BlockId, BlockNumber};
+    use ethers::providers::MockProvider;
+
+    /// `queryBatchSwap` return data of a 1 WETH → USDC swap: the vault takes
+    /// 1e18 wei and pays out 2500 USDC.
+    const RECORDED_DELTAS: &str = "\
+        0000000000000000000000000000000000000000000000000000000000000020\
+        0000000000000000000000000000000000000000000000000000000000000002\
+        0000000000000000000000000000000000000000000000000de0b6b3a7640000\
+        ffffffffffffffffffffffffffffffffffffffffffffffffffffffff6afd0700";
+
+    fn decode_deltas(hex: &str) -> Vec<I256> {
+        let abi = load_router_abi(BALANCER_VAULT_ABI_PATH).unwrap();
+        let output = abi
+            .function("queryBatchSwap")
+            .unwrap()
+            .decode_output(&ethers::utils::hex::decode(hex).unwrap())
+            .unwrap();
+        Vec::<I256>::from_tokens(output).unwrap()
+    }
+
+    #[test]
+    fn balancer_amount_out_reads_recorded_deltas() {
+        let deltas = decode_deltas(RECORDED_DELTAS);
+        assert_eq!(deltas, [I256::exp10(18), I256::from(-2_500_000_000i64)]);
+        assert_eq!(
+            balancer_amount_out(&deltas, 1).unwrap(),
+            U256::from(2_500_000_000u64)
+        );
+    }
+
+    #[test]
+    fn balancer_amount_out_rejects_non_negative_output() {
+        let deltas = decode_deltas(RECORDED_DELTAS);
+        // The input side is paid in, so reading it as the output fails.
+        assert!(matches!(
+            balancer_amount_out(&deltas, 0),
+            Err(PriceError::NoLiquidityPath(_))
+        ));
+        assert!(matches!(
+            balancer_amount_out(&[I256::exp10(18), I256::zero()], 1),
+            Err(PriceError::NoLiquidityPath(_))
+        ));
+        assert!(matches!(
+            balancer_amount_out(&deltas, 2),
+            Err(PriceError::Rpc(_))
+        ));
+    }
+
+    #[test]
+    fn embedded_router_abi_quotes_amounts_out() {
+        let abi = default_router_abi();
+        let function = abi.function("getAmountsOut").unwrap();
+        assert_eq!(
+            function.signature(),
+            "getAmountsOut(uint256,address[]):(uint256[])"
+        );
+    }
+
+    #[test]
+    fn broken_abi_override_names_its_dex() {
+        let path = std::env::temp_dir().join(format!("broken_abi_{}.json", std::process::id()));
+        fs::write(&path, "{\"not\": \"an abi\"}").unwrap();
+        let entry: DexEntry = toml::from_str(&format!(
+            "name = \"MyFork\"\nkind = \"uniswap_v2\"\n\
+             address = \"0xa5E0829CaCEd8fFDD4De3c43696c57f7d7a678ff\"\nabi_path = {:?}",
+            path.display().to_string()
+        ))
+        .unwrap();
+        // Never called: the ABI fails before any request.
+        let provider = Arc::new(Provider::<Http>::try_from("http://127.0.0.1:1").unwrap());
+        let result = build_sources(&[entry], provider, &default_router_abi(), 137);
+        fs::remove_file(&path).unwrap();
+
+        let Err(BotError::Abi(message)) = result else {
+            panic!("a broken override must fail as an ABI error");
+        };
+        assert!(
+            message.starts_with(&format!(
+                "dex MyFork: abi_path: {} is neither",
+                path.display()
+            )),
+            "{}",
+            message
+        );
+    }
+
+    /// `amounts` as a node returns them from `getAmountsOut`, decoded through
+    /// the built-in router ABI.
+    fn returned_amounts(amounts: &[u64]) -> Vec<U256> {
+        let data = ethers::abi::encode(&[Token::Array(
+            amounts.iter().map(|&n| Token::Uint(n.into())).collect(),
+        )]);
+        let output = default_router_abi()
+            .function("getAmountsOut")
+            .unwrap()
+            .decode_output(&data)
+            .unwrap();
+        Vec::<U256>::from_tokens(output).unwrap()
+    }
+
+    #[test]
+    fn amounts_out_takes_the_last_amount() {
+        let direct = returned_amounts(&[1_000_000, 2_500_000_000]);
+        assert_eq!(
+            amounts_out(&direct, 2).unwrap(),
+            U256::from(2_500_000_000u64)
+        );
+        let via = returned_amounts(&[1_000_000, 400_000, 2_490_000_000]);
+        assert_eq!(amounts_out(&via, 3).unwrap(), U256::from(2_490_000_000u64));
+    }
+
+    #[test]
+    fn mismatched_amounts_fail_the_quote() {
+        // A 2-token path answered with an intermediate hop's amounts.
+        let amounts = returned_amounts(&[1_000_000, 400_000, 2_490_000_000]);
+        assert!(matches!(amounts_out(&amounts, 2), Err(PriceError::Rpc(_))));
+        assert!(matches!(
+            amounts_out(&returned_amounts(&[1_000_000]), 2),
+            Err(PriceError::Rpc(_))
+        ));
+        assert!(matches!(
+            amounts_out(&returned_amounts(&[]), 2),
+            Err(PriceError::EmptyResponse)
+        ));
+    }
+
+    const WETH: Address = Address::repeat_byte(0x11);
+    const USDC: Address = Address::repeat_byte(0x22);
+
+    /// A Curve source on a mock node, whose `eth_call` results are `replies`
+    /// in order.
+    fn curve_on(replies: &[Token]) -> (CurveSource<MockProvider>, MockProvider) {
+        let mock = MockProvider::new();
+        // The mock answers the last reply pushed first.
+        for reply in replies.iter().rev() {
+            mock.push::<Bytes, _>(Bytes::from(ethers::abi::encode(std::slice::from_ref(
+                reply,
+            ))))
+            .unwrap();
+        }
+        let source = CurveSource::new(
+            "Curve".into(),
+            Address::repeat_byte(0xcc),
+            load_router_abi(CURVE_POOL_ABI_PATH).unwrap(),
+            [0, 1],
+            Arc::new(Provider::new(mock.clone())),
+        );
+        (source, mock)
+    }
+
+    /// `coins(0)`, `coins(1)` and both coins' `decimals()` as `validate` reads them.
+    fn coins(coin_0: Address, decimals: [u32; 2]) -> [Token; 4] {
+        [
+            Token::Address(coin_0),
+            Token::Address(USDC),
+            Token::Uint(decimals[0].into()),
+            Token::Uint(decimals[1].into()),
+        ]
+    }
+
+    /// Checks that the next request was `eth_call` of `tx` at the latest block.
+    fn expect_call(mock: &MockProvider, tx: &TypedTransaction) {
+        let latest = BlockId::from(BlockNumber::Latest);
+        mock.assert_request(
+            "eth_call",
+            [
+                ethers::utils::serialize(tx),
+                ethers::utils::serialize(&latest),
+            ],
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn curve_rescales_between_pool_and_pair_decimals() {
+        // A pair configured in 18 decimals on both sides, whose USDC coin has 6.
+        let usdc_out = U256::from(2_500_123_456u64);
+        let mut replies = coins(WETH, [18, 6]).to_vec();
+        replies.push(Token::Uint(usdc_out));
+        let (source, _) = curve_on(&replies);
+        source.validate(&[(WETH, 18), (USDC, 18)]).await.unwrap();
+        assert_eq!(
+            source
+                .quote_at(U256::exp10(18), &[WETH, USDC], None)
+                .await
+                .unwrap(),
+            usdc_out * U256::exp10(12)
+        );
+
+        // Back the other way, the input is sent to the pool in 6 decimals.
+        let mut replies = coins(WETH, [18, 6]).to_vec();
+        replies.push(Token::Uint(U256::exp10(18)));
+        let (source, mock) = curve_on(&replies);
+        source.validate(&[(WETH, 18), (USDC, 18)]).await.unwrap();
+        let usdc_in = U256::from(2_500_123_456_789_000_000_000u128);
+        assert_eq!(
+            source.quote_at(usdc_in, &[USDC, WETH], None).await.unwrap(),
+            U256::exp10(18)
+        );
+        let erc20 = parse_abi(&["function decimals() view returns (uint8)"]).unwrap();
+        let client = source.pool.client();
+        let pool_call = |index: u32| {
+            source
+                .pool
+                .method::<_, Address>("coins", U256::from(index))
+                .unwrap()
+                .tx
+        };
+        let decimals_call = |token| {
+            Contract::new(token, erc20.clone(), client.clone())
+                .method::<_, u8>("decimals", ())
+                .unwrap()
+                .tx
+        };
+        expect_call(&mock, &pool_call(0));
+        expect_call(&mock, &pool_call(1));
+        expect_call(&mock, &decimals_call(WETH));
+        expect_call(&mock, &decimals_call(USDC));
+        // The digits below a 6-decimal unit are truncated.
+        let get_dy = source
+            .pool
+            .method::<_, U256>(
+                "get_dy",
+                (I256::from(1), I256::from(0), U256::from(2_500_123_456u64)),
+            )
+            .unwrap()
+            .tx;
+        expect_call(&mock, &get_dy);
+    }
+
+    #[tokio::test]
+    async fn curve_with_other_coins_fails_startup() {
+        let stranger = Address::repeat_byte(0x99);
+        let (source, _) = curve_on(&coins(stranger, [18, 6]));
+        let Err(BotError::Config(message)) = source.validate(&[(WETH, 18), (USDC, 6)]).await else {
+            panic!("coins(0) is not the configured WETH");
+        };
+        assert_eq!(
+            message,
+            format!(
+                "dex Curve: coins(0)/coins(1) are {:?}/{:?}, expected {:?}/{:?}",
+                stranger, USDC, WETH, USDC
+            )
+        );
+        // Unvalidated, the source quotes nothing.
+        assert!(matches!(
+            source.quote_at(U256::exp10(18), &[WETH, USDC], None).await,
+            Err(PriceError::Rpc(_))
+        ));
+    }
+
+    #[test]
+    fn solidly_routes_encode_as_a_tuple_array() {
+        let abi = load_router_abi(SOLIDLY_ROUTER_ABI_PATH).unwrap();
+        // Never called: encoding needs no node.
+        let provider = Arc::new(Provider::<Http>::try_from("http://127.0.0.1:1").unwrap());
+        let source = SolidlySource::new("Pearl".into(), Address::zero(), abi, None, provider);
+        let (weth, usdc, dai) = (
+            Address::repeat_byte(0x11),
+            Address::repeat_byte(0x22),
+            Address::repeat_byte(0x33),
+        );
+        let call = source
+            .get_amounts_out(U256::exp10(18), &[weth, usdc, dai], true)
+            .unwrap();
+        let calldata = call.calldata().unwrap();
+
+        let selector = ethers::utils::id("getAmountsOut(uint256,(address,address,bool)[])");
+        assert_eq!(calldata[..4], selector);
+        let word = |i: usize| ethers::utils::hex::encode(&calldata[4 + 32 * i..4 + 32 * (i + 1)]);
+        let address = |a: Address| format!("{:0>64}", ethers::utils::hex::encode(a));
+        let uint = |n: u64| format!("{:064x}", n);
+        let expected = [
+            uint(1_000_000_000_000_000_000),
+            // The array follows both head words.
+            uint(0x40),
+            uint(2),
+            address(weth),
+            address(usdc),
+            uint(1),
+            address(usdc),
+            address(dai),
+            uint(1),
+        ];
+        assert_eq!(calldata.len(), 4 + 32 * expected.len());
+        for (i, expected) in expected.iter().enumerate() {
+            assert_eq!(&word(i), expected, "word {}", i);
+        }
+    }
+}
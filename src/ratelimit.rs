@@ -0,0 +1,64 @@
+//! Token-bucket limit on the requests sent to one RPC endpoint, shared by
+//! every scanner on it.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Allows `per_sec` requests a second on average, bursting up to one second's
+/// worth after a quiet spell.
+///
+/// Callers that find the bucket empty wait for it to refill rather than fail.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_sec: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    /// A full bucket of `per_sec` tokens, refilling at `per_sec` a second.
+    pub fn new(per_sec: u32) -> Self {
+        let per_sec = f64::from(per_sec.max(1));
+        Self {
+            per_sec,
+            bucket: Mutex::new(Bucket {
+                tokens: per_sec,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until `requests` tokens are available and takes them; a batch
+    /// larger than the bucket waits for a full one.
+    pub async fn acquire(&self, requests: u32) {
+        let wanted = f64::from(requests).min(self.per_sec);
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.per_sec).min(self.per_sec);
+                bucket.refilled = now;
+                if bucket.tokens >= wanted {
+                    bucket.tokens -= wanted;
+                    return;
+                }
+                Duration::from_secs_f64((wanted - bucket.tokens) / self.per_sec)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// [`Self::acquire`], giving up after `timeout`; returns whether the
+    /// tokens were taken.
+    pub async fn acquire_within(&self, requests: u32, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, self.acquire(requests))
+            .await
+            .is_ok()
+    }
+}
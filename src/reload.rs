@@ -0,0 +1,327 @@
+//! Config hot-reload on `SIGHUP` or a change of the file.
+
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Interval;
+use tracing::{info, warn};
+
+use crate::config::{BotSettings, Config, load_config};
+
+/// Config shared between the reload handler and the scanner.
+pub type SharedConfig = Arc<RwLock<Config>>;
+
+#[cfg(unix)]
+type Hangup = tokio::signal::unix::Signal;
+#[cfg(not(unix))]
+type Hangup = ();
+
+/// Re-reads `path` on every `SIGHUP`, and every `watch` interval in which its
+/// modification time changed, and applies the hot-reloadable settings to
+/// `shared`. A config that fails to load or validate is logged and ignored.
+///
+/// `pairs` is notified whenever the reload added, removed or changed a pair, so
+/// the scanners can be restarted to match. Abort the returned handle on
+/// shutdown, like the signal handler's. Without `SIGHUP` (non-Unix targets)
+/// and without `watch`, the task exits immediately.
+pub fn spawn_reload_handler(
+    path: String,
+    shared: SharedConfig,
+    watch: Option<Duration>,
+    pairs: Arc<Notify>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut hangup = install_hangup();
+        if hangup.is_none() && watch.is_none() {
+            return;
+        }
+        let mut ticker = watch.map(tokio::time::interval);
+        let mut modified = modified_at(&path);
+        loop {
+            let trigger = tokio::select! {
+                _ = next_hangup(&mut hangup) => "SIGHUP received",
+                _ = next_tick(&mut ticker) => {
+                    let now = modified_at(&path);
+                    if now == modified {
+                        continue;
+                    }
+                    modified = now;
+                    "Config file changed"
+                }
+            };
+            info!(path = %path, "{}; reloading config", trigger);
+            match load_config(&path) {
+                Ok(new) => {
+                    if apply_reload(&mut shared.write().unwrap(), &new) {
+                        pairs.notify_one();
+                    }
+                }
+                Err(err) => warn!(%err, "Reloaded config is invalid; keeping the current one"),
+            }
+        }
+    })
+}
+
+fn install_hangup() -> Option<Hangup> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::hangup()) {
+            Ok(hangup) => Some(hangup),
+            Err(err) => {
+                warn!(%err, "Failed to install SIGHUP handler; hot-reload unavailable");
+                None
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    None
+}
+
+/// Resolves on the next `SIGHUP`; never without a handler.
+async fn next_hangup(hangup: &mut Option<Hangup>) {
+    #[cfg(unix)]
+    if let Some(hangup) = hangup
+        && hangup.recv().await.is_some()
+    {
+        return;
+    }
+    #[cfg(not(unix))]
+    let _ = hangup;
+    std::future::pending::<()>().await
+}
+
+/// Resolves on the next watch interval; never without one.
+async fn next_tick(ticker: &mut Option<Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending::<()>().await,
+    }
+}
+
+fn modified_at(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// Copies `field` from `new` into `current` when it differs, logging the old
+/// and new values and noting it in `applied`.
+fn reload_field<T: PartialEq + Clone + fmt::Debug>(
+    field: &'static str,
+    current: &mut T,
+    new: &T,
+    applied: &mut Vec<&'static str>,
+) {
+    if current != new {
+        info!(field, old = ?current, new = ?new, "Config field reloaded");
+        *current = new.clone();
+        applied.push(field);
+    }
+}
+
+/// Copies the hot-reloadable settings from `new` into `current`, logging each
+/// change, and returns whether the pairs of any chain changed.
+///
+/// Hot fields are `min_profit_usdc`, `trade_size`, `refresh_rate`,
+/// `est_gas_cost_usdc`, `telegram_min_interval_secs`, `[webhook]`,
+/// `[telegram]`, `[notify_throttle]` and the pairs of an otherwise unchanged
+/// chain. Any other difference needs a restart and is only warned about.
+pub fn apply_reload(current: &mut Config, new: &Config) -> bool {
+    let mut applied = Vec::new();
+    let settings = &mut current.settings;
+    reload_field(
+        "min_profit_usdc",
+        &mut settings.min_profit_usdc,
+        &new.settings.min_profit_usdc,
+        &mut applied,
+    );
+    reload_field(
+        "trade_size",
+        &mut settings.trade_size,
+        &new.settings.trade_size,
+        &mut applied,
+    );
+    reload_field(
+        "refresh_rate",
+        &mut settings.refresh_rate,
+        &new.settings.refresh_rate,
+        &mut applied,
+    );
+    reload_field(
+        "est_gas_cost_usdc",
+        &mut settings.est_gas_cost_usdc,
+        &new.settings.est_gas_cost_usdc,
+        &mut applied,
+    );
+    reload_field(
+        "telegram_min_interval_secs",
+        &mut settings.telegram_min_interval_secs,
+        &new.settings.telegram_min_interval_secs,
+        &mut applied,
+    );
+    reload_field("webhook", &mut current.webhook, &new.webhook, &mut applied);
+    reload_field(
+        "telegram",
+        &mut current.telegram,
+        &new.telegram,
+        &mut applied,
+    );
+    reload_field(
+        "notify_throttle",
+        &mut current.notify_throttle,
+        &new.notify_throttle,
+        &mut applied,
+    );
+    let pairs_changed = reload_pairs(current, new);
+    if pairs_changed {
+        applied.push("pairs");
+    }
+
+    // With the hot fields in sync, anything still differing is restart-only.
+    let mut rest = new.clone();
+    rest.settings = BotSettings {
+        min_profit_usdc: current.settings.min_profit_usdc,
+        trade_size: current.settings.trade_size.clone(),
+        refresh_rate: current.settings.refresh_rate,
+        est_gas_cost_usdc: current.settings.est_gas_cost_usdc,
+        telegram_min_interval_secs: current.settings.telegram_min_interval_secs,
+        // `--dry-run` sets these over the file, so the file differing is expected.
+        dry_run: current.settings.dry_run,
+        dry_run_table: current.settings.dry_run_table,
+        ..rest.settings
+    };
+    for (chain, rest_chain) in current.chains.iter().zip(&mut rest.chains) {
+        if chain.chain_id != rest_chain.chain_id {
+            continue;
+        }
+        if chain.rpc_url != rest_chain.rpc_url {
+            warn!(
+                chain_id = chain.chain_id,
+                "RPC URL change needs a restart; ignored"
+            );
+        }
+        rest_chain.tokens = chain.tokens.clone();
+    }
+    let sections = [
+        // These redact secrets in their `Debug` output, so compare them directly.
+        ("chains", current.chains != rest.chains),
+        ("jobs", differs(&current.jobs, &rest.jobs)),
+        ("scan_matrix", current.scan_matrix != rest.scan_matrix),
+        ("settings", differs(&current.settings, &rest.settings)),
+        (
+            "metrics_port",
+            differs(&current.metrics_port, &rest.metrics_port),
+        ),
+        (
+            "rpc_latency_log_secs",
+            differs(&current.rpc_latency_log_secs, &rest.rpc_latency_log_secs),
+        ),
+        (
+            "max_requests_per_sec",
+            differs(&current.max_requests_per_sec, &rest.max_requests_per_sec),
+        ),
+        ("api_listen", differs(&current.api_listen, &rest.api_listen)),
+        (
+            "health_max_age_secs",
+            differs(&current.health_max_age_secs, &rest.health_max_age_secs),
+        ),
+        (
+            "config_watch_secs",
+            differs(&current.config_watch_secs, &rest.config_watch_secs),
+        ),
+        ("simulation", differs(&current.simulation, &rest.simulation)),
+        ("retention", differs(&current.retention, &rest.retention)),
+        ("state_file", differs(&current.state_file, &rest.state_file)),
+        (
+            "state_save_interval_secs",
+            differs(
+                &current.state_save_interval_secs,
+                &rest.state_save_interval_secs,
+            ),
+        ),
+        ("gas", differs(&current.gas, &rest.gas)),
+        ("abi_path", differs(&current.abi_path, &rest.abi_path)),
+        // `DatabaseUrl` redacts the password in its `Debug` output, so compare it directly.
+        ("database_url", current.database_url != rest.database_url),
+        (
+            "instance_id",
+            differs(&current.instance_id, &rest.instance_id),
+        ),
+        // `ExecutionSettings` redacts the key in its `Debug` output, so compare it directly.
+        (
+            "execution",
+            current.execution.private_key != rest.execution.private_key
+                || differs(&current.execution, &rest.execution),
+        ),
+    ];
+    let mut ignored = Vec::new();
+    for (field, changed) in sections {
+        if changed {
+            warn!(field, "Config change needs a restart; ignored");
+            ignored.push(field);
+        }
+    }
+    if applied.is_empty() && ignored.is_empty() {
+        info!("Config reloaded; nothing changed");
+    } else {
+        info!(
+            applied = %applied.join(", "),
+            restart_needed = %ignored.join(", "),
+            "Config reloaded"
+        );
+    }
+    pairs_changed
+}
+
+/// Takes the pairs of each chain whose other fields are unchanged, logging
+/// every pair added, removed or changed; returns whether any was.
+///
+/// Chains are matched by position and id, so adding, removing or reordering
+/// `[[chains]]` entries needs a restart.
+fn reload_pairs(current: &mut Config, new: &Config) -> bool {
+    let mut changed = false;
+    for (chain, new_chain) in current.chains.iter_mut().zip(&new.chains) {
+        if chain.chain_id != new_chain.chain_id
+            || chain.rpc_url != new_chain.rpc_url
+            || chain.dex != new_chain.dex
+            || chain.tokens == new_chain.tokens
+        {
+            continue;
+        }
+        for pair in &chain.tokens {
+            match new_chain.tokens.iter().find(|p| p.label() == pair.label()) {
+                None => info!(
+                    chain_id = chain.chain_id,
+                    pair = pair.label(),
+                    "Pair removed"
+                ),
+                Some(new_pair) if new_pair != pair => {
+                    info!(
+                        chain_id = chain.chain_id,
+                        pair = pair.label(),
+                        "Pair changed"
+                    )
+                }
+                Some(_) => {}
+            }
+        }
+        for pair in &new_chain.tokens {
+            if !chain.tokens.iter().any(|p| p.label() == pair.label()) {
+                info!(chain_id = chain.chain_id, pair = pair.label(), "Pair added");
+            }
+        }
+        chain.tokens = new_chain.tokens.clone();
+        changed = true;
+    }
+    changed
+}
+
+/// Compares through `Debug`, which every config type derives.
+fn differs<T: std::fmt::Debug>(a: &T, b: &T) -> bool {
+    format!("{:?}", a) != format!("{:?}", b)
+}
@@ -0,0 +1,84 @@
+//! Periodic pruning of the opportunity and price tables.
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use rusqlite::Connection;
+use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+use crate::db::DbPool;
+
+/// Tables pruned, each keyed on its `timestamp_ms`.
+const PRUNED_TABLES: [&str; 2] = ["arbitrage_bot", "price_history"];
+
+/// What the pruning job keeps (`[retention]`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RetentionConfig {
+    /// Rows older than this many days are deleted.
+    pub retention_days: Option<u64>,
+    /// Each table keeps at most this many of its most recent rows.
+    pub max_rows: Option<u64>,
+    /// Seconds between runs; the first runs at startup.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+fn default_interval_secs() -> u64 {
+    3600
+}
+
+/// Deletes the rows of every pruned table that fall outside `cfg`, returning
+/// each table with the number of rows deleted.
+pub fn prune(conn: &Connection, cfg: &RetentionConfig) -> Result<Vec<(&'static str, usize)>> {
+    // A retention too long to represent keeps everything.
+    let cutoff_ms = cfg
+        .retention_days
+        .and_then(|days| Duration::try_days(i64::try_from(days).ok()?))
+        .map(|age| (Utc::now() - age).timestamp_millis());
+    let mut pruned = Vec::with_capacity(PRUNED_TABLES.len());
+    for table in PRUNED_TABLES {
+        let mut deleted = 0;
+        if let Some(cutoff_ms) = cutoff_ms {
+            deleted += conn.execute(
+                &format!("DELETE FROM {} WHERE timestamp_ms < ?1", table),
+                [cutoff_ms],
+            )?;
+        }
+        if let Some(max_rows) = cfg.max_rows {
+            deleted += conn.execute(
+                &format!(
+                    "DELETE FROM {table} WHERE id NOT IN
+                     (SELECT id FROM {table} ORDER BY timestamp_ms DESC, id DESC LIMIT ?1)"
+                ),
+                [max_rows],
+            )?;
+        }
+        pruned.push((table, deleted));
+    }
+    Ok(pruned)
+}
+
+/// Runs [`prune`] every `interval_secs` until `shutdown` is cancelled; a failed
+/// run is logged and retried at the next interval.
+pub async fn run(pool: DbPool, cfg: RetentionConfig, shutdown: CancellationToken) {
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(cfg.interval_secs));
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = ticker.tick() => {}
+        }
+        let result = pool
+            .get()
+            .map_err(anyhow::Error::from)
+            .and_then(|conn| prune(&conn, &cfg));
+        match result {
+            Ok(pruned) => {
+                for (table, rows) in pruned {
+                    info!(table, rows, "Pruned rows outside retention");
+                }
+            }
+            Err(err) => error!(%err, "Retention run failed"),
+        }
+    }
+}
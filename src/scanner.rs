@@ -0,0 +1,2672 @@
+//! The polling loop: quote, compare, record.
+
+use chrono::Utc;
+use ethers::abi::Abi;
+use ethers::core::types::{Address, U256};
+use ethers::providers::{Http, Middleware, Provider};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::anomaly::AnomalyDetector;
+use crate::api::StreamEvent;
+use crate::arb::{self, Decision, Quote, Spread, TrendDirection};
+use crate::breaker::{CircuitBreaker, RpcTransition, RpcWatch};
+use crate::config::{
+    Config, DEFAULT_JOB, DexEntry, DexKind, JobConfig, ReportCurrency, TokenPair, parse_address,
+    redact_url,
+};
+use crate::db::{self, DbWrite, PriceTick};
+use crate::error::{BotError, BotResult};
+use crate::execute::{Executor, ExposureLedger, Trade};
+use crate::gas::{GasCost, GasEstimator, MaticPricer};
+use crate::health::Health;
+use crate::metrics::{JobMetrics, Metrics};
+use crate::nonce::NonceManager;
+use crate::notify::{Notifier, OpportunityAlert, RpcHealthAlert, SpreadClosedAlert};
+use crate::oracle::{self, ChainlinkFeed};
+use crate::output::{QuoteReport, TickFormatter, TickReport, Verdict};
+use crate::pacing::{RefreshController, VolatilityPacer};
+use crate::price::{self, PriceError, PriceSource, TradeSizeSearchConfig};
+use crate::ratelimit::RateLimiter;
+use crate::reload::SharedConfig;
+use crate::simulate::{SimulationError, Simulator};
+use crate::state::{self, LastPrice, PriceBoard, StateSnapshot};
+use crate::store::OpportunityStore;
+use crate::throttle::NotifyThrottle;
+
+/// How long an in-flight tick may keep running once shutdown is requested.
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+/// Longest a quote waits for the endpoint's request budget before its DEX is
+/// skipped for the tick.
+const QUOTE_BUDGET_WAIT: Duration = Duration::from_secs(1);
+
+/// Ticks of spread history [`arb::spread_trend`] is fitted over.
+const SPREAD_HISTORY_LEN: usize = 5;
+
+/// Counters reported when the polling loop stops.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionSummary {
+    /// Ticks started, including one abandoned at shutdown.
+    pub ticks: u64,
+    /// Opportunities recorded.
+    pub opportunities: u64,
+}
+
+/// Result of one tick.
+#[derive(Debug, Clone, Copy, Default)]
+struct TickOutcome {
+    /// An opportunity was recorded.
+    recorded: bool,
+    /// No venue quoted and at least one failed at the RPC level.
+    rpc_failed: bool,
+    /// Gross spread in USDC between the cheapest and richest venue, when compared.
+    spread_usdc: Option<f64>,
+}
+
+/// The chain and pair one scanner watches.
+pub struct ScanTarget {
+    /// The `[[jobs]]` entry the scanner belongs to; `None` for the [`DEFAULT_JOB`].
+    pub job: Option<JobConfig>,
+    /// EIP-155 id, stored with every record.
+    pub chain_id: u64,
+    /// Client for this chain's RPC endpoint.
+    pub provider: Provider<Http>,
+    /// Venues on this chain, narrowed to the job's `dex` list.
+    pub dex: Vec<DexEntry>,
+    /// The pair quoted on every venue.
+    pub tokens: TokenPair,
+    /// Snapshot file of this scanner; nothing is persisted when absent.
+    pub state_file: Option<String>,
+    /// Execution wallet of the chain, shared by its scanners; live mode only.
+    pub wallet: Option<Arc<NonceManager>>,
+    /// Request budget of the chain's endpoint, shared by its scanners; none
+    /// without `max_requests_per_sec`.
+    pub limiter: Option<Arc<RateLimiter>>,
+}
+
+/// Process-wide handles every scanner shares.
+#[derive(Clone)]
+pub struct ScanServices {
+    /// Opportunity, price and execution history, read back for the TWAP
+    /// window and the daily loss limit.
+    pub store: Arc<dyn OpportunityStore>,
+    /// Queue of the background writer, which applies every opportunity,
+    /// expiry, price and execution write.
+    pub writes: mpsc::Sender<DbWrite>,
+    /// Uniswap V2 router ABI.
+    pub abi: Abi,
+    /// Prometheus counters.
+    pub metrics: Arc<Metrics>,
+    /// Recorded opportunities, fanned out to `/stream` clients.
+    pub events: broadcast::Sender<StreamEvent>,
+    /// Tick liveness behind `/health`.
+    pub health: Arc<Health>,
+    /// Latest quotes of every scanner, behind `/prices`.
+    pub prices: PriceBoard,
+    /// USDC committed by executions, held against `execution.max_exposure_usdc`.
+    pub exposure: Arc<ExposureLedger>,
+    /// Set by `POST /admin/pause`: opportunities are recorded but not executed.
+    pub execution_paused: Arc<AtomicBool>,
+}
+
+/// Long-lived state of the polling loop.
+pub struct Scanner {
+    /// Snapshot used by the current tick; hot settings are re-synced from `shared`.
+    cfg: Config,
+    shared: SharedConfig,
+    /// Overrides applied over `[settings]` on every sync.
+    job: Option<JobConfig>,
+    chain_id: u64,
+    /// Label of the quoted pair.
+    pair: String,
+    state_file: Option<String>,
+    store: Arc<dyn OpportunityStore>,
+    writes: mpsc::Sender<DbWrite>,
+    provider: Provider<Http>,
+    /// `rpc_url` of the chain, redacted, labelling its latency metrics.
+    endpoint: String,
+    limiter: Option<Arc<RateLimiter>>,
+    sources: Vec<Box<dyn PriceSource>>,
+    /// Quote path candidates per source, in source order: the entry's own `path`,
+    /// or the direct path followed by the `path_via` route.
+    paths: Vec<Vec<Vec<Address>>>,
+    /// Per-leg `fee_bps` of each source, in source order.
+    fee_bps: Vec<u32>,
+    /// `priority` of each source, in source order.
+    priorities: Vec<u32>,
+    /// Swap fee of each source's pools, in source order: its `pool_fee_bps`, or
+    /// probed at startup; `None` when the probe failed.
+    pool_fee_bps: Vec<Option<u32>>,
+    /// Interface of each source, in source order, for the gas model.
+    kinds: Vec<DexKind>,
+    trade_size: U256,
+    trade_size_weth: f64,
+    /// Decimals `trade_size` is scaled by.
+    base_decimals: u32,
+    /// Decimals of the quote token, for displayed and stored prices.
+    quote_decimals: u32,
+    /// Symbol of the quote token in log lines.
+    quote_symbol: String,
+    /// Symbol of the base token, recorded with base-denominated profits.
+    base_symbol: String,
+    /// Mean quoted price of one base token this tick, in the quote token;
+    /// converts profits for `report_currency = "base"`.
+    base_price: Option<f64>,
+    no_path_warned: HashSet<(String, String)>,
+    /// Last successful quote per (dex, pair), persisted in the state snapshot.
+    last_prices: BTreeMap<(String, String), LastPrice>,
+    /// Spread of the last [`SPREAD_HISTORY_LEN`] ticks that compared venues, oldest first.
+    spread_history: VecDeque<f64>,
+    /// Last raw quote per DEX and the number of ticks it has since repeated exactly.
+    repeated_quotes: HashMap<String, (U256, u32)>,
+    oracle: Option<ChainlinkFeed>,
+    /// Present when `[settings.anomaly_filter]` is set.
+    anomalies: Option<AnomalyDetector>,
+    /// Present when `simulate_before_record` is on.
+    simulator: Option<Simulator>,
+    /// Present when `estimate_gas_from` is set.
+    gas_estimator: Option<GasEstimator>,
+    /// Present when `matic_price_dex` names one of this scanner's venues.
+    matic_pricer: Option<MaticPricer>,
+    /// Ticks evaluated so far, aging cached gas estimates.
+    ticks: u64,
+    /// Present when `[execution]` is enabled.
+    executor: Option<Executor>,
+    /// Last execution per (buy dex, sell dex), gating repeats for `cooldown_secs`.
+    last_action: HashMap<(String, String), Instant>,
+    exposure: Arc<ExposureLedger>,
+    execution_paused: Arc<AtomicBool>,
+    formatter: TickFormatter,
+    metrics: Arc<Metrics>,
+    /// Per-loop metrics labelled with the job's name.
+    job_metrics: JobMetrics,
+    health: Arc<Health>,
+    prices: PriceBoard,
+    notifier: Notifier,
+    /// Repeat-alert throttle of open spreads, when `[notify_throttle]` is set.
+    throttle: Option<NotifyThrottle>,
+    events: broadcast::Sender<StreamEvent>,
+}
+
+impl Scanner {
+    /// Builds the price sources and quote paths of `target`, checking each source
+    /// against the pair on-chain; loop settings come from `shared`.
+    pub async fn new(
+        shared: SharedConfig,
+        mut target: ScanTarget,
+        services: ScanServices,
+    ) -> BotResult<Self> {
+        let weth = parse_address("tokens.weth", &target.tokens.weth)?;
+        let usdc = parse_address("tokens.usdc", &target.tokens.usdc)?;
+        let provider = Arc::new(target.provider.clone());
+        let limiter = target.limiter.clone();
+        let base_decimals = match target.tokens.weth_decimals {
+            Some(decimals) => decimals,
+            None => {
+                throttle(limiter.as_deref(), 1).await;
+                price::token_decimals(weth, provider.clone()).await?
+            }
+        };
+        let quote_decimals = match target.tokens.usdc_decimals {
+            Some(decimals) => decimals,
+            None => {
+                throttle(limiter.as_deref(), 1).await;
+                price::token_decimals(usdc, provider.clone()).await?
+            }
+        };
+        target.tokens.weth_decimals = Some(base_decimals);
+        target.tokens.usdc_decimals = Some(quote_decimals);
+        let sources = price::build_sources(
+            &target.dex,
+            provider.clone(),
+            &services.abi,
+            target.chain_id,
+        )?;
+        for source in &sources {
+            source
+                .validate(&[(weth, base_decimals), (usdc, quote_decimals)])
+                .await?;
+        }
+        let dex = target.dex.clone();
+        let wallet = target.wallet.take();
+        let oracle_feed = target.tokens.oracle_feed.clone();
+        let abi = services.abi.clone();
+        let mut scanner = Self::with_sources(shared, target, services, sources)?;
+
+        if scanner.cfg.execution.enabled && !scanner.cfg.settings.dry_run {
+            let executor = Executor::new(
+                &scanner.cfg.execution,
+                &dex,
+                (weth, scanner.base_decimals),
+                (usdc, scanner.quote_decimals),
+                abi,
+                provider.clone(),
+                wallet,
+            )?;
+            executor.prepare().await?;
+            scanner.executor = Some(executor);
+        }
+        // One quote token around each venue without a configured pool fee.
+        let probe = U256::exp10(scanner.quote_decimals as usize);
+        for ((source, candidates), fee) in scanner
+            .sources
+            .iter()
+            .zip(&scanner.paths)
+            .zip(&mut scanner.pool_fee_bps)
+            .filter(|(_, fee)| fee.is_none())
+        {
+            throttle(limiter.as_deref(), 2).await;
+            match price::probe_fee_bps(source.as_ref(), &candidates[0], probe).await {
+                Ok(probed) => {
+                    info!(
+                        dex = source.name(),
+                        pool_fee_bps = probed,
+                        "Venue fee probed"
+                    );
+                    *fee = Some(probed);
+                }
+                Err(err) => {
+                    warn!(dex = source.name(), %err, "Venue fee probe failed; set pool_fee_bps to use it");
+                }
+            }
+        }
+        if let Some(feed) = oracle_feed {
+            let feed = parse_address("tokens.oracle_feed", &feed)?;
+            scanner.oracle = Some(ChainlinkFeed::connect(feed, provider).await?);
+        }
+        Ok(scanner)
+    }
+
+    /// The loop of `target` over `sources`, one per `target.dex` entry in the
+    /// same order, without reading the chain: both token decimals must be set
+    /// in `target.tokens`, venue fees are only the configured `pool_fee_bps`,
+    /// and there is no executor or oracle check.
+    ///
+    /// [`Self::new`] builds on it; any [`PriceSource`], e.g. a
+    /// [`price::ScriptedSource`], can be scanned this way.
+    pub fn with_sources(
+        shared: SharedConfig,
+        target: ScanTarget,
+        services: ScanServices,
+        sources: Vec<Box<dyn PriceSource>>,
+    ) -> BotResult<Self> {
+        let mut cfg = shared.read().unwrap().clone();
+        let ScanServices {
+            store,
+            writes,
+            abi,
+            metrics,
+            events,
+            health,
+            prices,
+            exposure,
+            execution_paused,
+        } = services;
+        let ScanTarget {
+            job,
+            chain_id,
+            provider,
+            dex,
+            tokens,
+            state_file,
+            wallet: _,
+            limiter,
+        } = target;
+        if let Some(job) = &job {
+            job.apply(&mut cfg.settings);
+        }
+        if sources.len() != dex.len() {
+            return Err(BotError::Config(format!(
+                "{} price sources for {} dex entries",
+                sources.len(),
+                dex.len()
+            )));
+        }
+        let (Some(base_decimals), Some(quote_decimals)) =
+            (tokens.weth_decimals, tokens.usdc_decimals)
+        else {
+            return Err(BotError::Config(
+                "tokens.weth_decimals and tokens.usdc_decimals must be set".into(),
+            ));
+        };
+        let weth = parse_address("tokens.weth", &tokens.weth)?;
+        let usdc = parse_address("tokens.usdc", &tokens.usdc)?;
+        let paths = quote_paths(&tokens, &dex)?;
+        let simulator = match (&cfg.simulation, cfg.settings.simulate_before_record) {
+            (Some(simulation), true) => Some(Simulator::new(
+                simulation,
+                &dex,
+                weth,
+                usdc,
+                abi.clone(),
+                Arc::new(provider.clone()),
+            )?),
+            _ => None,
+        };
+        let gas_estimator = match &cfg.settings.estimate_gas_from {
+            Some(from) => Some(GasEstimator::new(
+                parse_address("settings.estimate_gas_from", from)?,
+                &dex,
+                abi.clone(),
+                Arc::new(provider.clone()),
+                limiter.clone(),
+            )?),
+            None => None,
+        };
+        let matic_pricer = match &cfg.settings.matic_price_dex {
+            Some(name) => match price::v2_routers(&dex)?.get(name) {
+                Some(&router) => Some(MaticPricer::new(
+                    router,
+                    abi.clone(),
+                    parse_address("settings.wmatic", &cfg.settings.wmatic)?,
+                    usdc,
+                    Arc::new(provider.clone()),
+                )),
+                None => {
+                    warn!(
+                        dex = %name,
+                        "matic_price_dex is not a venue of this scanner; using matic_price_usdc"
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+        let trade_size = cfg.settings.trade_size.to_units(base_decimals)?;
+
+        Ok(Self {
+            trade_size,
+            trade_size_weth: arb::units_to_amount(trade_size, base_decimals),
+            base_decimals,
+            quote_decimals,
+            quote_symbol: tokens.quote_symbol().to_string(),
+            base_symbol: tokens.base_symbol().to_string(),
+            base_price: None,
+            paths,
+            fee_bps: dex.iter().map(|entry| entry.fee_bps).collect(),
+            priorities: dex.iter().map(|entry| entry.priority).collect(),
+            pool_fee_bps: dex.iter().map(|entry| entry.pool_fee_bps).collect(),
+            kinds: dex.iter().map(|entry| entry.kind).collect(),
+            no_path_warned: HashSet::new(),
+            last_prices: BTreeMap::new(),
+            spread_history: VecDeque::with_capacity(SPREAD_HISTORY_LEN + 1),
+            repeated_quotes: HashMap::new(),
+            oracle: None,
+            anomalies: cfg
+                .settings
+                .anomaly_filter
+                .clone()
+                .map(AnomalyDetector::new),
+            simulator,
+            gas_estimator,
+            matic_pricer,
+            ticks: 0,
+            executor: None,
+            last_action: HashMap::new(),
+            exposure,
+            execution_paused,
+            formatter: TickFormatter::new(
+                cfg.settings.output,
+                sources.iter().map(|s| s.name().to_string()).collect(),
+                tokens.quote_symbol(),
+            ),
+            notifier: Notifier::new(
+                cfg.webhook.clone(),
+                cfg.telegram.clone(),
+                Duration::from_secs(cfg.settings.telegram_min_interval_secs),
+            ),
+            throttle: cfg.notify_throttle.clone().map(NotifyThrottle::new),
+            job_metrics: metrics.for_job(job.as_ref().map_or(DEFAULT_JOB, |job| &job.name)),
+            metrics,
+            health,
+            prices,
+            events,
+            cfg,
+            shared,
+            job,
+            chain_id,
+            pair: tokens.label().to_string(),
+            state_file,
+            store,
+            writes,
+            endpoint: redact_url(provider.url().as_str()),
+            limiter,
+            provider,
+            sources,
+        })
+    }
+
+    /// Picks up hot-reloaded settings before a tick.
+    fn sync_settings(&mut self) {
+        let (mut latest, webhook, telegram, notify_throttle) = {
+            let shared = self.shared.read().unwrap();
+            (
+                shared.settings.clone(),
+                shared.webhook.clone(),
+                shared.telegram.clone(),
+                shared.notify_throttle.clone(),
+            )
+        };
+        if let Some(job) = &self.job {
+            job.apply(&mut latest);
+        }
+        if webhook != self.cfg.webhook
+            || telegram != self.cfg.telegram
+            || latest.telegram_min_interval_secs != self.cfg.settings.telegram_min_interval_secs
+        {
+            self.notifier.reconfigure(
+                webhook.clone(),
+                telegram.clone(),
+                Duration::from_secs(latest.telegram_min_interval_secs),
+            );
+            self.cfg.webhook = webhook;
+            self.cfg.telegram = telegram;
+        }
+        if notify_throttle != self.cfg.notify_throttle {
+            self.throttle = match (self.throttle.take(), notify_throttle.clone()) {
+                (Some(mut throttle), Some(cfg)) => {
+                    throttle.set_config(cfg);
+                    Some(throttle)
+                }
+                (None, Some(cfg)) => Some(NotifyThrottle::new(cfg)),
+                (_, None) => None,
+            };
+            self.cfg.notify_throttle = notify_throttle;
+        }
+        let settings = &mut self.cfg.settings;
+        settings.min_profit_usdc = latest.min_profit_usdc;
+        settings.refresh_rate = latest.refresh_rate;
+        settings.est_gas_cost_usdc = latest.est_gas_cost_usdc;
+        settings.telegram_min_interval_secs = latest.telegram_min_interval_secs;
+        if settings.trade_size != latest.trade_size {
+            settings.trade_size = latest.trade_size;
+            match settings.trade_size.to_units(self.base_decimals) {
+                Ok(units) => {
+                    self.trade_size = units;
+                    self.trade_size_weth = arb::units_to_amount(units, self.base_decimals);
+                }
+                Err(err) => error!(%err, "Reloaded trade_size rejected; keeping the previous size"),
+            }
+        }
+    }
+
+    /// Ticks until `shutdown` is cancelled, sleeping `refresh_rate` seconds (or the
+    /// adaptive delay) between ticks.
+    ///
+    /// An in-flight tick gets [`SHUTDOWN_GRACE`] to finish and pending notifications
+    /// are flushed before returning; pooled connections close as the scanner drops.
+    pub async fn run(mut self, shutdown: CancellationToken) -> BotResult<SessionSummary> {
+        let mut summary = SessionSummary::default();
+        let refresh_rate = Duration::from_secs(self.cfg.settings.refresh_rate);
+        let mut delay = Duration::ZERO;
+        let metrics = self.job_metrics.clone();
+        let health = self.health.clone();
+        let mut refresh = self
+            .cfg
+            .settings
+            .adaptive_refresh
+            .clone()
+            .map(|cfg| RefreshController::new(cfg, refresh_rate));
+        let mut pacer = self
+            .cfg
+            .settings
+            .adaptive_interval
+            .clone()
+            .map(VolatilityPacer::new);
+        let mut breaker = CircuitBreaker::new(
+            self.cfg.settings.circuit_open_threshold,
+            Duration::from_secs(self.cfg.settings.circuit_open_duration_secs),
+        );
+        let mut rpc_watch = RpcWatch::new(
+            self.cfg.settings.rpc_down_after_ticks,
+            self.cfg.settings.rpc_up_after_ticks,
+        );
+        let state_file = self.state_file.clone();
+        let pair = self.pair.clone();
+        let save_every = Duration::from_secs(self.cfg.state_save_interval_secs);
+        let mut last_saved = Instant::now();
+        if let Some(path) = &state_file {
+            match state::load_snapshot(path) {
+                Ok(Some(snapshot)) => {
+                    self.restore(&snapshot, pacer.as_mut());
+                    if let (Some(refresh), Some(secs)) =
+                        (refresh.as_mut(), snapshot.refresh_delay_secs)
+                    {
+                        refresh.resume_from(Duration::from_secs_f64(secs.max(0.0)));
+                    }
+                    info!(path = %path, saved_at = %snapshot.saved_at, "State restored");
+                }
+                Ok(None) => {}
+                Err(err) => warn!(path = %path, %err, "State file unreadable; starting fresh"),
+            }
+        }
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = tokio::time::sleep(delay) => {}
+            }
+
+            if let Some(pause) = breaker.open_remaining() {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = tokio::time::sleep(pause) => {}
+                }
+                breaker.try_half_open();
+            }
+
+            self.sync_settings();
+            let refresh_rate = Duration::from_secs(self.cfg.settings.refresh_rate);
+            let timer = metrics.tick_duration.start_timer();
+            let result = {
+                let tick = self.tick();
+                tokio::pin!(tick);
+                tokio::select! {
+                    res = &mut tick => Some(res),
+                    _ = shutdown.cancelled() => tokio::time::timeout(SHUTDOWN_GRACE, &mut tick).await.ok(),
+                }
+            };
+            timer.observe_duration();
+            summary.ticks += 1;
+            metrics.ticks.inc();
+            match result {
+                Some(res) => {
+                    let outcome = res?;
+                    if outcome.rpc_failed {
+                        breaker.record_failure();
+                    } else {
+                        breaker.record_success();
+                    }
+                    if let Some(change) = rpc_watch.record(!outcome.rpc_failed) {
+                        self.alert_rpc(change);
+                    }
+                    if outcome.recorded {
+                        summary.opportunities += 1;
+                        metrics.opportunities.inc();
+                    }
+                    health.record_tick(!outcome.rpc_failed, outcome.recorded);
+                    delay = if let Some(refresh) = refresh.as_mut() {
+                        refresh.after_tick(outcome.recorded)
+                    } else if let Some(pacer) = pacer.as_mut() {
+                        if let Some(spread) = outcome.spread_usdc {
+                            pacer.observe(&pair, spread);
+                        }
+                        pacer.next_interval()
+                    } else {
+                        refresh_rate
+                    };
+                    debug!(next_secs = delay.as_secs_f64(), "Next tick scheduled");
+                    if let Some(path) = &state_file
+                        && last_saved.elapsed() >= save_every
+                    {
+                        self.persist(path, pacer.as_ref(), refresh.as_ref());
+                        last_saved = Instant::now();
+                    }
+                }
+                None => {
+                    warn!(
+                        "In-flight tick did not finish within {:?}; abandoning it",
+                        SHUTDOWN_GRACE
+                    );
+                    break;
+                }
+            }
+        }
+
+        if let Some(path) = &state_file {
+            self.persist(path, pacer.as_ref(), refresh.as_ref());
+        }
+        self.notifier.flush(SHUTDOWN_GRACE).await;
+        Ok(summary)
+    }
+
+    /// Logs and notifies a debounced RPC health change.
+    fn alert_rpc(&self, change: RpcTransition) {
+        let (event, consecutive_ticks) = match change {
+            RpcTransition::Down(failures) => {
+                error!(failures, "RPC down: {} consecutive ticks failed", failures);
+                ("rpc_down", failures)
+            }
+            RpcTransition::Up(successes) => {
+                info!(successes, "RPC recovered");
+                ("rpc_up", successes)
+            }
+        };
+        self.notifier.notify_rpc(RpcHealthAlert {
+            event,
+            chain_id: self.chain_id,
+            pair: self.pair.clone(),
+            consecutive_ticks,
+            timestamp: Utc::now().to_rfc3339(),
+        });
+    }
+
+    /// Seeds last prices, the Telegram rate limit and the pacer from `snapshot`.
+    fn restore(&mut self, snapshot: &StateSnapshot, pacer: Option<&mut VolatilityPacer>) {
+        for price in &snapshot.last_prices {
+            if price.pair == self.pair {
+                self.metrics
+                    .record_price(self.chain_id, &price.pair, &price.dex, price.price_usdc);
+            }
+            self.last_prices
+                .insert((price.dex.clone(), price.pair.clone()), price.clone());
+        }
+        if let Some(at) = snapshot
+            .last_telegram_at
+            .as_deref()
+            .and_then(state::rfc3339_to_instant)
+        {
+            self.notifier.restore_last_telegram(at);
+        }
+        if let Some(pacer) = pacer {
+            for (pair, spreads) in &snapshot.spreads {
+                for &spread in spreads {
+                    pacer.observe(pair, spread);
+                }
+            }
+        }
+    }
+
+    /// Writes the current rolling state to `path`, warning on failure.
+    fn persist(
+        &self,
+        path: &str,
+        pacer: Option<&VolatilityPacer>,
+        refresh: Option<&RefreshController>,
+    ) {
+        let snapshot = StateSnapshot {
+            saved_at: Utc::now().to_rfc3339(),
+            last_prices: self.last_prices.values().cloned().collect(),
+            last_telegram_at: self.notifier.last_telegram().map(state::instant_to_rfc3339),
+            spreads: pacer
+                .map(|pacer| {
+                    pacer
+                        .history()
+                        .map(|(pair, spreads)| (pair.to_string(), spreads))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            refresh_delay_secs: refresh.map(|refresh| refresh.current().as_secs_f64()),
+        };
+        if let Err(err) = state::save_snapshot(path, &snapshot) {
+            warn!(path, %err, "Failed to save state");
+        }
+    }
+
+    /// Raw units of `amount` of the quote token.
+    fn quote_units(&self, amount: f64) -> U256 {
+        arb::amount_to_units(amount, self.quote_decimals)
+    }
+
+    /// Amount of the quote token in raw `units`.
+    fn quote_amount(&self, units: U256) -> f64 {
+        arb::units_to_amount(units, self.quote_decimals)
+    }
+
+    /// `min_profit_usdc` in USDC: as configured, or converted from the base token
+    /// at this tick's price when `report_currency = "base"`.
+    fn min_profit_usdc(&self) -> f64 {
+        let min_profit = self.cfg.settings.min_profit_usdc;
+        match (self.cfg.settings.report_currency, self.base_price) {
+            (ReportCurrency::Base, Some(price)) => min_profit * price,
+            _ => min_profit,
+        }
+    }
+
+    /// `profit_usdc` in the `report_currency` and that currency's symbol.
+    fn report_profit(&self, profit_usdc: f64) -> (f64, &str) {
+        match (self.cfg.settings.report_currency, self.base_price) {
+            (ReportCurrency::Base, Some(price)) => (profit_usdc / price, &self.base_symbol),
+            _ => (profit_usdc, &self.quote_symbol),
+        }
+    }
+
+    /// Qualifying spreads between every pair of quotes with their scores, best first.
+    fn rank_opportunities<'a>(
+        &self,
+        quotes: &[(Quote<'a>, String)],
+        gas: &GasCost<'_>,
+    ) -> Vec<(Spread<'a>, f64)> {
+        let min_profit = self.quote_units(self.min_profit_usdc());
+        let mut ranked = Vec::new();
+        for (i, (a, _)) in quotes.iter().enumerate() {
+            for (b, _) in &quotes[i + 1..] {
+                let gas_cost = gas.quote_units(a.dex, b.dex, self.quote_decimals);
+                if let Decision::Opportunity(spread) = arb::decide(*a, *b, gas_cost, min_profit) {
+                    let score = gas.score(
+                        self.quote_amount(spread.net_profit),
+                        spread.buy_dex,
+                        spread.sell_dex,
+                    );
+                    ranked.push((spread, score));
+                }
+            }
+        }
+        ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        ranked
+    }
+
+    /// Queues `write` for the database writer; logs it as lost once the writer
+    /// has stopped.
+    async fn write(&self, write: DbWrite) {
+        if self.writes.send(write).await.is_err() {
+            error!("Database writer stopped; write dropped");
+        }
+    }
+
+    /// Stores a dry run's `opportunity` in `dry_run_opportunities` when
+    /// `dry_run_table` is set, else only logs that it was dropped.
+    async fn record_dry_run(&self, opportunity: db::Opportunity) {
+        if self.cfg.settings.dry_run_table {
+            self.write(DbWrite::InsertDryRun(Box::new(opportunity)))
+                .await;
+            info!("[DRY RUN] Opportunity saved to {}", db::DRY_RUN_TABLE);
+        } else {
+            info!("[DRY RUN] Opportunity not saved");
+        }
+    }
+
+    /// Whether the tick is one of the first `warmup_ticks`.
+    fn warming_up(&self) -> bool {
+        self.ticks <= self.cfg.settings.warmup_ticks
+    }
+
+    /// `priority` of the venue named `dex`; 0 for an unknown one.
+    fn priority(&self, dex: &str) -> u32 {
+        self.sources
+            .iter()
+            .position(|source| source.name() == dex)
+            .map_or(0, |i| self.priorities[i])
+    }
+
+    /// Moves to the front of `ranked` the spread [`arb::prefer_by_priority`]
+    /// picks, logging which venue it displaced and why.
+    fn prefer_priority(&self, ranked: &mut [(Spread<'_>, f64)]) {
+        let tie = self.cfg.settings.priority_tie_threshold_usdc;
+        let preferred = arb::prefer_by_priority(
+            ranked.iter().map(|(spread, _)| *spread),
+            |dex| self.priority(dex),
+            self.quote_units(tie),
+        );
+        if preferred == 0 {
+            return;
+        }
+        let (top, chosen) = (ranked[0].0, ranked[preferred].0);
+        info!(
+            pair = self.pair.as_str(),
+            buy_dex = chosen.buy_dex,
+            sell_dex = chosen.sell_dex,
+            priority = self.priority(chosen.buy_dex),
+            over_buy_dex = top.buy_dex,
+            over_priority = self.priority(top.buy_dex),
+            profit_usdc = self.quote_amount(chosen.net_profit),
+            top_profit_usdc = self.quote_amount(top.net_profit),
+            priority_tie_threshold_usdc = tie,
+            "Spreads tied; preferring the buy venue of lower priority"
+        );
+        ranked[..=preferred].rotate_right(1);
+    }
+
+    /// Re-quotes each of `ranked` as an executable cycle in both directions: the
+    /// buy venue's USDC quote is spent on WETH there, which is then sold on the
+    /// other venue.
+    ///
+    /// Keeps the pairs whose better direction nets more than `min_profit_usdc`
+    /// after venue fees and gas, carrying that direction and net, ranked best first.
+    async fn round_trips<'a>(
+        &self,
+        quotes: &[(Quote<'a>, String)],
+        routes: &HashMap<&str, Vec<Address>>,
+        ranked: &[(Spread<'a>, f64)],
+        gas: &GasCost<'_>,
+    ) -> Vec<(Spread<'a>, f64)> {
+        let min_profit = self.quote_units(self.min_profit_usdc());
+        let source = |dex: &str| self.sources.iter().find(|s| s.name() == dex);
+        let mut kept = Vec::new();
+        for (spread, _) in ranked {
+            let gas_cost = gas.quote_units(spread.buy_dex, spread.sell_dex, self.quote_decimals);
+            let mut best: Option<Spread<'a>> = None;
+            for (buy_dex, sell_dex) in [
+                (spread.buy_dex, spread.sell_dex),
+                (spread.sell_dex, spread.buy_dex),
+            ] {
+                let (Some(buy), Some(sell), Some(buy_path), Some(sell_path)) = (
+                    source(buy_dex),
+                    source(sell_dex),
+                    routes.get(buy_dex),
+                    routes.get(sell_dex),
+                ) else {
+                    continue;
+                };
+                let quote_of =
+                    |dex: &str| quotes.iter().find(|(q, _)| q.dex == dex).map(|(q, _)| *q);
+                let (Some(buy_quote), Some(sell_quote)) = (quote_of(buy_dex), quote_of(sell_dex))
+                else {
+                    continue;
+                };
+                let usdc_in = buy_quote.amount_out;
+                throttle(self.limiter.as_deref(), 2).await;
+                let result = price::quote_round_trip(
+                    buy.as_ref(),
+                    buy_path,
+                    sell.as_ref(),
+                    sell_path,
+                    usdc_in,
+                )
+                .await;
+                let usdc_out = match result {
+                    Ok((_, usdc_out)) => usdc_out,
+                    Err(err) => {
+                        debug!(buy_dex, sell_dex, ?err, "Round trip not quoted");
+                        continue;
+                    }
+                };
+                let gross = usdc_out.saturating_sub(usdc_in);
+                let fees = arb::leg_fee(usdc_in, buy_quote.fee_bps)
+                    + arb::leg_fee(usdc_out, sell_quote.fee_bps);
+                let cycle = Spread {
+                    buy_dex,
+                    sell_dex,
+                    gross,
+                    net_profit: gross.saturating_sub(fees).saturating_sub(gas_cost),
+                };
+                debug!(
+                    buy_dex,
+                    sell_dex,
+                    usdc_in = self.quote_amount(usdc_in),
+                    usdc_out = self.quote_amount(usdc_out),
+                    "Round trip quoted"
+                );
+                if best.is_none_or(|b| cycle.net_profit > b.net_profit) {
+                    best = Some(cycle);
+                }
+            }
+            match best {
+                Some(cycle) if cycle.net_profit > min_profit => {
+                    let score = gas.score(
+                        self.quote_amount(cycle.net_profit),
+                        cycle.buy_dex,
+                        cycle.sell_dex,
+                    );
+                    kept.push((cycle, score));
+                }
+                _ => info!(
+                    pair = self.pair.as_str(),
+                    buy_dex = spread.buy_dex,
+                    sell_dex = spread.sell_dex,
+                    net_profit_usdc = best.map(|c| self.quote_amount(c.net_profit)),
+                    "Round trip does not clear the threshold"
+                ),
+            }
+        }
+        kept.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        kept
+    }
+
+    /// Gas pricing of this tick's `routes`: the flat `est_gas_cost_usdc`, or with
+    /// a `gas_model` each venue's leg by its kind and hop count at the node's gas
+    /// price, falling back to `gas_price_gwei` when that cannot be read.
+    async fn gas_cost<'a>(&self, routes: &HashMap<&'a str, Vec<Address>>) -> GasCost<'a> {
+        let settings = &self.cfg.settings;
+        let Some(model) = &settings.gas_model else {
+            return GasCost::flat(settings);
+        };
+        throttle(self.limiter.as_deref(), 1).await;
+        let started = Instant::now();
+        let gas_price = self.provider.get_gas_price().await;
+        self.metrics
+            .observe_rpc_latency(&self.endpoint, "gas_price", started.elapsed());
+        let gas_price_gwei = match gas_price {
+            Ok(wei) => wei.as_u128() as f64 / 1e9,
+            Err(err) => {
+                warn!(%err, gas_price_gwei = settings.gas_price_gwei, "Gas price unavailable; using gas_price_gwei");
+                settings.gas_price_gwei
+            }
+        };
+        let legs = self
+            .sources
+            .iter()
+            .zip(&self.kinds)
+            .filter_map(|(source, kind)| {
+                let (dex, route) = routes.get_key_value(source.name())?;
+                Some((*dex, model.leg_units(*kind, route.len().saturating_sub(1))))
+            })
+            .collect();
+        GasCost::routed(settings, model, legs, gas_price_gwei)
+    }
+
+    /// Whether both legs of `spread` quote within `max_oracle_deviation_pct` of
+    /// `reference`; logs the offending leg otherwise.
+    fn within_oracle_band(
+        &self,
+        quotes: &[(Quote<'_>, String)],
+        spread: &Spread<'_>,
+        reference: f64,
+    ) -> bool {
+        let max = self.cfg.settings.max_oracle_deviation_pct;
+        for dex in [spread.buy_dex, spread.sell_dex] {
+            let Some((quote, _)) = quotes.iter().find(|(q, _)| q.dex == dex) else {
+                continue;
+            };
+            let price =
+                arb::units_to_amount(quote.amount_out, self.quote_decimals) / self.trade_size_weth;
+            let deviation = oracle::deviation_pct(price, reference);
+            if deviation > max {
+                warn!(
+                    dex,
+                    pair = self.pair.as_str(),
+                    price,
+                    oracle = reference,
+                    deviation_pct = deviation,
+                    "Quote deviates from oracle; skipping opportunity"
+                );
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Path source `source` quoted on; failed quotes report its first candidate.
+    fn winning_route(
+        &self,
+        source: usize,
+        quote: &Result<(U256, usize), PriceError>,
+    ) -> &[Address] {
+        let candidates = &self.paths[source];
+        match quote {
+            Ok((_, i)) => &candidates[*i],
+            Err(_) => &candidates[0],
+        }
+    }
+
+    /// Logs the most profitable size for `spread` next to the configured `trade_size`.
+    ///
+    /// The search sells WETH on the sell venue and buys it back on the buy venue,
+    /// with the route's `gas_usdc` converted to WETH at the sell venue's price.
+    async fn log_optimal_size(
+        &self,
+        search: &TradeSizeSearchConfig,
+        spread: &Spread<'_>,
+        routes: &HashMap<&str, Vec<Address>>,
+        sell_price_usdc: f64,
+        gas_usdc: f64,
+    ) {
+        let (buy_dex, sell_dex) = (spread.buy_dex, spread.sell_dex);
+        let source = |dex: &str| self.sources.iter().find(|s| s.name() == dex);
+        let (Some(sell), Some(buy), Some(sell_path), Some(buy_path)) = (
+            source(sell_dex),
+            source(buy_dex),
+            routes.get(sell_dex),
+            routes.get(buy_dex),
+        ) else {
+            return;
+        };
+        let buy_back: Vec<Address> = buy_path.iter().rev().copied().collect();
+        let weth_price = sell_price_usdc / self.trade_size_weth;
+        let gas_cost = arb::amount_to_units(gas_usdc / weth_price, self.base_decimals);
+        match price::optimize_round_trip(
+            sell.as_ref(),
+            sell_path,
+            buy.as_ref(),
+            &buy_back,
+            search,
+            gas_cost,
+        )
+        .await
+        {
+            Ok((size, profit)) => {
+                let profit_weth = arb::units_to_amount(profit, self.base_decimals);
+                info!(
+                    pair = self.pair.as_str(),
+                    buy_dex,
+                    sell_dex,
+                    configured_weth = self.trade_size_weth,
+                    optimal_weth = arb::units_to_amount(size, self.base_decimals),
+                    expected_profit_weth = profit_weth,
+                    expected_profit_usdc = profit_weth * weth_price,
+                    "Recommended trade size"
+                );
+            }
+            Err(err) => warn!(
+                pair = self.pair.as_str(),
+                buy_dex,
+                sell_dex,
+                ?err,
+                "Trade size search failed"
+            ),
+        }
+    }
+
+    /// Fee of a leg on `dex` in basis points: its pool fee plus its `fee_bps`;
+    /// `None` when the pool fee is unknown.
+    fn leg_fee_bps(&self, dex: &str) -> Option<u32> {
+        let i = self.sources.iter().position(|s| s.name() == dex)?;
+        Some(self.pool_fee_bps[i]? + self.fee_bps[i])
+    }
+
+    /// Closed-form USDC size of buying on `buy_dex` and selling on `sell_dex`,
+    /// treating each venue as the constant-product pool implied by its quote at
+    /// `trade_size` and a probe quote at a thousandth of it.
+    ///
+    /// `None` when a pool fee is unknown, a probe fails or the pools leave no
+    /// profitable size.
+    async fn analytic_size(
+        &self,
+        buy_dex: &str,
+        sell_dex: &str,
+        quotes: &[(Quote<'_>, String)],
+        routes: &HashMap<&str, Vec<Address>>,
+    ) -> Option<f64> {
+        let probe = self.trade_size / 1000;
+        let probe_weth = arb::units_to_amount(probe, self.base_decimals);
+        let mut reserves = Vec::with_capacity(2);
+        for dex in [buy_dex, sell_dex] {
+            let i = self.sources.iter().position(|s| s.name() == dex)?;
+            let quote = quotes.iter().find(|(q, _)| q.dex == dex)?.0;
+            let pool_fee = self.pool_fee_bps[i]?;
+            throttle(self.limiter.as_deref(), 1).await;
+            let probe_out = match self.sources[i].quote(probe, routes.get(dex)?).await {
+                Ok(amount) => amount,
+                Err(err) => {
+                    debug!(dex, %err, "Size probe not quoted");
+                    return None;
+                }
+            };
+            let spot = arb::units_to_amount(probe_out, self.quote_decimals) / probe_weth;
+            let size_out = arb::units_to_amount(quote.amount_out, self.quote_decimals);
+            reserves.push(arb::CpReserves::implied(
+                spot,
+                self.trade_size_weth,
+                size_out,
+                pool_fee,
+            )?);
+        }
+        arb::optimal_cycle_input(
+            reserves[0],
+            self.leg_fee_bps(buy_dex)?,
+            reserves[1],
+            self.leg_fee_bps(sell_dex)?,
+        )
+    }
+
+    /// Drops from `quotes` and `routes` every venue whose pool holds less than
+    /// `min_usdc`, and returns the liquidity of each venue it could measure.
+    ///
+    /// Liquidity comes from the constant-product pool implied by the venue's
+    /// quote and a probe at a thousandth of `trade_size`, both at `block`; a
+    /// venue whose probe fails or shows no price impact is kept.
+    async fn filter_liquidity<'a>(
+        &self,
+        quotes: &mut Vec<(Quote<'a>, String)>,
+        routes: &mut HashMap<&'a str, Vec<Address>>,
+        min_usdc: f64,
+        block: Option<u64>,
+    ) -> HashMap<&'a str, f64> {
+        let probe = self.trade_size / 1000;
+        let probe_weth = arb::units_to_amount(probe, self.base_decimals);
+        let mut liquidity = HashMap::new();
+        for (quote, _) in quotes.iter() {
+            let dex = quote.dex;
+            let Some(i) = self.sources.iter().position(|s| s.name() == dex) else {
+                continue;
+            };
+            let Some(route) = routes.get(dex) else {
+                continue;
+            };
+            throttle(self.limiter.as_deref(), 1).await;
+            let probe_out = match self.sources[i].quote_at(probe, route, block).await {
+                Ok(amount) => amount,
+                Err(err) => {
+                    debug!(dex, %err, "Liquidity probe not quoted; keeping the venue");
+                    continue;
+                }
+            };
+            let spot = arb::units_to_amount(probe_out, self.quote_decimals) / probe_weth;
+            let size_out = arb::units_to_amount(quote.amount_out, self.quote_decimals);
+            // An unknown pool fee only shifts the implied reserves by the fee.
+            let fee_bps = self.pool_fee_bps[i].unwrap_or_default();
+            match arb::CpReserves::implied(spot, self.trade_size_weth, size_out, fee_bps) {
+                Some(reserves) => {
+                    liquidity.insert(dex, reserves.liquidity());
+                }
+                None => debug!(dex, "Quotes show no price impact; keeping the venue"),
+            }
+        }
+        quotes.retain(|(quote, _)| match liquidity.get(quote.dex) {
+            Some(&liquidity_usdc) if liquidity_usdc < min_usdc => {
+                info!(
+                    dex = quote.dex,
+                    pair = self.pair.as_str(),
+                    liquidity_usdc,
+                    min_pool_liquidity_usdc = min_usdc,
+                    "Pool below min_pool_liquidity_usdc; excluded this tick"
+                );
+                routes.remove(quote.dex);
+                false
+            }
+            _ => true,
+        });
+        liquidity
+    }
+
+    /// Quotes the round trip buying on `buy_dex` and selling on `sell_dex` at each
+    /// `trade_size_sweep` size and logs the one with the largest net profit.
+    ///
+    /// Returns that size and its net profit in USDC, or `None` when no size could
+    /// be quoted.
+    async fn sweep_sizes(
+        &self,
+        buy_dex: &str,
+        sell_dex: &str,
+        quotes: &[(Quote<'_>, String)],
+        routes: &HashMap<&str, Vec<Address>>,
+        gas: &GasCost<'_>,
+    ) -> Option<(f64, f64)> {
+        let source = |dex: &str| self.sources.iter().find(|s| s.name() == dex);
+        let fee_bps = |dex: &str| {
+            quotes
+                .iter()
+                .find(|(q, _)| q.dex == dex)
+                .map_or(0, |(q, _)| q.fee_bps)
+        };
+        let (Some(buy), Some(sell), Some(buy_path), Some(sell_path)) = (
+            source(buy_dex),
+            source(sell_dex),
+            routes.get(buy_dex),
+            routes.get(sell_dex),
+        ) else {
+            return None;
+        };
+        let gas_usdc = gas.usdc(buy_dex, sell_dex);
+        let mut best: Option<(f64, f64)> = None;
+        for &size in &self.cfg.settings.trade_size_sweep {
+            let usdc_in = self.quote_units(size);
+            throttle(self.limiter.as_deref(), 2).await;
+            let usdc_out = match price::quote_round_trip(
+                buy.as_ref(),
+                buy_path,
+                sell.as_ref(),
+                sell_path,
+                usdc_in,
+            )
+            .await
+            {
+                Ok((_, usdc_out)) => usdc_out,
+                Err(err) => {
+                    debug!(
+                        buy_dex,
+                        sell_dex,
+                        size_usdc = size,
+                        ?err,
+                        "Sweep size not quoted"
+                    );
+                    continue;
+                }
+            };
+            let fees =
+                arb::leg_fee(usdc_in, fee_bps(buy_dex)) + arb::leg_fee(usdc_out, fee_bps(sell_dex));
+            let net = self.quote_amount(usdc_out) - size - self.quote_amount(fees) - gas_usdc;
+            debug!(
+                buy_dex,
+                sell_dex,
+                size_usdc = size,
+                net_profit_usdc = net,
+                "Sweep size quoted"
+            );
+            if best.is_none_or(|(_, best_net)| net > best_net) {
+                best = Some((size, net));
+            }
+        }
+        if let Some((size, net)) = best {
+            info!(
+                pair = self.pair.as_str(),
+                buy_dex,
+                sell_dex,
+                optimal_size_usdc = size,
+                net_profit_usdc = net,
+                "Best sweep size: {} USDC nets {:.6} USDC",
+                size,
+                net
+            );
+        }
+        best
+    }
+
+    /// Dry-runs the round trip of `spread`, starting from the buy venue's quote,
+    /// net of the route's `gas_usdc`.
+    ///
+    /// `Some(false)` when a leg reverts, returns less than its `getAmountsOut`
+    /// by more than `max_quote_shortfall_bps`, or the simulated profit falls
+    /// short of `profit_usdc` by more than the tolerance; `None` when the
+    /// simulation could not be run at all. The larger leg shortfall, in basis
+    /// points, comes with any completed simulation.
+    async fn simulate(
+        &self,
+        simulator: &Simulator,
+        spread: &Spread<'_>,
+        routes: &HashMap<&str, Vec<Address>>,
+        usdc_in: U256,
+        profit_usdc: f64,
+        gas_usdc: f64,
+    ) -> (Option<bool>, Option<f64>) {
+        let (buy_dex, sell_dex) = (spread.buy_dex, spread.sell_dex);
+        let result = simulator
+            .round_trip(
+                buy_dex,
+                routes.get(buy_dex).map_or(&[][..], |r| r),
+                sell_dex,
+                routes.get(sell_dex).map_or(&[][..], |r| r),
+                usdc_in,
+            )
+            .await;
+        match result {
+            Ok(trip) => {
+                let shortfall = trip.quote_shortfall_bps();
+                let mut quotes_hold = true;
+                for ((leg, dex), short_bps) in [("buy", buy_dex), ("sell", sell_dex)]
+                    .into_iter()
+                    .zip(shortfall)
+                {
+                    if short_bps > simulator.max_quote_shortfall_bps() {
+                        quotes_hold = false;
+                        warn!(
+                            leg,
+                            dex,
+                            shortfall_bps = short_bps,
+                            "Simulated swap returns less than getAmountsOut; the token may charge a transfer fee"
+                        );
+                    }
+                }
+                let simulated = trip.profit_usdc(gas_usdc, self.quote_decimals);
+                let ok = quotes_hold && simulated >= profit_usdc - simulator.tolerance_usdc();
+                let worst = shortfall[0].max(shortfall[1]);
+                if simulated >= profit_usdc - simulator.tolerance_usdc() {
+                    info!(
+                        buy_dex,
+                        sell_dex,
+                        simulated,
+                        quoted = profit_usdc,
+                        "Round trip simulated"
+                    );
+                } else {
+                    warn!(
+                        buy_dex,
+                        sell_dex,
+                        simulated,
+                        quoted = profit_usdc,
+                        "Simulated profit falls short of the quote"
+                    );
+                }
+                (Some(ok), Some(worst))
+            }
+            Err(SimulationError::Reverted(reason)) => {
+                warn!(buy_dex, sell_dex, %reason, "Simulated round trip reverted");
+                (Some(false), None)
+            }
+            Err(err) => {
+                warn!(buy_dex, sell_dex, %err, "Round trip not simulated");
+                (None, None)
+            }
+        }
+    }
+
+    /// Executes the recorded opportunity `opportunity_id` and queues the attempt
+    /// for the writer; the wallet must hold `gas_usdc` worth of the native coin.
+    ///
+    /// Errors are logged and never reach the loop. Returns whether the attempt
+    /// got past the safety checks, which starts the direction's cooldown.
+    async fn execute(
+        &self,
+        executor: &Executor,
+        spread: &Spread<'_>,
+        routes: &HashMap<&str, Vec<Address>>,
+        usdc_in: U256,
+        gas_usdc: f64,
+        opportunity_id: i64,
+    ) -> bool {
+        let (buy_dex, sell_dex) = (spread.buy_dex, spread.sell_dex);
+        let loss_today = match self.store.realized_loss_today(self.chain_id).await {
+            Ok(loss) => loss,
+            Err(err) => {
+                error!(%err, "Could not read today's losses; not executing");
+                return false;
+            }
+        };
+        let trade = Trade {
+            buy_dex,
+            buy_path: routes.get(buy_dex).map_or(&[][..], |r| r),
+            sell_dex,
+            sell_path: routes.get(sell_dex).map_or(&[][..], |r| r),
+            usdc_in,
+            // The native coin has 18 decimals on every supported chain.
+            gas_reserve: arb::amount_to_units(gas_usdc / self.cfg.settings.matic_price_usdc, 18),
+        };
+        let record = executor.execute(&trade, loss_today).await;
+        match record.status {
+            "failed" => warn!(buy_dex, sell_dex, error = ?record.error, "Execution failed"),
+            "skipped" => info!(buy_dex, sell_dex, reason = ?record.error, "Execution skipped"),
+            status => info!(buy_dex, sell_dex, status, "Execution recorded"),
+        }
+        let executed = record.status != "skipped";
+        self.write(DbWrite::InsertExecution(
+            opportunity_id,
+            self.chain_id,
+            Box::new(record),
+        ))
+        .await;
+        executed
+    }
+
+    /// Quotes every venue once and records any qualifying opportunity.
+    ///
+    /// Returns whether an opportunity was saved and whether the RPC looked down.
+    async fn tick(&mut self) -> BotResult<TickOutcome> {
+        self.formatter.start();
+        let mut report = TickReport::new();
+        let outcome = self.evaluate(&mut report).await;
+        if outcome.is_ok() {
+            self.formatter.render(&self.pair, &report);
+            if !matches!(report.verdict, Verdict::InvalidPrices) {
+                self.close_spreads();
+            }
+        }
+        outcome
+    }
+
+    /// Ends the throttle's tick, sending a summary of each spread that closed
+    /// when `close_summary` is set.
+    fn close_spreads(&mut self) {
+        let Some(throttle) = &mut self.throttle else {
+            return;
+        };
+        let summaries = throttle.close_summary();
+        for closed in throttle.end_tick() {
+            let (buy_dex, sell_dex) = closed.direction;
+            info!(
+                pair = self.pair.as_str(),
+                buy_dex = buy_dex.as_str(),
+                sell_dex = sell_dex.as_str(),
+                open_secs = closed.open_for.as_secs(),
+                peak_profit_usdc = closed.peak_profit_usdc,
+                suppressed = closed.suppressed,
+                "Spread closed"
+            );
+            if summaries {
+                self.notifier.notify_closed(SpreadClosedAlert {
+                    event: "spread_closed",
+                    pair: self.pair.clone(),
+                    buy_dex,
+                    sell_dex,
+                    open_secs: closed.open_for.as_secs(),
+                    peak_profit_usdc: closed.peak_profit_usdc,
+                    suppressed: closed.suppressed,
+                    timestamp: Utc::now().to_rfc3339(),
+                });
+            }
+        }
+    }
+
+    /// Replaces `matic_price_usdc` with the live price once the cached one is
+    /// `matic_price_cache_ticks` old; a failed fetch keeps the last price.
+    async fn refresh_matic_price(&mut self) {
+        let cache_ticks = self.cfg.settings.matic_price_cache_ticks;
+        let Some(pricer) = self
+            .matic_pricer
+            .as_mut()
+            .filter(|p| p.due(self.ticks, cache_ticks))
+        else {
+            return;
+        };
+        // getAmountsOut plus decimals().
+        throttle(self.limiter.as_deref(), 2).await;
+        match pricer.fetch(self.ticks).await {
+            Ok(price) => {
+                debug!(matic_price_usdc = price, "MATIC price fetched");
+                self.cfg.settings.matic_price_usdc = price;
+            }
+            Err(err) => warn!(
+                %err,
+                matic_price_usdc = self.cfg.settings.matic_price_usdc,
+                "MATIC price unavailable; keeping the last one"
+            ),
+        }
+    }
+
+    /// Body of [`Self::tick`]; fills `report` for the formatter as it goes.
+    async fn evaluate(&mut self, report: &mut TickReport) -> BotResult<TickOutcome> {
+        self.ticks += 1;
+        let warmup_ticks = self.cfg.settings.warmup_ticks;
+        if warmup_ticks > 0 && self.ticks == warmup_ticks + 1 {
+            info!(
+                pair = self.pair.as_str(),
+                warmup_ticks, "Warmup complete; recording opportunities"
+            );
+        }
+        throttle(self.limiter.as_deref(), 1).await;
+        let block_number = self
+            .provider
+            .get_block_number()
+            .await
+            .ok()
+            .map(|b| b.as_u64());
+        report.block = block_number;
+        // Every venue reads the same state, so the spread carries no time skew.
+        let pinned = block_number.filter(|_| self.cfg.settings.pin_quote_block);
+        if self.cfg.settings.pin_quote_block && pinned.is_none() {
+            warn!(
+                pair = self.pair.as_str(),
+                "Block number unavailable; quoting at the latest block"
+            );
+        }
+        self.refresh_matic_price().await;
+        if !self.cfg.settings.dry_run {
+            self.write(DbWrite::MarkExpired(Utc::now().timestamp_millis() as u64))
+                .await;
+        }
+
+        let mut quotes = Vec::with_capacity(self.sources.len());
+        let mut routes = HashMap::new();
+        let mut rpc_errors = 0;
+        let mut stale = HashSet::new();
+        let mut prices = Vec::with_capacity(self.sources.len());
+        let mut tick_prices = Vec::with_capacity(self.sources.len());
+        for (i, source) in self.sources.iter().enumerate() {
+            let dex = source.name();
+            // One request per path candidate.
+            let requests = self.paths[i].len() as u32;
+            if let Some(limiter) = &self.limiter
+                && !limiter.acquire_within(requests, QUOTE_BUDGET_WAIT).await
+            {
+                warn!(
+                    dex,
+                    pair = self.pair.as_str(),
+                    "RPC rate limit reached; skipping this DEX for the tick"
+                );
+                continue;
+            }
+            let started = Instant::now();
+            let mut result =
+                price::fetch_best_price(source.as_ref(), self.trade_size, &self.paths[i], pinned)
+                    .await;
+            // Transient failures get one immediate retry; reverts and ABI mistakes
+            // would fail the same way again.
+            if let Err(err) = &result
+                && err.is_transient()
+            {
+                debug!(dex, pair = self.pair.as_str(), %err, "Quote failed; retrying once");
+                throttle(self.limiter.as_deref(), requests).await;
+                result = price::fetch_best_price(
+                    source.as_ref(),
+                    self.trade_size,
+                    &self.paths[i],
+                    pinned,
+                )
+                .await;
+            }
+            self.metrics
+                .observe_rpc_latency(&self.endpoint, "quote", started.elapsed());
+            // Only failures of the endpoint count toward the circuit breaker.
+            if result.as_ref().is_err_and(PriceError::is_transient) {
+                self.job_metrics.rpc_errors.inc();
+                rpc_errors += 1;
+            }
+            let route = self.winning_route(i, &result).to_vec();
+            let path = price::path_label(&route);
+            let price = resolve_quote(
+                dex,
+                &self.pair,
+                result.map(|(amount, _)| amount),
+                &mut self.no_path_warned,
+            );
+            if price.is_zero() {
+                continue;
+            }
+
+            routes.insert(dex, route);
+            let repeats = count_repeats(&mut self.repeated_quotes, dex, price);
+            let threshold = self.cfg.settings.stale_price_tick_threshold;
+            if threshold > 0 && repeats >= threshold {
+                // Warn once per `threshold` repeats rather than every tick.
+                if repeats.is_multiple_of(threshold) {
+                    warn!(
+                        dex,
+                        pair = self.pair.as_str(),
+                        stale_ticks = repeats,
+                        "Quote unchanged for {} ticks; the RPC may be serving cached responses",
+                        repeats
+                    );
+                }
+                stale.insert(dex);
+            }
+            let price_usdc = arb::units_to_amount(price, self.quote_decimals);
+            self.metrics
+                .record_price(self.chain_id, &self.pair, dex, price_usdc);
+            let last = LastPrice {
+                dex: dex.to_string(),
+                pair: self.pair.clone(),
+                price_usdc,
+                at: Utc::now().to_rfc3339(),
+            };
+            let key = (dex.to_string(), self.pair.clone());
+            self.prices
+                .lock()
+                .unwrap()
+                .insert(key.clone(), last.clone());
+            tick_prices.push(last.clone());
+            self.last_prices.insert(key, last);
+            let impermanent_loss_pct = self.cfg.settings.lp_entry_price.map(|entry| {
+                arb::compute_impermanent_loss(entry, price_usdc / self.trade_size_weth)
+            });
+            report.quotes.push(QuoteReport {
+                dex: dex.to_string(),
+                price: price_usdc,
+                path: path.clone(),
+                impermanent_loss_pct,
+            });
+            prices.push(db::PriceRecord {
+                dex: dex.to_string(),
+                pair: self.pair.clone(),
+                amount_in: self.trade_size_weth,
+                price_usdc,
+                // The quote is for the whole trade size, so it is the volume.
+                volume_usdc: price_usdc,
+                block_number,
+                impermanent_loss_pct,
+            });
+            quotes.push((
+                Quote {
+                    dex,
+                    amount_out: price,
+                    fee_bps: self.fee_bps[i],
+                },
+                path,
+            ));
+        }
+
+        if !tick_prices.is_empty() {
+            let _ = self.events.send(StreamEvent::Prices(tick_prices));
+        }
+        self.base_price = (!prices.is_empty()).then(|| {
+            prices.iter().map(|p| p.price_usdc).sum::<f64>()
+                / (prices.len() as f64 * self.trade_size_weth)
+        });
+        if self.cfg.settings.record_prices && !prices.is_empty() && !self.warming_up() {
+            let tick = PriceTick {
+                chain_id: self.chain_id,
+                at: Utc::now(),
+                records: prices.clone(),
+            };
+            if self.cfg.settings.twap_window_secs.is_some() {
+                // The TWAP window read below must see this tick's prices.
+                let (reply, committed) = oneshot::channel();
+                self.write(DbWrite::InsertPrices(tick, Some(reply))).await;
+                let _ = committed.await;
+            } else {
+                self.write(DbWrite::InsertPrices(tick, None)).await;
+            }
+        }
+
+        let mut outcome = TickOutcome {
+            recorded: false,
+            rpc_failed: quotes.is_empty() && rpc_errors > 0,
+            spread_usdc: None,
+        };
+        let liquidity = match self.cfg.settings.min_pool_liquidity_usdc {
+            Some(min_usdc) if quotes.len() >= 2 => {
+                let liquidity = self
+                    .filter_liquidity(&mut quotes, &mut routes, min_usdc, pinned)
+                    .await;
+                if quotes.len() < 2 {
+                    info!(
+                        pair = self.pair.as_str(),
+                        "Fewer than two venues with enough liquidity; skipping the pair this tick"
+                    );
+                }
+                liquidity
+            }
+            _ => HashMap::new(),
+        };
+        if quotes.len() < 2 {
+            return Ok(outcome);
+        }
+
+        let amounts = quotes.iter().map(|(q, _)| q.amount_out);
+        let (Some(lowest), Some(highest)) = (amounts.clone().min(), amounts.max()) else {
+            return Ok(outcome);
+        };
+        let spread_usdc = self.quote_amount(highest - lowest);
+        outcome.spread_usdc = Some(spread_usdc);
+        self.spread_history.push_back(spread_usdc);
+        if self.spread_history.len() > SPREAD_HISTORY_LEN {
+            self.spread_history.pop_front();
+        }
+        let trend = arb::spread_trend(&self.spread_history);
+        let anomaly = self.anomalies.as_mut().and_then(|detector| {
+            let prices: Vec<(&str, f64)> = prices
+                .iter()
+                .map(|p| (p.dex.as_str(), p.price_usdc))
+                .collect();
+            detector.observe(spread_usdc, &prices)
+        });
+        if let Some(anomaly) = &anomaly {
+            warn!(pair = self.pair.as_str(), %anomaly, "Anomalous tick; a pool may be stale or broken");
+        }
+        let gas = self.gas_cost(&routes).await;
+        let candidates: Vec<Quote> = quotes.iter().map(|(q, _)| *q).collect();
+        let decision = arb::decide_best(
+            &candidates,
+            |buy_dex, sell_dex| gas.quote_units(buy_dex, sell_dex, self.quote_decimals),
+            self.quote_units(self.min_profit_usdc()),
+            |dex| self.priority(dex),
+            self.quote_units(self.cfg.settings.priority_tie_threshold_usdc),
+        );
+        let spread = match decision {
+            Decision::NoSpread => {
+                report.verdict = Verdict::NoSpread;
+                return Ok(outcome);
+            }
+            Decision::BelowThreshold(spread) | Decision::Opportunity(spread) => spread,
+        };
+
+        // Kept for the recorded row when the same direction wins the ranking.
+        let sweep = if self.cfg.settings.trade_size_sweep.is_empty() {
+            None
+        } else {
+            let (buy_dex, sell_dex) = (spread.buy_dex, spread.sell_dex);
+            let best = self
+                .sweep_sizes(buy_dex, sell_dex, &quotes, &routes, &gas)
+                .await;
+            Some(((buy_dex, sell_dex), best))
+        };
+
+        let profit_usdc = self.quote_amount(spread.net_profit);
+        self.job_metrics.net_profit_last.set(profit_usdc);
+        report.net_profit_usdc = Some(profit_usdc);
+
+        if !matches!(decision, Decision::Opportunity(_)) {
+            report.verdict = Verdict::BelowThreshold;
+            return Ok(outcome);
+        }
+
+        let record_anomaly = self
+            .cfg
+            .settings
+            .anomaly_filter
+            .as_ref()
+            .is_some_and(|filter| filter.record);
+        if anomaly.is_some() && !record_anomaly {
+            report.verdict = Verdict::Filtered("anomalous tick".into());
+            return Ok(outcome);
+        }
+
+        // Every qualifying venue pair competes on score; only the top one is recorded.
+        let mut ranked = self.rank_opportunities(&quotes, &gas);
+        if let Some(oracle) = &self.oracle {
+            let reference = match oracle.latest_price().await {
+                Ok(price) => price,
+                Err(err) => {
+                    warn!(pair = self.pair.as_str(), %err, "Oracle read failed; not recording this tick");
+                    report.verdict = Verdict::Filtered("oracle unavailable".into());
+                    return Ok(outcome);
+                }
+            };
+            ranked.retain(|(spread, _)| self.within_oracle_band(&quotes, spread, reference));
+        }
+        if self.cfg.settings.round_trip_quotes && !ranked.is_empty() {
+            ranked = self.round_trips(&quotes, &routes, &ranked, &gas).await;
+            let Some((cycle, _)) = ranked.first() else {
+                report.verdict = Verdict::Filtered("round trip unprofitable".into());
+                return Ok(outcome);
+            };
+            report.net_profit_usdc = Some(self.quote_amount(cycle.net_profit));
+        }
+        self.prefer_priority(&mut ranked);
+        let Some(&(spread, score)) = ranked.first() else {
+            report.verdict = Verdict::Filtered("oracle deviation".into());
+            return Ok(outcome);
+        };
+        for (spread, score) in ranked.iter().skip(1) {
+            info!(
+                pair = self.pair.as_str(),
+                buy_dex = spread.buy_dex,
+                sell_dex = spread.sell_dex,
+                score,
+                "Lower-ranked opportunity, not recorded"
+            );
+        }
+        let profit_usdc = self.quote_amount(spread.net_profit);
+        let (buy_on, sell_on) = (spread.buy_dex, spread.sell_dex);
+        let gas_usdc = gas.usdc(buy_on, sell_on);
+
+        // Warmup ticks store no prices, so their window could come up empty.
+        if let Some(window) = self.cfg.settings.twap_window_secs
+            && !self.warming_up()
+        {
+            let stats = match self
+                .store
+                .opportunity_stats(buy_on, &self.pair, window)
+                .await
+            {
+                Ok(buy) => self
+                    .store
+                    .opportunity_stats(sell_on, &self.pair, window)
+                    .await
+                    .map(|sell| (buy, sell)),
+                Err(err) => Err(err),
+            };
+            // An empty window or a failed read skips this tick, not the scanner.
+            let (buy, sell) = match stats {
+                Ok(stats) => stats,
+                Err(err) => {
+                    warn!(pair = self.pair.as_str(), %err, "TWAP read failed; not recording this tick");
+                    report.verdict = Verdict::Filtered("TWAP unavailable".into());
+                    return Ok(outcome);
+                }
+            };
+            let twap_spread = (sell.twap - buy.twap).abs();
+            info!(
+                "TWAP ({}s): {} {:.6} | {} {:.6} | spread {:.6} {}",
+                window, buy_on, buy.twap, sell_on, sell.twap, twap_spread, self.quote_symbol
+            );
+            for stats in [&buy, &sell] {
+                info!(
+                    dex = %stats.dex,
+                    pair = %stats.pair,
+                    twap = stats.twap,
+                    vwap = stats.vwap,
+                    "VWAP ({}s): {:.6} {}",
+                    window,
+                    stats.vwap,
+                    self.quote_symbol
+                );
+            }
+            if twap_spread <= self.min_profit_usdc() {
+                report.verdict = Verdict::Filtered("TWAP spread too small".into());
+                return Ok(outcome);
+            }
+        }
+
+        let path_of = |dex: &str| {
+            quotes
+                .iter()
+                .find(|(q, _)| q.dex == dex)
+                .map(|(_, path)| path.clone())
+                .unwrap_or_default()
+        };
+        let (buy_path, sell_path) = (path_of(buy_on), path_of(sell_on));
+        if let Some(search) = &self.cfg.settings.trade_size_search {
+            let sell_price_usdc = quotes
+                .iter()
+                .find(|(q, _)| q.dex == sell_on)
+                .map(|(q, _)| self.quote_amount(q.amount_out))
+                .unwrap_or_default();
+            self.log_optimal_size(search, &spread, &routes, sell_price_usdc, gas_usdc)
+                .await;
+        }
+        let optimal_size_usdc = match sweep {
+            Some((direction, best)) if direction == (buy_on, sell_on) => best,
+            Some(_) => {
+                self.sweep_sizes(buy_on, sell_on, &quotes, &routes, &gas)
+                    .await
+            }
+            None => None,
+        }
+        .map(|(size, _)| size);
+        let usdc_in = quotes
+            .iter()
+            .find(|(q, _)| q.dex == buy_on)
+            .map(|(q, _)| q.amount_out)
+            .unwrap_or_default();
+        let usdc_out = quotes
+            .iter()
+            .find(|(q, _)| q.dex == sell_on)
+            .map(|(q, _)| q.amount_out)
+            .unwrap_or_default();
+        // The modelled gas picked the spread; an estimate of the actual swaps has
+        // the final say before it is recorded or executed.
+        let (profit_usdc, gas_usdc) = match self.gas_estimator.as_mut() {
+            Some(estimator) => {
+                let estimate = estimator
+                    .units(self.ticks, &spread, &routes, usdc_in, self.trade_size)
+                    .await;
+                throttle(self.limiter.as_deref(), 1).await;
+                let gas_price = self.provider.get_gas_price().await;
+                match (estimate, gas_price) {
+                    (Ok(units), Ok(wei)) => {
+                        let estimated_usdc = units as f64
+                            * wei.as_u128() as f64
+                            * 1e-18
+                            * self.cfg.settings.matic_price_usdc;
+                        info!(
+                            buy_dex = buy_on,
+                            sell_dex = sell_on,
+                            gas_units = units,
+                            gas_cost_usdc = estimated_usdc,
+                            modelled_usdc = gas_usdc,
+                            "Gas estimated"
+                        );
+                        let profit = profit_usdc + gas_usdc - estimated_usdc;
+                        if profit <= self.min_profit_usdc() {
+                            report.net_profit_usdc = Some(profit);
+                            report.verdict =
+                                Verdict::Filtered("estimated gas exceeds profit".into());
+                            return Ok(outcome);
+                        }
+                        (profit, estimated_usdc)
+                    }
+                    (Err(err), _) => {
+                        warn!(buy_dex = buy_on, sell_dex = sell_on, %err, "Gas not estimated; keeping the modelled cost");
+                        (profit_usdc, gas_usdc)
+                    }
+                    (_, Err(err)) => {
+                        warn!(%err, "Gas price unavailable; keeping the modelled gas cost");
+                        (profit_usdc, gas_usdc)
+                    }
+                }
+            }
+            None => (profit_usdc, gas_usdc),
+        };
+        // A flashloan executor borrows the buy leg's USDC and repays it with the premium.
+        let profit_usdc =
+            if self.executor.is_some() && self.cfg.execution.flashloan_executor.is_some() {
+                let premium_usdc = self.quote_amount(crate::flashloan::premium(
+                    usdc_in,
+                    self.cfg.execution.flashloan_premium_bps,
+                ));
+                let profit = profit_usdc - premium_usdc;
+                if profit <= self.min_profit_usdc() {
+                    info!(
+                        buy_dex = buy_on,
+                        sell_dex = sell_on,
+                        premium_usdc,
+                        "Flashloan premium leaves no profit above min_profit_usdc"
+                    );
+                    report.net_profit_usdc = Some(profit);
+                    report.verdict = Verdict::Filtered("flashloan premium".into());
+                    return Ok(outcome);
+                }
+                profit
+            } else {
+                profit_usdc
+            };
+        let (buy_fee_bps, sell_fee_bps) = (self.leg_fee_bps(buy_on), self.leg_fee_bps(sell_on));
+        let buy_fee_usdc = buy_fee_bps.map(|bps| self.quote_amount(arb::leg_fee(usdc_in, bps)));
+        let sell_fee_usdc = sell_fee_bps.map(|bps| self.quote_amount(arb::leg_fee(usdc_out, bps)));
+        let break_even_bps = buy_fee_bps.zip(sell_fee_bps).map(|(buy, sell)| {
+            arb::break_even_spread_bps(buy, sell, gas_usdc, self.quote_amount(usdc_in))
+        });
+        let analytic_size_usdc = self.analytic_size(buy_on, sell_on, &quotes, &routes).await;
+        let (simulated_ok, quote_shortfall_bps) = match &self.simulator {
+            Some(simulator) => {
+                self.simulate(simulator, &spread, &routes, usdc_in, profit_usdc, gas_usdc)
+                    .await
+            }
+            None => (None, None),
+        };
+        let now = Utc::now();
+        let timestamp = now.to_rfc3339();
+        let expires_at = now + Duration::from_secs(self.cfg.settings.opportunity_ttl_secs);
+        let (report_profit, report_currency) = self.report_profit(profit_usdc);
+        let dry_run = self.cfg.settings.dry_run;
+        let potentially_stale = stale.contains(buy_on) || stale.contains(sell_on);
+        let opportunity = db::Opportunity {
+            chain_id: self.chain_id,
+            pair: self.pair.clone(),
+            buy_dex: buy_on.to_string(),
+            sell_dex: sell_on.to_string(),
+            profit_usdc,
+            profit_pct: profit_usdc / self.quote_amount(usdc_in) * 100.0,
+            gas_cost_usdc: gas_usdc,
+            score,
+            buy_path,
+            sell_path,
+            simulated_ok,
+            potentially_stale,
+            anomaly: anomaly.is_some(),
+            optimal_size_usdc,
+            buy_fee_usdc,
+            sell_fee_usdc,
+            break_even_bps,
+            analytic_size_usdc,
+            timestamp: timestamp.clone(),
+            timestamp_ms: now.timestamp_millis(),
+            expires_at: expires_at.to_rfc3339(),
+            expires_at_ms: expires_at.timestamp_millis(),
+            buy_liquidity_usdc: liquidity.get(buy_on).copied(),
+            sell_liquidity_usdc: liquidity.get(sell_on).copied(),
+            report_profit,
+            report_currency: report_currency.to_string(),
+            quote_shortfall_bps,
+            trend: trend.to_string(),
+        };
+        log_opportunity(&opportunity, dry_run, block_number);
+        let direction = (buy_on.to_string(), sell_on.to_string());
+        if self.warming_up() {
+            // Seeds the throttle, so a spread open since startup does not alert
+            // the moment warmup ends.
+            if let Some(throttle) = &mut self.throttle {
+                throttle.offer(&direction, profit_usdc, Instant::now());
+            }
+            info!(
+                tick = self.ticks,
+                warmup_ticks = self.cfg.settings.warmup_ticks,
+                "Warming up; not recording, notifying or executing"
+            );
+            report.verdict = Verdict::Filtered("warming up".into());
+            return Ok(outcome);
+        }
+        if let Some(anomaly) = anomaly {
+            if dry_run {
+                info!(%anomaly, "Anomalous opportunity; not notifying");
+                self.record_dry_run(opportunity).await;
+            } else {
+                self.write(DbWrite::InsertOpportunity(Box::new(opportunity), None))
+                    .await;
+                info!(%anomaly, "Anomalous opportunity saved; not notifying or executing");
+            }
+            report.verdict = Verdict::Filtered("anomalous tick".into());
+            return Ok(outcome);
+        }
+        let alert = OpportunityAlert {
+            buy_dex: buy_on.to_string(),
+            sell_dex: sell_on.to_string(),
+            profit_usdc,
+            timestamp,
+            dry_run,
+        };
+        let send = self
+            .throttle
+            .as_mut()
+            .is_none_or(|throttle| throttle.offer(&direction, profit_usdc, Instant::now()));
+        if send {
+            self.notifier.notify(alert);
+        } else {
+            self.job_metrics.notifications_suppressed.inc();
+            debug!(
+                buy_dex = buy_on,
+                sell_dex = sell_on,
+                profit_usdc,
+                "Spread still open; alert suppressed by notify_throttle"
+            );
+        }
+        if dry_run {
+            self.record_dry_run(opportunity).await;
+            report.verdict = Verdict::Opportunity;
+            return Ok(TickOutcome {
+                recorded: true,
+                ..outcome
+            });
+        }
+        let (id_sender, id) = oneshot::channel();
+        self.write(DbWrite::InsertOpportunity(
+            Box::new(opportunity.clone()),
+            Some(id_sender),
+        ))
+        .await;
+        info!("Opportunity saved!");
+        // Streamed once the writer has committed the row; only a trade, which
+        // references the row, waits for it.
+        let publish = move |events: &broadcast::Sender<StreamEvent>, id: i64| {
+            // Sending only fails when no stream client is connected.
+            let _ = events.send(StreamEvent::Opportunity(db::RecentOpportunity::recorded(
+                id,
+                &opportunity,
+            )));
+        };
+        let opportunity_id = match &self.executor {
+            Some(_) => id.await.ok().inspect(|id| publish(&self.events, *id)),
+            None => {
+                let events = self.events.clone();
+                tokio::spawn(async move {
+                    if let Ok(id) = id.await {
+                        publish(&events, id);
+                    }
+                });
+                None
+            }
+        };
+        let cooldown = Duration::from_secs(self.cfg.execution.cooldown_secs);
+        let acted = match (&self.executor, opportunity_id) {
+            (Some(_), None) => {
+                warn!("Opportunity not stored; not executing");
+                false
+            }
+            (Some(_), _) if self.execution_paused.load(Ordering::Relaxed) => {
+                info!(
+                    buy_dex = buy_on,
+                    sell_dex = sell_on,
+                    "[PAUSED] Execution paused; not executing"
+                );
+                false
+            }
+            (Some(_), _) if simulated_ok == Some(false) => {
+                info!("Round trip failed simulation; not executing");
+                false
+            }
+            (Some(_), _)
+                if self.cfg.settings.require_rising_trend && trend != TrendDirection::Rising =>
+            {
+                info!(%trend, "Spread not rising; not executing");
+                false
+            }
+            (Some(_), _)
+                if self
+                    .last_action
+                    .get(&direction)
+                    .is_some_and(|at| at.elapsed() < cooldown) =>
+            {
+                info!(
+                    buy_dex = buy_on,
+                    sell_dex = sell_on,
+                    "Direction cooling down; not executing"
+                );
+                false
+            }
+            (Some(executor), Some(opportunity_id)) => {
+                let committed = self.quote_amount(usdc_in);
+                match self.exposure.reserve(
+                    committed,
+                    self.cfg.execution.max_exposure_usdc,
+                    cooldown,
+                ) {
+                    Ok(ticket) => {
+                        let acted = self
+                            .execute(
+                                executor,
+                                &spread,
+                                &routes,
+                                usdc_in,
+                                gas_usdc,
+                                opportunity_id,
+                            )
+                            .await;
+                        if !acted {
+                            self.exposure.release(ticket);
+                        }
+                        acted
+                    }
+                    Err(exposure_usdc) => {
+                        info!(
+                            exposure_usdc,
+                            trade_usdc = committed,
+                            cap_usdc = self.cfg.execution.max_exposure_usdc,
+                            "Exposure cap reached; not executing"
+                        );
+                        false
+                    }
+                }
+            }
+            (None, _) => false,
+        };
+        if acted {
+            self.last_action.insert(direction, Instant::now());
+        }
+        report.verdict = Verdict::Opportunity;
+        Ok(TickOutcome {
+            recorded: true,
+            ..outcome
+        })
+    }
+}
+
+/// Candidate paths of each venue in `dex`, in order: its own `path`, else the
+/// direct pair plus the `path_via` route; an aggregator gets the direct pair only.
+pub fn quote_paths(tokens: &TokenPair, dex: &[DexEntry]) -> BotResult<Vec<Vec<Vec<Address>>>> {
+    let weth = parse_address("tokens.weth", &tokens.weth)?;
+    let usdc = parse_address("tokens.usdc", &tokens.usdc)?;
+    let mut shared_paths = vec![vec![weth, usdc]];
+    if !tokens.path_via.is_empty() {
+        let mut routed = vec![weth];
+        for hop in &tokens.path_via {
+            routed.push(parse_address("tokens.path_via", hop)?);
+        }
+        routed.push(usdc);
+        shared_paths.push(routed);
+    }
+    let mut paths = Vec::with_capacity(dex.len());
+    for entry in dex {
+        // An aggregator finds its own route, so only the direct path is quoted.
+        if entry.kind == DexKind::Aggregator {
+            paths.push(vec![vec![weth, usdc]]);
+            continue;
+        }
+        let Some(path) = &entry.path else {
+            paths.push(shared_paths.clone());
+            continue;
+        };
+        let field = format!("dex {}: path", entry.name);
+        let path = path
+            .iter()
+            .map(|token| parse_address(&field, token))
+            .collect::<BotResult<Vec<Address>>>()?;
+        if path.first() != Some(&weth) || path.last() != Some(&usdc) {
+            return Err(BotError::Config(format!(
+                "dex {}: path must start at WETH and end at USDC",
+                entry.name
+            )));
+        }
+        paths.push(vec![path]);
+    }
+    Ok(paths)
+}
+
+/// Waits for `requests` of the endpoint's budget when it has a [`RateLimiter`].
+async fn throttle(limiter: Option<&RateLimiter>, requests: u32) {
+    if let Some(limiter) = limiter {
+        limiter.acquire(requests).await;
+    }
+}
+
+/// Records `price` as the latest quote of `dex` and returns how many ticks in
+/// a row it has now repeated exactly.
+fn count_repeats(repeated: &mut HashMap<String, (U256, u32)>, dex: &str, price: U256) -> u32 {
+    match repeated.get_mut(dex) {
+        Some((last, repeats)) if *last == price => {
+            *repeats += 1;
+            *repeats
+        }
+        _ => {
+            repeated.insert(dex.to_string(), (price, 0));
+            0
+        }
+    }
+}
+
+/// Collapses a quote result into a price, treating failures as zero.
+///
+/// Missing liquidity paths and ABI mistakes are reported once per (dex, pair)
+/// until the pair quotes successfully again; transient errors every time.
+fn resolve_quote(
+    dex: &str,
+    pair: &str,
+    result: Result<U256, PriceError>,
+    no_path_warned: &mut HashSet<(String, String)>,
+) -> U256 {
+    let key = (dex.to_string(), pair.to_string());
+    match result {
+        Ok(price) => {
+            no_path_warned.remove(&key);
+            price
+        }
+        Err(err @ (PriceError::NoLiquidityPath(_) | PriceError::Revert(_))) => {
+            if no_path_warned.insert(key) {
+                warn!(dex, pair, reason = %err, "No liquidity path; suppressing repeats");
+            }
+            U256::zero()
+        }
+        Err(err @ PriceError::AbiEncoding(_)) => {
+            if no_path_warned.insert(key) {
+                error!(dex, pair, %err, "Quote call does not match the venue's ABI; check abi_path; suppressing repeats");
+            }
+            U256::zero()
+        }
+        Err(err @ (PriceError::Rpc(_) | PriceError::EmptyResponse)) => {
+            error!(dex, pair, %err, "Error fetching price");
+            U256::zero()
+        }
+    }
+}
+
+/// Emits `opportunity` as one event with target `opportunity`, one field per
+/// column of its row plus `dry_run` and `block`; optional columns that are
+/// unset are left out.
+fn log_opportunity(opportunity: &db::Opportunity, dry_run: bool, block: Option<u64>) {
+    // No `..`: a new column does not compile until it is logged too.
+    let db::Opportunity {
+        chain_id,
+        pair,
+        buy_dex,
+        sell_dex,
+        profit_usdc,
+        profit_pct,
+        gas_cost_usdc,
+        score,
+        buy_path,
+        sell_path,
+        simulated_ok,
+        potentially_stale,
+        anomaly,
+        optimal_size_usdc,
+        buy_fee_usdc,
+        sell_fee_usdc,
+        break_even_bps,
+        analytic_size_usdc,
+        timestamp,
+        timestamp_ms,
+        expires_at,
+        expires_at_ms,
+        buy_liquidity_usdc,
+        sell_liquidity_usdc,
+        report_profit,
+        report_currency,
+        quote_shortfall_bps,
+        trend,
+    } = opportunity;
+    info!(
+        target: "opportunity",
+        dry_run,
+        chain_id,
+        pair = pair.as_str(),
+        buy_dex = buy_dex.as_str(),
+        sell_dex = sell_dex.as_str(),
+        profit_usdc,
+        profit_pct,
+        gas_cost_usdc,
+        score,
+        buy_path = buy_path.as_str(),
+        sell_path = sell_path.as_str(),
+        simulated_ok,
+        potentially_stale,
+        anomaly,
+        optimal_size_usdc,
+        buy_fee_usdc,
+        sell_fee_usdc,
+        break_even_bps,
+        analytic_size_usdc,
+        timestamp = timestamp.as_str(),
+        timestamp_ms,
+        expires_at = expires_at.as_str(),
+        expires_at_ms,
+        buy_liquidity_usdc,
+        sell_liquidity_usdc,
+        report_profit,
+        report_currency = report_currency.as_str(),
+        quote_shortfall_bps,
+        trend = trend.as_str(),
+        block,
+        "{}Arbitrage Opportunity: Buy on {} → Sell on {}",
+        if dry_run { "[DRY RUN] " } else { "" },
+        buy_dex,
+        sell_dex
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::db::DbPool;
+    use crate::price::ScriptedSource;
+    use crate::store::SqliteStore;
+    use std::sync::RwLock;
+    use tokio::task::JoinHandle;
+
+    /// A scanner quoting QuickSwap and SushiSwap from `script`, in USDC per
+    /// WETH and one output per tick, into the in-memory database `name`;
+    /// `settings` are appended to `[settings]`.
+    async fn scripted(
+        name: &str,
+        settings: &str,
+        script: [&[u64]; 2],
+    ) -> (Scanner, DbPool, JoinHandle<()>) {
+        let sources = ["QuickSwap", "SushiSwap"]
+            .into_iter()
+            .zip(script)
+            .map(|(dex, outputs)| {
+                let outputs = outputs
+                    .iter()
+                    .map(|&usdc| U256::from(usdc) * U256::exp10(6))
+                    .collect();
+                Box::new(ScriptedSource::new(dex.to_string(), outputs)) as Box<dyn PriceSource>
+            })
+            .collect();
+        scanning(name, settings, sources).await
+    }
+
+    /// A scanner of `sources`, standing for QuickSwap and SushiSwap, writing to
+    /// the in-memory database `name`; `settings` are appended to `[settings]`,
+    /// and replace its 1 WETH `trade_size` when they set one.
+    async fn scanning(
+        name: &str,
+        settings: &str,
+        sources: Vec<Box<dyn PriceSource>>,
+    ) -> (Scanner, DbPool, JoinHandle<()>) {
+        let trade_size = if settings.contains("trade_size") {
+            ""
+        } else {
+            "trade_size = \"1\""
+        };
+        let cfg = config::parse_config(&format!(
+            r#"
+            rpc_url = "http://127.0.0.1:1"
+
+            [[dex]]
+            name = "QuickSwap"
+            kind = "uniswap_v2"
+            address = "0xa5E0829CaCEd8fFDD4De3c43696c57f7d7a678ff"
+
+            [[dex]]
+            name = "SushiSwap"
+            kind = "uniswap_v2"
+            address = "0x1b02dA8Cb0d097eB8D57A175b88c7D8b47997506"
+
+            [tokens]
+            weth = "0x7ceb23fd6bc0add59e62ac25578270cff1b9f619"
+            usdc = "0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174"
+            weth_decimals = 18
+            usdc_decimals = 6
+
+            [settings]
+            min_profit_usdc = 5.0
+            est_gas_cost_usdc = 1.0
+            refresh_rate = 1
+            {}
+            {}
+            "#,
+            trade_size, settings
+        ))
+        .unwrap();
+        let chain = cfg.chains[0].clone();
+
+        let pool = db::open_pool(&format!("file:{}?mode=memory&cache=shared", name)).unwrap();
+        let store: Arc<dyn OpportunityStore> = Arc::new(SqliteStore::new(pool.clone()));
+        store.migrate().await.unwrap();
+        let (writes, writer) = db::spawn_writer(store.clone());
+        let services = ScanServices {
+            store,
+            writes,
+            abi: price::default_router_abi(),
+            metrics: Arc::new(Metrics::new().unwrap()),
+            events: broadcast::channel(16).0,
+            health: Arc::new(Health::new()),
+            prices: PriceBoard::default(),
+            exposure: Arc::new(ExposureLedger::default()),
+            execution_paused: Arc::new(AtomicBool::new(false)),
+        };
+        let target = ScanTarget {
+            job: None,
+            chain_id: chain.chain_id,
+            provider: Provider::<Http>::try_from(chain.rpc_url.as_str()).unwrap(),
+            dex: chain.dex,
+            tokens: chain.tokens[0].clone(),
+            state_file: None,
+            wallet: None,
+            limiter: None,
+        };
+        let shared = Arc::new(RwLock::new(cfg));
+        let scanner = Scanner::with_sources(shared, target, services, sources).unwrap();
+        (scanner, pool, writer)
+    }
+
+    /// Ticks `scanner` `ticks` times, then waits for its writes to commit.
+    async fn run_ticks(mut scanner: Scanner, ticks: usize, writer: JoinHandle<()>) {
+        for _ in 0..ticks {
+            scanner.tick().await.unwrap();
+        }
+        // The writer drains its queue once the last sender is gone.
+        drop(scanner);
+        writer.await.unwrap();
+    }
+
+    fn recorded(pool: &DbPool, table: &str) -> Vec<(String, String, f64)> {
+        let conn = pool.get().unwrap();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT buy_dex, sell_dex, profit_usdc FROM {} ORDER BY id",
+                table
+            ))
+            .unwrap();
+        stmt.query_map((), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    fn count(pool: &DbPool, table: &str) -> i64 {
+        pool.get()
+            .unwrap()
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), (), |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn scripted_ticks_record_qualifying_spreads() {
+        let (scanner, pool, writer) = scripted(
+            "scripted_ticks_record_qualifying_spreads",
+            "",
+            [&[4000, 4000, 4050], &[4030, 4001, 4000]],
+        )
+        .await;
+        run_ticks(scanner, 3, writer).await;
+
+        // The second tick's 1 USDC spread does not cover gas.
+        assert_eq!(
+            recorded(&pool, "arbitrage_bot"),
+            [
+                ("QuickSwap".to_string(), "SushiSwap".to_string(), 29.0),
+                ("SushiSwap".to_string(), "QuickSwap".to_string(), 49.0),
+            ]
+        );
+        assert_eq!(count(&pool, "price_history"), 6);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn quoted_output_is_the_recorded_volume() {
+        let (scanner, pool, writer) = scripted(
+            "quoted_output_is_the_recorded_volume",
+            "trade_size = \"2\"",
+            [&[8000], &[8010]],
+        )
+        .await;
+        run_ticks(scanner, 1, writer).await;
+
+        let conn = pool.get().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT amount_in, price_usdc, volume_usdc FROM price_history ORDER BY id")
+            .unwrap();
+        let rows: Vec<(f64, f64, f64)> = stmt
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        // 2 WETH for 8000 USDC trades 8000 USDC, not 2 × 8000.
+        assert_eq!(rows, [(2.0, 8000.0, 8000.0), (2.0, 8010.0, 8010.0)]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dry_run_records_prices_but_no_opportunities() {
+        let (scanner, pool, writer) = scripted(
+            "dry_run_records_prices_but_no_opportunities",
+            "dry_run = true\ntwap_window_secs = 300",
+            [&[4000, 4000, 4050], &[4030, 4001, 4000]],
+        )
+        .await;
+        run_ticks(scanner, 3, writer).await;
+
+        assert_eq!(count(&pool, "arbitrage_bot"), 0);
+        assert_eq!(count(&pool, db::DRY_RUN_TABLE), 0);
+        assert_eq!(count(&pool, "price_history"), 6);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dry_run_table_takes_the_opportunities_instead() {
+        let (scanner, pool, writer) = scripted(
+            "dry_run_table_takes_the_opportunities_instead",
+            "dry_run = true\ndry_run_table = true\ntwap_window_secs = 300",
+            [&[4000, 4000, 4050], &[4030, 4001, 4000]],
+        )
+        .await;
+        run_ticks(scanner, 3, writer).await;
+
+        assert_eq!(count(&pool, "arbitrage_bot"), 0);
+        assert_eq!(
+            recorded(&pool, db::DRY_RUN_TABLE),
+            [
+                ("QuickSwap".to_string(), "SushiSwap".to_string(), 29.0),
+                ("SushiSwap".to_string(), "QuickSwap".to_string(), 49.0),
+            ]
+        );
+    }
+
+    /// A writer appending to a shared buffer, for capturing log lines.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn opportunity_event_carries_every_column() {
+        let opportunity = db::Opportunity {
+            chain_id: 137,
+            pair: "WETH/USDC".into(),
+            buy_dex: "SushiSwap".into(),
+            sell_dex: "QuickSwap".into(),
+            profit_usdc: 44.5,
+            profit_pct: 1.1,
+            gas_cost_usdc: 0.2,
+            score: 3.0,
+            buy_path: "USDC>WETH".into(),
+            sell_path: "WETH>USDC".into(),
+            simulated_ok: Some(true),
+            potentially_stale: false,
+            anomaly: false,
+            optimal_size_usdc: Some(2000.0),
+            buy_fee_usdc: Some(12.0),
+            sell_fee_usdc: Some(12.1),
+            break_even_bps: Some(61.0),
+            analytic_size_usdc: Some(1800.0),
+            timestamp: "2025-10-06T12:00:00+00:00".into(),
+            timestamp_ms: 1_759_752_000_000,
+            expires_at: "2025-10-06T12:01:00+00:00".into(),
+            expires_at_ms: 1_759_752_060_000,
+            buy_liquidity_usdc: Some(1e6),
+            sell_liquidity_usdc: Some(2e6),
+            report_profit: 44.5,
+            report_currency: "USDC".into(),
+            quote_shortfall_bps: Some(3.0),
+            trend: "rising".into(),
+        };
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_current_span(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            log_opportunity(&opportunity, false, Some(77_000_000))
+        });
+
+        let line = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let event: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+        assert_eq!(event["target"], "opportunity");
+        let fields: HashSet<String> = event["fields"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        let mut expected: HashSet<String> = serde_json::to_value(&opportunity)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        expected.extend(["dry_run", "block", "message"].map(String::from));
+        assert_eq!(fields, expected);
+        assert_eq!(event["fields"]["expires_at_ms"], 1_759_752_060_000_i64);
+        assert_eq!(
+            event["fields"]["message"],
+            "Arbitrage Opportunity: Buy on SushiSwap → Sell on QuickSwap"
+        );
+    }
+
+    /// A venue whose every quote fails with `error()`, counting its calls.
+    struct FailingSource {
+        name: &'static str,
+        error: fn() -> PriceError,
+        calls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl PriceSource for FailingSource {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn quote_at(
+            &self,
+            _amount_in: U256,
+            _path: &[Address],
+            _block: Option<u64>,
+        ) -> Result<U256, PriceError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err((self.error)())
+        }
+    }
+
+    /// Ticks once with both venues failing with `error()`; returns the calls
+    /// each venue got, whether the tick counts against the circuit breaker,
+    /// and the venues whose failure is suppressed from now on.
+    async fn tick_failing(name: &str, error: fn() -> PriceError) -> (usize, bool, usize) {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let sources = ["QuickSwap", "SushiSwap"]
+            .into_iter()
+            .map(|name| {
+                Box::new(FailingSource {
+                    name,
+                    error,
+                    calls: calls.clone(),
+                }) as Box<dyn PriceSource>
+            })
+            .collect();
+        let (mut scanner, _pool, _writer) = scanning(name, "", sources).await;
+        let outcome = scanner.tick().await.unwrap();
+        assert!(!outcome.recorded);
+        let calls = calls.load(Ordering::SeqCst) / 2;
+        (calls, outcome.rpc_failed, scanner.no_path_warned.len())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn rpc_errors_retry_once_and_trip_the_breaker() {
+        let decision = tick_failing("rpc_errors_retry", || PriceError::Rpc("timeout".into())).await;
+        assert_eq!(decision, (2, true, 0));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn empty_responses_retry_once_and_trip_the_breaker() {
+        let decision = tick_failing("empty_responses_retry", || PriceError::EmptyResponse).await;
+        assert_eq!(decision, (2, true, 0));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reverts_skip_the_venue_without_retry() {
+        let decision = tick_failing("reverts_skip", || PriceError::Revert("no pair".into())).await;
+        assert_eq!(decision, (1, false, 2));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn missing_paths_skip_the_venue_without_retry() {
+        let decision = tick_failing("missing_paths_skip", || {
+            PriceError::NoLiquidityPath("no pool".into())
+        })
+        .await;
+        assert_eq!(decision, (1, false, 2));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn abi_mismatches_skip_the_venue_without_retry() {
+        let decision = tick_failing("abi_mismatches_skip", || {
+            PriceError::AbiEncoding("no getAmountsOut".into())
+        })
+        .await;
+        assert_eq!(decision, (1, false, 2));
+    }
+}
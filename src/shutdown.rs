@@ -0,0 +1,59 @@
+//! Signal-driven graceful shutdown.
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+/// Cancels `token` on the first Ctrl-C or SIGTERM; a second signal exits immediately.
+///
+/// Abort the returned handle once shutdown has completed: tokio wakes signal
+/// listeners while the runtime tears down, which would otherwise read as the
+/// second signal.
+pub fn spawn_signal_handler(token: CancellationToken) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut signals = match Signals::new() {
+            Ok(signals) => signals,
+            Err(err) => {
+                warn!(%err, "Failed to install signal handlers; graceful shutdown unavailable");
+                return;
+            }
+        };
+
+        signals.recv().await;
+        warn!("Shutdown requested; finishing the current tick (signal again to force exit)");
+        token.cancel();
+
+        signals.recv().await;
+        warn!("Second signal received; forcing exit");
+        std::process::exit(130);
+    })
+}
+
+/// Ctrl-C and, on Unix, SIGTERM.
+struct Signals {
+    #[cfg(unix)]
+    sigterm: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            sigterm: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?,
+        })
+    }
+
+    /// Waits for the next signal.
+    #[cfg(unix)]
+    async fn recv(&mut self) {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = self.sigterm.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn recv(&mut self) {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
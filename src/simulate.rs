@@ -0,0 +1,331 @@
+//! `eth_call` dry run of an opportunity's buy and sell swaps before it is recorded.
+
+use anyhow::Result;
+use ethers::abi::{Abi, parse_abi};
+use ethers::contract::Contract;
+use ethers::core::types::{Address, H256, U256, spoof};
+use ethers::core::utils::keccak256;
+use ethers::providers::call_raw::RawCall;
+use ethers::providers::{Http, Provider};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::arb;
+use crate::config::{self, DexEntry};
+use crate::error::BotResult;
+use crate::price::{self, PriceError};
+
+/// Storage slots of an ERC-20's `balanceOf` and `allowance` mappings.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct TokenSlots {
+    /// Slot of `mapping(address => uint256)` balances.
+    pub balance: u64,
+    /// Slot of `mapping(address => mapping(address => uint256))` allowances.
+    pub allowance: u64,
+}
+
+/// Round-trip simulation settings (`[simulation]`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulationConfig {
+    /// Address the swaps are simulated from.
+    pub from: String,
+    /// How far, in USDC, the simulated profit may fall short of the quoted one.
+    #[serde(default = "default_tolerance_usdc")]
+    pub tolerance_usdc: f64,
+    /// USDC storage layout; when set, `from` is funded and approved through state
+    /// overrides instead of needing a real balance and approval.
+    pub usdc_slots: Option<TokenSlots>,
+    /// WETH storage layout, as for `usdc_slots`.
+    pub weth_slots: Option<TokenSlots>,
+    /// How far, in basis points, a leg's simulated output may fall below the
+    /// router's `getAmountsOut` for the same input before the quote is flagged.
+    #[serde(default = "default_max_quote_shortfall_bps")]
+    pub max_quote_shortfall_bps: f64,
+}
+
+fn default_tolerance_usdc() -> f64 {
+    1.0
+}
+
+fn default_max_quote_shortfall_bps() -> f64 {
+    10.0
+}
+
+/// Why a round trip could not be confirmed.
+#[derive(Debug)]
+pub enum SimulationError {
+    /// A leg reverted: the quoted spread is not executable as-is.
+    Reverted(String),
+    /// `from` lacks the balance or router approval for a leg and the token has no
+    /// slot override configured.
+    MissingApproval(String),
+    /// A leg's venue is not a `uniswap_v2` router.
+    Unsupported(String),
+    /// Transport failures and other non-revert errors.
+    Rpc(String),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reverted(reason) => write!(f, "reverted: {}", reason),
+            Self::MissingApproval(reason) => write!(f, "missing approval: {}", reason),
+            Self::Unsupported(reason) => write!(f, "unsupported: {}", reason),
+            Self::Rpc(reason) => write!(f, "rpc: {}", reason),
+        }
+    }
+}
+
+impl From<PriceError> for SimulationError {
+    fn from(err: PriceError) -> Self {
+        match err {
+            PriceError::NoLiquidityPath(reason) | PriceError::Revert(reason) => {
+                Self::Reverted(reason)
+            }
+            PriceError::AbiEncoding(reason) => Self::Unsupported(reason),
+            err @ (PriceError::Rpc(_) | PriceError::EmptyResponse) => Self::Rpc(err.to_string()),
+        }
+    }
+}
+
+/// Amounts of a simulated USDC → WETH → USDC round trip, in token units.
+#[derive(Debug, Clone, Copy)]
+pub struct RoundTrip {
+    /// USDC spent on the buy leg.
+    pub usdc_in: U256,
+    /// WETH received on the buy leg and sold on the sell leg.
+    pub weth_mid: U256,
+    /// USDC received on the sell leg.
+    pub usdc_out: U256,
+    /// `getAmountsOut` of the buy leg for `usdc_in`.
+    pub weth_mid_quoted: U256,
+    /// `getAmountsOut` of the sell leg for `weth_mid`.
+    pub usdc_out_quoted: U256,
+}
+
+impl RoundTrip {
+    /// USDC gained by the round trip after `gas_cost_usdc`, for a quote token of
+    /// `decimals` decimals; negative on a loss.
+    pub fn profit_usdc(&self, gas_cost_usdc: f64, decimals: u32) -> f64 {
+        arb::units_to_amount(self.usdc_out, decimals)
+            - arb::units_to_amount(self.usdc_in, decimals)
+            - gas_cost_usdc
+    }
+
+    /// Shortfall of the buy and sell leg's simulated output below its quote, in
+    /// basis points; `0` where the swap returned at least the quote.
+    ///
+    /// A shortfall means `getAmountsOut` misses something the swap pays, such
+    /// as a fee-on-transfer token.
+    pub fn quote_shortfall_bps(&self) -> [f64; 2] {
+        [
+            (self.weth_mid, self.weth_mid_quoted),
+            (self.usdc_out, self.usdc_out_quoted),
+        ]
+        .map(|(simulated, quoted)| {
+            if quoted.is_zero() || simulated >= quoted {
+                return 0.0;
+            }
+            let short = arb::units_to_amount(quoted - simulated, 0);
+            short / arb::units_to_amount(quoted, 0) * 10_000.0
+        })
+    }
+}
+
+/// Simulates `swapExactTokensForTokens` on both legs of an opportunity.
+pub struct Simulator {
+    provider: Arc<Provider<Http>>,
+    router_abi: Abi,
+    erc20: Abi,
+    /// Router per `uniswap_v2` entry name.
+    routers: HashMap<String, Address>,
+    from: Address,
+    weth: Address,
+    usdc: Address,
+    usdc_slots: Option<TokenSlots>,
+    weth_slots: Option<TokenSlots>,
+    tolerance_usdc: f64,
+    max_quote_shortfall_bps: f64,
+}
+
+impl Simulator {
+    /// Prepares simulations from `cfg.from` through the `uniswap_v2` entries of `dex`.
+    pub fn new(
+        cfg: &SimulationConfig,
+        dex: &[DexEntry],
+        weth: Address,
+        usdc: Address,
+        router_abi: Abi,
+        provider: Arc<Provider<Http>>,
+    ) -> BotResult<Self> {
+        let routers = price::v2_routers(dex)?;
+        Ok(Self {
+            provider,
+            router_abi,
+            erc20: parse_abi(&[
+                "function balanceOf(address) view returns (uint256)",
+                "function allowance(address,address) view returns (uint256)",
+            ])
+            .expect("static ERC-20 fragment parses"),
+            routers,
+            from: config::parse_address("simulation.from", &cfg.from)?,
+            weth,
+            usdc,
+            usdc_slots: cfg.usdc_slots,
+            weth_slots: cfg.weth_slots,
+            tolerance_usdc: cfg.tolerance_usdc,
+            max_quote_shortfall_bps: cfg.max_quote_shortfall_bps,
+        })
+    }
+
+    /// Configured shortfall, in USDC, still accepted as a pass.
+    pub fn tolerance_usdc(&self) -> f64 {
+        self.tolerance_usdc
+    }
+
+    /// Configured leg shortfall, in basis points, below which quotes are trusted.
+    pub fn max_quote_shortfall_bps(&self) -> f64 {
+        self.max_quote_shortfall_bps
+    }
+
+    /// Buys WETH with `usdc_in` on `buy_dex`, then sells it on `sell_dex`.
+    ///
+    /// `buy_path` and `sell_path` are the quoted WETH → … → USDC paths; the buy
+    /// leg trades `buy_path` in reverse.
+    pub async fn round_trip(
+        &self,
+        buy_dex: &str,
+        buy_path: &[Address],
+        sell_dex: &str,
+        sell_path: &[Address],
+        usdc_in: U256,
+    ) -> Result<RoundTrip, SimulationError> {
+        let buy_router = self.router(buy_dex)?;
+        let sell_router = self.router(sell_dex)?;
+        let reversed: Vec<Address> = buy_path.iter().rev().copied().collect();
+        let (weth_mid, weth_mid_quoted) = self
+            .swap(buy_router, &reversed, self.usdc, self.usdc_slots, usdc_in)
+            .await?;
+        let (usdc_out, usdc_out_quoted) = self
+            .swap(sell_router, sell_path, self.weth, self.weth_slots, weth_mid)
+            .await?;
+        Ok(RoundTrip {
+            usdc_in,
+            weth_mid,
+            usdc_out,
+            weth_mid_quoted,
+            usdc_out_quoted,
+        })
+    }
+
+    fn router(&self, dex: &str) -> Result<Address, SimulationError> {
+        self.routers.get(dex).copied().ok_or_else(|| {
+            SimulationError::Unsupported(format!("{} is not a uniswap_v2 router", dex))
+        })
+    }
+
+    /// Simulates one exact-input swap of `amount_in` of `token_in` and returns
+    /// its output with the router's `getAmountsOut` for the same input.
+    async fn swap(
+        &self,
+        router: Address,
+        path: &[Address],
+        token_in: Address,
+        slots: Option<TokenSlots>,
+        amount_in: U256,
+    ) -> Result<(U256, U256), SimulationError> {
+        let mut state = spoof::state();
+        match slots {
+            Some(slots) => {
+                state
+                    .account(token_in)
+                    .store(balance_key(self.from, slots.balance), word(amount_in))
+                    .store(
+                        allowance_key(self.from, router, slots.allowance),
+                        word(U256::MAX),
+                    );
+            }
+            None => self.check_funding(token_in, router, amount_in).await?,
+        }
+        let contract = Contract::new(router, self.router_abi.clone(), self.provider.clone());
+        let amounts: Vec<U256> = contract
+            .method::<_, Vec<U256>>(
+                "swapExactTokensForTokens",
+                (amount_in, U256::zero(), path.to_vec(), self.from, U256::MAX),
+            )
+            .map_err(|err| SimulationError::Rpc(format!("{:?}", err)))?
+            .from(self.from)
+            .call_raw()
+            .state(&state)
+            .await
+            .map_err(|err| SimulationError::from(price::classify_call_error(err)))?;
+        let simulated = amounts
+            .last()
+            .copied()
+            .ok_or_else(|| SimulationError::Rpc("swap returned no amounts".into()))?;
+        let quoted: Vec<U256> = contract
+            .method::<_, Vec<U256>>("getAmountsOut", (amount_in, path.to_vec()))
+            .map_err(|err| SimulationError::Rpc(format!("{:?}", err)))?
+            .call()
+            .await
+            .map_err(|err| SimulationError::from(price::classify_call_error(err)))?;
+        let quoted = quoted
+            .last()
+            .copied()
+            .ok_or_else(|| SimulationError::Rpc("getAmountsOut returned no amounts".into()))?;
+        Ok((simulated, quoted))
+    }
+
+    /// Without slot overrides, `from` must really hold and have approved `amount`.
+    async fn check_funding(
+        &self,
+        token: Address,
+        router: Address,
+        amount: U256,
+    ) -> Result<(), SimulationError> {
+        let contract = Contract::new(token, self.erc20.clone(), self.provider.clone());
+        let balance: U256 = contract
+            .method::<_, U256>("balanceOf", self.from)
+            .map_err(|err| SimulationError::Rpc(format!("{:?}", err)))?
+            .call()
+            .await
+            .map_err(|err| SimulationError::from(price::classify_call_error(err)))?;
+        let allowance: U256 = contract
+            .method::<_, U256>("allowance", (self.from, router))
+            .map_err(|err| SimulationError::Rpc(format!("{:?}", err)))?
+            .call()
+            .await
+            .map_err(|err| SimulationError::from(price::classify_call_error(err)))?;
+        if balance < amount || allowance < amount {
+            return Err(SimulationError::MissingApproval(format!(
+                "{:?} holds {} and approved {} of token {:?} for router {:?}, needs {}; \
+                 fund and approve it or set the token's slots under [simulation]",
+                self.from, balance, allowance, token, router, amount
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Storage key of `balances[holder]` for a mapping at `slot`.
+fn balance_key(holder: Address, slot: u64) -> H256 {
+    mapping_key(H256::from(holder), H256::from_low_u64_be(slot))
+}
+
+/// Storage key of `allowances[owner][spender]` for a nested mapping at `slot`.
+fn allowance_key(owner: Address, spender: Address, slot: u64) -> H256 {
+    let inner = mapping_key(H256::from(owner), H256::from_low_u64_be(slot));
+    mapping_key(H256::from(spender), inner)
+}
+
+fn mapping_key(key: H256, slot: H256) -> H256 {
+    H256(keccak256([key.as_bytes(), slot.as_bytes()].concat()))
+}
+
+fn word(value: U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    H256(bytes)
+}
@@ -0,0 +1,91 @@
+//! JSON snapshot of rolling loop state, carried across restarts.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Last successful quote of one DEX/pair.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastPrice {
+    /// DEX quoted.
+    pub dex: String,
+    /// Pair quoted.
+    pub pair: String,
+    /// Output of the trade size, in USDC.
+    pub price_usdc: f64,
+    /// RFC 3339 time of the quote.
+    pub at: String,
+}
+
+/// Latest [`LastPrice`] of every scanner by (dex, pair), served by `GET /prices`.
+pub type PriceBoard = Arc<Mutex<BTreeMap<(String, String), LastPrice>>>;
+
+/// Contents of the `state_file`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// RFC 3339 time the snapshot was written.
+    pub saved_at: String,
+    /// Last quote per DEX/pair.
+    #[serde(default)]
+    pub last_prices: Vec<LastPrice>,
+    /// RFC 3339 time of the last Telegram alert, which rate-limits the next one.
+    pub last_telegram_at: Option<String>,
+    /// Recent gross spreads per pair seen by the volatility pacer, oldest first.
+    #[serde(default)]
+    pub spreads: BTreeMap<String, Vec<f64>>,
+    /// Current delay of the adaptive refresh controller, in seconds.
+    pub refresh_delay_secs: Option<f64>,
+}
+
+/// Reads the snapshot at `path`; `Ok(None)` when no snapshot was written yet.
+pub fn load_snapshot(path: &str) -> Result<Option<StateSnapshot>> {
+    match fs::read_to_string(path) {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Writes `snapshot` to `path` through a temporary file, so a crash mid-write
+/// never leaves a truncated snapshot behind.
+pub fn save_snapshot(path: &str, snapshot: &StateSnapshot) -> Result<()> {
+    let tmp = Path::new(path).with_extension("tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(snapshot)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Per-scanner variant of `path` for runs with several chains or pairs, e.g.
+/// `state.json` → `state.137.WETH-USDC.json`.
+pub fn scoped_path(path: &str, chain_id: u64, pair: &str) -> String {
+    let pair: String = pair
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let path = Path::new(path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("state");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("json");
+    path.with_file_name(format!("{}.{}.{}.{}", stem, chain_id, pair, ext))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Wall-clock rendering of a monotonic instant in the past.
+pub fn instant_to_rfc3339(at: Instant) -> String {
+    let age = chrono::Duration::from_std(at.elapsed()).unwrap_or_default();
+    (Utc::now() - age).to_rfc3339()
+}
+
+/// Monotonic instant matching a stored RFC 3339 time; `None` when unparsable, in
+/// the future, or older than the process clock can represent.
+pub fn rfc3339_to_instant(at: &str) -> Option<Instant> {
+    let at = DateTime::parse_from_rfc3339(at).ok()?.with_timezone(&Utc);
+    let age = (Utc::now() - at).to_std().ok()?;
+    Instant::now().checked_sub(age)
+}
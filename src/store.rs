@@ -0,0 +1,190 @@
+//! Persistence of opportunities and prices behind [`OpportunityStore`], so the
+//! bot can write to its own SQLite file or a PostgreSQL database shared by
+//! several instances.
+
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::db::{
+    self, DbPool, DbWrite, ExecutionRecord, HistoryStats, Opportunity, OpportunityStats, PriceTick,
+    RecentOpportunity,
+};
+use crate::error::{BotError, BotResult};
+
+/// Backend a `database_url` selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend<'a> {
+    /// `sqlite://<path>`: the SQLite file at `path`, e.g. `sqlite://arbitrage.db`.
+    Sqlite(&'a str),
+    /// `postgres://…` or `postgresql://…`: the whole URL, as tokio-postgres reads it.
+    Postgres(&'a str),
+}
+
+impl<'a> Backend<'a> {
+    /// The backend of `url`, from its scheme.
+    pub fn parse(url: &'a str) -> Result<Self, String> {
+        match url.split_once("://") {
+            Some(("sqlite", "")) => Err("sqlite:// needs a file path".into()),
+            Some(("sqlite", path)) => Ok(Self::Sqlite(path)),
+            Some(("postgres" | "postgresql", _)) => Ok(Self::Postgres(url)),
+            _ => Err("expected a sqlite:// or postgres:// URL".into()),
+        }
+    }
+}
+
+/// Where opportunities and price ticks are stored and read back from.
+///
+/// The scanners and the HTTP API only go through this trait; sent
+/// transactions and the backtests stay in SQLite.
+#[async_trait]
+pub trait OpportunityStore: Send + Sync {
+    /// `sqlite` or `postgres`, for logs.
+    fn backend(&self) -> &'static str;
+
+    /// Creates the schema, or migrates one written by an older version.
+    async fn migrate(&self) -> BotResult<()>;
+
+    /// Stores `opportunity` in `arbitrage_bot` with status `detected` and
+    /// returns its row id.
+    async fn insert_opportunity(&self, opportunity: &Opportunity) -> BotResult<i64>;
+
+    /// Records one tick's quotes in `price_history` under one timestamp.
+    async fn insert_prices(&self, tick: &PriceTick) -> BotResult<()>;
+
+    /// Applies `batch` in one transaction, as the writer of
+    /// [`db::spawn_writer`] does.
+    ///
+    /// Returns one outcome per write, in order: the row id of a stored
+    /// opportunity, the number of rows written otherwise. A failed write does
+    /// not fail the batch; an error of the whole batch means none was kept.
+    async fn apply_batch(&self, batch: &[DbWrite]) -> BotResult<Vec<BotResult<i64>>>;
+
+    /// The `limit` most recently stored opportunities, newest first.
+    async fn recent_opportunities(&self, limit: u64) -> BotResult<Vec<RecentOpportunity>>;
+
+    /// TWAP and VWAP of `dex`/`pair` over the last `window_secs` seconds.
+    async fn opportunity_stats(
+        &self,
+        dex: &str,
+        pair: &str,
+        window_secs: u64,
+    ) -> BotResult<OpportunityStats>;
+
+    /// Aggregates over every stored opportunity, zeros when there are none.
+    async fn history_stats(&self) -> BotResult<HistoryStats>;
+
+    /// Stores `record` in `executions` against the opportunity `opportunity_id`.
+    async fn insert_execution(
+        &self,
+        opportunity_id: i64,
+        chain_id: u64,
+        record: &ExecutionRecord,
+    ) -> BotResult<()>;
+
+    /// Realized USDC lost by live executions on `chain_id` since UTC midnight,
+    /// as a positive number, see [`db::realized_loss_today`].
+    async fn realized_loss_today(&self, chain_id: u64) -> BotResult<f64>;
+}
+
+/// Opens the store `database_url` selects; a Postgres store records
+/// `instance_id` on every row it writes.
+pub async fn open(
+    database_url: &str,
+    instance_id: Option<&str>,
+) -> BotResult<Arc<dyn OpportunityStore>> {
+    match Backend::parse(database_url).map_err(BotError::Config)? {
+        Backend::Sqlite(path) => Ok(Arc::new(SqliteStore::new(db::open_pool(path)?))),
+        #[cfg(feature = "postgres")]
+        Backend::Postgres(url) => {
+            let instance_id = instance_id.ok_or_else(|| {
+                BotError::Config("instance_id is required with a postgres:// database_url".into())
+            })?;
+            Ok(Arc::new(
+                crate::postgres::PostgresStore::connect(url, instance_id).await?,
+            ))
+        }
+        #[cfg(not(feature = "postgres"))]
+        Backend::Postgres(_) => {
+            let _ = instance_id;
+            Err(BotError::Config(
+                "postgres:// database_url needs a build with --features postgres".into(),
+            ))
+        }
+    }
+}
+
+/// The SQLite file, through the functions of [`db`].
+///
+/// Each call checks out a pooled connection and runs on the calling worker
+/// thread, which the runtime is told may block, so it needs the
+/// multi-threaded runtime.
+pub struct SqliteStore {
+    pool: DbPool,
+}
+
+impl SqliteStore {
+    /// Stores into the file `pool` is opened on.
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    fn blocking<T>(
+        &self,
+        run: impl FnOnce(&mut rusqlite::Connection) -> BotResult<T>,
+    ) -> BotResult<T> {
+        tokio::task::block_in_place(|| run(&mut *self.pool.get()?))
+    }
+}
+
+#[async_trait]
+impl OpportunityStore for SqliteStore {
+    fn backend(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn migrate(&self) -> BotResult<()> {
+        tokio::task::block_in_place(|| db::init_db(&self.pool))
+    }
+
+    async fn insert_opportunity(&self, opportunity: &Opportunity) -> BotResult<i64> {
+        self.blocking(|conn| db::insert_opportunity(conn, opportunity))
+    }
+
+    async fn insert_prices(&self, tick: &PriceTick) -> BotResult<()> {
+        self.blocking(|conn| db::insert_prices(conn, tick.chain_id, tick.at, &tick.records))
+    }
+
+    async fn apply_batch(&self, batch: &[DbWrite]) -> BotResult<Vec<BotResult<i64>>> {
+        self.blocking(|conn| db::stage_writes(conn, batch))
+    }
+
+    async fn recent_opportunities(&self, limit: u64) -> BotResult<Vec<RecentOpportunity>> {
+        self.blocking(|conn| db::recent_opportunities(conn, limit))
+    }
+
+    async fn opportunity_stats(
+        &self,
+        dex: &str,
+        pair: &str,
+        window_secs: u64,
+    ) -> BotResult<OpportunityStats> {
+        self.blocking(|conn| db::opportunity_stats(conn, dex, pair, window_secs))
+    }
+
+    async fn history_stats(&self) -> BotResult<HistoryStats> {
+        self.blocking(|conn| db::history_stats(conn))
+    }
+
+    async fn insert_execution(
+        &self,
+        opportunity_id: i64,
+        chain_id: u64,
+        record: &ExecutionRecord,
+    ) -> BotResult<()> {
+        self.blocking(|conn| db::insert_execution(conn, opportunity_id, chain_id, record))
+    }
+
+    async fn realized_loss_today(&self, chain_id: u64) -> BotResult<f64> {
+        self.blocking(|conn| db::realized_loss_today(conn, chain_id))
+    }
+}
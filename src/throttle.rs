@@ -0,0 +1,220 @@
+//! Per-direction throttling of opportunity notifications, so a spread that
+//! stays open alerts once instead of every tick.
+
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// When repeat alerts of an open spread go out (`[notify_throttle]`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NotifyThrottleConfig {
+    /// Seconds after an alert before the same direction alerts again.
+    #[serde(default = "default_min_notify_interval_secs")]
+    pub min_notify_interval_secs: u64,
+    /// A repeat alert also needs the profit to have grown this much, in
+    /// percent, over the last one sent.
+    pub min_improvement_pct: Option<f64>,
+    /// Sends one summary when a spread closes.
+    #[serde(default)]
+    pub close_summary: bool,
+}
+
+fn default_min_notify_interval_secs() -> u64 {
+    60
+}
+
+/// Buy and sell venue of an open spread.
+pub type Direction = (String, String);
+
+/// A spread that stopped qualifying, as reported by [`NotifyThrottle::end_tick`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosedSpread {
+    /// Buy and sell venue.
+    pub direction: Direction,
+    /// Time from its first to its last qualifying tick.
+    pub open_for: Duration,
+    /// Highest profit seen while it was open, in USDC.
+    pub peak_profit_usdc: f64,
+    /// Alerts held back while it was open.
+    pub suppressed: u64,
+}
+
+#[derive(Debug)]
+struct OpenSpread {
+    opened: Instant,
+    last_seen: Instant,
+    last_sent: Instant,
+    sent_profit: f64,
+    peak_profit: f64,
+    suppressed: u64,
+}
+
+/// Open spreads of one pair and the alerts sent for them.
+#[derive(Debug)]
+pub struct NotifyThrottle {
+    cfg: NotifyThrottleConfig,
+    open: HashMap<Direction, OpenSpread>,
+    seen: HashSet<Direction>,
+}
+
+impl NotifyThrottle {
+    /// Creates a throttle with no open spreads.
+    pub fn new(cfg: NotifyThrottleConfig) -> Self {
+        Self {
+            cfg,
+            open: HashMap::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Replaces the thresholds after a config reload, keeping the open spreads.
+    pub fn set_config(&mut self, cfg: NotifyThrottleConfig) {
+        self.cfg = cfg;
+    }
+
+    /// Whether `cfg` asks for summaries of closed spreads.
+    pub fn close_summary(&self) -> bool {
+        self.cfg.close_summary
+    }
+
+    /// Records an opportunity of `direction` at `now` and returns whether its
+    /// alert should be sent.
+    ///
+    /// The first alert of a spread always goes out; later ones wait for
+    /// `min_notify_interval_secs` and, when set, `min_improvement_pct`.
+    pub fn offer(&mut self, direction: &Direction, profit_usdc: f64, now: Instant) -> bool {
+        self.seen.insert(direction.clone());
+        let Some(spread) = self.open.get_mut(direction) else {
+            self.open.insert(
+                direction.clone(),
+                OpenSpread {
+                    opened: now,
+                    last_seen: now,
+                    last_sent: now,
+                    sent_profit: profit_usdc,
+                    peak_profit: profit_usdc,
+                    suppressed: 0,
+                },
+            );
+            return true;
+        };
+        spread.last_seen = now;
+        spread.peak_profit = spread.peak_profit.max(profit_usdc);
+        let interval = Duration::from_secs(self.cfg.min_notify_interval_secs);
+        let waited = now.duration_since(spread.last_sent) >= interval;
+        let improved = self.cfg.min_improvement_pct.is_none_or(|pct| {
+            profit_usdc >= spread.sent_profit + spread.sent_profit.abs() * pct / 100.0
+        });
+        if waited && improved {
+            spread.last_sent = now;
+            spread.sent_profit = profit_usdc;
+            true
+        } else {
+            spread.suppressed += 1;
+            false
+        }
+    }
+
+    /// Closes every open spread not offered since the last call and returns them.
+    ///
+    /// Called only after ticks that compared venues, so a tick lost to the RPC
+    /// closes nothing.
+    pub fn end_tick(&mut self) -> Vec<ClosedSpread> {
+        let seen = std::mem::take(&mut self.seen);
+        let closed: Vec<Direction> = self
+            .open
+            .keys()
+            .filter(|direction| !seen.contains(*direction))
+            .cloned()
+            .collect();
+        closed
+            .into_iter()
+            .filter_map(|direction| {
+                let spread = self.open.remove(&direction)?;
+                Some(ClosedSpread {
+                    direction,
+                    open_for: spread.last_seen.duration_since(spread.opened),
+                    peak_profit_usdc: spread.peak_profit,
+                    suppressed: spread.suppressed,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttle(min_improvement_pct: Option<f64>) -> NotifyThrottle {
+        NotifyThrottle::new(NotifyThrottleConfig {
+            min_notify_interval_secs: 60,
+            min_improvement_pct,
+            close_summary: true,
+        })
+    }
+
+    fn quick_to_sushi() -> Direction {
+        ("QuickSwap".into(), "SushiSwap".into())
+    }
+
+    #[test]
+    fn repeats_inside_the_interval_are_suppressed() {
+        let mut throttle = throttle(None);
+        let (direction, start) = (quick_to_sushi(), Instant::now());
+        let at = |secs| start + Duration::from_secs(secs);
+        assert!(throttle.offer(&direction, 20.0, at(0)));
+        throttle.end_tick();
+        for secs in [30, 59] {
+            assert!(!throttle.offer(&direction, 40.0, at(secs)));
+            throttle.end_tick();
+        }
+        assert!(throttle.offer(&direction, 20.0, at(60)));
+        // The interval restarts from the alert just sent.
+        assert!(!throttle.offer(&direction, 20.0, at(90)));
+        // The other direction is a spread of its own.
+        let reverse = (direction.1.clone(), direction.0.clone());
+        assert!(throttle.offer(&reverse, 20.0, at(90)));
+    }
+
+    #[test]
+    fn repeats_need_the_profit_to_improve() {
+        let mut throttle = throttle(Some(10.0));
+        let (direction, start) = (quick_to_sushi(), Instant::now());
+        let at = |secs| start + Duration::from_secs(secs);
+        assert!(throttle.offer(&direction, 20.0, at(0)));
+        // Past the interval, 21.9 is short of 10% over the 20 sent.
+        assert!(!throttle.offer(&direction, 21.9, at(60)));
+        assert!(throttle.offer(&direction, 22.0, at(120)));
+        // Improvement is measured from the last alert, not the first.
+        assert!(!throttle.offer(&direction, 24.0, at(180)));
+        assert!(throttle.offer(&direction, 24.3, at(240)));
+        // An improvement inside the interval still waits.
+        assert!(!throttle.offer(&direction, 100.0, at(250)));
+    }
+
+    #[test]
+    fn closing_summarizes_the_spread_once() {
+        let mut throttle = throttle(None);
+        let (direction, start) = (quick_to_sushi(), Instant::now());
+        let at = |secs| start + Duration::from_secs(secs);
+        for (secs, profit) in [(0, 20.0), (10, 35.0), (20, 25.0), (30, 22.0)] {
+            throttle.offer(&direction, profit, at(secs));
+            assert_eq!(throttle.end_tick(), []);
+        }
+        assert_eq!(
+            throttle.end_tick(),
+            [ClosedSpread {
+                direction: direction.clone(),
+                open_for: Duration::from_secs(30),
+                peak_profit_usdc: 35.0,
+                suppressed: 3,
+            }]
+        );
+        assert_eq!(throttle.end_tick(), []);
+        // Reopening alerts at once and counts from zero.
+        assert!(throttle.offer(&direction, 20.0, at(40)));
+        assert_eq!(throttle.end_tick(), []);
+        assert_eq!(throttle.end_tick()[0].suppressed, 0);
+    }
+}